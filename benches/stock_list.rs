@@ -0,0 +1,172 @@
+//! Compares price lookups for a listing page: one query at a time against the
+//! legacy per-ticker collections, the concurrent fan-out over them, the
+//! concurrent read of every series from the time-series store, and the read
+//! of only the 1mo/1d series `stockList` shows.
+//!
+//! The sequential legacy lookup is what `stockList` did before it fetched a
+//! page at once; the 1mo-only column is what it does now.
+//!
+//! Seeds throwaway `*_bench` databases, so it needs a reachable MongoDB
+//! (`BENCH_MONGO_URI`, default `mongodb://localhost:27017`):
+//!
+//! ```text
+//! cargo bench --bench stock_list
+//! ```
+
+use std::time::{Duration, Instant};
+use mongodb::{bson::{doc, from_document, Document}, options::ClientOptions, Client};
+use trading_sim_server::db::mongo::{Databases, DEFAULT_MONGO_URI};
+use trading_sim_server::db::price_store::PriceStore;
+use trading_sim_server::models::price_bar_models::{BarMeta, PriceBar};
+use trading_sim_server::models::stock_models::PriceDataDetails;
+
+const LEGACY_DATABASE: &str = "stock_data_bench";
+const STORE_DATABASE: &str = "trading_simulator_bench";
+/// Has no catalog, so reads through it fall back to the legacy layout.
+const EMPTY_DATABASE: &str = "trading_simulator_bench_empty";
+const TICKERS: usize = 100;
+const PAGE_SIZES: [usize; 3] = [10, 50, 100];
+const ITERATIONS: usize = 30;
+const CONCURRENCY: usize = 16;
+/// (period, interval, points, seconds between points) stored per ticker.
+const SERIES: [(&str, &str, usize, i64); 3] = [
+    ("1mo", "1d", 22, 86_400),
+    ("1y", "1d", 252, 86_400),
+    ("5d", "5m", 390, 300),
+];
+
+fn series_document(ticker: &str, period: &str, interval: &str, points: usize, step: i64) -> Document {
+    let start = 1_700_000_000i64;
+    let closes: Vec<f64> = (0..points).map(|i| 100.0 + (i as f64 / 7.0).sin() * 5.0).collect();
+    doc! {
+        "symbol": ticker,
+        "period": period,
+        "interval": interval,
+        "opens": closes.clone(),
+        "highs": closes.iter().map(|close| close + 1.0).collect::<Vec<_>>(),
+        "lows": closes.iter().map(|close| close - 1.0).collect::<Vec<_>>(),
+        "closes": closes,
+        "timestamps": (0..points as i64).map(|i| start + i * step).collect::<Vec<_>>(),
+        "volumes": (0..points as i64).map(|i| 1_000 + i).collect::<Vec<_>>(),
+    }
+}
+
+fn to_bars(ticker: &str, document: &Document) -> Vec<PriceBar> {
+    let series: PriceDataDetails = from_document(document.clone()).expect("seeded series is valid");
+    let closes = series.closes.unwrap_or_default();
+    let timestamps = series.timestamps.unwrap_or_default();
+    timestamps
+        .iter()
+        .zip(&closes)
+        .map(|(timestamp, &close)| PriceBar {
+            id: None,
+            timestamp: mongodb::bson::DateTime::from_millis(timestamp.timestamp_millis()),
+            meta: BarMeta::new(ticker, &series.interval),
+            open: close,
+            high: close + 1.0,
+            low: close - 1.0,
+            close,
+            volume: 1_000,
+        })
+        .collect()
+}
+
+/// Writes the same dataset in both layouts.
+async fn seed(client: &Client, store: &PriceStore) -> mongodb::error::Result<Vec<String>> {
+    for database in [LEGACY_DATABASE, STORE_DATABASE, EMPTY_DATABASE] {
+        client.database(database).drop(None).await?;
+    }
+    store.ensure_collections().await?;
+
+    let legacy = client.database(LEGACY_DATABASE);
+    let tickers: Vec<String> = (0..TICKERS).map(|i| format!("BENCH{:03}", i)).collect();
+    for ticker in &tickers {
+        let documents: Vec<Document> = SERIES
+            .iter()
+            .map(|(period, interval, points, step)| series_document(ticker, period, interval, *points, *step))
+            .collect();
+        for (document, (period, interval, points, _)) in documents.iter().zip(SERIES) {
+            store.upsert_bars(ticker, interval, to_bars(ticker, document)).await?;
+            store.record_series(ticker, period, interval, points).await?;
+        }
+        legacy.collection::<Document>(ticker).insert_many(documents, None).await?;
+    }
+    Ok(tickers)
+}
+
+fn summarize(mut samples: Vec<Duration>) -> (f64, f64) {
+    samples.sort();
+    let mean = samples.iter().sum::<Duration>().as_secs_f64() * 1000.0 / samples.len() as f64;
+    let p95 = samples[(samples.len() * 95 / 100).min(samples.len() - 1)].as_secs_f64() * 1000.0;
+    (mean, p95)
+}
+
+/// Mean and p95 of `lookup` over `ITERATIONS` runs, after one warm-up run so
+/// every variant starts from the same connection pool state.
+async fn measure<F, Fut>(lookup: F) -> mongodb::error::Result<(f64, f64)>
+where
+    F: Fn() -> Fut,
+    Fut: std::future::Future<Output = mongodb::error::Result<()>>,
+{
+    lookup().await?;
+    let mut samples = Vec::with_capacity(ITERATIONS);
+    for _ in 0..ITERATIONS {
+        let started = Instant::now();
+        lookup().await?;
+        samples.push(started.elapsed());
+    }
+    Ok(summarize(samples))
+}
+
+/// Reads every series of each ticker, as `stockList` used to.
+async fn all_series(store: &PriceStore, tickers: &[String], concurrency: usize) -> mongodb::error::Result<()> {
+    let loaded = store.price_documents_many(tickers, concurrency).await?;
+    assert_eq!(loaded.len(), tickers.len());
+    Ok(())
+}
+
+/// Reads only the series a listing shows.
+async fn listing_series(store: &PriceStore, tickers: &[String]) -> mongodb::error::Result<()> {
+    let loaded = store.series_documents_many(tickers, "1mo", "1d", CONCURRENCY).await?;
+    assert_eq!(loaded.len(), tickers.len());
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> mongodb::error::Result<()> {
+    let uri = std::env::var("BENCH_MONGO_URI").unwrap_or_else(|_| DEFAULT_MONGO_URI.to_string());
+    let mut options = ClientOptions::parse(&uri).await?;
+    options.server_selection_timeout = Some(Duration::from_secs(2));
+    let client = Client::with_options(options)?;
+    if let Err(err) = client.database("admin").run_command(doc! { "ping": 1 }, None).await {
+        eprintln!("Skipping stock_list benchmark, MongoDB at {} is unreachable: {}", uri, err);
+        return Ok(());
+    }
+
+    let store = PriceStore::new(&Databases::with_names(&client, STORE_DATABASE, "stock_data_bench_unused"));
+    let legacy_store = PriceStore::new(&Databases::with_names(&client, EMPTY_DATABASE, LEGACY_DATABASE));
+    let tickers = seed(&client, &store).await?;
+
+    // Printed as a Markdown table so a run can be pasted into the README.
+    println!("| page | legacy sequential (before) | legacy concurrent | time-series concurrent | time-series 1mo only (after) |");
+    println!("|-----:|---------------------------:|------------------:|-----------------------:|-----------------------------:|");
+    for page_size in PAGE_SIZES {
+        let page = &tickers[..page_size];
+        let results = [
+            measure(|| all_series(&legacy_store, page, 1)).await?,
+            measure(|| all_series(&legacy_store, page, CONCURRENCY)).await?,
+            measure(|| all_series(&store, page, CONCURRENCY)).await?,
+            measure(|| listing_series(&store, page)).await?,
+        ];
+        let mut row = format!("| {} |", page_size);
+        for (mean, p95) in results {
+            row.push_str(&format!(" {:.2} ms (p95 {:.2} ms) |", mean, p95));
+        }
+        println!("{}", row);
+    }
+
+    for database in [LEGACY_DATABASE, STORE_DATABASE, EMPTY_DATABASE] {
+        client.database(database).drop(None).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,159 @@
+//! Session tokens for endpoints that act on behalf of a signed-in user.
+//!
+//! Login and registration return a token of the form
+//! `<user_id>.<expires_at>.<signature>`, the signature being an HMAC-SHA256
+//! of the first two parts. Tokens carry no server-side state, so every
+//! instance configured with the same `auth.token_secret` accepts them.
+//! Handlers take [`SessionUser`] to require one, sent as
+//! `Authorization: Bearer <token>` or, for clients such as `EventSource`
+//! that cannot set headers, in the [`SESSION_COOKIE`] cookie that login and
+//! registration set. Tokens are never read from the query string, which
+//! access logs and error reports record.
+//!
+//! Operator endpoints take [`Operator`] instead, which checks the
+//! `X-Operator-Token` header against `auth.operator_token`.
+
+use std::time::Duration;
+use actix_web::cookie::{Cookie, SameSite};
+use actix_web::dev::Payload;
+use actix_web::{web, FromRequest, HttpRequest};
+use chrono::Utc;
+use futures::future::{ready, Ready};
+use hmac::{Hmac, Mac};
+use log::warn;
+use sha2::Sha256;
+use crate::config::{AppConfig, AuthConfig};
+use crate::error::AppError;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Cookie carrying the session token for clients that cannot set headers.
+pub const SESSION_COOKIE: &str = "session_token";
+
+/// Issues and checks session tokens.
+pub struct SessionTokens {
+    key: Vec<u8>,
+    ttl: Duration,
+}
+
+impl SessionTokens {
+    /// An empty `token_secret` gets a random key: tokens are then only
+    /// accepted by this process and stop working when it restarts.
+    pub fn from_config(config: &AuthConfig) -> Self {
+        let key = if config.token_secret.is_empty() {
+            warn!("auth.token_secret is not set; session tokens are signed with a random per-process key");
+            rand::random::<[u8; 32]>().to_vec()
+        } else {
+            config.token_secret.as_bytes().to_vec()
+        };
+        SessionTokens { key, ttl: config.token_ttl() }
+    }
+
+    fn mac(&self, payload: &str) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.key).expect("HMAC takes keys of any length");
+        mac.update(payload.as_bytes());
+        mac
+    }
+
+    pub fn issue(&self, user_id: &str) -> String {
+        let expires_at = Utc::now().timestamp() + self.ttl.as_secs() as i64;
+        let payload = format!("{}.{}", user_id, expires_at);
+        let signature = hex::encode(self.mac(&payload).finalize().into_bytes());
+        format!("{}.{}", payload, signature)
+    }
+
+    /// `token` as an HTTP-only [`SESSION_COOKIE`] for the API routes that
+    /// expires with it.
+    pub fn cookie(&self, token: &str) -> Cookie<'static> {
+        Cookie::build(SESSION_COOKIE, token.to_string())
+            .path("/api")
+            .http_only(true)
+            .secure(true)
+            .same_site(SameSite::Strict)
+            .max_age(actix_web::cookie::time::Duration::seconds(self.ttl.as_secs() as i64))
+            .finish()
+    }
+
+    /// The user id of a correctly signed token that has not expired.
+    pub fn verify(&self, token: &str) -> Option<String> {
+        let (payload, signature) = token.rsplit_once('.')?;
+        self.mac(payload).verify_slice(&hex::decode(signature).ok()?).ok()?;
+        let (user_id, expires_at) = payload.split_once('.')?;
+        (expires_at.parse::<i64>().ok()? > Utc::now().timestamp()).then(|| user_id.to_string())
+    }
+}
+
+/// The user whose session token came with the request.
+#[derive(Debug, Clone)]
+pub struct SessionUser(pub String);
+
+impl SessionUser {
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+}
+
+fn request_token(req: &HttpRequest) -> Option<String> {
+    let header = req.headers()
+        .get("Authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    if let Some(token) = header {
+        return Some(token.trim().to_string());
+    }
+    req.cookie(SESSION_COOKIE).map(|cookie| cookie.value().to_string())
+}
+
+fn authenticate(req: &HttpRequest) -> Result<SessionUser, AppError> {
+    let tokens = req.app_data::<web::Data<SessionTokens>>()
+        .ok_or_else(|| AppError::Internal("Session tokens are not configured".to_string()))?;
+    let token = request_token(req).ok_or_else(|| AppError::Unauthorized("Missing session token".to_string()))?;
+    tokens.verify(&token)
+        .map(SessionUser)
+        .ok_or_else(|| AppError::Unauthorized("Invalid or expired session token".to_string()))
+}
+
+impl FromRequest for SessionUser {
+    type Error = AppError;
+    type Future = Ready<Result<Self, AppError>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(authenticate(req))
+    }
+}
+
+/// A request carrying the configured operator token.
+#[derive(Debug, Clone, Copy)]
+pub struct Operator;
+
+/// Compares in time independent of where the inputs first differ.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+fn authorize_operator(req: &HttpRequest) -> Result<Operator, AppError> {
+    let config = req.app_data::<web::Data<AppConfig>>()
+        .ok_or_else(|| AppError::Internal("Configuration is not available".to_string()))?;
+    let expected = &config.auth.operator_token;
+    if expected.is_empty() {
+        return Err(AppError::Unauthorized("Operator endpoints are disabled".to_string()));
+    }
+    let token = req.headers()
+        .get("X-Operator-Token")
+        .and_then(|value| value.to_str().ok())
+        .ok_or_else(|| AppError::Unauthorized("Missing operator token".to_string()))?;
+    if constant_time_eq(token.trim().as_bytes(), expected.as_bytes()) {
+        Ok(Operator)
+    } else {
+        Err(AppError::Unauthorized("Invalid operator token".to_string()))
+    }
+}
+
+impl FromRequest for Operator {
+    type Error = AppError;
+    type Future = Ready<Result<Self, AppError>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(authorize_operator(req))
+    }
+}
@@ -0,0 +1,128 @@
+//! Imports price history and company fundamentals from CSV, JSON or Parquet.
+//!
+//! ```text
+//! import_market_data prices bars.csv --period 1y --interval 1d --dry-run
+//! import_market_data fundamentals companies.parquet
+//! ```
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+use clap::{Parser, Subcommand};
+use env_logger::Env;
+use log::{error, info};
+use trading_sim_server::config::{AppConfig, ConfigArgs};
+use trading_sim_server::db::mongo::{init, Databases};
+use trading_sim_server::services::cache_service::record_data_update;
+use trading_sim_server::import::{fundamentals, prices, readers, ImportError, ImportFormat, ImportReport};
+use trading_sim_server::repositories::Repositories;
+
+#[derive(Parser)]
+#[command(about = "Load market data files into MongoDB")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+    /// Input format; inferred from the file extension when omitted.
+    #[arg(long, global = true)]
+    format: Option<ImportFormat>,
+    /// Validate and report without writing anything.
+    #[arg(long, global = true)]
+    dry_run: bool,
+    /// Abort without writing if any row fails validation.
+    #[arg(long, global = true)]
+    strict: bool,
+    /// TOML configuration file, as for the server.
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+    /// Overrides `mongo.uri` from the configuration.
+    #[arg(long, global = true)]
+    mongo_uri: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// One OHLCV bar per row: ticker, timestamp, open, high, low, close, volume.
+    Prices {
+        file: PathBuf,
+        /// Period for rows without a `period` column, e.g. `1y`.
+        #[arg(long)]
+        period: Option<String>,
+        /// Interval for rows without an `interval` column, e.g. `1d`.
+        #[arg(long)]
+        interval: Option<String>,
+    },
+    /// One company per row: ticker, name, profile fields and metrics.
+    Fundamentals { file: PathBuf },
+}
+
+async fn connect(cli: &Cli) -> Result<Databases, ImportError> {
+    let config = AppConfig::load(&ConfigArgs { config: cli.config.clone(), mongo_uri: cli.mongo_uri.clone(), ..Default::default() })?;
+    Ok(init(&config.mongo).await?)
+}
+
+async fn run(cli: Cli) -> Result<ImportReport, ImportError> {
+    let file = match &cli.command {
+        Command::Prices { file, .. } | Command::Fundamentals { file } => file,
+    };
+    let format = cli.format
+        .or_else(|| ImportFormat::from_path(file))
+        .ok_or_else(|| ImportError::UnknownFormat(file.display().to_string()))?;
+    let records = readers::read_records(file, format)?;
+    let mut report = ImportReport { dry_run: cli.dry_run, ..Default::default() };
+
+    match &cli.command {
+        Command::Prices { period, interval, .. } => {
+            let defaults = prices::SeriesDefaults { period: period.clone(), interval: interval.clone() };
+            let series = prices::build_series(&records, &defaults, &mut report);
+            if cli.strict && report.rows_rejected > 0 {
+                return Err(ImportError::Invalid(report.rows_rejected));
+            }
+            if !cli.dry_run {
+                let databases = connect(&cli).await?;
+                let repositories = Repositories::mongo(&databases);
+                prices::write_series(repositories.prices.as_ref(), &series, &mut report).await?;
+                record_data_update(&databases, &report.tickers).await?;
+            }
+        }
+        Command::Fundamentals { .. } => {
+            let updates = fundamentals::build_updates(&records, &mut report);
+            if cli.strict && report.rows_rejected > 0 {
+                return Err(ImportError::Invalid(report.rows_rejected));
+            }
+            if !cli.dry_run {
+                let databases = connect(&cli).await?;
+                fundamentals::write_updates(&databases, &updates, &mut report).await?;
+                record_data_update(&databases, &report.tickers).await?;
+            }
+        }
+    }
+    Ok(report)
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+    let cli = Cli::parse();
+
+    match run(cli).await {
+        Ok(report) => {
+            for row_error in &report.errors {
+                error!("Rejected {}", row_error);
+            }
+            info!(
+                "{} rows read, {} rejected, {} documents ({} inserted, {} updated){}",
+                report.rows_read,
+                report.rows_rejected,
+                report.documents,
+                report.inserted,
+                report.updated,
+                if report.dry_run { " [dry run]" } else { "" },
+            );
+            println!("{}", serde_json::to_string_pretty(&report).unwrap_or_default());
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            error!("Import failed: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,85 @@
+//! Moves legacy per-ticker price collections in `stock_data` into the
+//! `price_bars` time-series collection.
+//!
+//! ```text
+//! migrate_price_storage --dry-run
+//! migrate_price_storage AAPL MSFT --drop-legacy
+//! ```
+
+use std::process::ExitCode;
+use clap::Parser;
+use env_logger::Env;
+use log::{error, info, warn};
+use std::path::PathBuf;
+use trading_sim_server::config::{AppConfig, ConfigArgs};
+use trading_sim_server::db::mongo::init;
+use trading_sim_server::db::price_store::PriceStore;
+use trading_sim_server::services::cache_service::record_data_update;
+
+#[derive(Parser)]
+#[command(about = "Migrate legacy price collections to the time-series price store")]
+struct Cli {
+    /// Tickers to migrate; every legacy collection when omitted.
+    tickers: Vec<String>,
+    /// Report what would be migrated without writing anything.
+    #[arg(long)]
+    dry_run: bool,
+    /// Drop each legacy collection once all of its series were migrated.
+    #[arg(long)]
+    drop_legacy: bool,
+    /// TOML configuration file, as for the server.
+    #[arg(long)]
+    config: Option<PathBuf>,
+    /// Overrides `mongo.uri` from the configuration.
+    #[arg(long)]
+    mongo_uri: Option<String>,
+}
+
+async fn run(cli: Cli) -> Result<bool, Box<dyn std::error::Error>> {
+    let config = AppConfig::load(&ConfigArgs { config: cli.config, mongo_uri: cli.mongo_uri, ..Default::default() })?;
+    let databases = init(&config.mongo).await?;
+    let store = PriceStore::new(&databases);
+    if !cli.dry_run {
+        store.ensure_collections().await?;
+    }
+
+    let tickers = if cli.tickers.is_empty() { store.legacy_tickers().await? } else { cli.tickers };
+    let mut complete = true;
+    for ticker in &tickers {
+        let migration = store.migrate_legacy_ticker(ticker, cli.dry_run).await?;
+        for skipped in &migration.skipped {
+            warn!("{}: skipped {}", ticker, skipped);
+        }
+        info!("{}: {} series, {} bars{}", ticker, migration.series, migration.bars, if cli.dry_run { " [dry run]" } else { "" });
+        println!("{}", serde_json::to_string(&migration).unwrap_or_default());
+
+        if cli.dry_run {
+            continue;
+        }
+        record_data_update(&databases, std::slice::from_ref(ticker)).await?;
+        if !migration.skipped.is_empty() {
+            complete = false;
+        } else if cli.drop_legacy {
+            store.drop_legacy(ticker).await?;
+            info!("{}: dropped legacy collection", ticker);
+        }
+    }
+    Ok(complete)
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+
+    match run(Cli::parse()).await {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => {
+            warn!("Some legacy series failed validation and were not migrated");
+            ExitCode::FAILURE
+        }
+        Err(err) => {
+            error!("Migration failed: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
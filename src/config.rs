@@ -0,0 +1,319 @@
+//! Server, database and observability settings.
+//!
+//! Each layer overrides the one before it: built-in defaults, a TOML file
+//! (`--config`, `TRADING_SIM_CONFIG`, or `config.toml` in the working
+//! directory if present), environment variables, then command-line flags.
+//! The result is validated once at startup; see `config.example.toml`.
+
+use std::fmt::Display;
+use std::net::ToSocketAddrs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use crate::db::mongo::{PoolSettings, DEFAULT_APP_DATABASE, DEFAULT_MONGO_URI, DEFAULT_STOCK_DATABASE};
+
+const DEFAULT_CONFIG_FILE: &str = "config.toml";
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("failed to read {path}: {source}")]
+    Read { path: String, source: std::io::Error },
+    #[error("invalid configuration file {path}: {source}")]
+    Parse { path: String, source: toml::de::Error },
+    #[error("{name} is not valid: '{value}'")]
+    Env { name: &'static str, value: String },
+    #[error("invalid configuration: {}", .0.join("; "))]
+    Invalid(Vec<String>),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    /// `host:port` the HTTP server listens on.
+    pub bind_address: String,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig { bind_address: "127.0.0.1:8080".to_string() }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MongoConfig {
+    pub uri: String,
+    /// Users, trades, companies and everything else the server owns.
+    pub app_database: String,
+    /// Legacy per-ticker price collections.
+    pub stock_database: String,
+    pub min_pool_size: Option<u32>,
+    pub max_pool_size: Option<u32>,
+    pub connect_timeout_ms: Option<u64>,
+    pub server_selection_timeout_ms: Option<u64>,
+    pub max_idle_time_ms: Option<u64>,
+}
+
+impl Default for MongoConfig {
+    fn default() -> Self {
+        MongoConfig {
+            uri: DEFAULT_MONGO_URI.to_string(),
+            app_database: DEFAULT_APP_DATABASE.to_string(),
+            stock_database: DEFAULT_STOCK_DATABASE.to_string(),
+            min_pool_size: None,
+            max_pool_size: None,
+            connect_timeout_ms: None,
+            server_selection_timeout_ms: None,
+            max_idle_time_ms: None,
+        }
+    }
+}
+
+impl MongoConfig {
+    pub fn pool_settings(&self) -> PoolSettings {
+        PoolSettings {
+            min_pool_size: self.min_pool_size,
+            max_pool_size: self.max_pool_size,
+            connect_timeout: self.connect_timeout_ms.map(Duration::from_millis),
+            server_selection_timeout: self.server_selection_timeout_ms.map(Duration::from_millis),
+            max_idle_time: self.max_idle_time_ms.map(Duration::from_millis),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SentryConfig {
+    /// Empty, the default, disables error reporting.
+    pub dsn: String,
+    pub environment: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TradingConfig {
+    /// Cash credited to every new account.
+    pub starting_balance: f64,
+}
+
+impl Default for TradingConfig {
+    fn default() -> Self {
+        TradingConfig { starting_balance: 10000.0 }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    /// Key that signs session tokens. Instances behind one load balancer
+    /// must share it; empty uses a random key per process.
+    pub token_secret: String,
+    pub token_ttl_hours: u64,
+    /// Shared secret for operator endpoints such as recording corporate
+    /// actions. Empty disables those endpoints.
+    pub operator_token: String,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        AuthConfig { token_secret: String::new(), token_ttl_hours: 24, operator_token: String::new() }
+    }
+}
+
+impl AuthConfig {
+    pub fn token_ttl(&self) -> Duration {
+        Duration::from_secs(self.token_ttl_hours * 3600)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HealthConfig {
+    /// Age of the newest stored price after which `/health/ready` reports the
+    /// market data as stale. Covers a long weekend of daily bars by default.
+    pub max_data_age_hours: u64,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        HealthConfig { max_data_age_hours: 96 }
+    }
+}
+
+impl HealthConfig {
+    pub fn max_data_age(&self) -> Duration {
+        Duration::from_secs(self.max_data_age_hours * 3600)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AppConfig {
+    pub server: ServerConfig,
+    pub mongo: MongoConfig,
+    pub sentry: SentryConfig,
+    pub trading: TradingConfig,
+    pub auth: AuthConfig,
+    pub health: HealthConfig,
+}
+
+/// Command-line layer. Binaries flatten it, or fill in the fields they expose.
+#[derive(Debug, Clone, Default, clap::Args)]
+pub struct ConfigArgs {
+    /// TOML configuration file.
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+    /// Address the HTTP server listens on, e.g. `0.0.0.0:8080`.
+    #[arg(long)]
+    pub bind_address: Option<String>,
+    #[arg(long)]
+    pub mongo_uri: Option<String>,
+    /// Database for users, trades, companies and the price store.
+    #[arg(long)]
+    pub app_database: Option<String>,
+    /// Database holding the legacy per-ticker price collections.
+    #[arg(long)]
+    pub stock_database: Option<String>,
+    /// Sentry DSN; pass an empty string to disable error reporting.
+    #[arg(long)]
+    pub sentry_dsn: Option<String>,
+    /// Cash credited to new accounts.
+    #[arg(long)]
+    pub starting_balance: Option<f64>,
+}
+
+fn env_value<T: FromStr>(name: &'static str) -> Result<Option<T>, ConfigError> {
+    match std::env::var(name) {
+        Ok(value) => value.trim().parse().map(Some).map_err(|_| ConfigError::Env { name, value }),
+        Err(_) => Ok(None),
+    }
+}
+
+fn override_with<T>(target: &mut T, value: Option<T>) {
+    if let Some(value) = value {
+        *target = value;
+    }
+}
+
+fn override_optional<T>(target: &mut Option<T>, value: Option<T>) {
+    if value.is_some() {
+        *target = value;
+    }
+}
+
+/// MongoDB database names: 1 to 63 bytes, none of `/\. "$`.
+fn check_database_name(field: &str, name: &str, problems: &mut Vec<String>) {
+    if name.is_empty() || name.len() > 63 || name.contains(['/', '\\', '.', ' ', '"', '$', '\0']) {
+        problems.push(format!("{} '{}' is not a valid MongoDB database name", field, name));
+    }
+}
+
+fn check_positive<T: PartialOrd + Default + Display>(field: &str, value: Option<T>, problems: &mut Vec<String>) {
+    if let Some(value) = value {
+        if value <= T::default() {
+            problems.push(format!("{} must be positive, got {}", field, value));
+        }
+    }
+}
+
+impl AppConfig {
+    /// Resolves every layer and validates the result.
+    pub fn load(args: &ConfigArgs) -> Result<AppConfig, ConfigError> {
+        let path = match &args.config {
+            Some(path) => Some(path.clone()),
+            None => env_value::<PathBuf>("TRADING_SIM_CONFIG")?,
+        };
+        let mut config = match path {
+            Some(path) => AppConfig::from_file(&path)?,
+            None if Path::new(DEFAULT_CONFIG_FILE).is_file() => AppConfig::from_file(Path::new(DEFAULT_CONFIG_FILE))?,
+            None => AppConfig::default(),
+        };
+        config.apply_env()?;
+        config.apply_args(args);
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_file(path: &Path) -> Result<AppConfig, ConfigError> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|source| ConfigError::Read { path: path.display().to_string(), source })?;
+        toml::from_str(&text).map_err(|source| ConfigError::Parse { path: path.display().to_string(), source })
+    }
+
+    fn apply_env(&mut self) -> Result<(), ConfigError> {
+        override_with(&mut self.server.bind_address, env_value("TRADING_SIM_BIND_ADDRESS")?);
+        override_with(&mut self.mongo.uri, env_value("MONGO_URI")?);
+        override_with(&mut self.mongo.app_database, env_value("MONGO_DB_NAME")?);
+        override_with(&mut self.mongo.stock_database, env_value("MONGO_STOCK_DB_NAME")?);
+        override_optional(&mut self.mongo.min_pool_size, env_value("MONGO_MIN_POOL_SIZE")?);
+        override_optional(&mut self.mongo.max_pool_size, env_value("MONGO_MAX_POOL_SIZE")?);
+        override_optional(&mut self.mongo.connect_timeout_ms, env_value("MONGO_CONNECT_TIMEOUT_MS")?);
+        override_optional(&mut self.mongo.server_selection_timeout_ms, env_value("MONGO_SERVER_SELECTION_TIMEOUT_MS")?);
+        override_optional(&mut self.mongo.max_idle_time_ms, env_value("MONGO_MAX_IDLE_TIME_MS")?);
+        override_with(&mut self.sentry.dsn, env_value("SENTRY_DSN")?);
+        override_optional(&mut self.sentry.environment, env_value("SENTRY_ENVIRONMENT")?);
+        override_with(&mut self.trading.starting_balance, env_value("TRADING_SIM_STARTING_BALANCE")?);
+        override_with(&mut self.auth.token_secret, env_value("TRADING_SIM_TOKEN_SECRET")?);
+        override_with(&mut self.auth.token_ttl_hours, env_value("TRADING_SIM_TOKEN_TTL_HOURS")?);
+        override_with(&mut self.auth.operator_token, env_value("TRADING_SIM_OPERATOR_TOKEN")?);
+        override_with(&mut self.health.max_data_age_hours, env_value("HEALTH_MAX_DATA_AGE_HOURS")?);
+        Ok(())
+    }
+
+    fn apply_args(&mut self, args: &ConfigArgs) {
+        override_with(&mut self.server.bind_address, args.bind_address.clone());
+        override_with(&mut self.mongo.uri, args.mongo_uri.clone());
+        override_with(&mut self.mongo.app_database, args.app_database.clone());
+        override_with(&mut self.mongo.stock_database, args.stock_database.clone());
+        override_with(&mut self.sentry.dsn, args.sentry_dsn.clone());
+        override_with(&mut self.trading.starting_balance, args.starting_balance);
+    }
+
+    /// Reports every problem at once rather than stopping at the first.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems = Vec::new();
+
+        if self.server.bind_address.to_socket_addrs().map_or(true, |mut addrs| addrs.next().is_none()) {
+            problems.push(format!("server.bind_address '{}' is not a host:port address", self.server.bind_address));
+        }
+
+        let mongo = &self.mongo;
+        if !(mongo.uri.starts_with("mongodb://") || mongo.uri.starts_with("mongodb+srv://")) {
+            problems.push(format!("mongo.uri '{}' must start with mongodb:// or mongodb+srv://", mongo.uri));
+        }
+        check_database_name("mongo.app_database", &mongo.app_database, &mut problems);
+        check_database_name("mongo.stock_database", &mongo.stock_database, &mut problems);
+        if mongo.app_database == mongo.stock_database {
+            problems.push("mongo.app_database and mongo.stock_database must differ".to_string());
+        }
+        check_positive("mongo.max_pool_size", mongo.max_pool_size, &mut problems);
+        if let (Some(min), Some(max)) = (mongo.min_pool_size, mongo.max_pool_size) {
+            if min > max {
+                problems.push(format!("mongo.min_pool_size {} exceeds mongo.max_pool_size {}", min, max));
+            }
+        }
+        check_positive("mongo.connect_timeout_ms", mongo.connect_timeout_ms, &mut problems);
+        check_positive("mongo.server_selection_timeout_ms", mongo.server_selection_timeout_ms, &mut problems);
+        check_positive("mongo.max_idle_time_ms", mongo.max_idle_time_ms, &mut problems);
+
+        if !self.sentry.dsn.is_empty() && self.sentry.dsn.parse::<sentry::types::Dsn>().is_err() {
+            problems.push("sentry.dsn is not a valid Sentry DSN".to_string());
+        }
+
+        let balance = self.trading.starting_balance;
+        if !balance.is_finite() || balance < 0.0 {
+            problems.push(format!("trading.starting_balance must be a non-negative amount, got {}", balance));
+        }
+
+        check_positive("auth.token_ttl_hours", Some(self.auth.token_ttl_hours), &mut problems);
+        check_positive("health.max_data_age_hours", Some(self.health.max_data_age_hours), &mut problems);
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(problems))
+        }
+    }
+}
@@ -1 +1,2 @@
 pub mod mongo;
+pub mod price_store;
@@ -1,6 +1,182 @@
-use mongodb::{Client, options::ClientOptions};
+use mongodb::{bson::{doc, Document}, error::{ErrorKind, WriteFailure}, options::{ClientOptions, IndexOptions}, Client, Database, IndexModel};
+use log::{error, info};
+use std::time::Duration;
+use crate::config::MongoConfig;
+use crate::db::price_store::PriceStore;
 
-pub async fn init() -> mongodb::error::Result<Client> {
-    let client_options = ClientOptions::parse("mongodb://localhost:27017").await?;
-    Client::with_options(client_options)
+pub const DEFAULT_MONGO_URI: &str = "mongodb://localhost:27017";
+pub const DEFAULT_APP_DATABASE: &str = "trading_simulator";
+pub const DEFAULT_STOCK_DATABASE: &str = "stock_data";
+
+pub const DEFAULT_MAX_POOL_SIZE: u32 = 32;
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+pub const DEFAULT_SERVER_SELECTION_TIMEOUT: Duration = Duration::from_secs(10);
+pub const DEFAULT_MAX_IDLE_TIME: Duration = Duration::from_secs(300);
+
+const INDEX_NOT_FOUND: i32 = 27;
+const DUPLICATE_KEY: i32 = 11000;
+
+/// Whether a write failed on a unique index.
+pub fn is_duplicate_key(err: &mongodb::error::Error) -> bool {
+    matches!(&*err.kind, ErrorKind::Write(WriteFailure::WriteError(write_error)) if write_error.code == DUPLICATE_KEY)
+}
+
+/// Whether a command failed for want of an index, such as a `$text` query
+/// without a text index or dropping an index that is already gone.
+pub fn is_index_not_found(err: &mongodb::error::Error) -> bool {
+    matches!(&*err.kind, ErrorKind::Command(command) if command.code == INDEX_NOT_FOUND)
+}
+
+/// Connection pool sizing and timeouts. Unset fields fall back to the value in
+/// the connection string, then to the `DEFAULT_*` constants.
+#[derive(Debug, Clone, Default)]
+pub struct PoolSettings {
+    pub min_pool_size: Option<u32>,
+    pub max_pool_size: Option<u32>,
+    pub connect_timeout: Option<Duration>,
+    pub server_selection_timeout: Option<Duration>,
+    pub max_idle_time: Option<Duration>,
+}
+
+pub async fn client_options(uri: &str, pool: &PoolSettings) -> mongodb::error::Result<ClientOptions> {
+    let mut options = ClientOptions::parse(uri).await?;
+    options.min_pool_size = pool.min_pool_size.or(options.min_pool_size);
+    options.max_pool_size = pool.max_pool_size.or(options.max_pool_size).or(Some(DEFAULT_MAX_POOL_SIZE));
+    options.connect_timeout = pool.connect_timeout.or(options.connect_timeout).or(Some(DEFAULT_CONNECT_TIMEOUT));
+    options.server_selection_timeout = pool.server_selection_timeout
+        .or(options.server_selection_timeout)
+        .or(Some(DEFAULT_SERVER_SELECTION_TIMEOUT));
+    options.max_idle_time = pool.max_idle_time.or(options.max_idle_time).or(Some(DEFAULT_MAX_IDLE_TIME));
+    Ok(options)
+}
+
+/// The databases the server reads and writes, under their configured names.
+/// Clones share the client's connection pool.
+#[derive(Clone, Debug)]
+pub struct Databases {
+    /// Users, trades, companies and the price store.
+    pub app: Database,
+    /// Legacy per-ticker price collections.
+    pub stock: Database,
+}
+
+impl Databases {
+    pub fn new(client: &Client, config: &MongoConfig) -> Self {
+        Self::with_names(client, &config.app_database, &config.stock_database)
+    }
+
+    pub fn with_names(client: &Client, app_database: &str, stock_database: &str) -> Self {
+        Databases { app: client.database(app_database), stock: client.database(stock_database) }
+    }
+}
+
+/// Builds the one client the whole process shares. The driver pools
+/// connections per client, so services take `&Databases` rather than
+/// connecting.
+pub async fn init(config: &MongoConfig) -> mongodb::error::Result<Databases> {
+    let client = connect_with(&config.uri, &config.pool_settings()).await?;
+    Ok(Databases::new(&client, config))
+}
+
+pub async fn connect_with(uri: &str, pool: &PoolSettings) -> mongodb::error::Result<Client> {
+    let options = client_options(uri, pool).await?;
+    info!(
+        "Connecting to MongoDB with pool size {}..{}",
+        options.min_pool_size.unwrap_or(0),
+        options.max_pool_size.unwrap_or(DEFAULT_MAX_POOL_SIZE),
+    );
+    Client::with_options(options)
+}
+
+/// Fields the screener filters and sorts on.
+const SCREENER_INDEX_PATHS: [&str; 10] = [
+    "financials.marketCap.raw",
+    "financials.trailingPE.raw",
+    "financials.forwardPE.raw",
+    "financials.dividendYield.raw",
+    "financials.dividendRate.raw",
+    "financials.beta.raw",
+    "keyStatistics.pegRatio.raw",
+    "keyStatistics.profitMargins.raw",
+    "keyStatistics.trailingEPS.raw",
+    "keyStatistics.forwardEPS.raw",
+];
+
+/// Creates the indexes the query paths rely on. Failures are logged rather
+/// than fatal so the server still starts against a read-only replica.
+pub async fn ensure_indexes(databases: &Databases) {
+    let companies = databases.app.collection::<Document>("companies");
+
+    let mut indexes = vec![
+        IndexModel::builder().keys(doc! { "ticker": 1 }).build(),
+        // Prefix lookups for typo-tolerant search.
+        IndexModel::builder().keys(doc! { "name": 1 }).build(),
+        IndexModel::builder().keys(doc! { "profile.sector": 1, "profile.industry": 1 }).build(),
+        IndexModel::builder().keys(doc! { "earnings.nextReportDate": 1 }).build(),
+        // Search: names weigh more than business summaries.
+        IndexModel::builder()
+            .keys(doc! { "name": "text", "profile.longBusinessSummary": "text" })
+            .options(IndexOptions::builder()
+                .name("company_search".to_string())
+                .weights(doc! { "name": 10, "profile.longBusinessSummary": 1 })
+                .build())
+            .build(),
+    ];
+    indexes.extend(SCREENER_INDEX_PATHS.iter().map(|path| IndexModel::builder().keys(doc! { *path: 1 }).build()));
+
+    match companies.create_indexes(indexes, None).await {
+        Ok(result) => info!("Ensured {} indexes on companies", result.index_names.len()),
+        Err(err) => error!("Failed to create indexes on companies: {}", err),
+    }
+
+    let corporate_actions = databases.app.collection::<Document>("corporate_actions");
+    let indexes = vec![
+        IndexModel::builder().keys(doc! { "processed": 1, "ex_date": 1 }).build(),
+        IndexModel::builder().keys(doc! { "ticker": 1, "ex_date": -1 }).build(),
+    ];
+    match corporate_actions.create_indexes(indexes, None).await {
+        Ok(result) => info!("Ensured {} indexes on corporate_actions", result.index_names.len()),
+        Err(err) => error!("Failed to create indexes on corporate_actions: {}", err),
+    }
+
+    let ledger = databases.app.collection::<Document>("ledger");
+    let indexes = vec![
+        IndexModel::builder().keys(doc! { "user_id": 1, "created_at": -1 }).build(),
+        // One entry per user, trade and corporate action, even if processing
+        // is retried. Dividend entries have no trade_id, so they are unique
+        // per user.
+        IndexModel::builder()
+            .keys(doc! { "corporate_action_id": 1, "user_id": 1, "trade_id": 1 })
+            .options(IndexOptions::builder()
+                .unique(true)
+                .partial_filter_expression(doc! { "corporate_action_id": { "$type": "objectId" } })
+                .build())
+            .build(),
+    ];
+    match ledger.create_indexes(indexes, None).await {
+        Ok(result) => info!("Ensured {} indexes on ledger", result.index_names.len()),
+        Err(err) => error!("Failed to create indexes on ledger: {}", err),
+    }
+
+    let quarantine = databases.app.collection::<Document>("price_data_quarantine");
+    let index = IndexModel::builder().keys(doc! { "ticker": 1, "quarantined_at": -1 }).build();
+    match quarantine.create_index(index, None).await {
+        Ok(_) => info!("Ensured index on price_data_quarantine"),
+        Err(err) => error!("Failed to create index on price_data_quarantine: {}", err),
+    }
+
+    match PriceStore::new(databases).ensure_collections().await {
+        Ok(()) => info!("Ensured price_bars time-series collection and indexes"),
+        Err(err) => error!("Failed to set up price_bars: {}", err),
+    }
+
+    let data_updates = databases.app.collection::<Document>("data_updates");
+    let indexes = vec![
+        IndexModel::builder().keys(doc! { "ticker": 1 }).options(IndexOptions::builder().unique(true).build()).build(),
+        IndexModel::builder().keys(doc! { "updated_at": 1 }).build(),
+    ];
+    match data_updates.create_indexes(indexes, None).await {
+        Ok(result) => info!("Ensured {} indexes on data_updates", result.index_names.len()),
+        Err(err) => error!("Failed to create indexes on data_updates: {}", err),
+    }
 }
@@ -0,0 +1,417 @@
+//! Price storage on a single MongoDB time-series collection.
+//!
+//! Bars live in `price_bars` in the app database, one document per
+//! (ticker, interval, timestamp). `price_series_catalog` lists which
+//! period/interval combinations are served for each ticker. Readers still get
+//! the legacy document shape (`period`, `interval` and parallel arrays), and
+//! tickers with no catalog entry are read from their legacy per-ticker
+//! collection in the stock database until they are migrated.
+//!
+//! A write stores only the bars that are new or changed. A changed bar's new
+//! version is inserted before the old one is deleted, so readers never miss
+//! a bar; they keep the newest version while both exist. Deleting single
+//! bars from a time-series collection needs MongoDB 7.0.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use futures::stream::{self, StreamExt, TryStreamExt};
+use mongodb::{
+    bson::{doc, from_document, oid::ObjectId, DateTime, Document},
+    options::{CreateCollectionOptions, FindOneOptions, FindOptions, IndexOptions, TimeseriesGranularity, TimeseriesOptions, UpdateOptions},
+    Collection, Database, IndexModel,
+};
+use serde::Serialize;
+use crate::db::mongo::Databases;
+use crate::models::price_bar_models::{BarMeta, PriceBar, SeriesCatalogEntry};
+use crate::models::stock_models::PriceDataDetails;
+use crate::services::data_quality_service::structural_issues;
+
+pub const PRICE_BARS_COLLECTION: &str = "price_bars";
+pub const SERIES_CATALOG_COLLECTION: &str = "price_series_catalog";
+
+#[derive(Clone)]
+pub struct PriceStore {
+    app: Database,
+    legacy: Database,
+}
+
+/// Result of moving one ticker from its legacy collection into the store.
+#[derive(Debug, Default, Serialize)]
+pub struct LegacyMigration {
+    pub ticker: String,
+    pub series: usize,
+    /// Bars written; on a dry run, the distinct bars that would be stored.
+    pub bars: usize,
+    /// Legacy documents left behind because they failed validation.
+    pub skipped: Vec<String>,
+}
+
+/// Builds the legacy period/interval document from bars in time order.
+pub fn legacy_document(ticker: &str, period: &str, interval: &str, bars: &[&PriceBar]) -> Document {
+    doc! {
+        "symbol": ticker,
+        "period": period,
+        "interval": interval,
+        "opens": bars.iter().map(|bar| bar.open).collect::<Vec<_>>(),
+        "highs": bars.iter().map(|bar| bar.high).collect::<Vec<_>>(),
+        "lows": bars.iter().map(|bar| bar.low).collect::<Vec<_>>(),
+        "closes": bars.iter().map(|bar| bar.close).collect::<Vec<_>>(),
+        "timestamps": bars.iter().map(|bar| bar.timestamp.timestamp_millis() / 1000).collect::<Vec<_>>(),
+        "volumes": bars.iter().map(|bar| bar.volume).collect::<Vec<_>>(),
+    }
+}
+
+/// Splits a validated legacy series into bars.
+pub fn bars_from_series(ticker: &str, series: &PriceDataDetails) -> Vec<PriceBar> {
+    let Some(timestamps) = &series.timestamps else { return Vec::new() };
+    let closes = series.closes.as_deref().unwrap_or_default();
+    let value = |values: &Option<Vec<f64>>, index: usize| values.as_ref().and_then(|values| values.get(index)).copied();
+    timestamps
+        .iter()
+        .zip(closes)
+        .enumerate()
+        .map(|(index, (timestamp, &close))| PriceBar {
+            id: None,
+            timestamp: DateTime::from_millis(timestamp.timestamp_millis()),
+            meta: BarMeta::new(ticker, &series.interval),
+            open: value(&series.opens, index).unwrap_or(close),
+            high: value(&series.highs, index).unwrap_or(close),
+            low: value(&series.lows, index).unwrap_or(close),
+            close,
+            volume: series.volumes.as_ref().and_then(|volumes| volumes.get(index)).copied().unwrap_or(0),
+        })
+        .collect()
+}
+
+fn series_filter(ticker: &str, interval: &str) -> Document {
+    doc! { "meta.ticker": ticker, "meta.interval": interval }
+}
+
+/// Keeps the newest stored version of each bar in `bars`, which are sorted
+/// by timestamp. Two versions exist only while a write replaces a bar.
+fn latest_versions(bars: Vec<PriceBar>) -> Vec<PriceBar> {
+    let mut latest: Vec<PriceBar> = Vec::with_capacity(bars.len());
+    for bar in bars {
+        match latest.last_mut() {
+            Some(previous) if previous.timestamp == bar.timestamp => {
+                if bar.id > previous.id {
+                    *previous = bar;
+                }
+            }
+            _ => latest.push(bar),
+        }
+    }
+    latest
+}
+
+fn same_values(a: &PriceBar, b: &PriceBar) -> bool {
+    (a.open, a.high, a.low, a.close, a.volume) == (b.open, b.high, b.low, b.close, b.volume)
+}
+
+impl PriceStore {
+    pub fn new(databases: &Databases) -> Self {
+        PriceStore { app: databases.app.clone(), legacy: databases.stock.clone() }
+    }
+
+    fn bars(&self) -> Collection<PriceBar> {
+        self.app.collection(PRICE_BARS_COLLECTION)
+    }
+
+    fn catalog(&self) -> Collection<SeriesCatalogEntry> {
+        self.app.collection(SERIES_CATALOG_COLLECTION)
+    }
+
+    /// Creates the time-series collection if it does not exist yet, plus the
+    /// indexes the readers rely on.
+    pub async fn ensure_collections(&self) -> mongodb::error::Result<()> {
+        let existing = self.app.list_collection_names(doc! { "name": PRICE_BARS_COLLECTION }).await?;
+        if existing.is_empty() {
+            let timeseries = TimeseriesOptions::builder()
+                .time_field("timestamp".to_string())
+                .meta_field(Some("meta".to_string()))
+                .granularity(Some(TimeseriesGranularity::Minutes))
+                .build();
+            let options = CreateCollectionOptions::builder().timeseries(Some(timeseries)).build();
+            self.app.create_collection(PRICE_BARS_COLLECTION, options).await?;
+        }
+
+        let bars_index = IndexModel::builder().keys(doc! { "meta.ticker": 1, "meta.interval": 1, "timestamp": 1 }).build();
+        self.bars().create_index(bars_index, None).await?;
+        // The newest bar of any ticker, for the readiness check.
+        let newest_index = IndexModel::builder().keys(doc! { "timestamp": -1 }).build();
+        self.bars().create_index(newest_index, None).await?;
+        let catalog_index = IndexModel::builder()
+            .keys(doc! { "ticker": 1, "period": 1, "interval": 1 })
+            .options(IndexOptions::builder().unique(true).build())
+            .build();
+        self.catalog().create_index(catalog_index, None).await?;
+        Ok(())
+    }
+
+    async fn catalog_entries(&self, tickers: &[String]) -> mongodb::error::Result<HashMap<String, Vec<SeriesCatalogEntry>>> {
+        let entries: Vec<SeriesCatalogEntry> = self.catalog().find(doc! { "ticker": { "$in": tickers } }, None).await?.try_collect().await?;
+        let mut by_ticker: HashMap<String, Vec<SeriesCatalogEntry>> = HashMap::new();
+        for entry in entries {
+            by_ticker.entry(entry.ticker.clone()).or_default().push(entry);
+        }
+        Ok(by_ticker)
+    }
+
+    /// Bars of one series in `[from, to]`, oldest first. Either bound may be open.
+    pub async fn bars_in_range(&self, ticker: &str, interval: &str, from: Option<DateTime>, to: Option<DateTime>) -> mongodb::error::Result<Vec<PriceBar>> {
+        let mut range = Document::new();
+        if let Some(from) = from {
+            range.insert("$gte", from);
+        }
+        if let Some(to) = to {
+            range.insert("$lte", to);
+        }
+        let mut filter = series_filter(ticker, interval);
+        if !range.is_empty() {
+            filter.insert("timestamp", range);
+        }
+        let options = FindOptions::builder().sort(doc! { "timestamp": 1 }).build();
+        Ok(latest_versions(self.bars().find(filter, options).await?.try_collect().await?))
+    }
+
+    /// The most recent `count` bars of one series, newest first; a `count`
+    /// of 0 reads them all.
+    async fn newest_bars(&self, ticker: &str, interval: &str, count: i64) -> mongodb::error::Result<Vec<PriceBar>> {
+        let options = FindOptions::builder().sort(doc! { "timestamp": -1 }).limit(count).build();
+        Ok(latest_versions(self.bars().find(series_filter(ticker, interval), options).await?.try_collect().await?))
+    }
+
+    /// Intervals of `ticker` that have bars.
+    async fn intervals(&self, ticker: &str) -> mongodb::error::Result<Vec<String>> {
+        let intervals = self.bars().distinct("meta.interval", doc! { "meta.ticker": ticker }, None).await?;
+        Ok(intervals.into_iter().filter_map(|interval| interval.as_str().map(str::to_string)).collect())
+    }
+
+    /// The most recent `count` bars of `ticker`, newest first, taken from the
+    /// interval that holds the latest bar. Empty if the ticker has no bars.
+    pub async fn latest_bars(&self, ticker: &str, count: i64) -> mongodb::error::Result<Vec<PriceBar>> {
+        let mut latest: Option<Vec<PriceBar>> = None;
+        for interval in self.intervals(ticker).await? {
+            let bars = self.newest_bars(ticker, &interval, count).await?;
+            let newer = |current: &Vec<PriceBar>| bars.first().map(|bar| bar.timestamp) > current.first().map(|bar| bar.timestamp);
+            if latest.as_ref().is_none_or(newer) {
+                latest = Some(bars);
+            }
+        }
+        Ok(latest.unwrap_or_default())
+    }
+
+    /// Legacy-shaped documents for the catalogued series of `ticker`. Each
+    /// interval is read once, over the widest window it is served for.
+    async fn series_documents(&self, ticker: &str, entries: &[SeriesCatalogEntry]) -> mongodb::error::Result<Vec<Document>> {
+        let mut documents = Vec::with_capacity(entries.len());
+        let intervals: BTreeSet<&str> = entries.iter().map(|entry| entry.interval.as_str()).collect();
+
+        for interval in intervals {
+            let periods: Vec<&SeriesCatalogEntry> = entries.iter().filter(|entry| entry.interval == interval).collect();
+            let widest = periods.iter().map(|entry| entry.bars).max().unwrap_or(0);
+            if widest == 0 {
+                continue;
+            }
+            // Newest first, as many as the widest window needs.
+            let bars = self.newest_bars(ticker, interval, widest as i64).await?;
+            if bars.is_empty() {
+                continue;
+            }
+            for entry in periods {
+                let mut selected: Vec<&PriceBar> = bars.iter().take(entry.bars as usize).collect();
+                selected.reverse();
+                documents.push(legacy_document(ticker, &entry.period, interval, &selected));
+            }
+        }
+        Ok(documents)
+    }
+
+    async fn legacy_documents(&self, ticker: &str) -> mongodb::error::Result<Vec<Document>> {
+        self.legacy.collection::<Document>(ticker).find(None, None).await?.try_collect().await
+    }
+
+    /// Every served period/interval document of `ticker`, unvalidated.
+    pub async fn price_documents(&self, ticker: &str) -> mongodb::error::Result<Vec<Document>> {
+        let mut documents = self.price_documents_many(&[ticker.to_string()], 1).await?;
+        Ok(documents.remove(ticker).unwrap_or_default())
+    }
+
+    /// Price documents for several tickers: one catalog query, then at most
+    /// `concurrency` tickers read at a time. Tickers without data map to an
+    /// empty list.
+    pub async fn price_documents_many(&self, tickers: &[String], concurrency: usize) -> mongodb::error::Result<HashMap<String, Vec<Document>>> {
+        let catalog = self.catalog_entries(tickers).await?;
+        stream::iter(tickers.iter().cloned())
+            .map(|ticker| {
+                let entries = catalog.get(&ticker);
+                async move {
+                    let documents = match entries {
+                        Some(entries) => self.series_documents(&ticker, entries).await?,
+                        None => self.legacy_documents(&ticker).await?,
+                    };
+                    Ok::<_, mongodb::error::Error>((ticker, documents))
+                }
+            })
+            .buffer_unordered(concurrency.max(1))
+            .try_collect()
+            .await
+    }
+
+    /// The `period`/`interval` document of each of `tickers` that has one:
+    /// one catalog query, then at most `concurrency` tickers read at a time,
+    /// each reading only that series. Tickers without it are left out.
+    pub async fn series_documents_many(&self, tickers: &[String], period: &str, interval: &str, concurrency: usize) -> mongodb::error::Result<HashMap<String, Document>> {
+        let catalog = self.catalog_entries(tickers).await?;
+        let found: Vec<(String, Option<Document>)> = stream::iter(tickers.iter().cloned())
+            .map(|ticker| {
+                let entries = catalog.get(&ticker);
+                async move {
+                    let document = match entries {
+                        Some(entries) => {
+                            let requested: Vec<SeriesCatalogEntry> = entries
+                                .iter()
+                                .filter(|entry| entry.period == period && entry.interval == interval)
+                                .cloned()
+                                .collect();
+                            self.series_documents(&ticker, &requested).await?.pop()
+                        }
+                        None => self.legacy.collection::<Document>(&ticker).find_one(doc! { "period": period, "interval": interval }, None).await?,
+                    };
+                    Ok::<_, mongodb::error::Error>((ticker, document))
+                }
+            })
+            .buffer_unordered(concurrency.max(1))
+            .try_collect()
+            .await?;
+        Ok(found.into_iter().filter_map(|(ticker, document)| Some((ticker, document?))).collect())
+    }
+
+    /// Merges `bars` into the stored series of `ticker`/`interval`; a new bar
+    /// replaces a stored one with the same timestamp. Only bars that are new
+    /// or differ from the stored ones are written, and the stored series is
+    /// read only over the span of `bars`. Returns the number of bars written.
+    pub async fn upsert_bars(&self, ticker: &str, interval: &str, bars: Vec<PriceBar>) -> mongodb::error::Result<usize> {
+        let incoming: BTreeMap<i64, PriceBar> = bars.into_iter().map(|bar| (bar.timestamp.timestamp_millis(), bar)).collect();
+        let (Some(first), Some(last)) = (incoming.keys().next(), incoming.keys().next_back()) else { return Ok(0) };
+        let stored: HashMap<i64, PriceBar> = self
+            .bars_in_range(ticker, interval, Some(DateTime::from_millis(*first)), Some(DateTime::from_millis(*last)))
+            .await?
+            .into_iter()
+            .map(|bar| (bar.timestamp.timestamp_millis(), bar))
+            .collect();
+
+        let mut replaced = Vec::new();
+        let mut changed = Vec::new();
+        for (timestamp, bar) in incoming {
+            match stored.get(&timestamp) {
+                Some(existing) if same_values(existing, &bar) => continue,
+                Some(_) => replaced.push(bar.timestamp),
+                None => {}
+            }
+            changed.push(bar);
+        }
+        if changed.is_empty() {
+            return Ok(0);
+        }
+        // Ids only grow, so the versions this write replaces are the ones
+        // older than its first.
+        let first_version = ObjectId::new();
+        for (index, bar) in changed.iter_mut().enumerate() {
+            bar.id = Some(if index == 0 { first_version } else { ObjectId::new() });
+            bar.meta = BarMeta::new(ticker, interval);
+        }
+        let count = changed.len();
+        self.bars().insert_many(changed, None).await?;
+
+        if !replaced.is_empty() {
+            let mut filter = series_filter(ticker, interval);
+            filter.insert("timestamp", doc! { "$in": replaced });
+            filter.insert("_id", doc! { "$lt": first_version });
+            self.bars().delete_many(filter, None).await?;
+        }
+        Ok(count)
+    }
+
+    /// Serves `period`/`interval` for `ticker` from the store from now on, as
+    /// at least its latest `bars` bars. A smaller import, such as one new bar
+    /// appended to the series, keeps the window already served. Returns
+    /// whether the series is new to the catalog.
+    pub async fn record_series(&self, ticker: &str, period: &str, interval: &str, bars: usize) -> mongodb::error::Result<bool> {
+        let filter = doc! { "ticker": ticker, "period": period, "interval": interval };
+        let options = UpdateOptions::builder().upsert(true).build();
+        let result = self.catalog().update_one(filter, doc! { "$max": { "bars": bars as i64 } }, options).await?;
+        Ok(result.upserted_id.is_some())
+    }
+
+    /// Stops serving `period`/`interval` for `ticker`. Its bars stay stored
+    /// for the interval's other periods.
+    pub async fn remove_series(&self, ticker: &str, period: &str, interval: &str) -> mongodb::error::Result<()> {
+        self.catalog().delete_one(doc! { "ticker": ticker, "period": period, "interval": interval }, None).await?;
+        Ok(())
+    }
+
+    /// Tickers with at least one series served from the store.
+    pub async fn catalog_tickers(&self) -> mongodb::error::Result<Vec<String>> {
+        let tickers = self.catalog().distinct("ticker", None, None).await?;
+        let mut tickers: Vec<String> = tickers.into_iter().filter_map(|ticker| ticker.as_str().map(str::to_string)).collect();
+        tickers.sort();
+        Ok(tickers)
+    }
+
+    /// Tickers that still have a legacy collection in `stock_data`.
+    pub async fn legacy_tickers(&self) -> mongodb::error::Result<Vec<String>> {
+        let mut tickers = self.legacy.list_collection_names(None).await?;
+        tickers.sort();
+        Ok(tickers)
+    }
+
+    /// Epoch seconds of the newest bar of any ticker in the store. Legacy
+    /// collections are not read. `None` if the store holds no bars.
+    pub async fn newest_timestamp(&self) -> mongodb::error::Result<Option<i64>> {
+        let newest_first = FindOneOptions::builder().sort(doc! { "timestamp": -1 }).build();
+        Ok(self.bars().find_one(None, newest_first).await?.map(|bar| bar.timestamp.timestamp_millis() / 1000))
+    }
+
+    /// Copies every valid legacy series of `ticker` into the store. The
+    /// legacy collection is left in place; `drop_legacy` removes it.
+    pub async fn migrate_legacy_ticker(&self, ticker: &str, dry_run: bool) -> mongodb::error::Result<LegacyMigration> {
+        let mut migration = LegacyMigration { ticker: ticker.to_string(), ..Default::default() };
+        let mut series_keys: Vec<(String, String, usize)> = Vec::new();
+        let mut bars_by_interval: BTreeMap<String, Vec<PriceBar>> = BTreeMap::new();
+
+        for doc in self.legacy_documents(ticker).await? {
+            let label = format!("{}/{}", doc.get_str("period").unwrap_or("?"), doc.get_str("interval").unwrap_or("?"));
+            if let Some(issue) = structural_issues(&doc).first() {
+                migration.skipped.push(format!("{}: {}", label, issue.message));
+                continue;
+            }
+            match from_document::<PriceDataDetails>(doc) {
+                Ok(series) => {
+                    let bars = bars_from_series(ticker, &series);
+                    series_keys.push((series.period.clone(), series.interval.clone(), bars.len()));
+                    bars_by_interval.entry(series.interval).or_default().extend(bars);
+                }
+                Err(err) => migration.skipped.push(format!("{}: {}", label, err)),
+            }
+        }
+
+        migration.series = series_keys.len();
+        for (interval, bars) in bars_by_interval {
+            migration.bars += if dry_run {
+                bars.iter().map(|bar| bar.timestamp.timestamp_millis()).collect::<BTreeSet<_>>().len()
+            } else {
+                self.upsert_bars(ticker, &interval, bars).await?
+            };
+        }
+        if !dry_run {
+            for (period, interval, bars) in &series_keys {
+                self.record_series(ticker, period, interval, *bars).await?;
+            }
+        }
+        Ok(migration)
+    }
+
+    pub async fn drop_legacy(&self, ticker: &str) -> mongodb::error::Result<()> {
+        self.legacy.collection::<Document>(ticker).drop(None).await
+    }
+}
@@ -0,0 +1,157 @@
+//! Errors returned by the HTTP API.
+//!
+//! Every handler error becomes a response with the variant's status code and
+//! a JSON body of the form
+//!
+//! ```json
+//! { "error": { "code": "invalid_id", "message": "Invalid user_id" } }
+//! ```
+//!
+//! `code` is stable and meant for clients to branch on; `message` is for
+//! people and may change. Validation failures also list each rejected field
+//! under `fields`. Database failures are logged in full but reported to the
+//! client only as `database_error`.
+
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use log::error;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use utoipa::ToSchema;
+use crate::repositories::RepositoryError;
+
+#[derive(Debug, Error)]
+pub enum AppError {
+    /// The request body, query string or path could not be parsed.
+    #[error("{0}")]
+    MalformedRequest(String),
+    /// The request parsed but asks for something the endpoint does not offer.
+    #[error("{0}")]
+    BadRequest(String),
+    /// The request parsed but some fields break the model's validation rules.
+    #[error("Request validation failed")]
+    Validation(Vec<FieldError>),
+    /// The named parameter is not a valid ObjectId.
+    #[error("Invalid {0}")]
+    InvalidId(&'static str),
+    #[error("Invalid email or password")]
+    InvalidCredentials,
+    /// The request needs a session or operator token it did not carry.
+    #[error("{0}")]
+    Unauthorized(String),
+    #[error("User not found")]
+    UserNotFound,
+    #[error("{0}")]
+    NotFound(String),
+    #[error("Email already registered")]
+    EmailTaken,
+    /// Data the request depends on is not ready yet; the client may retry.
+    #[error("{0}")]
+    Unavailable(String),
+    #[error("{0}")]
+    Database(#[from] RepositoryError),
+    #[error("{0}")]
+    Internal(String),
+}
+
+pub type AppResult<T> = Result<T, AppError>;
+
+impl From<mongodb::error::Error> for AppError {
+    fn from(err: mongodb::error::Error) -> Self {
+        AppError::Database(err.into())
+    }
+}
+
+impl AppError {
+    /// Stable machine-readable identifier sent as `error.code`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::MalformedRequest(_) => "malformed_request",
+            AppError::BadRequest(_) => "bad_request",
+            AppError::Validation(_) => "validation_failed",
+            AppError::InvalidId(_) => "invalid_id",
+            AppError::InvalidCredentials => "invalid_credentials",
+            AppError::Unauthorized(_) => "unauthorized",
+            AppError::UserNotFound => "user_not_found",
+            AppError::NotFound(_) => "not_found",
+            AppError::EmailTaken => "email_taken",
+            AppError::Unavailable(_) => "unavailable",
+            AppError::Database(_) => "database_error",
+            AppError::Internal(_) => "internal_error",
+        }
+    }
+
+    fn public_message(&self) -> String {
+        match self {
+            AppError::Database(_) => "Internal server error".to_string(),
+            other => other.to_string(),
+        }
+    }
+}
+
+/// The JSON body of every error response.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ErrorResponse {
+    pub error: ErrorBody,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ErrorBody {
+    pub code: String,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<FieldError>,
+}
+
+/// One rejected field. `field` is the JSON path, e.g. `filters[1].min`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct FieldError {
+    pub field: String,
+    pub code: String,
+    pub message: String,
+}
+
+impl ResponseError for AppError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AppError::MalformedRequest(_) | AppError::BadRequest(_) | AppError::InvalidId(_) => StatusCode::BAD_REQUEST,
+            AppError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::InvalidCredentials | AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::UserNotFound | AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::EmailTaken => StatusCode::CONFLICT,
+            AppError::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            AppError::Database(_) | AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        if let AppError::Database(err) = self {
+            error!("Request failed with a database error: {}", err);
+        }
+        HttpResponse::build(self.status_code()).json(ErrorResponse {
+            error: ErrorBody {
+                code: self.code().to_string(),
+                message: self.public_message(),
+                fields: match self {
+                    AppError::Validation(fields) => fields.clone(),
+                    _ => Vec::new(),
+                },
+            },
+        })
+    }
+}
+
+/// JSON body extractor settings that report parse failures in the error envelope.
+pub fn json_config() -> web::JsonConfig {
+    web::JsonConfig::default().error_handler(|err, _| AppError::MalformedRequest(err.to_string()).into())
+}
+
+/// Query string extractor settings that report parse failures in the error envelope.
+pub fn query_config() -> web::QueryConfig {
+    web::QueryConfig::default().error_handler(|err, _| AppError::MalformedRequest(err.to_string()).into())
+}
+
+/// Path extractor settings that report parse failures in the error envelope.
+pub fn path_config() -> web::PathConfig {
+    web::PathConfig::default().error_handler(|err, _| AppError::MalformedRequest(err.to_string()).into())
+}
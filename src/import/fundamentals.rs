@@ -0,0 +1,147 @@
+//! Company rows to `trading_simulator.companies` documents.
+//!
+//! One row per ticker. Only the columns present in a row are written, so a
+//! file with just valuation metrics leaves the stored profile untouched.
+
+use mongodb::{
+    bson::{doc, Bson, Document},
+    options::UpdateOptions,
+};
+use super::{ImportError, ImportReport, Record, RowError};
+use crate::db::mongo::Databases;
+
+/// Profile columns and the stored key under `profile`.
+const PROFILE_FIELDS: [(&str, &str); 7] = [
+    ("address", "address"),
+    ("city", "city"),
+    ("state", "state"),
+    ("country", "country"),
+    ("industry", "industry"),
+    ("sector", "sector"),
+    ("long_business_summary", "longBusinessSummary"),
+];
+
+#[derive(Clone, Copy)]
+enum Format {
+    Decimal,
+    Percent,
+    Large,
+}
+
+/// Metric columns, the stored path and how `fmt` is rendered.
+const METRIC_FIELDS: [(&str, &str, Format); 16] = [
+    ("beta", "financials.beta", Format::Decimal),
+    ("day_high", "financials.dayHigh", Format::Decimal),
+    ("day_low", "financials.dayLow", Format::Decimal),
+    ("dividend_rate", "financials.dividendRate", Format::Decimal),
+    ("dividend_yield", "financials.dividendYield", Format::Percent),
+    ("forward_pe", "financials.forwardPE", Format::Decimal),
+    ("market_cap", "financials.marketCap", Format::Large),
+    ("open", "financials.open", Format::Decimal),
+    ("previous_close", "financials.previousClose", Format::Decimal),
+    ("trailing_pe", "financials.trailingPE", Format::Decimal),
+    ("enterprise_value", "keyStatistics.enterpriseValue", Format::Large),
+    ("forward_eps", "keyStatistics.forwardEPS", Format::Decimal),
+    ("peg_ratio", "keyStatistics.pegRatio", Format::Decimal),
+    ("profit_margins", "keyStatistics.profitMargins", Format::Percent),
+    ("shares_outstanding", "keyStatistics.sharesOutstanding", Format::Large),
+    ("trailing_eps", "keyStatistics.trailingEPS", Format::Decimal),
+];
+
+/// Renders `raw` the way the existing data does: `2.41`, `0.52%`, `2.95T`.
+fn format_metric(raw: f64, format: Format) -> String {
+    match format {
+        Format::Decimal => format!("{:.2}", raw),
+        Format::Percent => format!("{:.2}%", raw * 100.0),
+        Format::Large => {
+            let units = [(1e12, "T"), (1e9, "B"), (1e6, "M"), (1e3, "k")];
+            units
+                .iter()
+                .find(|(size, _)| raw.abs() >= *size)
+                .map(|(size, suffix)| format!("{:.2}{}", raw / size, suffix))
+                .unwrap_or_else(|| format!("{:.0}", raw))
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct CompanyUpdate {
+    pub ticker: String,
+    set: Document,
+}
+
+impl CompanyUpdate {
+    pub fn update(&self) -> Document {
+        doc! { "$set": self.set.clone() }
+    }
+}
+
+fn parse_company(record: &Record) -> Result<CompanyUpdate, String> {
+    let ticker = record.text("ticker").ok_or("ticker is missing")?.to_uppercase();
+    let mut set = doc! { "ticker": &ticker };
+
+    if let Some(name) = record.text("name") {
+        set.insert("name", name);
+    }
+    for (column, key) in PROFILE_FIELDS {
+        if let Some(value) = record.text(column) {
+            set.insert(format!("profile.{}", key), value);
+        }
+    }
+    for (column, path, format) in METRIC_FIELDS {
+        let Some(raw) = record.number(column)? else { continue };
+        // SharesOutstanding stores its raw value as an integer.
+        let raw_value = if column == "shares_outstanding" {
+            if raw < 0.0 || raw.fract() != 0.0 {
+                return Err(format!("{} must be a non-negative whole number", column));
+            }
+            Bson::Int64(raw as i64)
+        } else {
+            Bson::Double(raw)
+        };
+        set.insert(path, doc! { "raw": raw_value, "fmt": format_metric(raw, format) });
+    }
+
+    if set.len() == 1 {
+        return Err("row has no recognised company fields".to_string());
+    }
+    Ok(CompanyUpdate { ticker, set })
+}
+
+/// Validates rows into per-ticker updates. A later row for the same ticker
+/// is rejected rather than silently overriding the first.
+pub fn build_updates(records: &[Record], report: &mut ImportReport) -> Vec<CompanyUpdate> {
+    let mut updates: Vec<CompanyUpdate> = Vec::new();
+    for record in records {
+        report.rows_read += 1;
+        match parse_company(record) {
+            Ok(update) if updates.iter().any(|existing| existing.ticker == update.ticker) => report.errors.push(RowError {
+                row: record.row,
+                message: format!("duplicate row for {}", update.ticker),
+            }),
+            Ok(update) => updates.push(update),
+            Err(message) => report.errors.push(RowError { row: record.row, message }),
+        }
+    }
+
+    report.rows_rejected = report.errors.len();
+    report.documents = updates.len();
+    report.tickers = updates.iter().map(|update| update.ticker.clone()).collect();
+    report.tickers.sort();
+    updates
+}
+
+/// Upserts each company by ticker.
+pub async fn write_updates(databases: &Databases, updates: &[CompanyUpdate], report: &mut ImportReport) -> Result<(), ImportError> {
+    let companies = databases.app.collection::<Document>("companies");
+    for update in updates {
+        let options = UpdateOptions::builder().upsert(true).build();
+        let result = companies.update_one(doc! { "ticker": &update.ticker }, update.update(), options).await?;
+        if result.upserted_id.is_some() {
+            report.inserted += 1;
+        } else {
+            report.updated += 1;
+        }
+    }
+    Ok(())
+}
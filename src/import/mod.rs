@@ -0,0 +1,240 @@
+//! Loading market data files into the `companies` collection and the price store.
+//!
+//! Files are read into loosely typed [`Record`]s by [`readers`], then turned
+//! into the exact document shapes the API deserializes by [`prices`] and
+//! [`fundamentals`].
+
+pub mod fundamentals;
+pub mod prices;
+pub mod readers;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use serde::Serialize;
+use thiserror::Error;
+use crate::repositories::RepositoryError;
+
+#[derive(Debug, Error)]
+pub enum ImportError {
+    #[error("failed to read {path}: {source}")]
+    Io { path: String, source: std::io::Error },
+    #[error("invalid CSV: {0}")]
+    Csv(#[from] csv::Error),
+    #[error("invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("invalid Parquet: {0}")]
+    Parquet(#[from] parquet::errors::ParquetError),
+    #[error("cannot infer the file format of {0}; pass --format")]
+    UnknownFormat(String),
+    #[error(transparent)]
+    Config(#[from] crate::config::ConfigError),
+    #[error("database error: {0}")]
+    Database(#[from] mongodb::error::Error),
+    #[error("{0} rows failed validation")]
+    Invalid(usize),
+}
+
+impl From<RepositoryError> for ImportError {
+    fn from(err: RepositoryError) -> Self {
+        match err {
+            RepositoryError::Database(err) => ImportError::Database(err),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ImportFormat {
+    Csv,
+    Json,
+    Parquet,
+}
+
+impl ImportFormat {
+    pub fn from_path(path: &Path) -> Option<ImportFormat> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "csv" => Some(ImportFormat::Csv),
+            "json" | "jsonl" | "ndjson" => Some(ImportFormat::Json),
+            "parquet" | "pq" => Some(ImportFormat::Parquet),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RawValue {
+    Null,
+    Integer(i64),
+    Number(f64),
+    Text(String),
+}
+
+/// One input row with normalised (lower-case, snake_case) column names.
+#[derive(Debug, Default)]
+pub struct Record {
+    /// 1-based row number in the source file, for error reporting.
+    pub row: usize,
+    pub values: HashMap<String, RawValue>,
+}
+
+/// Column names accepted in place of the canonical ones.
+const ALIASES: [(&str, &str); 6] = [
+    ("symbol", "ticker"),
+    ("date", "timestamp"),
+    ("datetime", "timestamp"),
+    ("time", "timestamp"),
+    ("adj_close", "adjusted_close"),
+    ("company_name", "name"),
+];
+
+pub fn normalize_column(name: &str) -> String {
+    // camelCase boundaries only: `forwardPE` becomes `forward_pe`, not `forward_p_e`.
+    let mut normalized = String::with_capacity(name.len());
+    let mut previous: Option<char> = None;
+    for c in name.trim().chars() {
+        if c.is_uppercase() && previous.is_some_and(|p| p.is_lowercase() || p.is_ascii_digit()) {
+            normalized.push('_');
+        }
+        match c {
+            ' ' | '-' | '.' => normalized.push('_'),
+            _ => normalized.extend(c.to_lowercase()),
+        }
+        previous = Some(c);
+    }
+    ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == normalized)
+        .map(|(_, canonical)| canonical.to_string())
+        .unwrap_or(normalized)
+}
+
+/// Parses a number written with a `.` decimal point. Commas are read as
+/// thousands separators only where nothing else fits: in groups of three,
+/// and with either a decimal point (`1,500.25`) or more than one group
+/// (`1,500,000`). `1,5` and `1,500` could be decimal commas, so they are
+/// rejected rather than guessed at.
+fn parse_number(text: &str) -> Result<f64, &'static str> {
+    let text = text.trim();
+    if !text.contains(',') {
+        return text.parse().map_err(|_| "is not a number");
+    }
+    let (integer, fraction) = match text.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (text, None),
+    };
+    let digits = integer.strip_prefix(['-', '+']).unwrap_or(integer);
+    let groups: Vec<&str> = digits.split(',').collect();
+    let all_digits = |group: &str| group.bytes().all(|b| b.is_ascii_digit());
+    if fraction.is_none() && groups.len() == 2 && groups.iter().all(|group| !group.is_empty() && all_digits(group)) {
+        return Err("uses an ambiguous ',' separator; write it without thousands separators and with '.' as the decimal point");
+    }
+    let grouped = (1..=3).contains(&groups[0].len())
+        && groups.iter().all(|group| all_digits(group))
+        && groups[1..].iter().all(|group| group.len() == 3)
+        && fraction.is_none_or(|fraction| !fraction.is_empty() && all_digits(fraction));
+    if !grouped {
+        return Err("is not a number");
+    }
+    text.replace(',', "").parse().map_err(|_| "is not a number")
+}
+
+impl Record {
+    pub fn new(row: usize) -> Self {
+        Record { row, values: HashMap::new() }
+    }
+
+    pub fn insert(&mut self, column: &str, value: RawValue) {
+        self.values.insert(normalize_column(column), value);
+    }
+
+    fn value(&self, column: &str) -> Option<&RawValue> {
+        self.values.get(column).filter(|value| match value {
+            RawValue::Null => false,
+            RawValue::Text(text) => !text.trim().is_empty(),
+            _ => true,
+        })
+    }
+
+    pub fn text(&self, column: &str) -> Option<String> {
+        match self.value(column)? {
+            RawValue::Text(text) => Some(text.trim().to_string()),
+            RawValue::Integer(value) => Some(value.to_string()),
+            RawValue::Number(value) => Some(value.to_string()),
+            RawValue::Null => None,
+        }
+    }
+
+    /// `Ok(None)` when the column is absent or empty, `Err` when it holds
+    /// something that is not a finite number.
+    pub fn number(&self, column: &str) -> Result<Option<f64>, String> {
+        let value = match self.value(column) {
+            None => return Ok(None),
+            Some(RawValue::Integer(value)) => *value as f64,
+            Some(RawValue::Number(value)) => *value,
+            Some(RawValue::Text(text)) => parse_number(text).map_err(|problem| format!("{} {}: '{}'", column, problem, text))?,
+            Some(RawValue::Null) => return Ok(None),
+        };
+        if value.is_finite() {
+            Ok(Some(value))
+        } else {
+            Err(format!("{} is not finite", column))
+        }
+    }
+
+    /// Accepts epoch seconds or milliseconds, RFC 3339, `YYYY-MM-DD HH:MM:SS`
+    /// and `YYYY-MM-DD` (midnight UTC).
+    pub fn timestamp(&self, column: &str) -> Result<Option<DateTime<Utc>>, String> {
+        let epoch = |value: i64| {
+            let seconds = if value.abs() >= 100_000_000_000 { value / 1000 } else { value };
+            Utc.timestamp_opt(seconds, 0).single().ok_or_else(|| format!("{} is out of range", column))
+        };
+        match self.value(column) {
+            None | Some(RawValue::Null) => Ok(None),
+            Some(RawValue::Integer(value)) => epoch(*value).map(Some),
+            Some(RawValue::Number(value)) => epoch(*value as i64).map(Some),
+            Some(RawValue::Text(text)) => {
+                let text = text.trim();
+                if let Ok(value) = text.parse::<i64>() {
+                    return epoch(value).map(Some);
+                }
+                if let Ok(datetime) = DateTime::parse_from_rfc3339(text) {
+                    return Ok(Some(datetime.with_timezone(&Utc)));
+                }
+                if let Ok(datetime) = NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S") {
+                    return Ok(Some(datetime.and_utc()));
+                }
+                NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                    .ok()
+                    .and_then(|date| date.and_hms_opt(0, 0, 0))
+                    .map(|datetime| Some(datetime.and_utc()))
+                    .ok_or_else(|| format!("{} is not a timestamp: '{}'", column, text))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct RowError {
+    pub row: usize,
+    pub message: String,
+}
+
+impl fmt::Display for RowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "row {}: {}", self.row, self.message)
+    }
+}
+
+/// What an import did, or would do under `--dry-run`.
+#[derive(Debug, Default, Serialize)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub rows_read: usize,
+    pub rows_rejected: usize,
+    pub documents: usize,
+    pub inserted: u64,
+    pub updated: u64,
+    pub tickers: Vec<String>,
+    pub errors: Vec<RowError>,
+}
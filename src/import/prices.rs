@@ -0,0 +1,146 @@
+//! OHLCV rows to bars in the price store.
+//!
+//! Each input row is one bar. Rows are grouped by (ticker, period, interval)
+//! and every group is merged into the `price_bars` time-series collection,
+//! replacing stored bars with the same timestamp.
+
+use std::collections::BTreeMap;
+use mongodb::bson::DateTime;
+use crate::repositories::PriceRepository;
+use crate::models::price_bar_models::{BarMeta, PriceBar};
+use super::{ImportError, ImportReport, Record, RowError};
+
+/// Values used for rows that do not carry a `period` or `interval` column.
+#[derive(Debug, Default)]
+pub struct SeriesDefaults {
+    pub period: Option<String>,
+    pub interval: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Bar {
+    row: usize,
+    timestamp: i64,
+    open: f64,
+    high: f64,
+    low: f64,
+    close: f64,
+    volume: i64,
+}
+
+#[derive(Debug)]
+pub struct PriceSeries {
+    pub ticker: String,
+    pub period: String,
+    pub interval: String,
+    bars: Vec<Bar>,
+}
+
+fn required(record: &Record, column: &str) -> Result<f64, String> {
+    record.number(column)?.ok_or_else(|| format!("{} is missing", column))
+}
+
+fn parse_bar(record: &Record) -> Result<Bar, String> {
+    let timestamp = record.timestamp("timestamp")?.ok_or("timestamp is missing")?;
+    let open = required(record, "open")?;
+    let high = required(record, "high")?;
+    let low = required(record, "low")?;
+    let close = required(record, "close")?;
+    let volume = record.number("volume")?.unwrap_or(0.0);
+
+    if [open, high, low, close].iter().any(|price| *price <= 0.0) {
+        return Err("prices must be positive".to_string());
+    }
+    if high < low {
+        return Err(format!("high {} is below low {}", high, low));
+    }
+    if open > high || open < low || close > high || close < low {
+        return Err("open and close must lie between low and high".to_string());
+    }
+    if volume < 0.0 || volume.fract() != 0.0 {
+        return Err(format!("volume must be a non-negative whole number, got {}", volume));
+    }
+
+    Ok(Bar { row: record.row, timestamp: timestamp.timestamp(), open, high, low, close, volume: volume as i64 })
+}
+
+/// Validates and groups rows. Rejected rows are recorded in `report` and left out.
+pub fn build_series(records: &[Record], defaults: &SeriesDefaults, report: &mut ImportReport) -> Vec<PriceSeries> {
+    let mut groups: BTreeMap<(String, String, String), Vec<Bar>> = BTreeMap::new();
+
+    for record in records {
+        report.rows_read += 1;
+        let key = (
+            record.text("ticker").map(|ticker| ticker.to_uppercase()),
+            record.text("period").or_else(|| defaults.period.clone()),
+            record.text("interval").or_else(|| defaults.interval.clone()),
+        );
+        let parsed = match key {
+            (Some(ticker), Some(period), Some(interval)) => parse_bar(record).map(|bar| ((ticker, period, interval), bar)),
+            (None, _, _) => Err("ticker is missing".to_string()),
+            (_, None, _) => Err("period is missing; add a column or pass --period".to_string()),
+            (_, _, None) => Err("interval is missing; add a column or pass --interval".to_string()),
+        };
+        match parsed {
+            Ok((key, bar)) => groups.entry(key).or_default().push(bar),
+            Err(message) => report.errors.push(RowError { row: record.row, message }),
+        }
+    }
+
+    let mut series = Vec::with_capacity(groups.len());
+    for ((ticker, period, interval), mut bars) in groups {
+        bars.sort_by_key(|bar| (bar.timestamp, bar.row));
+        let mut unique: Vec<Bar> = Vec::with_capacity(bars.len());
+        for bar in bars {
+            match unique.last() {
+                Some(previous) if previous.timestamp == bar.timestamp => report.errors.push(RowError {
+                    row: bar.row,
+                    message: format!("duplicate timestamp {} for {} {}/{} (first seen on row {})", bar.timestamp, ticker, period, interval, previous.row),
+                }),
+                _ => unique.push(bar),
+            }
+        }
+        if !unique.is_empty() {
+            series.push(PriceSeries { ticker, period, interval, bars: unique });
+        }
+    }
+
+    report.errors.sort_by_key(|err| err.row);
+    report.rows_rejected = report.errors.len();
+    report.documents = series.len();
+    report.tickers = series.iter().map(|s| s.ticker.clone()).collect();
+    report.tickers.dedup();
+    series
+}
+
+impl PriceSeries {
+    /// The series as bars for the time-series store.
+    pub fn to_bars(&self) -> Vec<PriceBar> {
+        self.bars
+            .iter()
+            .map(|bar| PriceBar {
+                id: None,
+                timestamp: DateTime::from_millis(bar.timestamp * 1000),
+                meta: BarMeta::new(&self.ticker, &self.interval),
+                open: bar.open,
+                high: bar.high,
+                low: bar.low,
+                close: bar.close,
+                volume: bar.volume,
+            })
+            .collect()
+    }
+}
+
+/// Merges each series into the price store and serves its period from then on.
+pub async fn write_series(prices: &dyn PriceRepository, series: &[PriceSeries], report: &mut ImportReport) -> Result<(), ImportError> {
+    for series in series {
+        prices.merge_bars(&series.ticker, &series.interval, series.to_bars()).await?;
+        if prices.record_series(&series.ticker, &series.period, &series.interval, series.bars.len()).await? {
+            report.inserted += 1;
+        } else {
+            report.updated += 1;
+        }
+    }
+    Ok(())
+}
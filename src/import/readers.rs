@@ -0,0 +1,111 @@
+use std::fs::File;
+use std::path::Path;
+use parquet::file::reader::SerializedFileReader;
+use parquet::record::Field;
+use serde_json::Value;
+use super::{ImportError, ImportFormat, Record, RawValue};
+
+pub fn read_records(path: &Path, format: ImportFormat) -> Result<Vec<Record>, ImportError> {
+    let open = || File::open(path).map_err(|source| ImportError::Io { path: path.display().to_string(), source });
+    match format {
+        ImportFormat::Csv => read_csv(open()?),
+        ImportFormat::Json => {
+            let contents = std::fs::read_to_string(path)
+                .map_err(|source| ImportError::Io { path: path.display().to_string(), source })?;
+            read_json(&contents)
+        }
+        ImportFormat::Parquet => read_parquet(open()?),
+    }
+}
+
+pub fn read_csv<R: std::io::Read>(reader: R) -> Result<Vec<Record>, ImportError> {
+    let mut reader = csv::ReaderBuilder::new().trim(csv::Trim::All).from_reader(reader);
+    let headers = reader.headers()?.clone();
+
+    let mut records = Vec::new();
+    for (i, row) in reader.records().enumerate() {
+        let row = row?;
+        // Header is row 1.
+        let mut record = Record::new(i + 2);
+        for (column, value) in headers.iter().zip(row.iter()) {
+            record.insert(column, RawValue::Text(value.to_string()));
+        }
+        records.push(record);
+    }
+    Ok(records)
+}
+
+fn json_value(value: &Value) -> RawValue {
+    match value {
+        Value::Null => RawValue::Null,
+        Value::Bool(value) => RawValue::Text(value.to_string()),
+        Value::Number(number) => number.as_i64()
+            .map(RawValue::Integer)
+            .unwrap_or_else(|| RawValue::Number(number.as_f64().unwrap_or(f64::NAN))),
+        Value::String(text) => RawValue::Text(text.clone()),
+        other => RawValue::Text(other.to_string()),
+    }
+}
+
+/// Accepts either a JSON array of flat objects or newline-delimited objects.
+pub fn read_json(contents: &str) -> Result<Vec<Record>, ImportError> {
+    let rows: Vec<Value> = if contents.trim_start().starts_with('[') {
+        serde_json::from_str(contents)?
+    } else {
+        contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()?
+    };
+
+    Ok(rows
+        .iter()
+        .enumerate()
+        .map(|(i, row)| {
+            let mut record = Record::new(i + 1);
+            if let Value::Object(fields) = row {
+                for (column, value) in fields {
+                    record.insert(column, json_value(value));
+                }
+            }
+            record
+        })
+        .collect())
+}
+
+fn parquet_value(field: &Field) -> RawValue {
+    match field {
+        Field::Null => RawValue::Null,
+        Field::Byte(v) => RawValue::Integer(*v as i64),
+        Field::Short(v) => RawValue::Integer(*v as i64),
+        Field::Int(v) => RawValue::Integer(*v as i64),
+        Field::Long(v) => RawValue::Integer(*v),
+        Field::UByte(v) => RawValue::Integer(*v as i64),
+        Field::UShort(v) => RawValue::Integer(*v as i64),
+        Field::UInt(v) => RawValue::Integer(*v as i64),
+        Field::ULong(v) => RawValue::Integer(*v as i64),
+        Field::Float(v) => RawValue::Number(*v as f64),
+        Field::Double(v) => RawValue::Number(*v),
+        Field::Float16(v) => RawValue::Number(f64::from(*v)),
+        Field::Str(v) => RawValue::Text(v.clone()),
+        Field::Date(days) => RawValue::Integer(*days as i64 * 86_400),
+        Field::TimestampMillis(v) => RawValue::Integer(*v / 1000),
+        Field::TimestampMicros(v) => RawValue::Integer(*v / 1_000_000),
+        other => RawValue::Text(other.to_string()),
+    }
+}
+
+pub fn read_parquet(file: File) -> Result<Vec<Record>, ImportError> {
+    let reader = SerializedFileReader::new(file)?;
+    let mut records = Vec::new();
+    for (i, row) in reader.into_iter().enumerate() {
+        let row = row?;
+        let mut record = Record::new(i + 1);
+        for (column, field) in row.get_column_iter() {
+            record.insert(column, parquet_value(field));
+        }
+        records.push(record);
+    }
+    Ok(records)
+}
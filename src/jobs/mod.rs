@@ -0,0 +1,113 @@
+use std::time::Duration;
+use actix_web::web;
+use log::{error, info};
+use crate::db::mongo::Databases;
+use crate::repositories::Repositories;
+use crate::services::activity_service::ActivityFeed;
+use crate::services::cache_service::MarketDataCache;
+use crate::services::corporate_action_service::process_due_corporate_actions;
+use crate::services::data_quality_service::quarantine_invalid_price_data;
+use crate::services::health_service::HealthMonitor;
+use crate::services::market_service::{compute_snapshot, MarketOverview};
+use crate::services::trade_service::execute_exit_levels;
+
+const MARKET_OVERVIEW_REFRESH_INTERVAL: Duration = Duration::from_secs(300);
+const CORPORATE_ACTION_INTERVAL: Duration = Duration::from_secs(900);
+const EXIT_LEVEL_INTERVAL: Duration = Duration::from_secs(60);
+const DATA_QUALITY_SCAN_INTERVAL: Duration = Duration::from_secs(3600);
+const CACHE_INVALIDATION_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Names the jobs are reported under by `/health/ready`.
+pub const MARKET_OVERVIEW_REFRESH: &str = "market_overview_refresh";
+pub const CORPORATE_ACTION_PROCESSOR: &str = "corporate_action_processor";
+pub const EXIT_LEVEL_MONITOR: &str = "exit_level_monitor";
+pub const DATA_QUALITY_SCAN: &str = "data_quality_scan";
+pub const CACHE_INVALIDATION: &str = "cache_invalidation";
+
+/// Recomputes the movers snapshot; the first run happens immediately.
+pub fn spawn_market_overview_refresh(repositories: Repositories, overview: web::Data<MarketOverview>, monitor: web::Data<HealthMonitor>) {
+    monitor.register(MARKET_OVERVIEW_REFRESH, MARKET_OVERVIEW_REFRESH_INTERVAL);
+    actix_web::rt::spawn(async move {
+        let mut interval = actix_web::rt::time::interval(MARKET_OVERVIEW_REFRESH_INTERVAL);
+        loop {
+            interval.tick().await;
+            let result = compute_snapshot(&repositories).await;
+            monitor.record(MARKET_OVERVIEW_REFRESH, &result);
+            match result {
+                Ok(snapshot) => overview.replace(snapshot),
+                Err(err) => error!("Market overview refresh failed: {}", err),
+            }
+        }
+    });
+}
+
+/// Applies corporate actions (dividends and splits) once their ex-date has passed.
+pub fn spawn_corporate_action_processor(repositories: Repositories, feed: web::Data<ActivityFeed>, monitor: web::Data<HealthMonitor>) {
+    monitor.register(CORPORATE_ACTION_PROCESSOR, CORPORATE_ACTION_INTERVAL);
+    actix_web::rt::spawn(async move {
+        let mut interval = actix_web::rt::time::interval(CORPORATE_ACTION_INTERVAL);
+        loop {
+            interval.tick().await;
+            let result = process_due_corporate_actions(&repositories, &feed).await;
+            monitor.record(CORPORATE_ACTION_PROCESSOR, &result);
+            match result {
+                Ok(0) => {}
+                Ok(processed) => info!("Processed {} corporate actions", processed),
+                Err(err) => error!("Corporate action processing failed: {}", err),
+            }
+        }
+    });
+}
+
+/// Closes trades whose take-profit or stop-loss level the latest price has
+/// reached.
+pub fn spawn_exit_level_monitor(repositories: Repositories, feed: web::Data<ActivityFeed>, monitor: web::Data<HealthMonitor>) {
+    monitor.register(EXIT_LEVEL_MONITOR, EXIT_LEVEL_INTERVAL);
+    actix_web::rt::spawn(async move {
+        let mut interval = actix_web::rt::time::interval(EXIT_LEVEL_INTERVAL);
+        loop {
+            interval.tick().await;
+            let result = execute_exit_levels(&repositories, &feed).await;
+            monitor.record(EXIT_LEVEL_MONITOR, &result);
+            match result {
+                Ok(0) => {}
+                Ok(closed) => info!("Closed {} trades at their exit levels", closed),
+                Err(err) => error!("Exit level monitor failed: {}", err),
+            }
+        }
+    });
+}
+
+/// Moves malformed price documents into quarantine so readers skip them.
+pub fn spawn_data_quality_scan(databases: web::Data<Databases>, monitor: web::Data<HealthMonitor>) {
+    monitor.register(DATA_QUALITY_SCAN, DATA_QUALITY_SCAN_INTERVAL);
+    actix_web::rt::spawn(async move {
+        let mut interval = actix_web::rt::time::interval(DATA_QUALITY_SCAN_INTERVAL);
+        loop {
+            interval.tick().await;
+            let result = quarantine_invalid_price_data(&databases).await;
+            monitor.record(DATA_QUALITY_SCAN, &result);
+            match result {
+                Ok(0) => {}
+                Ok(quarantined) => info!("Data quality scan quarantined {} price documents", quarantined),
+                Err(err) => error!("Data quality scan failed: {}", err),
+            }
+        }
+    });
+}
+
+/// Drops cached data for tickers that imports or quarantines have changed.
+pub fn spawn_cache_invalidation(databases: web::Data<Databases>, cache: web::Data<MarketDataCache>, monitor: web::Data<HealthMonitor>) {
+    monitor.register(CACHE_INVALIDATION, CACHE_INVALIDATION_INTERVAL);
+    actix_web::rt::spawn(async move {
+        let mut interval = actix_web::rt::time::interval(CACHE_INVALIDATION_INTERVAL);
+        loop {
+            interval.tick().await;
+            let result = cache.apply_data_updates(&databases).await;
+            monitor.record(CACHE_INVALIDATION, &result);
+            if let Err(err) = result {
+                error!("Cache invalidation poll failed: {}", err);
+            }
+        }
+    });
+}
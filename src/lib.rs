@@ -0,0 +1,12 @@
+pub mod auth;
+pub mod config;
+pub mod error;
+pub mod validation;
+pub mod openapi;
+pub mod routes;
+pub mod db;
+pub mod repositories;
+pub mod models;
+pub mod services;
+pub mod jobs;
+pub mod import;
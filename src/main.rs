@@ -1,45 +1,88 @@
 use actix_web::{web, App, HttpServer, middleware::Logger};
+use clap::Parser;
 use std::io;
-use crate::routes::configure_routes;
-use crate::db::mongo::init;
+use trading_sim_server::auth::SessionTokens;
+use trading_sim_server::config::{AppConfig, ConfigArgs};
+use trading_sim_server::routes::configure_routes;
+use trading_sim_server::db::mongo::{ensure_indexes, init};
+use trading_sim_server::jobs::{
+    spawn_cache_invalidation, spawn_corporate_action_processor, spawn_data_quality_scan, spawn_exit_level_monitor, spawn_market_overview_refresh,
+};
+use trading_sim_server::repositories::Repositories;
+use trading_sim_server::services::activity_service::ActivityFeed;
+use trading_sim_server::services::cache_service::MarketDataCache;
+use trading_sim_server::services::health_service::HealthMonitor;
+use trading_sim_server::services::market_service::MarketOverview;
 use env_logger::Env;
 use sentry::ClientOptions;
 use sentry_actix::Sentry;
-use log::info;
+use log::{error, info};
 
-mod routes;
-mod db;
-mod models;
-mod services;
+#[derive(Parser)]
+#[command(about = "Trading simulator API server")]
+struct Cli {
+    #[command(flatten)]
+    config: ConfigArgs,
+}
 
 #[actix_web::main]
 async fn main() -> io::Result<()> {
     // Initialize env_logger
     env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
 
-    // Initialize Sentry
-    let _guard = sentry::init((
-        "https://9887c9e734c125f670d197754fa7e2a2@o4507585839890432.ingest.de.sentry.io/4507585841463376",
-        ClientOptions {
-            release: sentry::release_name!(),
-            ..Default::default()
-        },
-    ));
+    // Load and validate configuration
+    let config = match AppConfig::load(&Cli::parse().config) {
+        Ok(config) => config,
+        Err(err) => {
+            error!("{}", err);
+            std::process::exit(2);
+        }
+    };
+
+    // Initialize Sentry; an empty DSN disables it
+    let _guard = sentry::init(ClientOptions {
+        dsn: config.sentry.dsn.parse().ok(),
+        environment: config.sentry.environment.clone().map(Into::into),
+        release: sentry::release_name!(),
+        ..Default::default()
+    });
 
     info!("Starting the application.");
 
     // Initialize MongoDB
-    let mongo_data = web::Data::new(init().await.expect("Failed to initialize MongoDB client"));
+    let databases = web::Data::new(init(&config.mongo).await.expect("Failed to initialize MongoDB client"));
+    ensure_indexes(&databases).await;
+    let repositories = Repositories::mongo(&databases);
+    let activity_feed = web::Data::new(ActivityFeed::new());
+    let market_overview = web::Data::new(MarketOverview::new());
+    let market_data_cache = web::Data::new(MarketDataCache::new());
+    let health_monitor = web::Data::new(HealthMonitor::new());
+    let session_tokens = web::Data::new(SessionTokens::from_config(&config.auth));
+    let bind_address = config.server.bind_address.clone();
+    let app_config = web::Data::new(config);
+
+    // Start background jobs
+    spawn_market_overview_refresh(repositories.clone(), market_overview.clone(), health_monitor.clone());
+    spawn_corporate_action_processor(repositories.clone(), activity_feed.clone(), health_monitor.clone());
+    spawn_exit_level_monitor(repositories.clone(), activity_feed.clone(), health_monitor.clone());
+    spawn_data_quality_scan(databases.clone(), health_monitor.clone());
+    spawn_cache_invalidation(databases.clone(), market_data_cache.clone(), health_monitor.clone());
 
     // Start the Actix Web server
     HttpServer::new(move || {
         App::new()
             .wrap(Logger::default())  // Logger middleware
             .wrap(Sentry::new())  // Sentry middleware
-            .app_data(mongo_data.clone())  // Share MongoDB data
+            .app_data(app_config.clone())  // Share validated configuration
+            .configure(|cfg| repositories.configure(cfg))  // Share users, trades, companies, prices and the database probe
+            .app_data(activity_feed.clone())  // Share account activity feed
+            .app_data(market_overview.clone())  // Share cached market movers
+            .app_data(market_data_cache.clone())  // Share company and price cache
+            .app_data(health_monitor.clone())  // Share background job status for readiness
+            .app_data(session_tokens.clone())  // Share the session token key
             .configure(configure_routes)  // Configure routes
     })
-    .bind("127.0.0.1:8080")?
+    .bind(bind_address)?
     .run()
     .await
 }
@@ -0,0 +1,60 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+use crate::models::trade_models::TradeStatus;
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct AccountEvent {
+    pub id: u64,
+    pub user_id: String,
+    pub timestamp: DateTime<Utc>,
+    #[serde(flatten)]
+    pub kind: AccountEventKind,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AccountEventKind {
+    OrderStatus {
+        trade_id: String,
+        ticker: String,
+        status: TradeStatus,
+    },
+    Fill {
+        trade_id: String,
+        ticker: String,
+        quantity: u32,
+        price: f64,
+    },
+    /// The latest price reached the trade's take-profit level and the trade
+    /// was closed at that price.
+    TakeProfitTriggered {
+        trade_id: String,
+        ticker: String,
+        trigger_price: f64,
+        exit_price: f64,
+    },
+    /// Like `TakeProfitTriggered`, for the stop-loss level.
+    StopLossTriggered {
+        trade_id: String,
+        ticker: String,
+        trigger_price: f64,
+        exit_price: f64,
+    },
+    BalanceUpdate {
+        balance: f64,
+        change: f64,
+    },
+}
+
+impl AccountEventKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AccountEventKind::OrderStatus { .. } => "order_status",
+            AccountEventKind::Fill { .. } => "fill",
+            AccountEventKind::TakeProfitTriggered { .. } => "take_profit_triggered",
+            AccountEventKind::StopLossTriggered { .. } => "stop_loss_triggered",
+            AccountEventKind::BalanceUpdate { .. } => "balance_update",
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// Share of lookups served from the cache, from 0 to 1.
+    pub hit_rate: f64,
+    pub evictions: u64,
+    pub invalidations: u64,
+    pub entries: usize,
+    /// Current size of the cache, in `unit`.
+    pub size: usize,
+    /// Largest size the cache grows to, in `unit`.
+    pub capacity: usize,
+    pub unit: CacheUnit,
+    pub ttl_seconds: u64,
+}
+
+/// What a cache's size and capacity count.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheUnit {
+    Entries,
+    Bytes,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CacheStatsResponse {
+    pub companies: CacheStats,
+    pub price_series: CacheStats,
+    pub series: CacheStats,
+    pub listings: CacheStats,
+}
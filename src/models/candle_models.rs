@@ -0,0 +1,41 @@
+use chrono::{DateTime, FixedOffset};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use validator::Validate;
+
+#[derive(Debug, Deserialize, Validate, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CandleQuery {
+    /// Target bar size: `5m`, `1h`, `1d`, `1w`, `1mo`, ... Sub-day sizes must
+    /// divide a day and monthly ones a year.
+    #[validate(length(min = 1, message = "must not be empty"))]
+    pub interval: String,
+    /// IANA time zone used to align bucket boundaries. Defaults to UTC.
+    pub tz: Option<String>,
+    /// Restrict the source series to one stored period.
+    pub period: Option<String>,
+    /// Split-adjusted prices unless `adjusted=false`.
+    pub adjusted: Option<bool>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CandleResponse {
+    pub ticker: String,
+    pub interval: String,
+    pub source_period: String,
+    pub source_interval: String,
+    pub time_zone: String,
+    pub adjusted: bool,
+    pub candles: Vec<Candle>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Candle {
+    /// Start of the bucket in the requested time zone.
+    pub start: DateTime<FixedOffset>,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: i64,
+}
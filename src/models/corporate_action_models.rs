@@ -0,0 +1,100 @@
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::{Validate, ValidationError};
+use crate::openapi::ObjectIdJson;
+use crate::validation::rule_error;
+
+/// Dates are stored as epoch seconds, like the `stock_data` timestamps.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CorporateAction {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<ObjectIdJson>)]
+    pub id: Option<ObjectId>,
+    pub ticker: String,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    #[schema(value_type = i64)]
+    pub ex_date: DateTime<Utc>,
+    #[serde(default, with = "chrono::serde::ts_seconds_option")]
+    #[schema(value_type = Option<i64>)]
+    pub pay_date: Option<DateTime<Utc>>,
+    pub action: CorporateActionKind,
+    pub processed: bool,
+    #[serde(default, with = "chrono::serde::ts_seconds_option")]
+    #[schema(value_type = Option<i64>)]
+    pub processed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CorporateActionKind {
+    Dividend { amount_per_share: f64 },
+    /// `numerator`-for-`denominator`: a 4-for-1 split is 4/1, a 1-for-10
+    /// reverse split is 1/10.
+    Split { numerator: u32, denominator: u32 },
+}
+
+impl CorporateActionKind {
+    /// Shares held after the action per share held before it.
+    pub fn split_ratio(&self) -> Option<f64> {
+        match self {
+            CorporateActionKind::Split { numerator, denominator } => Some(*numerator as f64 / *denominator as f64),
+            CorporateActionKind::Dividend { .. } => None,
+        }
+    }
+}
+
+/// A trade's position after a split.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SplitAdjustment {
+    pub quantity: u32,
+    pub price: f64,
+    pub take_profit: Option<f64>,
+    pub stop_loss: Option<f64>,
+    pub amount: f64,
+    /// Shares the split left over, paid out instead of held.
+    pub fractional_shares: f64,
+    /// Value of `fractional_shares` at the adjusted price, always positive.
+    pub cash_in_lieu: f64,
+}
+
+impl SplitAdjustment {
+    /// A reverse split can leave less than one share, which closes the trade.
+    pub fn closes_trade(&self) -> bool {
+        self.quantity == 0
+    }
+}
+
+fn valid_action(action: &CorporateActionKind) -> Result<(), ValidationError> {
+    match action {
+        CorporateActionKind::Dividend { amount_per_share } if !amount_per_share.is_finite() || *amount_per_share <= 0.0 => {
+            Err(rule_error("dividend_amount", "dividend amount per share must be positive"))
+        }
+        CorporateActionKind::Split { numerator, denominator } if *numerator == 0 || *denominator == 0 || numerator == denominator => {
+            Err(rule_error("split_ratio", "split ratio must be two different positive numbers"))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct CorporateActionPayload {
+    #[validate(length(min = 1, message = "must not be empty"))]
+    pub ticker: String,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    #[schema(value_type = i64)]
+    pub ex_date: DateTime<Utc>,
+    #[serde(default, with = "chrono::serde::ts_seconds_option")]
+    #[schema(value_type = Option<i64>)]
+    pub pay_date: Option<DateTime<Utc>>,
+    #[validate(custom(function = "valid_action"))]
+    pub action: CorporateActionKind,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CorporateActionRecorded {
+    pub message: String,
+    /// Hex id of the new corporate action.
+    pub corporate_action_id: String,
+}
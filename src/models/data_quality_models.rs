@@ -0,0 +1,79 @@
+use chrono::{DateTime, Utc};
+use mongodb::bson::{oid::ObjectId, Document};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueSeverity {
+    /// The series cannot be served as-is and is quarantined.
+    Error,
+    /// Suspicious but usable; reported only.
+    Warning,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    MissingField,
+    NonNumeric,
+    LengthMismatch,
+    NonMonotonicTimestamps,
+    OhlcInconsistent,
+    Gap,
+    Outlier,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DataIssue {
+    pub kind: IssueKind,
+    pub severity: IssueSeverity,
+    /// Position in the series arrays, when the issue is about one point.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<usize>,
+    pub message: String,
+}
+
+/// A price document moved out of `stock_data` because it failed validation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuarantinedDocument {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub ticker: String,
+    pub period: Option<String>,
+    pub interval: Option<String>,
+    pub document: Document,
+    pub issues: Vec<DataIssue>,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub quarantined_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SeriesHealth {
+    pub period: Option<String>,
+    pub interval: Option<String>,
+    pub points: usize,
+    pub first_timestamp: Option<i64>,
+    pub last_timestamp: Option<i64>,
+    pub errors: usize,
+    pub warnings: usize,
+    pub issues: Vec<DataIssue>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct QuarantineSummary {
+    pub period: Option<String>,
+    pub interval: Option<String>,
+    pub quarantined_at: DateTime<Utc>,
+    pub issues: Vec<DataIssue>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DataHealthReport {
+    pub ticker: String,
+    pub checked_at: DateTime<Utc>,
+    /// No stored series has errors. Warnings do not count.
+    pub healthy: bool,
+    pub series: Vec<SeriesHealth>,
+    pub quarantined: Vec<QuarantineSummary>,
+}
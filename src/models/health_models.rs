@@ -0,0 +1,97 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Outcome of a check, ordered from best to worst so a report takes the
+/// worst of its checks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Pass,
+    /// Degraded but still able to serve requests.
+    Warn,
+    /// Not ready; `/health/ready` answers 503.
+    Fail,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct LivenessReport {
+    pub status: HealthStatus,
+    pub version: String,
+    pub started_at: DateTime<Utc>,
+    pub uptime_seconds: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ReadinessReport {
+    /// The worst status of the checks.
+    pub status: HealthStatus,
+    pub checked_at: DateTime<Utc>,
+    pub checks: ReadinessChecks,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ReadinessChecks {
+    pub mongo: DatabaseCheck,
+    pub workers: WorkersCheck,
+    pub data: DataFreshnessCheck,
+}
+
+/// A ping of the MongoDB server.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DatabaseCheck {
+    pub status: HealthStatus,
+    /// Round-trip time, when the ping succeeded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkerState {
+    /// No run has finished yet.
+    Starting,
+    Ok,
+    /// The last run returned an error.
+    Failing,
+    /// No run has finished for several intervals.
+    Stalled,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct WorkerStatus {
+    pub name: String,
+    pub status: HealthStatus,
+    pub state: WorkerState,
+    pub interval_seconds: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_success: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_failure: Option<DateTime<Utc>>,
+    pub consecutive_failures: u32,
+}
+
+/// Background jobs. A failing job only degrades the report; a stalled one
+/// fails it.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct WorkersCheck {
+    pub status: HealthStatus,
+    pub jobs: Vec<WorkerStatus>,
+}
+
+/// Age of the most recent price data of any ticker. Stale data is reported as
+/// a warning only: every instance reads the same database, so taking this
+/// one out of rotation would not help.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DataFreshnessCheck {
+    pub status: HealthStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub newest_price_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub age_seconds: Option<i64>,
+    pub max_age_seconds: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
@@ -0,0 +1,82 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use validator::Validate;
+
+#[derive(Debug, Deserialize, Validate, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct IndicatorQuery {
+    pub period: Option<String>,
+    pub interval: Option<String>,
+    /// Comma separated list, e.g. `sma,rsi,macd`. All indicators when omitted.
+    pub indicators: Option<String>,
+    #[validate(range(min = 1, message = "must be at least 1"))]
+    pub sma_window: Option<usize>,
+    #[validate(range(min = 1, message = "must be at least 1"))]
+    pub ema_window: Option<usize>,
+    #[validate(range(min = 1, message = "must be at least 1"))]
+    pub rsi_window: Option<usize>,
+    /// Must be less than `macd_slow`; 12 and 26 when omitted.
+    #[validate(range(min = 1, message = "must be at least 1"))]
+    pub macd_fast: Option<usize>,
+    #[validate(range(min = 1, message = "must be at least 1"))]
+    pub macd_slow: Option<usize>,
+    #[validate(range(min = 1, message = "must be at least 1"))]
+    pub macd_signal: Option<usize>,
+    #[validate(range(min = 1, message = "must be at least 1"))]
+    pub bollinger_window: Option<usize>,
+    #[validate(range(exclusive_min = 0.0, message = "must be greater than 0"))]
+    pub bollinger_std_dev: Option<f64>,
+    #[validate(range(min = 1, message = "must be at least 1"))]
+    pub atr_window: Option<usize>,
+    #[validate(range(min = 1, message = "must be at least 1"))]
+    pub stochastic_k: Option<usize>,
+    #[validate(range(min = 1, message = "must be at least 1"))]
+    pub stochastic_d: Option<usize>,
+}
+
+/// Every series is aligned with `timestamps`; `null` marks points where the
+/// indicator window has not filled yet.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct IndicatorResponse {
+    pub ticker: String,
+    pub period: String,
+    pub interval: String,
+    pub timestamps: Vec<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sma: Option<Vec<Option<f64>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ema: Option<Vec<Option<f64>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rsi: Option<Vec<Option<f64>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub macd: Option<MacdSeries>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bollinger: Option<BollingerSeries>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub atr: Option<Vec<Option<f64>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vwap: Option<Vec<Option<f64>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stochastic: Option<StochasticSeries>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MacdSeries {
+    pub macd: Vec<Option<f64>>,
+    pub signal: Vec<Option<f64>>,
+    pub histogram: Vec<Option<f64>>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BollingerSeries {
+    pub middle: Vec<Option<f64>>,
+    pub upper: Vec<Option<f64>>,
+    pub lower: Vec<Option<f64>>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct StochasticSeries {
+    pub k: Vec<Option<f64>>,
+    pub d: Vec<Option<f64>>,
+}
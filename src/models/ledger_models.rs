@@ -0,0 +1,54 @@
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use validator::Validate;
+use crate::openapi::ObjectIdJson;
+use crate::validation::object_id;
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct LedgerEntry {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<ObjectIdJson>)]
+    pub id: Option<ObjectId>,
+    #[schema(value_type = ObjectIdJson)]
+    pub user_id: ObjectId,
+    pub kind: LedgerEntryKind,
+    pub ticker: String,
+    /// Signed cash movement: positive credits the user, negative debits.
+    pub amount: f64,
+    /// Net shares the entry was computed on (negative for short holdings).
+    pub quantity: f64,
+    #[schema(value_type = Option<ObjectIdJson>)]
+    pub corporate_action_id: Option<ObjectId>,
+    /// The trade a split adjusted; dividends are paid per user, not per trade.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<ObjectIdJson>)]
+    pub trade_id: Option<ObjectId>,
+    /// False until the amount has been applied to the user's balance.
+    /// Entries written before settlement was tracked were always applied.
+    #[serde(default = "settled_by_default")]
+    pub settled: bool,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    #[schema(value_type = i64)]
+    pub created_at: DateTime<Utc>,
+}
+
+fn settled_by_default() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LedgerEntryKind {
+    Dividend,
+    /// Fractional shares left over by a split, paid out in cash.
+    SplitCashInLieu,
+}
+
+#[derive(Debug, Deserialize, Validate, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LedgerQuery {
+    #[validate(custom(function = "object_id"))]
+    pub user_id: String,
+}
@@ -0,0 +1,72 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use validator::Validate;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum MoverList {
+    Gainers,
+    Losers,
+    Active,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum MoverWindow {
+    Daily,
+    Weekly,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum MoverRank {
+    Percent,
+    Absolute,
+}
+
+#[derive(Debug, Deserialize, Validate, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct MoversQuery {
+    pub list: MoverList,
+    pub window: Option<MoverWindow>,
+    pub rank: Option<MoverRank>,
+    pub sector: Option<String>,
+    #[validate(range(min = 1, max = 100, message = "must be between 1 and 100"))]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, Validate, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct MarketOverviewQuery {
+    pub sector: Option<String>,
+    #[validate(range(min = 1, max = 100, message = "must be between 1 and 100"))]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct MoverEntry {
+    pub ticker: String,
+    pub name: String,
+    pub sector: Option<String>,
+    pub price: f64,
+    pub daily_change: Option<f64>,
+    pub daily_change_percent: Option<f64>,
+    pub weekly_change: Option<f64>,
+    pub weekly_change_percent: Option<f64>,
+    pub volume: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MoversResponse {
+    pub computed_at: DateTime<Utc>,
+    pub results: Vec<MoverEntry>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MarketOverviewResponse {
+    pub computed_at: DateTime<Utc>,
+    pub gainers: Vec<MoverEntry>,
+    pub losers: Vec<MoverEntry>,
+    pub most_active: Vec<MoverEntry>,
+}
@@ -1,3 +1,17 @@
 pub mod users;
 pub mod stock_models;
-pub mod trade_models;
\ No newline at end of file
+pub mod trade_models;
+pub mod activity_models;
+pub mod indicator_models;
+pub mod candle_models;
+pub mod screener_models;
+pub mod search_models;
+pub mod taxonomy_models;
+pub mod market_models;
+pub mod corporate_action_models;
+pub mod ledger_models;
+pub mod data_quality_models;
+pub mod cache_models;
+pub mod price_bar_models;
+pub mod order_models;
+pub mod health_models;
@@ -0,0 +1,93 @@
+//! Trades as exposed by the v2 API: ids are plain hex strings and orders
+//! are submitted without the unused `user_balance` field.
+
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use validator::Validate;
+use crate::models::trade_models::{trade_position, Trade, TradeData, TradeStatus};
+use crate::validation::object_id;
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct OrderRequest {
+    #[validate(length(min = 1, max = 12, message = "must be between 1 and 12 characters"))]
+    pub ticker: String,
+    /// `long`, `buy`, `short` or `sell`.
+    #[validate(custom(function = "trade_position"))]
+    pub position: String,
+    #[validate(range(min = 1, message = "must be at least 1"))]
+    pub quantity: u32,
+    #[validate(range(exclusive_min = 0.0, message = "must be greater than 0"))]
+    pub price: f64,
+    #[validate(range(exclusive_min = 0.0, message = "must be greater than 0"))]
+    pub take_profit: Option<f64>,
+    #[validate(range(exclusive_min = 0.0, message = "must be greater than 0"))]
+    pub stop_loss: Option<f64>,
+    #[validate(custom(function = "object_id"))]
+    pub user_id: String,
+    /// Cash debited from the user's balance.
+    #[validate(range(exclusive_min = 0.0, message = "must be greater than 0"))]
+    pub amount: f64,
+    #[validate(length(min = 1, message = "must not be empty"))]
+    pub trade_type: String,
+}
+
+impl From<OrderRequest> for TradeData {
+    fn from(order: OrderRequest) -> Self {
+        TradeData {
+            ticker: order.ticker,
+            position: order.position,
+            quantity: order.quantity,
+            price: order.price,
+            take_profit: order.take_profit,
+            stop_loss: order.stop_loss,
+            user_id: order.user_id,
+            amount: order.amount,
+            trade_type: order.trade_type,
+            // Sent by v1 clients but never read.
+            user_balance: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct Order {
+    pub id: String,
+    pub user_id: String,
+    pub ticker: String,
+    pub position: String,
+    pub quantity: u32,
+    pub price: f64,
+    pub take_profit: Option<f64>,
+    pub stop_loss: Option<f64>,
+    pub status: TradeStatus,
+    pub amount: f64,
+    pub trade_type: String,
+    /// Set once the order is closed.
+    pub exit_price: Option<f64>,
+}
+
+impl From<Trade> for Order {
+    fn from(trade: Trade) -> Self {
+        Order {
+            id: trade.id.map(|id| id.to_hex()).unwrap_or_default(),
+            user_id: trade.user_id.to_hex(),
+            ticker: trade.ticker,
+            position: trade.position,
+            quantity: trade.quantity,
+            price: trade.price,
+            take_profit: trade.take_profit,
+            stop_loss: trade.stop_loss,
+            status: trade.status,
+            amount: trade.amount,
+            trade_type: trade.trade_type,
+            exit_price: trade.exit_price,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Validate, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct OrderListQuery {
+    #[validate(custom(function = "object_id"))]
+    pub user_id: String,
+}
@@ -0,0 +1,43 @@
+use mongodb::bson::{oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+
+/// Series a bar belongs to; the time-series `metaField`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct BarMeta {
+    pub ticker: String,
+    pub interval: String,
+}
+
+impl BarMeta {
+    pub fn new(ticker: &str, interval: &str) -> Self {
+        BarMeta { ticker: ticker.to_string(), interval: interval.to_string() }
+    }
+}
+
+/// One OHLCV point in the `price_bars` time-series collection.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceBar {
+    /// Orders the versions of a bar while a rewrite briefly stores both.
+    #[serde(rename = "_id", default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub timestamp: DateTime,
+    pub meta: BarMeta,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: i64,
+}
+
+/// A period/interval combination served for a ticker, as the most recent
+/// `bars` bars of the interval: the length of the legacy document or of the
+/// largest import. `1mo`/`1d` and `1y`/`1d` thus read the same bars.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeriesCatalogEntry {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub ticker: String,
+    pub period: String,
+    pub interval: String,
+    pub bars: u64,
+}
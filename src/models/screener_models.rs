@@ -0,0 +1,69 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::{Validate, ValidationError};
+use crate::validation::rule_error;
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct ScreenerPayload {
+    pub sector: Option<String>,
+    pub industry: Option<String>,
+    #[serde(default)]
+    #[validate(nested)]
+    pub filters: Vec<RangeFilter>,
+    pub sort_by: Option<String>,
+    pub sort_order: Option<SortOrder>,
+    pub page: Option<u32>,
+    #[validate(range(min = 1, max = 100, message = "must be between 1 and 100"))]
+    pub items_per_page: Option<u32>,
+}
+
+/// Inclusive range on one screener field; either bound may be omitted.
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[validate(schema(function = "non_empty_range"))]
+pub struct RangeFilter {
+    #[validate(length(min = 1, message = "must not be empty"))]
+    pub field: String,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+fn non_empty_range(range: &RangeFilter) -> Result<(), ValidationError> {
+    match (range.min, range.max) {
+        (Some(min), Some(max)) if min > max => Err(rule_error("empty_range", "min must not be greater than max")),
+        _ => Ok(()),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ScreenerResponse {
+    pub total: u64,
+    pub page: u32,
+    pub items_per_page: u32,
+    pub results: Vec<ScreenerRow>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ScreenerRow {
+    pub ticker: String,
+    pub name: String,
+    pub sector: Option<String>,
+    pub industry: Option<String>,
+    pub market_cap: Option<f64>,
+    pub trailing_pe: Option<f64>,
+    pub forward_pe: Option<f64>,
+    pub dividend_yield: Option<f64>,
+    pub dividend_rate: Option<f64>,
+    pub beta: Option<f64>,
+    pub peg_ratio: Option<f64>,
+    pub profit_margins: Option<f64>,
+    pub trailing_eps: Option<f64>,
+    pub forward_eps: Option<f64>,
+    pub one_month_return: Option<f64>,
+}
@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use validator::Validate;
+
+#[derive(Debug, Deserialize, Validate, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SearchQuery {
+    #[validate(length(min = 1, max = 100, message = "must be between 1 and 100 characters"))]
+    pub q: String,
+    #[validate(range(min = 1, max = 50, message = "must be between 1 and 50"))]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SearchResponse {
+    pub query: String,
+    pub results: Vec<SearchResult>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchMatch {
+    Ticker,
+    Name,
+    Summary,
+    Fuzzy,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SearchResult {
+    pub ticker: String,
+    pub name: String,
+    pub sector: Option<String>,
+    pub industry: Option<String>,
+    pub score: f64,
+    pub matched: SearchMatch,
+}
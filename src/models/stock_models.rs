@@ -1,132 +1,291 @@
-use serde::{Deserialize, Deserializer, Serialize};
+use mongodb::bson::Bson;
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize};
+use utoipa::{IntoParams, ToSchema};
 use chrono::prelude::*;
+use validator::Validate;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Validate, ToSchema)]
 pub struct StockListingPayload {
+    #[validate(required(message = "is required"))]
     pub user_id: Option<String>,
     pub industry: Option<String>,
+    #[validate(length(min = 1, message = "must not be empty"))]
     pub sector: String,
     pub page: Option<u32>,
+    #[validate(range(min = 1, max = 100, message = "must be between 1 and 100"))]
     pub items_per_page: Option<u32>, 
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone, ToSchema)]
 pub struct StockListingResponse {
     pub documents: Vec<StockData>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone, ToSchema)]
 pub struct StockData {
     pub name: String,
     pub ticker: String,
     pub price_data: Vec<PriceData>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone, ToSchema)]
 pub struct PriceData {
     pub date: String,
     pub price: f32,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 
 pub struct StockDetailsResponse {
     pub ticker: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub financials: Option<Financials>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub key_statistics: Option<KeyStatistics>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub price_data: Option<Vec<PriceDataDetails>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub profile: Option<Profile>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_metrics: Option<KeyMetrics>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quote: Option<Quote>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub earnings: Option<EarningsHistory>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// How much of a stock the details endpoint loads and returns.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DetailLevel {
+    /// Profile, key metrics and the latest quote only.
+    Summary,
+    /// Everything except the full price history: only the 1mo/1d series.
+    Standard,
+    /// Every stored price series.
+    Full,
+}
+
+impl DetailLevel {
+    pub fn parse(value: &str) -> Option<DetailLevel> {
+        match value.to_lowercase().as_str() {
+            "summary" => Some(DetailLevel::Summary),
+            "standard" => Some(DetailLevel::Standard),
+            "full" => Some(DetailLevel::Full),
+            _ => None,
+        }
+    }
+
+    pub fn default_fields(&self) -> &'static [&'static str] {
+        match self {
+            DetailLevel::Summary => &["profile", "key_metrics", "quote"],
+            DetailLevel::Standard | DetailLevel::Full => STOCK_DETAIL_FIELDS,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Validate, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct StockQuery {
+    /// `summary`, `standard` or `full` (the default).
+    pub detail_level: Option<String>,
+    /// Comma separated sections to return instead of the level's defaults.
+    pub fields: Option<String>,
+    /// Split-adjusted prices. The default is true on `/api/v2` and false on
+    /// the original endpoint, which keeps serving raw prices.
+    pub adjusted: Option<bool>,
+}
+
+pub const STOCK_DETAIL_FIELDS: &[&str] = &["financials", "key_statistics", "price_data", "profile", "key_metrics", "quote", "earnings"];
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct KeyMetrics {
+    pub market_cap: Option<f64>,
+    pub trailing_pe: Option<f64>,
+    pub forward_pe: Option<f64>,
+    pub dividend_yield: Option<f64>,
+    pub beta: Option<f64>,
+    pub trailing_eps: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct Quote {
+    pub price: f64,
+    pub previous_close: Option<f64>,
+    pub change: Option<f64>,
+    pub change_percent: Option<f64>,
+    pub volume: Option<i64>,
+    pub timestamp: DateTime<Utc>,
+    pub interval: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct Profile {
-    address: Option<String>,
-    city: Option<String>,
-    state: Option<String>,
-    country: Option<String>,
-    industry: Option<String>,
-    sector: Option<String>,
+    pub address: Option<String>,
+    pub city: Option<String>,
+    pub state: Option<String>,
+    pub country: Option<String>,
+    pub industry: Option<String>,
+    pub sector: Option<String>,
     #[serde(rename = "longBusinessSummary")]
-    long_business_summary: Option<String>,
+    pub long_business_summary: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct PriceDataDetails {
-    period: String,
-    interval: String,
-    closes: Option<Vec<f64>>,
-    highs: Option<Vec<f64>>,
-    lows: Option<Vec<f64>>,
-    opens: Option<Vec<f64>>,
+    pub period: String,
+    pub interval: String,
+    pub closes: Option<Vec<f64>>,
+    pub highs: Option<Vec<f64>>,
+    pub lows: Option<Vec<f64>>,
+    pub opens: Option<Vec<f64>>,
     #[serde(deserialize_with = "deserialize_timestamps")]
-    timestamps: Option<Vec<DateTime<Utc>>>,
-    volumes: Option<Vec<i64>>,
+    pub timestamps: Option<Vec<DateTime<Utc>>>,
+    pub volumes: Option<Vec<i64>>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct Financials {
-    beta: Option<Metric>,
+    pub beta: Option<Metric>,
     #[serde(rename = "dayHigh")]
-    day_high: Option<Metric>,
+    pub day_high: Option<Metric>,
     #[serde(rename = "dayLow")]
-    day_low: Option<Metric>,
+    pub day_low: Option<Metric>,
     #[serde(rename = "dividendRate")]
-    dividend_rate: Option<Metric>,
+    pub dividend_rate: Option<Metric>,
     #[serde(rename = "dividendYield")]
-    dividend_yield: Option<Metric>,
+    pub dividend_yield: Option<Metric>,
     #[serde(rename = "forwardPE")]
-    forward_pe: Option<Metric>,
+    pub forward_pe: Option<Metric>,
     #[serde(rename = "marketCap")]
-    market_cap: Option<MarketCap>,
-    open: Option<Metric>,
+    pub market_cap: Option<MarketCap>,
+    pub open: Option<Metric>,
     #[serde(rename = "previousClose")]
-    previous_close: Option<Metric>,
+    pub previous_close: Option<Metric>,
     #[serde(rename = "trailingPE")]
-    trailing_pe: Option<Metric>,
+    pub trailing_pe: Option<Metric>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct Metric {
-    fmt: Option<String>,
-    raw: Option<f64>,
+    pub fmt: Option<String>,
+    pub raw: Option<f64>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct MarketCap {
-    fmt: Option<String>,
-    raw: Option<f64>,
+    pub fmt: Option<String>,
+    pub raw: Option<f64>,
 }
 
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct EarningsHistory {
-    
+    #[serde(default)]
+    pub history: Vec<EarningsReport>,
+    #[serde(rename = "nextReportDate", default, deserialize_with = "deserialize_timestamp")]
+    pub next_report_date: Option<DateTime<Utc>>,
+    #[serde(rename = "nextEpsEstimate")]
+    pub next_eps_estimate: Option<Metric>,
+    #[serde(rename = "nextRevenueEstimate")]
+    pub next_revenue_estimate: Option<Metric>,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct EarningsReport {
+    #[serde(rename = "reportDate", default, deserialize_with = "deserialize_timestamp")]
+    pub report_date: Option<DateTime<Utc>>,
+    #[serde(rename = "fiscalQuarter")]
+    pub fiscal_quarter: Option<String>,
+    #[serde(rename = "epsActual")]
+    pub eps_actual: Option<Metric>,
+    #[serde(rename = "epsEstimate")]
+    pub eps_estimate: Option<Metric>,
+    #[serde(rename = "surprisePercent")]
+    pub surprise_percent: Option<Metric>,
+    pub revenue: Option<Metric>,
+    #[serde(rename = "revenueEstimate")]
+    pub revenue_estimate: Option<Metric>,
+}
+
+impl EarningsReport {
+    /// Surprise in percent of the estimate, derived from the EPS figures when
+    /// the source did not report it.
+    pub fn fill_surprise(&mut self) {
+        if self.surprise_percent.as_ref().and_then(|m| m.raw).is_some() {
+            return;
+        }
+        let actual = self.eps_actual.as_ref().and_then(|m| m.raw);
+        let estimate = self.eps_estimate.as_ref().and_then(|m| m.raw).filter(|e| *e != 0.0);
+        if let (Some(actual), Some(estimate)) = (actual, estimate) {
+            let surprise = (actual - estimate) / estimate.abs() * 100.0;
+            self.surprise_percent = Some(Metric { fmt: Some(format!("{:.2}%", surprise)), raw: Some(surprise) });
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Validate, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct EarningsCalendarQuery {
+    /// Inclusive `YYYY-MM-DD` bounds; defaults to the next two weeks.
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub sector: Option<String>,
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct EarningsCalendarEntry {
+    pub ticker: String,
+    pub name: String,
+    pub sector: Option<String>,
+    pub report_date: DateTime<Utc>,
+    pub eps_estimate: Option<f64>,
+    pub revenue_estimate: Option<f64>,
+    pub last_eps_actual: Option<f64>,
+    pub last_surprise_percent: Option<f64>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct KeyStatistics {
     #[serde(rename = "enterpriseValue")]
-    enterprise_value: Option<EnterpriseValue>,
+    pub enterprise_value: Option<EnterpriseValue>,
     #[serde(rename = "forwardEPS")]
-    forward_eps: Option<Metric>,
+    pub forward_eps: Option<Metric>,
     #[serde(rename = "pegRatio")]
-    peg_ratio: Option<Metric>,
+    pub peg_ratio: Option<Metric>,
     #[serde(rename = "profitMargins")]
-    profit_margins: Option<Metric>,
+    pub profit_margins: Option<Metric>,
     #[serde(rename = "sharesOutstanding")]
-    shares_outstanding: Option<SharesOutstanding>,
+    pub shares_outstanding: Option<SharesOutstanding>,
     #[serde(rename = "trailingEPS")]
-    trailing_eps: Option<Metric>,
+    pub trailing_eps: Option<Metric>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct EnterpriseValue {
-    fmt: Option<String>,
-    raw: Option<f64>,
+    pub fmt: Option<String>,
+    pub raw: Option<f64>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct SharesOutstanding {
-    fmt: Option<String>,
-    raw: Option<i64>,
+    pub fmt: Option<String>,
+    pub raw: Option<i64>,
+}
+
+/// Report dates are stored as epoch seconds by older imports and as BSON
+/// dates by newer ones.
+fn deserialize_timestamp<'de, D>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value: Option<Bson> = Option::deserialize(deserializer)?;
+    match value {
+        None | Some(Bson::Null) => Ok(None),
+        Some(Bson::DateTime(date)) => Ok(Utc.timestamp_millis_opt(date.timestamp_millis()).single()),
+        Some(Bson::Int64(seconds)) => Ok(Utc.timestamp_opt(seconds, 0).single()),
+        Some(Bson::Int32(seconds)) => Ok(Utc.timestamp_opt(seconds.into(), 0).single()),
+        Some(Bson::Double(seconds)) if seconds.fract() == 0.0 => Ok(Utc.timestamp_opt(seconds as i64, 0).single()),
+        Some(other) => Err(D::Error::custom(format!("expected epoch seconds or a date, found {:?}", other.element_type()))),
+    }
 }
 
 fn deserialize_timestamps<'de, D>(deserializer: D) -> Result<Option<Vec<DateTime<Utc>>>, D::Error>
@@ -134,5 +293,5 @@ where
     D: Deserializer<'de>,
 {
     let timestamps: Option<Vec<i64>> = Option::deserialize(deserializer)?;
-    Ok(timestamps.map(|ts| ts.into_iter().map(|t| Utc.timestamp(t, 0)).collect()))
+    Ok(timestamps.map(|ts| ts.into_iter().map(|t| Utc.timestamp_opt(t, 0).single().unwrap_or_default()).collect()))
 }
\ No newline at end of file
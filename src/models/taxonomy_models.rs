@@ -0,0 +1,25 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SectorSummary {
+    pub sector: String,
+    #[serde(flatten)]
+    pub stats: GroupStats,
+    pub industries: Vec<IndustrySummary>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct IndustrySummary {
+    pub industry: String,
+    #[serde(flatten)]
+    pub stats: GroupStats,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct GroupStats {
+    pub company_count: usize,
+    pub total_market_cap: f64,
+    pub median_trailing_pe: Option<f64>,
+    pub average_one_month_return: Option<f64>,
+}
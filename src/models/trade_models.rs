@@ -1,9 +1,15 @@
+use chrono::{DateTime, Utc};
 use mongodb::bson::oid::ObjectId;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::{Validate, ValidationError};
+use crate::openapi::ObjectIdJson;
+use crate::validation::{object_id, rule_error};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct Trade {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<ObjectIdJson>)]
     pub id: Option<ObjectId>,
     pub ticker: String,
     pub position: String,
@@ -12,27 +18,72 @@ pub struct Trade {
     pub take_profit: Option<f64>,
     pub stop_loss: Option<f64>,
     pub status: TradeStatus,
+    #[schema(value_type = ObjectIdJson)]
     pub user_id: ObjectId,
     pub amount: f64,
     pub trade_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_price: Option<f64>,
+    /// When the trade was closed; missing on trades closed before this was
+    /// recorded.
+    #[serde(default, with = "chrono::serde::ts_seconds_option", skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<i64>)]
+    pub closed_at: Option<DateTime<Utc>>,
+    /// Split corporate actions already applied to quantity and prices.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[schema(value_type = Vec<ObjectIdJson>)]
+    pub applied_splits: Vec<ObjectId>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl Trade {
+    pub fn is_short(&self) -> bool {
+        self.position.eq_ignore_ascii_case("short") || self.position.eq_ignore_ascii_case("sell")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
 pub enum TradeStatus {
     InProgress,
     Closed,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Position names accepted on submission; see [`Trade::is_short`].
+pub const TRADE_POSITIONS: &[&str] = &["long", "buy", "short", "sell"];
+
+pub(crate) fn trade_position(position: &str) -> Result<(), ValidationError> {
+    if TRADE_POSITIONS.iter().any(|known| known.eq_ignore_ascii_case(position)) {
+        Ok(())
+    } else {
+        Err(rule_error("position", "must be one of long, buy, short or sell"))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
 pub struct TradeData {
+    #[validate(length(min = 1, max = 12, message = "must be between 1 and 12 characters"))]
     pub ticker: String,
+    #[validate(custom(function = "trade_position"))]
     pub position: String,
+    #[validate(range(min = 1, message = "must be at least 1"))]
     pub quantity: u32,
+    #[validate(range(exclusive_min = 0.0, message = "must be greater than 0"))]
     pub price: f64,
+    #[validate(range(exclusive_min = 0.0, message = "must be greater than 0"))]
     pub take_profit: Option<f64>,
+    #[validate(range(exclusive_min = 0.0, message = "must be greater than 0"))]
     pub stop_loss: Option<f64>,
+    #[validate(custom(function = "object_id"))]
     pub user_id: String,
+    #[validate(range(exclusive_min = 0.0, message = "must be greater than 0"))]
     pub amount: f64,
+    #[validate(length(min = 1, message = "must not be empty"))]
     pub trade_type: String,
     pub user_balance: f64,  // Add this field
 }
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TradeSubmitted {
+    pub message: String,
+    /// Hex id of the new trade.
+    pub trade_id: String,
+}
@@ -1,7 +1,9 @@
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use mongodb::bson::oid::ObjectId;
+use validator::Validate;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct User {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
     pub id: Option<ObjectId>,
@@ -14,33 +16,50 @@ pub struct User {
     pub trades: Vec<ObjectId>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserProfile {
     pub bio: Option<String>,
     pub avatar_url: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserSettings {
     pub theme: String,
     pub notifications: UserNotifications,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserNotifications {
     pub email: bool,
     pub sms: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Login credentials. Passwords are only checked for presence so accounts
+/// created before the registration rules still sign in.
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
 pub struct AuthPayload {
+    #[validate(email(message = "must be a valid email address"))]
     pub email: String,
+    #[validate(length(min = 1, message = "must not be empty"))]
     pub password: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct RegisterPayload {
+    #[validate(email(message = "must be a valid email address"))]
+    pub email: String,
+    /// bcrypt ignores everything past 72 bytes.
+    #[validate(length(min = 8, max = 72, message = "must be between 8 and 72 characters"))]
+    pub password: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct AuthResponse {
     pub success: bool,
     pub message: String,
     pub user_id: Option<String>,
+    /// Session token for endpoints that act as this user, such as the
+    /// activity stream.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
 }
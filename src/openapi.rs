@@ -0,0 +1,121 @@
+//! OpenAPI description of the HTTP API.
+//!
+//! The document is generated from the handlers' `#[utoipa::path]`
+//! attributes and the models' `ToSchema` / `IntoParams` derives, so it
+//! changes whenever a route or a serialized model does. It is served at
+//! `/api/openapi.json` with Swagger UI at `/api/docs/`. `openapi.json` at
+//! the repository root is the reviewed copy; `tests/openapi.rs` fails when
+//! the two differ.
+
+use actix_web::web;
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, Http, HttpAuthScheme, SecurityScheme};
+use utoipa::openapi::OpenApi as OpenApiDocument;
+use utoipa::{Modify, OpenApi, ToSchema};
+use utoipa_swagger_ui::SwaggerUi;
+use crate::auth::SESSION_COOKIE;
+use crate::routes::{
+    activity, auth, cache, candles, corporate_actions, data_health, earnings, health, indicators, market, screener, search,
+    stock_details, stock_listing, taxonomy, trade_route, v2,
+};
+
+pub const SPEC_PATH: &str = "/api/openapi.json";
+
+/// Endpoints under `/api`, with paths relative to it.
+#[derive(OpenApi)]
+#[openapi(paths(
+    auth::login_route,
+    auth::register_route,
+    stock_listing::stock_list_route,
+    stock_details::stock_details_route,
+    screener::screener_route,
+    search::search_route,
+    taxonomy::sectors_route,
+    taxonomy::industries_route,
+    trade_route::submit_trade,
+    trade_route::get_trades,
+    activity::activity_stream,
+    indicators::indicators_route,
+    candles::candles_route,
+    market::movers_route,
+    market::overview_route,
+    earnings::earnings_calendar_route,
+    corporate_actions::create_corporate_action_route,
+    corporate_actions::corporate_actions_route,
+    corporate_actions::ledger_route,
+    data_health::data_health_route,
+    cache::cache_stats_route,
+))]
+struct ApiRoutes;
+
+/// Endpoints under `/api/v2`, with paths relative to it.
+#[derive(OpenApi)]
+#[openapi(paths(
+    v2::stocks::get_stock,
+    v2::orders::create_order,
+    v2::orders::list_orders,
+))]
+struct ApiV2Routes;
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Trading Sim API",
+        description = "Market data and simulated trading. Errors use the envelope described by `ErrorResponse`.",
+    ),
+    paths(health::liveness_route, health::readiness_route),
+    nest(
+        (path = "/api", api = ApiRoutes),
+        (path = "/api/v2", api = ApiV2Routes),
+    ),
+    modifiers(&WithoutLicense, &SecuritySchemes),
+    tags(
+        (name = "auth", description = "Accounts and sign-in"),
+        (name = "stocks", description = "Company listings, details, search and screening"),
+        (name = "trades", description = "Simulated trading"),
+        (name = "activity", description = "Live account events"),
+        (name = "market data", description = "Prices, indicators, movers and earnings"),
+        (name = "corporate actions", description = "Dividends, splits and the resulting cash ledger"),
+        (name = "operations", description = "Data quality and cache diagnostics"),
+        (name = "health", description = "Liveness and readiness probes for orchestrators"),
+        (name = "v2", description = "RESTful stock and order resources; new clients should use these"),
+    ),
+)]
+pub struct ApiDoc;
+
+/// The package declares no license, which would otherwise be rendered as
+/// an empty one.
+struct WithoutLicense;
+
+impl Modify for WithoutLicense {
+    fn modify(&self, openapi: &mut OpenApiDocument) {
+        openapi.info.license = None;
+    }
+}
+
+/// `session_token` is the token returned by login and registration, sent as
+/// a bearer token or, as `session_cookie`, in the cookie they set;
+/// `operator_token` is `auth.operator_token`.
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut OpenApiDocument) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme("session_token", SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)));
+        components.add_security_scheme("session_cookie", SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::new(SESSION_COOKIE))));
+        components.add_security_scheme("operator_token", SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("X-Operator-Token"))));
+    }
+}
+
+/// MongoDB ids serialize as extended JSON, e.g. `{"$oid": "65f1c0..."}`.
+#[derive(ToSchema)]
+#[schema(as = ObjectId)]
+pub struct ObjectIdJson {
+    #[schema(rename = "$oid")]
+    pub oid: String,
+}
+
+/// Serves the document and Swagger UI. Registered ahead of the `/api`
+/// scope, which would otherwise claim these paths.
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(SwaggerUi::new("/api/docs/{_:.*}").url(SPEC_PATH, ApiDoc::openapi()));
+}
@@ -0,0 +1,652 @@
+//! In-process repositories for tests and local runs without MongoDB.
+//!
+//! Companies and price series are stored in the same document shapes the
+//! Mongo repositories return, so fixtures can be written as `doc!` literals.
+
+use std::cmp::{Ordering as CmpOrdering, Reverse};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use async_trait::async_trait;
+use chrono::Utc;
+use mongodb::bson::{oid::ObjectId, Bson, Document};
+use crate::models::corporate_action_models::{CorporateAction, SplitAdjustment};
+use crate::models::data_quality_models::QuarantinedDocument;
+use crate::models::ledger_models::LedgerEntry;
+use crate::models::price_bar_models::PriceBar;
+use crate::models::trade_models::{Trade, TradeStatus};
+use crate::models::users::User;
+use crate::db::price_store::legacy_document;
+use crate::services::stock_service::{bson_to_f64, get_path};
+use super::{
+    CompanyQuery, CompanyRepository, CompanySort, CorporateActionRepository, DatabaseProbe, FieldRange, LatestPrice, PriceRepository, Repositories,
+    RepositoryError, RepositoryResult, TradeRepository, UserRepository,
+};
+
+#[derive(Default)]
+pub struct InMemoryUserRepository {
+    users: Mutex<Vec<User>>,
+    /// (user, ledger entry) pairs applied but not yet released.
+    settling: Mutex<HashSet<(ObjectId, ObjectId)>>,
+}
+
+impl InMemoryUserRepository {
+    pub fn find_by_id(&self, user_id: ObjectId) -> Option<User> {
+        self.users.lock().unwrap().iter().find(|user| user.id == Some(user_id)).cloned()
+    }
+
+    fn update(&self, user_id: ObjectId, apply: impl FnOnce(&mut User)) -> Option<User> {
+        let mut users = self.users.lock().unwrap();
+        let user = users.iter_mut().find(|user| user.id == Some(user_id))?;
+        apply(user);
+        Some(user.clone())
+    }
+}
+
+#[async_trait]
+impl UserRepository for InMemoryUserRepository {
+    async fn find_by_email(&self, email: &str) -> RepositoryResult<Option<User>> {
+        Ok(self.users.lock().unwrap().iter().find(|user| user.email == email).cloned())
+    }
+
+    async fn insert(&self, mut user: User) -> RepositoryResult<ObjectId> {
+        let id = *user.id.get_or_insert_with(ObjectId::new);
+        self.users.lock().unwrap().push(user);
+        Ok(id)
+    }
+
+    async fn debit_for_trade(&self, user_id: ObjectId, trade_id: ObjectId, amount: f64) -> RepositoryResult<Option<User>> {
+        Ok(self.update(user_id, |user| {
+            if !user.trades.contains(&trade_id) {
+                user.trades.push(trade_id);
+            }
+            user.balance -= amount;
+        }))
+    }
+
+    async fn credit(&self, user_id: ObjectId, amount: f64) -> RepositoryResult<Option<User>> {
+        Ok(self.update(user_id, |user| user.balance += amount))
+    }
+
+    async fn apply_ledger_entry(&self, user_id: ObjectId, entry_id: ObjectId, amount: f64) -> RepositoryResult<Option<User>> {
+        let mut settling = self.settling.lock().unwrap();
+        if settling.contains(&(user_id, entry_id)) {
+            return Ok(None);
+        }
+        let user = self.update(user_id, |user| user.balance += amount);
+        if user.is_some() {
+            settling.insert((user_id, entry_id));
+        }
+        Ok(user)
+    }
+
+    async fn release_ledger_entry(&self, user_id: ObjectId, entry_id: ObjectId) -> RepositoryResult<()> {
+        self.settling.lock().unwrap().remove(&(user_id, entry_id));
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryTradeRepository {
+    trades: Mutex<Vec<Trade>>,
+}
+
+impl InMemoryTradeRepository {
+    pub fn all(&self) -> Vec<Trade> {
+        self.trades.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl TradeRepository for InMemoryTradeRepository {
+    async fn insert(&self, mut trade: Trade) -> RepositoryResult<ObjectId> {
+        let id = *trade.id.get_or_insert_with(ObjectId::new);
+        self.trades.lock().unwrap().push(trade);
+        Ok(id)
+    }
+
+    async fn find_by_id(&self, trade_id: ObjectId) -> RepositoryResult<Option<Trade>> {
+        Ok(self.trades.lock().unwrap().iter().find(|trade| trade.id == Some(trade_id)).cloned())
+    }
+
+    async fn find_by_user(&self, user_id: ObjectId) -> RepositoryResult<Vec<Trade>> {
+        Ok(self.trades.lock().unwrap().iter().filter(|trade| trade.user_id == user_id).cloned().collect())
+    }
+
+    async fn close(&self, trade_id: ObjectId, exit_price: f64) -> RepositoryResult<bool> {
+        let mut trades = self.trades.lock().unwrap();
+        match trades.iter_mut().find(|trade| trade.id == Some(trade_id) && trade.status == TradeStatus::InProgress) {
+            Some(trade) => {
+                trade.status = TradeStatus::Closed;
+                trade.exit_price = Some(exit_price);
+                trade.closed_at = Some(Utc::now());
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn with_exit_levels(&self) -> RepositoryResult<Vec<Trade>> {
+        Ok(self.trades.lock().unwrap()
+            .iter()
+            .filter(|trade| trade.status == TradeStatus::InProgress && (trade.take_profit.is_some() || trade.stop_loss.is_some()))
+            .cloned()
+            .collect())
+    }
+
+    async fn held_on(&self, ticker: &str, date: i64) -> RepositoryResult<Vec<Trade>> {
+        let opened_before = |trade: &Trade| trade.id.is_some_and(|id| (id.timestamp().timestamp_millis() / 1000) < date);
+        let open_at = |trade: &Trade| match trade.status {
+            TradeStatus::InProgress => true,
+            TradeStatus::Closed => trade.closed_at.is_some_and(|closed_at| closed_at.timestamp() > date),
+        };
+        Ok(self.trades.lock().unwrap()
+            .iter()
+            .filter(|trade| trade.ticker == ticker && opened_before(trade) && open_at(trade))
+            .cloned()
+            .collect())
+    }
+
+    async fn apply_split(&self, trade_id: ObjectId, action_id: ObjectId, split: &SplitAdjustment) -> RepositoryResult<bool> {
+        let mut trades = self.trades.lock().unwrap();
+        let Some(trade) = trades.iter_mut().find(|trade| trade.id == Some(trade_id) && !trade.applied_splits.contains(&action_id)) else {
+            return Ok(false);
+        };
+        trade.quantity = split.quantity;
+        trade.price = split.price;
+        trade.take_profit = split.take_profit;
+        trade.stop_loss = split.stop_loss;
+        trade.amount = split.amount;
+        if split.closes_trade() {
+            trade.status = TradeStatus::Closed;
+            trade.exit_price = Some(split.price);
+            trade.closed_at = Some(Utc::now());
+        }
+        trade.applied_splits.push(action_id);
+        Ok(true)
+    }
+}
+
+/// Companies in insertion order, which stands in for the natural order
+/// MongoDB pages through.
+#[derive(Default)]
+pub struct InMemoryCompanyRepository {
+    companies: Mutex<Vec<Document>>,
+}
+
+impl InMemoryCompanyRepository {
+    pub fn insert(&self, company: Document) {
+        self.companies.lock().unwrap().push(company);
+    }
+
+    fn matching(&self, query: &CompanyQuery) -> Vec<Document> {
+        let matches = |company: &Document, path: &str, value: &Option<String>| {
+            value.as_deref().is_none_or(|value| get_path(company, path).and_then(Bson::as_str) == Some(value))
+        };
+        let in_range = |company: &Document, range: &FieldRange| match get_path(company, range.path) {
+            Some(_) if range.min.is_none() && range.max.is_none() => true,
+            Some(value) => bson_to_f64(value).is_some_and(|value| {
+                range.min.is_none_or(|min| value >= min) && range.max.is_none_or(|max| value <= max)
+            }),
+            None => false,
+        };
+        let unclassified = |company: &Document| {
+            query.include_unclassified && get_path(company, "profile.sector").is_none_or(|sector| matches!(sector, Bson::Null) || sector.as_str() == Some(""))
+        };
+        self.companies.lock().unwrap()
+            .iter()
+            .filter(|company| matches(company, "profile.sector", &query.sector) || unclassified(company))
+            .filter(|company| matches(company, "profile.industry", &query.industry))
+            .filter(|company| query.ranges.iter().all(|range| in_range(company, range)))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Orders two companies by `sort`, with those lacking a value last.
+fn compare_companies(a: &Document, b: &Document, sort: CompanySort) -> CmpOrdering {
+    let present = |value: &&Bson| match sort.numeric {
+        true => bson_to_f64(value).is_some(),
+        false => value.as_str().is_some_and(|text| !text.is_empty()),
+    };
+    match (get_path(a, sort.path).filter(present), get_path(b, sort.path).filter(present)) {
+        (Some(x), Some(y)) => {
+            let ordering = match (bson_to_f64(x), bson_to_f64(y)) {
+                (Some(x), Some(y)) => x.total_cmp(&y),
+                _ => x.as_str().cmp(&y.as_str()),
+            };
+            if sort.descending { ordering.reverse() } else { ordering }
+        }
+        (Some(_), None) => CmpOrdering::Less,
+        (None, Some(_)) => CmpOrdering::Greater,
+        (None, None) => CmpOrdering::Equal,
+    }
+}
+
+fn ticker_of(company: &Document) -> &str {
+    company.get_str("ticker").unwrap_or_default()
+}
+
+#[async_trait]
+impl CompanyRepository for InMemoryCompanyRepository {
+    async fn find_by_ticker(&self, ticker: &str) -> RepositoryResult<Option<Document>> {
+        Ok(self.companies.lock().unwrap().iter().find(|company| company.get_str("ticker") == Ok(ticker)).cloned())
+    }
+
+    async fn find_page(&self, sector: &str, industry: Option<&str>, skip: u64, limit: i64) -> RepositoryResult<Vec<Document>> {
+        let matches = |company: &&Document, path: &str, value: &str| get_path(company, path).and_then(Bson::as_str) == Some(value);
+        Ok(self.companies.lock().unwrap()
+            .iter()
+            .filter(|company| matches(company, "profile.sector", sector))
+            .filter(|company| industry.is_none_or(|industry| matches(company, "profile.industry", industry)))
+            .skip(skip as usize)
+            .take(limit.max(0) as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn find_companies(&self, query: &CompanyQuery) -> RepositoryResult<Vec<Document>> {
+        let mut companies = self.matching(query);
+        companies.sort_by(|a, b| {
+            let by_field = query.sort.map_or(CmpOrdering::Equal, |sort| compare_companies(a, b, sort));
+            by_field.then_with(|| ticker_of(a).cmp(ticker_of(b)))
+        });
+        let limit = query.limit.map_or(usize::MAX, |limit| limit.max(0) as usize);
+        Ok(companies.into_iter().skip(query.skip as usize).take(limit).collect())
+    }
+
+    async fn count_companies(&self, query: &CompanyQuery) -> RepositoryResult<u64> {
+        Ok(self.matching(query).len() as u64)
+    }
+
+    async fn search_names(&self, query: &str, limit: i64) -> RepositoryResult<Vec<Document>> {
+        let query = query.to_lowercase();
+        Ok(self.companies.lock().unwrap()
+            .iter()
+            .filter(|company| {
+                ticker_of(company).to_lowercase().starts_with(&query)
+                    || company.get_str("name").unwrap_or_default().to_lowercase().contains(&query)
+            })
+            .take(limit.max(0) as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn starting_with(&self, initial: char, limit: i64) -> RepositoryResult<Vec<Document>> {
+        let starts = |value: &str| value.chars().next().is_some_and(|first| first.to_lowercase().eq(initial.to_lowercase()));
+        Ok(self.companies.lock().unwrap()
+            .iter()
+            .filter(|company| starts(ticker_of(company)) || starts(company.get_str("name").unwrap_or_default()))
+            .take(limit.max(0) as usize)
+            .cloned()
+            .collect())
+    }
+
+    /// Scores a company by how many of the query's words appear in its name
+    /// or business summary, a rough stand-in for the text index.
+    async fn search_summaries(&self, query: &str, limit: i64) -> RepositoryResult<Vec<(Document, f64)>> {
+        let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+        let mut matches: Vec<(Document, f64)> = self.companies.lock().unwrap()
+            .iter()
+            .filter_map(|company| {
+                let text = format!(
+                    "{} {}",
+                    company.get_str("name").unwrap_or_default(),
+                    get_path(company, "profile.longBusinessSummary").and_then(Bson::as_str).unwrap_or_default(),
+                ).to_lowercase();
+                let text_words: Vec<&str> = text.split(|c: char| !c.is_alphanumeric()).collect();
+                let score = words.iter().filter(|word| text_words.contains(&word.as_str())).count();
+                (score > 0).then(|| (company.clone(), score as f64))
+            })
+            .collect();
+        matches.sort_by(|(a, a_score), (b, b_score)| b_score.total_cmp(a_score).then_with(|| ticker_of(a).cmp(ticker_of(b))));
+        matches.truncate(limit.max(0) as usize);
+        Ok(matches)
+    }
+
+    async fn reporting_between(&self, start: i64, end: i64, sector: Option<&str>) -> RepositoryResult<Vec<Document>> {
+        let report_date = |company: &Document| match get_path(company, "earnings.nextReportDate")? {
+            Bson::DateTime(date) => Some(date.timestamp_millis().div_euclid(1000)),
+            other => bson_to_f64(other).map(|date| date as i64),
+        };
+        let mut companies: Vec<Document> = self.companies.lock().unwrap()
+            .iter()
+            .filter(|company| report_date(company).is_some_and(|date| date >= start && date <= end))
+            .filter(|company| sector.is_none_or(|sector| get_path(company, "profile.sector").and_then(Bson::as_str) == Some(sector)))
+            .cloned()
+            .collect();
+        companies.sort_by(|a, b| report_date(a).cmp(&report_date(b)).then_with(|| ticker_of(a).cmp(ticker_of(b))));
+        Ok(companies)
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryPriceRepository {
+    series: Mutex<HashMap<String, Vec<Document>>>,
+    splits: Mutex<HashMap<String, Vec<(i64, f64)>>>,
+    quarantined: Mutex<Vec<QuarantinedDocument>>,
+    unreadable: Mutex<HashSet<String>>,
+    /// Bars written through [`PriceRepository::merge_bars`] by (ticker,
+    /// interval) and timestamp, served as documents like the price store's.
+    bars: Mutex<HashMap<(String, String), BTreeMap<i64, PriceBar>>>,
+    /// Bar counts served per (ticker, period, interval).
+    catalog: Mutex<HashMap<(String, String, String), usize>>,
+}
+
+impl InMemoryPriceRepository {
+    /// Adds a legacy-shaped series document (`period`, `interval`, `closes`,
+    /// `timestamps`, ...) for `ticker`.
+    pub fn insert_series(&self, ticker: &str, series: Document) {
+        self.series.lock().unwrap().entry(ticker.to_string()).or_default().push(series);
+    }
+
+    pub fn insert_split(&self, ticker: &str, ex_date: i64, ratio: f64) {
+        self.splits.lock().unwrap().entry(ticker.to_string()).or_default().push((ex_date, ratio));
+    }
+
+    pub fn insert_quarantined(&self, document: QuarantinedDocument) {
+        self.quarantined.lock().unwrap().push(document);
+    }
+
+    /// Makes every read of `ticker`'s price documents fail, as a series the
+    /// store cannot assemble would.
+    pub fn set_unreadable(&self, ticker: &str) {
+        self.unreadable.lock().unwrap().insert(ticker.to_string());
+    }
+
+    /// Replaces the catalogued documents of `ticker`/`interval` with the
+    /// latest bars each period is served.
+    fn refresh_series(&self, ticker: &str, interval: &str) {
+        let bars = self.bars.lock().unwrap();
+        let catalog = self.catalog.lock().unwrap();
+        let stored: Vec<&PriceBar> = bars.get(&(ticker.to_string(), interval.to_string())).map(|bars| bars.values().collect()).unwrap_or_default();
+        let mut series = self.series.lock().unwrap();
+        let documents = series.entry(ticker.to_string()).or_default();
+        for ((_, period, _), count) in catalog.iter().filter(|((t, _, i), _)| t == ticker && i == interval) {
+            documents.retain(|doc| doc.get_str("period") != Ok(period) || doc.get_str("interval") != Ok(interval));
+            let served = &stored[stored.len().saturating_sub(*count)..];
+            documents.push(legacy_document(ticker, period, interval, served));
+        }
+    }
+
+    fn check_readable<'a>(&self, tickers: impl IntoIterator<Item = &'a String>) -> RepositoryResult<()> {
+        let unreadable = self.unreadable.lock().unwrap();
+        match tickers.into_iter().find(|ticker| unreadable.contains(*ticker)) {
+            Some(ticker) => Err(unavailable(&format!("price data for {} is unreadable", ticker))),
+            None => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl PriceRepository for InMemoryPriceRepository {
+    async fn price_documents(&self, ticker: &str) -> RepositoryResult<Vec<Document>> {
+        self.check_readable([&ticker.to_string()])?;
+        Ok(self.series.lock().unwrap().get(ticker).cloned().unwrap_or_default())
+    }
+
+    async fn series_many(&self, tickers: &[String], period: &str, interval: &str) -> RepositoryResult<HashMap<String, Document>> {
+        self.check_readable(tickers)?;
+        let series = self.series.lock().unwrap();
+        Ok(tickers
+            .iter()
+            .filter_map(|ticker| {
+                let document = series.get(ticker)?.iter().find(|doc| doc.get_str("period") == Ok(period) && doc.get_str("interval") == Ok(interval))?;
+                Some((ticker.clone(), document.clone()))
+            })
+            .collect())
+    }
+
+    async fn latest_price(&self, ticker: &str) -> RepositoryResult<Option<LatestPrice>> {
+        let series = self.series.lock().unwrap();
+        let mut latest: Option<LatestPrice> = None;
+        for doc in series.get(ticker).into_iter().flatten() {
+            let numbers = |field: &str| -> Vec<f64> {
+                doc.get_array(field).map(|values| values.iter().filter_map(bson_to_f64).collect()).unwrap_or_default()
+            };
+            let (closes, timestamps) = (numbers("closes"), numbers("timestamps"));
+            let (Some(&close), Some(&timestamp)) = (closes.last(), timestamps.last()) else { continue };
+            if latest.as_ref().is_none_or(|latest| timestamp as i64 > latest.timestamp) {
+                latest = Some(LatestPrice {
+                    timestamp: timestamp as i64,
+                    close,
+                    previous: closes.len().checked_sub(2).map(|i| closes[i]),
+                    volume: numbers("volumes").last().map(|volume| *volume as i64),
+                    interval: doc.get_str("interval").unwrap_or_default().to_string(),
+                });
+            }
+        }
+        Ok(latest)
+    }
+
+    async fn split_history(&self, ticker: &str) -> RepositoryResult<Vec<(i64, f64)>> {
+        let now = Utc::now().timestamp();
+        Ok(self.splits.lock().unwrap()
+            .get(ticker)
+            .into_iter()
+            .flatten()
+            .filter(|(ex_date, _)| *ex_date <= now)
+            .copied()
+            .collect())
+    }
+
+    async fn split_histories(&self, tickers: &[String]) -> RepositoryResult<HashMap<String, Vec<(i64, f64)>>> {
+        let mut histories = HashMap::new();
+        for ticker in tickers {
+            let history = self.split_history(ticker).await?;
+            if !history.is_empty() {
+                histories.insert(ticker.clone(), history);
+            }
+        }
+        Ok(histories)
+    }
+
+    async fn newest_timestamp(&self) -> RepositoryResult<Option<i64>> {
+        Ok(self.series.lock().unwrap()
+            .values()
+            .flatten()
+            .filter_map(|doc| doc.get_array("timestamps").ok()?.last().and_then(bson_to_f64))
+            .map(|timestamp| timestamp as i64)
+            .max())
+    }
+
+    async fn merge_bars(&self, ticker: &str, interval: &str, bars: Vec<PriceBar>) -> RepositoryResult<usize> {
+        let count = {
+            let mut stored = self.bars.lock().unwrap();
+            let series = stored.entry((ticker.to_string(), interval.to_string())).or_default();
+            let values = |bar: &PriceBar| (bar.open, bar.high, bar.low, bar.close, bar.volume);
+            bars.into_iter()
+                .filter(|bar| {
+                    let replaced = series.insert(bar.timestamp.timestamp_millis(), bar.clone());
+                    replaced.is_none_or(|replaced| values(&replaced) != values(bar))
+                })
+                .count()
+        };
+        self.refresh_series(ticker, interval);
+        Ok(count)
+    }
+
+    async fn record_series(&self, ticker: &str, period: &str, interval: &str, bars: usize) -> RepositoryResult<bool> {
+        let inserted = {
+            let mut catalog = self.catalog.lock().unwrap();
+            let key = (ticker.to_string(), period.to_string(), interval.to_string());
+            let inserted = !catalog.contains_key(&key);
+            let count = catalog.entry(key).or_default();
+            *count = (*count).max(bars);
+            inserted
+        };
+        self.refresh_series(ticker, interval);
+        Ok(inserted)
+    }
+
+    async fn quarantined(&self, ticker: &str) -> RepositoryResult<Vec<QuarantinedDocument>> {
+        let mut documents: Vec<QuarantinedDocument> = self.quarantined.lock().unwrap()
+            .iter()
+            .filter(|document| document.ticker == ticker)
+            .cloned()
+            .collect();
+        documents.sort_by_key(|document| Reverse(document.quarantined_at));
+        Ok(documents)
+    }
+}
+
+#[derive(Default)]
+pub struct InMemoryCorporateActionRepository {
+    actions: Mutex<Vec<CorporateAction>>,
+    ledger: Mutex<Vec<LedgerEntry>>,
+}
+
+impl InMemoryCorporateActionRepository {
+    pub fn all(&self) -> Vec<CorporateAction> {
+        self.actions.lock().unwrap().clone()
+    }
+
+    pub fn insert_ledger_entry(&self, entry: LedgerEntry) {
+        self.ledger.lock().unwrap().push(entry);
+    }
+
+    /// Every ledger entry, settled or not, in insertion order.
+    pub fn ledger(&self) -> Vec<LedgerEntry> {
+        self.ledger.lock().unwrap().clone()
+    }
+
+    /// Marks `action_id` unprocessed again, as a run interrupted before
+    /// finishing it would leave it.
+    pub fn reopen(&self, action_id: ObjectId) {
+        for action in self.actions.lock().unwrap().iter_mut().filter(|action| action.id == Some(action_id)) {
+            action.processed = false;
+            action.processed_at = None;
+        }
+    }
+}
+
+#[async_trait]
+impl CorporateActionRepository for InMemoryCorporateActionRepository {
+    async fn insert(&self, mut action: CorporateAction) -> RepositoryResult<ObjectId> {
+        let id = *action.id.get_or_insert_with(ObjectId::new);
+        self.actions.lock().unwrap().push(action);
+        Ok(id)
+    }
+
+    async fn find_by_ticker(&self, ticker: &str) -> RepositoryResult<Vec<CorporateAction>> {
+        let mut actions: Vec<CorporateAction> = self.actions.lock().unwrap()
+            .iter()
+            .filter(|action| action.ticker == ticker)
+            .cloned()
+            .collect();
+        actions.sort_by_key(|action| Reverse(action.ex_date));
+        Ok(actions)
+    }
+
+    async fn ledger_for_user(&self, user_id: ObjectId) -> RepositoryResult<Vec<LedgerEntry>> {
+        let mut entries: Vec<LedgerEntry> = self.ledger.lock().unwrap()
+            .iter()
+            .filter(|entry| entry.user_id == user_id && entry.settled)
+            .cloned()
+            .collect();
+        entries.sort_by_key(|entry| Reverse(entry.created_at));
+        Ok(entries)
+    }
+
+    async fn due(&self, now: i64) -> RepositoryResult<Vec<CorporateAction>> {
+        let mut actions: Vec<CorporateAction> = self.actions.lock().unwrap()
+            .iter()
+            .filter(|action| !action.processed && action.ex_date.timestamp() <= now)
+            .cloned()
+            .collect();
+        actions.sort_by_key(|action| action.ex_date);
+        Ok(actions)
+    }
+
+    async fn mark_processed(&self, action_id: ObjectId) -> RepositoryResult<()> {
+        for action in self.actions.lock().unwrap().iter_mut().filter(|action| action.id == Some(action_id)) {
+            action.processed = true;
+            action.processed_at = Some(Utc::now());
+        }
+        Ok(())
+    }
+
+    /// Entries are unique per corporate action, user and trade, like the
+    /// ledger's unique index.
+    async fn record_entry(&self, mut entry: LedgerEntry) -> RepositoryResult<bool> {
+        let mut ledger = self.ledger.lock().unwrap();
+        let key = |entry: &LedgerEntry| (entry.corporate_action_id, entry.user_id, entry.trade_id);
+        if entry.corporate_action_id.is_some() && ledger.iter().any(|stored| key(stored) == key(&entry)) {
+            return Ok(false);
+        }
+        entry.id.get_or_insert_with(ObjectId::new);
+        ledger.push(entry);
+        Ok(true)
+    }
+
+    async fn unsettled_entries(&self, action_id: ObjectId) -> RepositoryResult<Vec<LedgerEntry>> {
+        Ok(self.ledger.lock().unwrap()
+            .iter()
+            .filter(|entry| entry.corporate_action_id == Some(action_id) && !entry.settled)
+            .cloned()
+            .collect())
+    }
+
+    async fn mark_settled(&self, entry_id: ObjectId) -> RepositoryResult<()> {
+        for entry in self.ledger.lock().unwrap().iter_mut().filter(|entry| entry.id == Some(entry_id)) {
+            entry.settled = true;
+        }
+        Ok(())
+    }
+}
+
+fn unavailable(message: &str) -> RepositoryError {
+    let err = std::io::Error::new(std::io::ErrorKind::ConnectionRefused, message.to_string());
+    mongodb::error::Error::from(err).into()
+}
+
+/// Reachable unless a test says otherwise.
+#[derive(Default)]
+pub struct InMemoryDatabaseProbe {
+    unreachable: AtomicBool,
+}
+
+impl InMemoryDatabaseProbe {
+    pub fn set_reachable(&self, reachable: bool) {
+        self.unreachable.store(!reachable, Ordering::Relaxed);
+    }
+}
+
+#[async_trait]
+impl DatabaseProbe for InMemoryDatabaseProbe {
+    async fn ping(&self) -> RepositoryResult<()> {
+        if self.unreachable.load(Ordering::Relaxed) {
+            return Err(unavailable("database unreachable"));
+        }
+        Ok(())
+    }
+}
+
+/// A complete in-memory backend. The typed handles stay available for
+/// seeding fixtures and inspecting state after requests.
+#[derive(Clone, Default)]
+pub struct InMemoryRepositories {
+    pub users: Arc<InMemoryUserRepository>,
+    pub trades: Arc<InMemoryTradeRepository>,
+    pub companies: Arc<InMemoryCompanyRepository>,
+    pub prices: Arc<InMemoryPriceRepository>,
+    pub corporate_actions: Arc<InMemoryCorporateActionRepository>,
+    pub database: Arc<InMemoryDatabaseProbe>,
+}
+
+impl InMemoryRepositories {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn repositories(&self) -> Repositories {
+        Repositories {
+            users: self.users.clone(),
+            trades: self.trades.clone(),
+            companies: self.companies.clone(),
+            prices: self.prices.clone(),
+            corporate_actions: self.corporate_actions.clone(),
+            database: self.database.clone(),
+        }
+    }
+}
@@ -0,0 +1,237 @@
+//! Storage behind the request handlers.
+//!
+//! Handlers and the services they call take these traits instead of a
+//! MongoDB handle, so the API runs against [`mongo`] in production and
+//! against [`memory`] in tests. Each repository is registered as its own
+//! `web::Data<dyn ...>` by [`Repositories::configure`].
+
+pub mod memory;
+pub mod mongo;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use actix_web::web;
+use async_trait::async_trait;
+use mongodb::bson::{oid::ObjectId, Document};
+use thiserror::Error;
+use crate::db::mongo::Databases;
+use crate::models::corporate_action_models::{CorporateAction, SplitAdjustment};
+use crate::models::data_quality_models::QuarantinedDocument;
+use crate::models::ledger_models::LedgerEntry;
+use crate::models::price_bar_models::PriceBar;
+use crate::models::trade_models::Trade;
+use crate::models::users::User;
+
+#[derive(Debug, Error)]
+pub enum RepositoryError {
+    #[error("database error: {0}")]
+    Database(#[from] mongodb::error::Error),
+}
+
+pub type RepositoryResult<T> = Result<T, RepositoryError>;
+
+#[async_trait]
+pub trait UserRepository: Send + Sync {
+    async fn find_by_email(&self, email: &str) -> RepositoryResult<Option<User>>;
+    /// Stores `user`, assigning an id if it has none.
+    async fn insert(&self, user: User) -> RepositoryResult<ObjectId>;
+    /// Records `trade_id` on the user and debits `amount`. Returns the updated
+    /// user, or `None` if there is no such user.
+    async fn debit_for_trade(&self, user_id: ObjectId, trade_id: ObjectId, amount: f64) -> RepositoryResult<Option<User>>;
+    /// Adds `amount` to the balance and returns the updated user.
+    async fn credit(&self, user_id: ObjectId, amount: f64) -> RepositoryResult<Option<User>>;
+    /// Adds the ledger entry's `amount` to the balance and marks the user as
+    /// carrying `entry_id`, in one write. Returns the updated user, or `None`
+    /// if there is no such user or it already carries the entry.
+    async fn apply_ledger_entry(&self, user_id: ObjectId, entry_id: ObjectId, amount: f64) -> RepositoryResult<Option<User>>;
+    /// Removes the mark left by [`UserRepository::apply_ledger_entry`] once
+    /// the entry itself is settled.
+    async fn release_ledger_entry(&self, user_id: ObjectId, entry_id: ObjectId) -> RepositoryResult<()>;
+}
+
+#[async_trait]
+pub trait TradeRepository: Send + Sync {
+    /// Stores `trade`, assigning an id if it has none.
+    async fn insert(&self, trade: Trade) -> RepositoryResult<ObjectId>;
+    async fn find_by_id(&self, trade_id: ObjectId) -> RepositoryResult<Option<Trade>>;
+    async fn find_by_user(&self, user_id: ObjectId) -> RepositoryResult<Vec<Trade>>;
+    /// Closes an in-progress trade at `exit_price`. Returns false if the trade
+    /// was not in progress, so a trade is never closed twice.
+    async fn close(&self, trade_id: ObjectId, exit_price: f64) -> RepositoryResult<bool>;
+    /// In-progress trades with a take-profit or stop-loss level.
+    async fn with_exit_levels(&self) -> RepositoryResult<Vec<Trade>>;
+    /// Trades in `ticker` opened before `date` (epoch seconds) and still
+    /// open at it, whether or not they have closed since. The opening time
+    /// comes from the trade's ObjectId; trades closed without a `closed_at`
+    /// are left out.
+    async fn held_on(&self, ticker: &str, date: i64) -> RepositoryResult<Vec<Trade>>;
+    /// Writes `split` to a trade and records `action_id` as applied, closing
+    /// the trade if the split leaves no whole share. Returns false if the
+    /// trade already had the split, so a split is never applied twice.
+    async fn apply_split(&self, trade_id: ObjectId, action_id: ObjectId, split: &SplitAdjustment) -> RepositoryResult<bool>;
+}
+
+/// Inclusive bounds on a numeric field of a `companies` document. A range
+/// with neither bound only requires the field to be present.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldRange {
+    pub path: &'static str,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+/// Companies without a value at `path` come last in either direction. For
+/// a `numeric` sort that is anything but a number, otherwise anything but a
+/// non-empty string.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CompanySort {
+    pub path: &'static str,
+    pub descending: bool,
+    pub numeric: bool,
+}
+
+/// A filtered, sorted and paged read of `companies`. Ties in `sort`, and
+/// results without one, are ordered by ticker.
+#[derive(Debug, Clone, Default)]
+pub struct CompanyQuery {
+    pub sector: Option<String>,
+    /// With `sector`, also match companies without one: missing, null or
+    /// empty.
+    pub include_unclassified: bool,
+    pub industry: Option<String>,
+    pub ranges: Vec<FieldRange>,
+    pub sort: Option<CompanySort>,
+    pub skip: u64,
+    pub limit: Option<i64>,
+    /// Paths the caller reads, so a backend may leave the rest out. Empty
+    /// means the whole document.
+    pub fields: Vec<&'static str>,
+}
+
+#[async_trait]
+pub trait CompanyRepository: Send + Sync {
+    /// The `companies` document for `ticker`.
+    async fn find_by_ticker(&self, ticker: &str) -> RepositoryResult<Option<Document>>;
+    /// One page of the companies in `sector`, optionally narrowed to `industry`.
+    async fn find_page(&self, sector: &str, industry: Option<&str>, skip: u64, limit: i64) -> RepositoryResult<Vec<Document>>;
+    async fn find_companies(&self, query: &CompanyQuery) -> RepositoryResult<Vec<Document>>;
+    /// Companies matching `query`, ignoring its sort and paging.
+    async fn count_companies(&self, query: &CompanyQuery) -> RepositoryResult<u64>;
+    /// Up to `limit` companies whose ticker starts with `query` or whose name
+    /// contains it, ignoring case.
+    async fn search_names(&self, query: &str, limit: i64) -> RepositoryResult<Vec<Document>>;
+    /// Up to `limit` companies whose ticker or name starts with `initial`,
+    /// in either case.
+    async fn starting_with(&self, initial: char, limit: i64) -> RepositoryResult<Vec<Document>>;
+    /// Up to `limit` companies whose business summary matches the words of
+    /// `query`, with their relevance, best first. Empty when the backend
+    /// has no text index.
+    async fn search_summaries(&self, query: &str, limit: i64) -> RepositoryResult<Vec<(Document, f64)>>;
+    /// Companies whose next earnings report falls between `start` and `end`
+    /// (epoch seconds, inclusive), ordered by report date and ticker.
+    async fn reporting_between(&self, start: i64, end: i64, sector: Option<&str>) -> RepositoryResult<Vec<Document>>;
+}
+
+/// Most recent stored point of a ticker, across every period and interval.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LatestPrice {
+    /// Epoch seconds.
+    pub timestamp: i64,
+    pub close: f64,
+    /// The close before `close` in the same series.
+    pub previous: Option<f64>,
+    pub volume: Option<i64>,
+    pub interval: String,
+}
+
+#[async_trait]
+pub trait PriceRepository: Send + Sync {
+    /// Every stored period/interval series of `ticker` in the legacy document
+    /// shape (`period`, `interval` and parallel arrays), unvalidated.
+    async fn price_documents(&self, ticker: &str) -> RepositoryResult<Vec<Document>>;
+    /// The `period`/`interval` document of each of `tickers` that has one,
+    /// in the shape of [`PriceRepository::price_documents`]. Reads no other
+    /// series; tickers without it are left out.
+    async fn series_many(&self, tickers: &[String], period: &str, interval: &str) -> RepositoryResult<HashMap<String, Document>>;
+    async fn latest_price(&self, ticker: &str) -> RepositoryResult<Option<LatestPrice>>;
+    /// `(ex_date, ratio)` of the splits of `ticker` that have taken effect.
+    async fn split_history(&self, ticker: &str) -> RepositoryResult<Vec<(i64, f64)>>;
+    /// [`PriceRepository::split_history`] of each of `tickers` in one read.
+    /// Tickers without splits are left out.
+    async fn split_histories(&self, tickers: &[String]) -> RepositoryResult<HashMap<String, Vec<(i64, f64)>>>;
+    /// Epoch seconds of the most recent price data: the newest stored point
+    /// of any ticker, or the latest write stamped by
+    /// [`record_data_update`](crate::services::cache_service::record_data_update),
+    /// whichever is later. Answered from indexes, without reading every series.
+    async fn newest_timestamp(&self) -> RepositoryResult<Option<i64>>;
+    /// Series of `ticker` taken out of service by the quarantine job, newest
+    /// first.
+    async fn quarantined(&self, ticker: &str) -> RepositoryResult<Vec<QuarantinedDocument>>;
+    /// Merges `bars` into the `ticker`/`interval` series, replacing stored
+    /// bars with the same timestamp. Returns the number of bars written.
+    async fn merge_bars(&self, ticker: &str, interval: &str, bars: Vec<PriceBar>) -> RepositoryResult<usize>;
+    /// Serves `period`/`interval` of `ticker` as at least its latest `bars`
+    /// bars; a smaller count keeps the current window. Returns whether the
+    /// series was not served before.
+    async fn record_series(&self, ticker: &str, period: &str, interval: &str, bars: usize) -> RepositoryResult<bool>;
+}
+
+#[async_trait]
+pub trait CorporateActionRepository: Send + Sync {
+    /// Stores `action`, assigning an id if it has none.
+    async fn insert(&self, action: CorporateAction) -> RepositoryResult<ObjectId>;
+    /// Actions recorded for `ticker`, latest ex-date first.
+    async fn find_by_ticker(&self, ticker: &str) -> RepositoryResult<Vec<CorporateAction>>;
+    /// Settled ledger entries of `user_id`, newest first.
+    async fn ledger_for_user(&self, user_id: ObjectId) -> RepositoryResult<Vec<LedgerEntry>>;
+    /// Unprocessed actions whose ex-date is at or before `now` (epoch
+    /// seconds), earliest ex-date first.
+    async fn due(&self, now: i64) -> RepositoryResult<Vec<CorporateAction>>;
+    async fn mark_processed(&self, action_id: ObjectId) -> RepositoryResult<()>;
+    /// Stores `entry`. Returns false if an entry for the same corporate
+    /// action, user and trade already exists, which is left as it is.
+    async fn record_entry(&self, entry: LedgerEntry) -> RepositoryResult<bool>;
+    /// Ledger entries of `action_id` not yet applied to a balance.
+    async fn unsettled_entries(&self, action_id: ObjectId) -> RepositoryResult<Vec<LedgerEntry>>;
+    async fn mark_settled(&self, entry_id: ObjectId) -> RepositoryResult<()>;
+}
+
+#[async_trait]
+pub trait DatabaseProbe: Send + Sync {
+    /// One round trip to the database server.
+    async fn ping(&self) -> RepositoryResult<()>;
+}
+
+/// One implementation of every repository, shared by handlers and jobs.
+#[derive(Clone)]
+pub struct Repositories {
+    pub users: Arc<dyn UserRepository>,
+    pub trades: Arc<dyn TradeRepository>,
+    pub companies: Arc<dyn CompanyRepository>,
+    pub prices: Arc<dyn PriceRepository>,
+    pub corporate_actions: Arc<dyn CorporateActionRepository>,
+    pub database: Arc<dyn DatabaseProbe>,
+}
+
+impl Repositories {
+    pub fn mongo(databases: &Databases) -> Self {
+        Repositories {
+            users: Arc::new(mongo::MongoUserRepository::new(databases)),
+            trades: Arc::new(mongo::MongoTradeRepository::new(databases)),
+            companies: Arc::new(mongo::MongoCompanyRepository::new(databases)),
+            prices: Arc::new(mongo::MongoPriceRepository::new(databases)),
+            corporate_actions: Arc::new(mongo::MongoCorporateActionRepository::new(databases)),
+            database: Arc::new(mongo::MongoDatabaseProbe::new(databases)),
+        }
+    }
+
+    /// Registers each repository as `web::Data<dyn ...>` for handlers.
+    pub fn configure(&self, cfg: &mut web::ServiceConfig) {
+        cfg.app_data(web::Data::from(self.users.clone()));
+        cfg.app_data(web::Data::from(self.trades.clone()));
+        cfg.app_data(web::Data::from(self.companies.clone()));
+        cfg.app_data(web::Data::from(self.prices.clone()));
+        cfg.app_data(web::Data::from(self.corporate_actions.clone()));
+        cfg.app_data(web::Data::from(self.database.clone()));
+    }
+}
@@ -0,0 +1,539 @@
+use std::collections::HashMap;
+use async_trait::async_trait;
+use chrono::Utc;
+use futures::TryStreamExt;
+use log::warn;
+use mongodb::{
+    bson::{doc, oid::ObjectId, Bson, DateTime, Document},
+    options::{FindOneAndUpdateOptions, FindOptions, ReturnDocument},
+    Collection, Database,
+};
+use crate::db::mongo::{is_duplicate_key, is_index_not_found, Databases};
+use crate::db::price_store::PriceStore;
+use crate::models::corporate_action_models::{CorporateAction, SplitAdjustment};
+use crate::models::data_quality_models::QuarantinedDocument;
+use crate::models::ledger_models::LedgerEntry;
+use crate::models::price_bar_models::PriceBar;
+use crate::models::trade_models::Trade;
+use crate::models::users::User;
+use crate::services::cache_service::latest_data_update;
+use crate::services::corporate_action_service::split_histories;
+use crate::services::data_quality_service::quarantine_collection;
+use crate::services::stock_service::bson_to_f64;
+use super::{
+    CompanyQuery, CompanyRepository, CorporateActionRepository, DatabaseProbe, LatestPrice, PriceRepository, RepositoryResult,
+    TradeRepository, UserRepository,
+};
+
+/// Price queries kept in flight at once when loading several tickers.
+const PRICE_FETCH_CONCURRENCY: usize = 16;
+/// Field added while sorting companies to rank those without a value last.
+const SORT_MISSING: &str = "_sort_missing";
+
+fn return_updated() -> FindOneAndUpdateOptions {
+    FindOneAndUpdateOptions::builder().return_document(ReturnDocument::After).build()
+}
+
+pub struct MongoUserRepository {
+    users: Collection<User>,
+}
+
+impl MongoUserRepository {
+    pub fn new(databases: &Databases) -> Self {
+        MongoUserRepository { users: databases.app.collection("users") }
+    }
+}
+
+#[async_trait]
+impl UserRepository for MongoUserRepository {
+    async fn find_by_email(&self, email: &str) -> RepositoryResult<Option<User>> {
+        Ok(self.users.find_one(doc! { "email": email }, None).await?)
+    }
+
+    async fn insert(&self, mut user: User) -> RepositoryResult<ObjectId> {
+        let id = *user.id.get_or_insert_with(ObjectId::new);
+        self.users.insert_one(user, None).await?;
+        Ok(id)
+    }
+
+    async fn debit_for_trade(&self, user_id: ObjectId, trade_id: ObjectId, amount: f64) -> RepositoryResult<Option<User>> {
+        let update = doc! {
+            "$addToSet": { "trades": trade_id },
+            "$inc": { "balance": -amount },
+        };
+        Ok(self.users.find_one_and_update(doc! { "_id": user_id }, update, return_updated()).await?)
+    }
+
+    async fn credit(&self, user_id: ObjectId, amount: f64) -> RepositoryResult<Option<User>> {
+        let update = doc! { "$inc": { "balance": amount } };
+        Ok(self.users.find_one_and_update(doc! { "_id": user_id }, update, return_updated()).await?)
+    }
+
+    async fn apply_ledger_entry(&self, user_id: ObjectId, entry_id: ObjectId, amount: f64) -> RepositoryResult<Option<User>> {
+        let filter = doc! { "_id": user_id, "settling_ledger_entries": { "$ne": entry_id } };
+        let update = doc! { "$inc": { "balance": amount }, "$push": { "settling_ledger_entries": entry_id } };
+        Ok(self.users.find_one_and_update(filter, update, return_updated()).await?)
+    }
+
+    async fn release_ledger_entry(&self, user_id: ObjectId, entry_id: ObjectId) -> RepositoryResult<()> {
+        self.users.update_one(doc! { "_id": user_id }, doc! { "$pull": { "settling_ledger_entries": entry_id } }, None).await?;
+        Ok(())
+    }
+}
+
+/// The smallest ObjectId generated at `timestamp`, so `_id < id` selects
+/// documents created before it.
+fn first_id_at(timestamp: i64) -> ObjectId {
+    let mut bytes = [0; 12];
+    bytes[..4].copy_from_slice(&(timestamp as u32).to_be_bytes());
+    ObjectId::from_bytes(bytes)
+}
+
+pub struct MongoTradeRepository {
+    trades: Collection<Trade>,
+}
+
+impl MongoTradeRepository {
+    pub fn new(databases: &Databases) -> Self {
+        MongoTradeRepository { trades: databases.app.collection("trades") }
+    }
+}
+
+#[async_trait]
+impl TradeRepository for MongoTradeRepository {
+    async fn insert(&self, mut trade: Trade) -> RepositoryResult<ObjectId> {
+        let id = *trade.id.get_or_insert_with(ObjectId::new);
+        self.trades.insert_one(trade, None).await?;
+        Ok(id)
+    }
+
+    async fn find_by_id(&self, trade_id: ObjectId) -> RepositoryResult<Option<Trade>> {
+        Ok(self.trades.find_one(doc! { "_id": trade_id }, None).await?)
+    }
+
+    async fn find_by_user(&self, user_id: ObjectId) -> RepositoryResult<Vec<Trade>> {
+        Ok(self.trades.find(doc! { "user_id": user_id }, None).await?.try_collect().await?)
+    }
+
+    async fn close(&self, trade_id: ObjectId, exit_price: f64) -> RepositoryResult<bool> {
+        let result = self.trades.update_one(
+            doc! { "_id": trade_id, "status": "InProgress" },
+            doc! { "$set": { "status": "Closed", "exit_price": exit_price, "closed_at": Utc::now().timestamp() } },
+            None,
+        ).await?;
+        Ok(result.modified_count > 0)
+    }
+
+    async fn with_exit_levels(&self) -> RepositoryResult<Vec<Trade>> {
+        let filter = doc! {
+            "status": "InProgress",
+            "$or": [
+                { "take_profit": { "$ne": null } },
+                { "stop_loss": { "$ne": null } },
+            ],
+        };
+        Ok(self.trades.find(filter, None).await?.try_collect().await?)
+    }
+
+    async fn held_on(&self, ticker: &str, date: i64) -> RepositoryResult<Vec<Trade>> {
+        let filter = doc! {
+            "ticker": ticker,
+            "_id": { "$lt": first_id_at(date) },
+            "$or": [
+                { "status": "InProgress" },
+                { "closed_at": { "$gt": date } },
+            ],
+        };
+        Ok(self.trades.find(filter, None).await?.try_collect().await?)
+    }
+
+    async fn apply_split(&self, trade_id: ObjectId, action_id: ObjectId, split: &SplitAdjustment) -> RepositoryResult<bool> {
+        let mut set = doc! {
+            "quantity": split.quantity as i64,
+            "price": split.price,
+            "take_profit": split.take_profit,
+            "stop_loss": split.stop_loss,
+            "amount": split.amount,
+        };
+        if split.closes_trade() {
+            set.insert("status", "Closed");
+            set.insert("exit_price", split.price);
+            set.insert("closed_at", Utc::now().timestamp());
+        }
+        let result = self.trades.update_one(
+            doc! { "_id": trade_id, "applied_splits": { "$ne": action_id } },
+            doc! { "$set": set, "$push": { "applied_splits": action_id } },
+            None,
+        ).await?;
+        Ok(result.modified_count > 0)
+    }
+}
+
+pub struct MongoCompanyRepository {
+    companies: Collection<Document>,
+}
+
+impl MongoCompanyRepository {
+    pub fn new(databases: &Databases) -> Self {
+        MongoCompanyRepository { companies: databases.app.collection("companies") }
+    }
+}
+
+#[async_trait]
+impl CompanyRepository for MongoCompanyRepository {
+    async fn find_by_ticker(&self, ticker: &str) -> RepositoryResult<Option<Document>> {
+        Ok(self.companies.find_one(doc! { "ticker": ticker }, None).await?)
+    }
+
+    async fn find_page(&self, sector: &str, industry: Option<&str>, skip: u64, limit: i64) -> RepositoryResult<Vec<Document>> {
+        let mut filter = doc! { "profile.sector": sector };
+        if let Some(industry) = industry {
+            filter.insert("profile.industry", industry);
+        }
+        let find_options = FindOptions::builder().skip(Some(skip)).limit(Some(limit)).build();
+        Ok(self.companies.find(filter, find_options).await?.try_collect().await?)
+    }
+
+    async fn find_companies(&self, query: &CompanyQuery) -> RepositoryResult<Vec<Document>> {
+        let projection = (!query.fields.is_empty()).then(|| {
+            let mut projection = doc! { "ticker": 1 };
+            for field in &query.fields {
+                projection.insert(*field, 1);
+            }
+            projection
+        });
+        let Some(sort) = query.sort else {
+            let find_options = FindOptions::builder()
+                .sort(doc! { "ticker": 1 })
+                .skip(Some(query.skip))
+                .limit(query.limit)
+                .projection(projection)
+                .build();
+            return Ok(self.companies.find(company_filter(query), find_options).await?.try_collect().await?);
+        };
+
+        // A plain sort puts missing values first in ascending order, so
+        // rank them explicitly to keep them last in both directions.
+        let value = format!("${}", sort.path);
+        let missing = if sort.numeric {
+            doc! { "$not": [{ "$isNumber": &value }] }
+        } else {
+            doc! { "$or": [{ "$ne": [{ "$type": &value }, "string"] }, { "$eq": [&value, ""] }] }
+        };
+        let mut pipeline = vec![
+            doc! { "$match": company_filter(query) },
+            doc! { "$addFields": { SORT_MISSING: missing } },
+            doc! { "$sort": { SORT_MISSING: 1, sort.path: if sort.descending { -1 } else { 1 }, "ticker": 1 } },
+            doc! { "$skip": query.skip as i64 },
+        ];
+        if let Some(limit) = query.limit.filter(|limit| *limit > 0) {
+            pipeline.push(doc! { "$limit": limit });
+        }
+        pipeline.push(doc! { "$project": projection.unwrap_or_else(|| doc! { SORT_MISSING: 0 }) });
+        Ok(self.companies.aggregate(pipeline, None).await?.try_collect().await?)
+    }
+
+    async fn count_companies(&self, query: &CompanyQuery) -> RepositoryResult<u64> {
+        Ok(self.companies.count_documents(company_filter(query), None).await?)
+    }
+
+    async fn search_names(&self, query: &str, limit: i64) -> RepositoryResult<Vec<Document>> {
+        let pattern = escape_regex(query);
+        let filter = doc! {
+            "$or": [
+                { "ticker": { "$regex": format!("^{}", pattern), "$options": "i" } },
+                { "name": { "$regex": pattern, "$options": "i" } },
+            ]
+        };
+        let find_options = FindOptions::builder().projection(search_projection()).limit(limit).build();
+        Ok(self.companies.find(filter, find_options).await?.try_collect().await?)
+    }
+
+    /// Each alternative is a case-sensitive anchored pattern, so it reads a
+    /// narrow range of the ticker or name index.
+    async fn starting_with(&self, initial: char, limit: i64) -> RepositoryResult<Vec<Document>> {
+        let mut alternatives = Vec::new();
+        for case in initial.to_uppercase().chain(initial.to_lowercase()) {
+            let pattern = format!("^{}", escape_regex(&case.to_string()));
+            alternatives.push(doc! { "ticker": { "$regex": &pattern } });
+            alternatives.push(doc! { "name": { "$regex": &pattern } });
+        }
+        let find_options = FindOptions::builder().projection(search_projection()).limit(limit).build();
+        Ok(self.companies.find(doc! { "$or": alternatives }, find_options).await?.try_collect().await?)
+    }
+
+    async fn search_summaries(&self, query: &str, limit: i64) -> RepositoryResult<Vec<(Document, f64)>> {
+        let mut projection = search_projection();
+        projection.insert("text_score", doc! { "$meta": "textScore" });
+        let find_options = FindOptions::builder()
+            .projection(projection)
+            .sort(doc! { "text_score": { "$meta": "textScore" } })
+            .limit(limit)
+            .build();
+        let found = match self.companies.find(doc! { "$text": { "$search": query } }, find_options).await {
+            Ok(cursor) => cursor.try_collect::<Vec<Document>>().await,
+            Err(err) => Err(err),
+        };
+        let docs = match found {
+            Ok(docs) => docs,
+            Err(err) if is_index_not_found(&err) => {
+                warn!("Searching without business summaries, the text index is missing: {}", err);
+                return Ok(Vec::new());
+            }
+            Err(err) => return Err(err.into()),
+        };
+        Ok(docs
+            .into_iter()
+            .map(|doc| {
+                let score = doc.get_f64("text_score").unwrap_or_default();
+                (doc, score)
+            })
+            .collect())
+    }
+
+    async fn reporting_between(&self, start: i64, end: i64, sector: Option<&str>) -> RepositoryResult<Vec<Document>> {
+        // Report dates are epoch seconds or BSON dates, which only match
+        // bounds of their own type.
+        let (first, last) = (DateTime::from_millis(start * 1000), DateTime::from_millis(end * 1000));
+        let mut filter = doc! { "$or": [
+            { "earnings.nextReportDate": { "$gte": start, "$lte": end } },
+            { "earnings.nextReportDate": { "$gte": first, "$lte": last } },
+        ] };
+        if let Some(sector) = sector {
+            filter.insert("profile.sector", sector);
+        }
+        let find_options = FindOptions::builder()
+            .projection(doc! { "ticker": 1, "name": 1, "profile.sector": 1, "earnings": 1 })
+            .sort(doc! { "earnings.nextReportDate": 1, "ticker": 1 })
+            .build();
+        Ok(self.companies.find(filter, find_options).await?.try_collect().await?)
+    }
+}
+
+fn company_filter(query: &CompanyQuery) -> Document {
+    let mut filter = Document::new();
+    match (&query.sector, query.include_unclassified) {
+        (Some(sector), true) => filter.insert("profile.sector", doc! { "$in": [sector, Bson::Null, ""] }),
+        (Some(sector), false) => filter.insert("profile.sector", sector),
+        (None, _) => None,
+    };
+    if let Some(industry) = &query.industry {
+        filter.insert("profile.industry", industry);
+    }
+    for range in &query.ranges {
+        let mut bounds = Document::new();
+        if let Some(min) = range.min {
+            bounds.insert("$gte", min);
+        }
+        if let Some(max) = range.max {
+            bounds.insert("$lte", max);
+        }
+        if bounds.is_empty() {
+            bounds.insert("$exists", true);
+        }
+        filter.insert(range.path, bounds);
+    }
+    filter
+}
+
+fn search_projection() -> Document {
+    doc! { "ticker": 1, "name": 1, "profile.sector": 1, "profile.industry": 1 }
+}
+
+fn escape_regex(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if "\\^$.|?*+()[]{}".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Reads through [`PriceStore`], so unmigrated tickers still come from their
+/// legacy `stock_data` collection.
+pub struct MongoPriceRepository {
+    databases: Databases,
+    store: PriceStore,
+}
+
+impl MongoPriceRepository {
+    pub fn new(databases: &Databases) -> Self {
+        MongoPriceRepository { databases: databases.clone(), store: PriceStore::new(databases) }
+    }
+
+    /// Latest point of a ticker still in its legacy collection. Only the
+    /// tail of each series is fetched.
+    async fn legacy_latest_price(&self, ticker: &str) -> RepositoryResult<Option<LatestPrice>> {
+        let stock_data_collection = self.databases.stock.collection::<Document>(ticker);
+        let projection = doc! {
+            "interval": 1,
+            "closes": { "$slice": -2 },
+            "timestamps": { "$slice": -2 },
+            "volumes": { "$slice": -1 },
+        };
+        let find_options = FindOptions::builder().projection(projection).build();
+        let mut cursor = stock_data_collection.find(None, find_options).await?;
+
+        let mut latest: Option<LatestPrice> = None;
+        while let Some(doc) = cursor.try_next().await? {
+            let (Ok(closes), Ok(timestamps)) = (doc.get_array("closes"), doc.get_array("timestamps")) else {
+                continue;
+            };
+            let (Some(close), Some(timestamp)) = (closes.last().and_then(bson_to_f64), timestamps.last().and_then(bson_to_f64)) else {
+                continue;
+            };
+            if latest.as_ref().is_none_or(|latest| timestamp as i64 > latest.timestamp) {
+                latest = Some(LatestPrice {
+                    timestamp: timestamp as i64,
+                    close,
+                    previous: closes.len().checked_sub(2).and_then(|i| bson_to_f64(&closes[i])),
+                    volume: doc.get_array("volumes").ok()
+                        .and_then(|volumes| volumes.last())
+                        .and_then(bson_to_f64)
+                        .map(|volume| volume as i64),
+                    interval: doc.get_str("interval").unwrap_or_default().to_string(),
+                });
+            }
+        }
+        Ok(latest)
+    }
+}
+
+#[async_trait]
+impl PriceRepository for MongoPriceRepository {
+    async fn price_documents(&self, ticker: &str) -> RepositoryResult<Vec<Document>> {
+        Ok(self.store.price_documents(ticker).await?)
+    }
+
+    async fn series_many(&self, tickers: &[String], period: &str, interval: &str) -> RepositoryResult<HashMap<String, Document>> {
+        Ok(self.store.series_documents_many(tickers, period, interval, PRICE_FETCH_CONCURRENCY).await?)
+    }
+
+    async fn latest_price(&self, ticker: &str) -> RepositoryResult<Option<LatestPrice>> {
+        let bars = self.store.latest_bars(ticker, 2).await?;
+        match bars.first() {
+            Some(bar) => Ok(Some(LatestPrice {
+                timestamp: bar.timestamp.timestamp_millis() / 1000,
+                close: bar.close,
+                previous: bars.get(1).map(|previous| previous.close),
+                volume: Some(bar.volume),
+                interval: bar.meta.interval.clone(),
+            })),
+            // Tickers not yet migrated to the time-series store.
+            None => self.legacy_latest_price(ticker).await,
+        }
+    }
+
+    async fn split_history(&self, ticker: &str) -> RepositoryResult<Vec<(i64, f64)>> {
+        let mut histories = split_histories(&self.databases, &[ticker.to_string()]).await?;
+        Ok(histories.remove(ticker).unwrap_or_default())
+    }
+
+    async fn split_histories(&self, tickers: &[String]) -> RepositoryResult<HashMap<String, Vec<(i64, f64)>>> {
+        Ok(split_histories(&self.databases, tickers).await?)
+    }
+
+    async fn newest_timestamp(&self) -> RepositoryResult<Option<i64>> {
+        let newest_bar = self.store.newest_timestamp().await?;
+        let newest_update = latest_data_update(&self.databases).await?;
+        Ok(newest_bar.max(newest_update))
+    }
+
+    async fn quarantined(&self, ticker: &str) -> RepositoryResult<Vec<QuarantinedDocument>> {
+        let find_options = FindOptions::builder().sort(doc! { "quarantined_at": -1 }).build();
+        Ok(quarantine_collection(&self.databases).find(doc! { "ticker": ticker }, find_options).await?.try_collect().await?)
+    }
+
+    async fn merge_bars(&self, ticker: &str, interval: &str, bars: Vec<PriceBar>) -> RepositoryResult<usize> {
+        Ok(self.store.upsert_bars(ticker, interval, bars).await?)
+    }
+
+    async fn record_series(&self, ticker: &str, period: &str, interval: &str, bars: usize) -> RepositoryResult<bool> {
+        Ok(self.store.record_series(ticker, period, interval, bars).await?)
+    }
+}
+
+pub struct MongoCorporateActionRepository {
+    actions: Collection<CorporateAction>,
+    ledger: Collection<LedgerEntry>,
+}
+
+impl MongoCorporateActionRepository {
+    pub fn new(databases: &Databases) -> Self {
+        MongoCorporateActionRepository {
+            actions: databases.app.collection("corporate_actions"),
+            ledger: databases.app.collection("ledger"),
+        }
+    }
+}
+
+#[async_trait]
+impl CorporateActionRepository for MongoCorporateActionRepository {
+    async fn insert(&self, mut action: CorporateAction) -> RepositoryResult<ObjectId> {
+        let id = *action.id.get_or_insert_with(ObjectId::new);
+        self.actions.insert_one(action, None).await?;
+        Ok(id)
+    }
+
+    async fn find_by_ticker(&self, ticker: &str) -> RepositoryResult<Vec<CorporateAction>> {
+        let find_options = FindOptions::builder().sort(doc! { "ex_date": -1 }).build();
+        Ok(self.actions.find(doc! { "ticker": ticker }, find_options).await?.try_collect().await?)
+    }
+
+    async fn ledger_for_user(&self, user_id: ObjectId) -> RepositoryResult<Vec<LedgerEntry>> {
+        let find_options = FindOptions::builder().sort(doc! { "created_at": -1 }).build();
+        Ok(self.ledger.find(doc! { "user_id": user_id, "settled": { "$ne": false } }, find_options).await?.try_collect().await?)
+    }
+
+    async fn due(&self, now: i64) -> RepositoryResult<Vec<CorporateAction>> {
+        let find_options = FindOptions::builder().sort(doc! { "ex_date": 1 }).build();
+        let filter = doc! { "processed": false, "ex_date": { "$lte": now } };
+        Ok(self.actions.find(filter, find_options).await?.try_collect().await?)
+    }
+
+    async fn mark_processed(&self, action_id: ObjectId) -> RepositoryResult<()> {
+        self.actions.update_one(
+            doc! { "_id": action_id },
+            doc! { "$set": { "processed": true, "processed_at": Utc::now().timestamp() } },
+            None,
+        ).await?;
+        Ok(())
+    }
+
+    async fn record_entry(&self, entry: LedgerEntry) -> RepositoryResult<bool> {
+        match self.ledger.insert_one(entry, None).await {
+            Ok(_) => Ok(true),
+            Err(err) if is_duplicate_key(&err) => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn unsettled_entries(&self, action_id: ObjectId) -> RepositoryResult<Vec<LedgerEntry>> {
+        Ok(self.ledger.find(doc! { "corporate_action_id": action_id, "settled": false }, None).await?.try_collect().await?)
+    }
+
+    async fn mark_settled(&self, entry_id: ObjectId) -> RepositoryResult<()> {
+        self.ledger.update_one(doc! { "_id": entry_id }, doc! { "$set": { "settled": true } }, None).await?;
+        Ok(())
+    }
+}
+
+pub struct MongoDatabaseProbe {
+    database: Database,
+}
+
+impl MongoDatabaseProbe {
+    pub fn new(databases: &Databases) -> Self {
+        MongoDatabaseProbe { database: databases.app.clone() }
+    }
+}
+
+#[async_trait]
+impl DatabaseProbe for MongoDatabaseProbe {
+    async fn ping(&self) -> RepositoryResult<()> {
+        self.database.run_command(doc! { "ping": 1 }, None).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,125 @@
+use std::time::Duration;
+use actix_web::{get, web, web::Bytes, HttpRequest, HttpResponse};
+use futures::stream;
+use log::{info, warn};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::time::{interval_at, Instant, Interval, MissedTickBehavior};
+use crate::auth::SessionUser;
+use crate::error::{AppError, ErrorResponse};
+use crate::models::activity_models::AccountEvent;
+use crate::services::activity_service::ActivityFeed;
+
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+struct StreamState {
+    user_id: String,
+    replay: std::vec::IntoIter<AccountEvent>,
+    receiver: broadcast::Receiver<AccountEvent>,
+    last_sent: u64,
+    sent_retry: bool,
+    /// One timer for the whole stream, so events for other users, which are
+    /// skipped, do not push the next keep-alive back.
+    keep_alive: Interval,
+}
+
+fn format_event(event: &AccountEvent) -> Bytes {
+    let data = serde_json::to_string(event).unwrap_or_default();
+    Bytes::from(format!("id: {}\nevent: {}\ndata: {}\n\n", event.id, event.kind.name(), data))
+}
+
+/// Ticks every [`KEEP_ALIVE_INTERVAL`], starting one interval from now.
+fn keep_alive_timer() -> Interval {
+    let mut timer = interval_at(Instant::now() + KEEP_ALIVE_INTERVAL, KEEP_ALIVE_INTERVAL);
+    timer.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    timer
+}
+
+/// Server-sent events for the signed-in user's orders, fills, exits and
+/// balance.
+///
+/// The session token from login goes in the `Authorization: Bearer` header
+/// or, for `EventSource` clients, the `session_token` cookie that login and
+/// registration set. Each event carries its sequence number as `id`;
+/// reconnecting with `Last-Event-ID` replays what was missed.
+#[utoipa::path(
+    operation_id = "activity_stream",
+    tag = "activity",
+    params(
+        ("Last-Event-ID" = Option<u64>, Header, description = "Last event id the client received"),
+    ),
+    security(("session_token" = []), ("session_cookie" = [])),
+    responses(
+        (status = 200, description = "Event stream; each `data` line is one event", content_type = "text/event-stream", body = AccountEvent),
+        (status = 401, description = "Missing, invalid or expired session token", body = ErrorResponse),
+    )
+)]
+#[get("/activity/stream")]
+async fn activity_stream(
+    req: HttpRequest,
+    feed: web::Data<ActivityFeed>,
+    user: SessionUser,
+) -> Result<HttpResponse, AppError> {
+    let user_id = user.into_inner();
+
+    let last_event_id = req.headers()
+        .get("Last-Event-ID")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok());
+    info!("Opening activity stream for user: {} (Last-Event-ID: {:?})", user_id, last_event_id);
+
+    let (replay, receiver) = feed.subscribe(&user_id, last_event_id);
+    let state = StreamState {
+        user_id,
+        replay: replay.into_iter(),
+        receiver,
+        last_sent: last_event_id.unwrap_or(0),
+        sent_retry: false,
+        keep_alive: keep_alive_timer(),
+    };
+
+    let body = stream::unfold(state, |mut state| async move {
+        if !state.sent_retry {
+            state.sent_retry = true;
+            return Some((Ok::<_, actix_web::Error>(Bytes::from_static(b"retry: 3000\n\n")), state));
+        }
+
+        if let Some(event) = state.replay.next() {
+            state.last_sent = event.id;
+            return Some((Ok(format_event(&event)), state));
+        }
+
+        loop {
+            tokio::select! {
+                received = state.receiver.recv() => match received {
+                    Ok(event) => {
+                        if event.user_id != state.user_id || event.id <= state.last_sent {
+                            continue;
+                        }
+                        state.last_sent = event.id;
+                        return Some((Ok(format_event(&event)), state));
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        // Closing the stream makes the client reconnect with
+                        // its Last-Event-ID and pick the gap up from history.
+                        warn!("Activity stream for user {} lagged by {} events, closing", state.user_id, skipped);
+                        return None;
+                    }
+                    Err(RecvError::Closed) => return None,
+                },
+                _ = state.keep_alive.tick() => {
+                    return Some((Ok(Bytes::from_static(b": keep-alive\n\n")), state));
+                }
+            }
+        }
+    });
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .insert_header(("X-Accel-Buffering", "no"))
+        .streaming(body))
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(activity_stream);
+}
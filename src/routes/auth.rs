@@ -1,45 +1,86 @@
 use actix_web::{web, HttpResponse, post};
+use crate::auth::SessionTokens;
+use crate::config::AppConfig;
+use crate::error::{AppError, ErrorResponse};
+use crate::repositories::UserRepository;
 use crate::services::auth_service::{login, register};
-use crate::models::users::{AuthPayload, AuthResponse};
+use crate::models::users::{AuthPayload, AuthResponse, RegisterPayload};
+use crate::validation::ValidJson;
 use sentry::capture_message;
 use log::info;
 
+/// Answers with `user`, also setting its token as the session cookie.
+fn with_session_cookie(tokens: &SessionTokens, user: AuthResponse) -> HttpResponse {
+    let mut response = HttpResponse::Ok();
+    if let Some(token) = &user.token {
+        response.cookie(tokens.cookie(token));
+    }
+    response.json(user)
+}
+
+/// Sign in with email and password.
+#[utoipa::path(
+    operation_id = "login",
+    tag = "auth",
+    request_body = AuthPayload,
+    responses(
+        (status = 200, description = "Signed in", body = AuthResponse),
+        (status = 400, description = "Malformed request", body = ErrorResponse),
+        (status = 401, description = "Wrong password", body = ErrorResponse),
+        (status = 404, description = "No user with this email", body = ErrorResponse),
+        (status = 422, description = "Request validation failed", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
 #[post("/login")]
-async fn login_route(form: web::Json<AuthPayload>) -> HttpResponse {
+async fn login_route(users: web::Data<dyn UserRepository>, tokens: web::Data<SessionTokens>, form: ValidJson<AuthPayload>) -> Result<HttpResponse, AppError> {
     let payload = form.into_inner();
     info!("Received login request for user: {}", payload.email);
     capture_message(&format!("Received login request for user: {}", payload.email), sentry::Level::Info);
 
-    match login(payload).await {
+    match login(users.as_ref(), &tokens, payload).await {
         Ok(user) => {
             info!("Login successful");
-            capture_message(&format!("Login successful "), sentry::Level::Info);
-            HttpResponse::Ok().json(user)
+            capture_message("Login successful", sentry::Level::Info);
+            Ok(with_session_cookie(&tokens, user))
         },
         Err(err) => {
             let error_message = format!("Login failed for user. Error: {:?}", err);
             capture_message(&error_message, sentry::Level::Error);
-            HttpResponse::BadRequest().body(err)
+            Err(err)
         },
     }
 }
 
+/// Create an account with the configured starting balance.
+#[utoipa::path(
+    operation_id = "register",
+    tag = "auth",
+    request_body = RegisterPayload,
+    responses(
+        (status = 200, description = "Account created", body = AuthResponse),
+        (status = 400, description = "Malformed request", body = ErrorResponse),
+        (status = 409, description = "Email already registered", body = ErrorResponse),
+        (status = 422, description = "Request validation failed", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
 #[post("/register")]
-async fn register_route(form: web::Json<AuthPayload>) -> HttpResponse {
+async fn register_route(users: web::Data<dyn UserRepository>, tokens: web::Data<SessionTokens>, config: web::Data<AppConfig>, form: ValidJson<RegisterPayload>) -> Result<HttpResponse, AppError> {
     let payload = form.into_inner();
     info!("Received registration request for user: {}", payload.email);
     capture_message(&format!("Received registration request for user: {}", payload.email), sentry::Level::Info);
 
-    match register(payload).await {
+    match register(users.as_ref(), &tokens, payload, config.trading.starting_balance).await {
         Ok(user) => {
             info!("Registration successful");
-            capture_message(&format!("Registration successful"), sentry::Level::Info);
-            HttpResponse::Ok().json(user)
+            capture_message("Registration successful", sentry::Level::Info);
+            Ok(with_session_cookie(&tokens, user))
         },
         Err(err) => {
             let error_message = format!("Registration failed for user. Error: {:?}", err);
             capture_message(&error_message, sentry::Level::Error);
-            HttpResponse::BadRequest().body(err)
+            Err(err)
         },
     }
 }
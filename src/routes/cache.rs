@@ -0,0 +1,23 @@
+use actix_web::{get, web, HttpResponse};
+use log::info;
+
+use crate::models::cache_models::CacheStatsResponse;
+use crate::services::cache_service::MarketDataCache;
+
+/// Hit rates and sizes of the market data caches.
+#[utoipa::path(
+    operation_id = "cache_stats",
+    tag = "operations",
+    responses(
+        (status = 200, description = "Cache statistics", body = CacheStatsResponse),
+    )
+)]
+#[get("/cache/stats")]
+async fn cache_stats_route(cache: web::Data<MarketDataCache>) -> HttpResponse {
+    info!("Received cache stats request");
+    HttpResponse::Ok().json(cache.stats())
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(cache_stats_route);
+}
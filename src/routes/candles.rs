@@ -0,0 +1,48 @@
+use actix_web::{get, web, HttpResponse};
+use sentry::capture_message;
+use log::info;
+
+use crate::error::{AppError, ErrorResponse};
+use crate::validation::ValidQuery;
+use crate::models::candle_models::{CandleQuery, CandleResponse};
+use crate::repositories::PriceRepository;
+use crate::services::candle_service::candles;
+
+/// OHLCV candles resampled to the requested interval and time zone.
+#[utoipa::path(
+    operation_id = "candles",
+    tag = "market data",
+    params(CandleQuery),
+    responses(
+        (status = 200, description = "Candles in time order", body = CandleResponse),
+        (status = 400, description = "Unknown or uneven interval, unknown time zone, or no stored series divides the interval", body = ErrorResponse),
+        (status = 404, description = "No matching price series", body = ErrorResponse),
+        (status = 422, description = "Request validation failed", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+#[get("/candles/{ticker}")]
+async fn candles_route(
+    prices: web::Data<dyn PriceRepository>,
+    path: web::Path<String>,
+    query: ValidQuery<CandleQuery>,
+) -> Result<HttpResponse, AppError> {
+    let ticker = path.into_inner();
+    info!("Received candle request for: {}", ticker);
+
+    match candles(prices.as_ref(), ticker.clone(), query.into_inner()).await {
+        Ok(response) => {
+            info!("Successfully built {} candles for: {}", response.candles.len(), ticker);
+            Ok(HttpResponse::Ok().json(response))
+        },
+        Err(err) => {
+            let error_message = format!("Failed to build candles for: {}. Error: {:?}", ticker, err);
+            capture_message(&error_message, sentry::Level::Error);
+            Err(err)
+        },
+    }
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(candles_route);
+}
@@ -0,0 +1,91 @@
+use actix_web::{get, post, web, HttpResponse};
+use log::{error, info};
+use crate::auth::Operator;
+use crate::error::{AppError, ErrorResponse};
+use crate::validation::{ValidJson, ValidQuery};
+use crate::models::corporate_action_models::{CorporateAction, CorporateActionPayload, CorporateActionRecorded};
+use crate::models::ledger_models::{LedgerEntry, LedgerQuery};
+use crate::repositories::CorporateActionRepository;
+use crate::services::corporate_action_service::{corporate_actions_for_ticker, create_corporate_action, ledger_for_user};
+
+/// Record a dividend or split to be applied on its ex-date. Operators only.
+#[utoipa::path(
+    operation_id = "create_corporate_action",
+    tag = "corporate actions",
+    request_body = CorporateActionPayload,
+    security(("operator_token" = [])),
+    responses(
+        (status = 200, description = "Corporate action recorded", body = CorporateActionRecorded),
+        (status = 400, description = "Malformed request", body = ErrorResponse),
+        (status = 401, description = "Missing or wrong operator token, or operator endpoints disabled", body = ErrorResponse),
+        (status = 422, description = "Request validation failed", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+#[post("/corporate-actions")]
+async fn create_corporate_action_route(_operator: Operator, actions: web::Data<dyn CorporateActionRepository>, payload: ValidJson<CorporateActionPayload>) -> Result<HttpResponse, AppError> {
+    let payload = payload.into_inner();
+    info!("Received corporate action for {}: {:?}", payload.ticker, payload.action);
+
+    match create_corporate_action(actions.as_ref(), payload).await {
+        Ok(action_id) => Ok(HttpResponse::Ok().json(CorporateActionRecorded {
+            message: "Corporate action recorded".to_string(),
+            corporate_action_id: action_id.to_hex(),
+        })),
+        Err(e) => {
+            error!("Failed to record corporate action: {}", e);
+            Err(e)
+        }
+    }
+}
+
+/// Corporate actions recorded for one ticker.
+#[utoipa::path(
+    operation_id = "list_corporate_actions",
+    tag = "corporate actions",
+    responses(
+        (status = 200, description = "Corporate actions by ex-date", body = Vec<CorporateAction>),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+#[get("/corporate-actions/{ticker}")]
+async fn corporate_actions_route(actions: web::Data<dyn CorporateActionRepository>, path: web::Path<String>) -> Result<HttpResponse, AppError> {
+    let ticker = path.into_inner();
+
+    match corporate_actions_for_ticker(actions.as_ref(), &ticker).await {
+        Ok(actions) => Ok(HttpResponse::Ok().json(actions)),
+        Err(e) => {
+            error!("Failed to fetch corporate actions for {}: {}", ticker, e);
+            Err(e)
+        }
+    }
+}
+
+/// Cash movements from corporate actions for one user.
+#[utoipa::path(
+    operation_id = "user_ledger",
+    tag = "corporate actions",
+    params(LedgerQuery),
+    responses(
+        (status = 200, description = "Ledger entries", body = Vec<LedgerEntry>),
+        (status = 400, description = "Malformed request", body = ErrorResponse),
+        (status = 422, description = "Request validation failed", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+#[get("/ledger")]
+async fn ledger_route(actions: web::Data<dyn CorporateActionRepository>, query: ValidQuery<LedgerQuery>) -> Result<HttpResponse, AppError> {
+    match ledger_for_user(actions.as_ref(), &query.user_id).await {
+        Ok(entries) => Ok(HttpResponse::Ok().json(entries)),
+        Err(e) => {
+            error!("Failed to fetch ledger for user_id: {}: {}", query.user_id, e);
+            Err(e)
+        }
+    }
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(create_corporate_action_route);
+    cfg.service(corporate_actions_route);
+    cfg.service(ledger_route);
+}
@@ -0,0 +1,40 @@
+use actix_web::{get, web, HttpResponse};
+use sentry::capture_message;
+use log::info;
+
+use crate::error::{AppError, ErrorResponse};
+use crate::models::data_quality_models::DataHealthReport;
+use crate::repositories::PriceRepository;
+use crate::services::data_quality_service::data_health;
+
+/// Validation report for every stored price series of a ticker.
+#[utoipa::path(
+    operation_id = "data_health",
+    tag = "operations",
+    responses(
+        (status = 200, description = "Health report", body = DataHealthReport),
+        (status = 404, description = "No price data for the ticker", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+#[get("/data-health/{ticker}")]
+async fn data_health_route(prices: web::Data<dyn PriceRepository>, path: web::Path<String>) -> Result<HttpResponse, AppError> {
+    let ticker = path.into_inner();
+    info!("Received data health request for: {}", ticker);
+
+    match data_health(prices.as_ref(), &ticker).await {
+        Ok(report) => {
+            info!("Checked {} price series for: {} (healthy: {})", report.series.len(), ticker, report.healthy);
+            Ok(HttpResponse::Ok().json(report))
+        },
+        Err(err) => {
+            let error_message = format!("Failed to check data health for: {}. Error: {:?}", ticker, err);
+            capture_message(&error_message, sentry::Level::Error);
+            Err(err)
+        },
+    }
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(data_health_route);
+}
@@ -0,0 +1,40 @@
+use actix_web::{get, web, HttpResponse};
+use sentry::capture_message;
+use log::info;
+
+use crate::error::{AppError, ErrorResponse};
+use crate::validation::ValidQuery;
+use crate::models::stock_models::{EarningsCalendarEntry, EarningsCalendarQuery};
+use crate::repositories::CompanyRepository;
+use crate::services::earnings_service::earnings_calendar;
+
+/// Upcoming earnings reports with estimates and the last surprise.
+#[utoipa::path(
+    operation_id = "earnings_calendar",
+    tag = "market data",
+    params(EarningsCalendarQuery),
+    responses(
+        (status = 200, description = "Reports by date", body = Vec<EarningsCalendarEntry>),
+        (status = 400, description = "Malformed request or bad date range", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+#[get("/earnings/calendar")]
+async fn earnings_calendar_route(companies: web::Data<dyn CompanyRepository>, query: ValidQuery<EarningsCalendarQuery>) -> Result<HttpResponse, AppError> {
+    info!("Received earnings calendar request");
+
+    match earnings_calendar(companies.as_ref(), query.into_inner()).await {
+        Ok(entries) => {
+            info!("Earnings calendar returned {} entries", entries.len());
+            Ok(HttpResponse::Ok().json(entries))
+        },
+        Err(err) => {
+            capture_message(&format!("Failed to build earnings calendar. Error: {:?}", err), sentry::Level::Error);
+            Err(err)
+        },
+    }
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(earnings_calendar_route);
+}
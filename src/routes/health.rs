@@ -0,0 +1,52 @@
+use actix_web::{get, web, HttpResponse};
+use log::warn;
+
+use crate::config::AppConfig;
+use crate::models::health_models::{HealthStatus, LivenessReport, ReadinessReport};
+use crate::repositories::{DatabaseProbe, PriceRepository};
+use crate::services::health_service::{readiness, HealthMonitor};
+
+/// Whether the process is up. Checks no dependencies, so a database outage
+/// never gets the server restarted.
+#[utoipa::path(
+    operation_id = "liveness",
+    tag = "health",
+    responses(
+        (status = 200, description = "The server is running", body = LivenessReport),
+    )
+)]
+#[get("/health/live")]
+async fn liveness_route(monitor: web::Data<HealthMonitor>) -> HttpResponse {
+    HttpResponse::Ok().json(monitor.liveness())
+}
+
+/// Whether the server can take traffic: MongoDB reachability, background
+/// job status and the age of the newest stored price.
+#[utoipa::path(
+    operation_id = "readiness",
+    tag = "health",
+    responses(
+        (status = 200, description = "Ready; `status` is `warn` when a check is degraded", body = ReadinessReport),
+        (status = 503, description = "A check failed", body = ReadinessReport),
+    )
+)]
+#[get("/health/ready")]
+async fn readiness_route(
+    monitor: web::Data<HealthMonitor>,
+    database: web::Data<dyn DatabaseProbe>,
+    prices: web::Data<dyn PriceRepository>,
+    config: web::Data<AppConfig>,
+) -> HttpResponse {
+    let report = readiness(&monitor, database.as_ref(), &prices.into_inner(), config.health.max_data_age()).await;
+    if report.status == HealthStatus::Fail {
+        warn!("Readiness check failed: {}", serde_json::to_string(&report.checks).unwrap_or_default());
+        HttpResponse::ServiceUnavailable().json(report)
+    } else {
+        HttpResponse::Ok().json(report)
+    }
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(liveness_route);
+    cfg.service(readiness_route);
+}
@@ -0,0 +1,48 @@
+use actix_web::{get, web, HttpResponse};
+use sentry::capture_message;
+use log::info;
+
+use crate::error::{AppError, ErrorResponse};
+use crate::validation::ValidQuery;
+use crate::models::indicator_models::{IndicatorQuery, IndicatorResponse};
+use crate::repositories::PriceRepository;
+use crate::services::indicator_service::indicators;
+
+/// Technical indicators computed over a stored price series.
+#[utoipa::path(
+    operation_id = "indicators",
+    tag = "market data",
+    params(IndicatorQuery),
+    responses(
+        (status = 200, description = "Indicator series aligned with the timestamps", body = IndicatorResponse),
+        (status = 400, description = "Unknown indicator, bad window, or a series without the highs, lows or volumes an indicator needs", body = ErrorResponse),
+        (status = 404, description = "No matching price series", body = ErrorResponse),
+        (status = 422, description = "Request validation failed", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+#[get("/indicators/{ticker}")]
+async fn indicators_route(
+    prices: web::Data<dyn PriceRepository>,
+    path: web::Path<String>,
+    query: ValidQuery<IndicatorQuery>,
+) -> Result<HttpResponse, AppError> {
+    let ticker = path.into_inner();
+    info!("Received indicator request for: {}", ticker);
+
+    match indicators(prices.as_ref(), ticker.clone(), query.into_inner()).await {
+        Ok(response) => {
+            info!("Successfully computed indicators for: {}", ticker);
+            Ok(HttpResponse::Ok().json(response))
+        },
+        Err(err) => {
+            let error_message = format!("Failed to compute indicators for: {}. Error: {:?}", ticker, err);
+            capture_message(&error_message, sentry::Level::Error);
+            Err(err)
+        },
+    }
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(indicators_route);
+}
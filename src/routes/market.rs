@@ -0,0 +1,49 @@
+use actix_web::{get, web, HttpResponse};
+use log::info;
+
+use crate::error::{AppError, ErrorResponse};
+use crate::validation::ValidQuery;
+use crate::models::market_models::{MarketOverviewQuery, MarketOverviewResponse, MoversQuery, MoversResponse};
+use crate::services::market_service::MarketOverview;
+
+/// Top gainers, losers or most active stocks.
+#[utoipa::path(
+    operation_id = "market_movers",
+    tag = "market data",
+    params(MoversQuery),
+    responses(
+        (status = 200, description = "Ranked movers", body = MoversResponse),
+        (status = 400, description = "Malformed request", body = ErrorResponse),
+        (status = 422, description = "Request validation failed", body = ErrorResponse),
+        (status = 503, description = "Market snapshot not computed yet", body = ErrorResponse),
+    )
+)]
+#[get("/market/movers")]
+async fn movers_route(overview: web::Data<MarketOverview>, query: ValidQuery<MoversQuery>) -> Result<HttpResponse, AppError> {
+    let query = query.into_inner();
+    info!("Received market movers request: {:?}", query);
+    Ok(HttpResponse::Ok().json(overview.movers(query)?))
+}
+
+/// Gainers, losers and most active stocks in one response.
+#[utoipa::path(
+    operation_id = "market_overview",
+    tag = "market data",
+    params(MarketOverviewQuery),
+    responses(
+        (status = 200, description = "Market overview", body = MarketOverviewResponse),
+        (status = 400, description = "Malformed request", body = ErrorResponse),
+        (status = 422, description = "Request validation failed", body = ErrorResponse),
+        (status = 503, description = "Market snapshot not computed yet", body = ErrorResponse),
+    )
+)]
+#[get("/market/overview")]
+async fn overview_route(overview: web::Data<MarketOverview>, query: ValidQuery<MarketOverviewQuery>) -> Result<HttpResponse, AppError> {
+    info!("Received market overview request");
+    Ok(HttpResponse::Ok().json(overview.overview(query.sector.as_deref(), query.limit)?))
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(movers_route);
+    cfg.service(overview_route);
+}
@@ -1,16 +1,54 @@
 pub mod auth;
 pub mod stock_listing;
 pub mod stock_details;
-use actix_web::web;
+use actix_web::{web, Scope};
+use crate::error::{json_config, path_config, query_config};
+use crate::openapi;
 pub mod trade_route;
+pub mod activity;
+pub mod indicators;
+pub mod candles;
+pub mod screener;
+pub mod search;
+pub mod taxonomy;
+pub mod market;
+pub mod earnings;
+pub mod corporate_actions;
+pub mod data_health;
+pub mod cache;
+pub mod health;
+pub mod v2;
 
 pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    openapi::configure_routes(cfg);
+    // Probes sit outside `/api` so they are not versioned with it.
+    cfg.configure(health::configure_routes);
+    // Registered before `/api`, whose scope would otherwise claim `/api/v2/...`.
+    cfg.service(api_scope("/api/v2").configure(v2::configure_routes));
     cfg.service(
-        web::scope("/api")
+        api_scope("/api")
             .configure(auth::configure_routes)
             .configure(stock_listing::configure_routes)
             .configure(stock_details::configure_routes)
             .configure(trade_route::configure_routes)
-
+            .configure(activity::configure_routes)
+            .configure(indicators::configure_routes)
+            .configure(candles::configure_routes)
+            .configure(screener::configure_routes)
+            .configure(search::configure_routes)
+            .configure(taxonomy::configure_routes)
+            .configure(market::configure_routes)
+            .configure(earnings::configure_routes)
+            .configure(corporate_actions::configure_routes)
+            .configure(data_health::configure_routes)
+            .configure(cache::configure_routes)
     );
 }
+
+/// A scope whose extractor failures are reported in the JSON error envelope.
+fn api_scope(path: &str) -> Scope {
+    web::scope(path)
+        .app_data(json_config())
+        .app_data(query_config())
+        .app_data(path_config())
+}
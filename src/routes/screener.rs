@@ -0,0 +1,48 @@
+use actix_web::{post, web, HttpResponse};
+use crate::error::{AppError, ErrorResponse};
+use crate::validation::ValidJson;
+use crate::repositories::{CompanyRepository, PriceRepository};
+use crate::services::cache_service::MarketDataCache;
+use crate::services::screener_service::screen_stocks;
+use crate::models::screener_models::{ScreenerPayload, ScreenerResponse};
+use sentry::capture_message;
+use log::info;
+
+/// Filter and sort companies by fundamentals and recent return.
+#[utoipa::path(
+    operation_id = "screen_stocks",
+    tag = "stocks",
+    request_body = ScreenerPayload,
+    responses(
+        (status = 200, description = "One page of matching companies", body = ScreenerResponse),
+        (status = 400, description = "Malformed request or unknown field", body = ErrorResponse),
+        (status = 422, description = "Request validation failed", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+#[post("/screener")]
+async fn screener_route(
+    companies: web::Data<dyn CompanyRepository>,
+    prices: web::Data<dyn PriceRepository>,
+    cache: web::Data<MarketDataCache>,
+    form: ValidJson<ScreenerPayload>,
+) -> Result<HttpResponse, AppError> {
+    let payload = form.into_inner();
+    info!("Received screener request with {} filters", payload.filters.len());
+
+    match screen_stocks(payload, companies.as_ref(), prices.as_ref(), cache.as_ref()).await {
+        Ok(response) => {
+            info!("Screener matched {} stocks.", response.total);
+            Ok(HttpResponse::Ok().json(response))
+        },
+        Err(err) => {
+            let error_message = format!("Failed to screen stocks. Error: {:?}", err);
+            capture_message(&error_message, sentry::Level::Error);
+            Err(err)
+        },
+    }
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(screener_route);
+}
@@ -0,0 +1,43 @@
+use actix_web::{get, web, HttpResponse};
+use sentry::capture_message;
+use log::info;
+
+use crate::error::{AppError, ErrorResponse};
+use crate::validation::ValidQuery;
+use crate::models::search_models::{SearchQuery, SearchResponse};
+use crate::repositories::CompanyRepository;
+use crate::services::search_service::search_stocks;
+
+/// Search companies by ticker, name or business summary.
+#[utoipa::path(
+    operation_id = "search_stocks",
+    tag = "stocks",
+    params(SearchQuery),
+    responses(
+        (status = 200, description = "Best matches first", body = SearchResponse),
+        (status = 400, description = "Malformed request or unusable query", body = ErrorResponse),
+        (status = 422, description = "Request validation failed", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+#[get("/search")]
+async fn search_route(companies: web::Data<dyn CompanyRepository>, query: ValidQuery<SearchQuery>) -> Result<HttpResponse, AppError> {
+    let query = query.into_inner();
+    info!("Received search request for: {}", query.q);
+
+    match search_stocks(companies.as_ref(), query.q.clone(), query.limit).await {
+        Ok(response) => {
+            info!("Search for '{}' returned {} results", query.q, response.results.len());
+            Ok(HttpResponse::Ok().json(response))
+        },
+        Err(err) => {
+            let error_message = format!("Search failed for: {}. Error: {:?}", query.q, err);
+            capture_message(&error_message, sentry::Level::Error);
+            Err(err)
+        },
+    }
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(search_route);
+}
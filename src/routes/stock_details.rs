@@ -1,33 +1,64 @@
-use actix_web::{web, post, HttpResponse, Error};
-use mongodb::Client;
-use serde::Deserialize;
+use actix_web::{web, post, HttpResponse};
 use sentry::capture_message;
 use log::info;
 
+use crate::error::{AppError, ErrorResponse};
+use crate::models::stock_models::{DetailLevel, StockDetailsResponse, StockQuery};
+use crate::validation::ValidQuery;
+use crate::repositories::{CompanyRepository, PriceRepository};
+use crate::services::cache_service::MarketDataCache;
 use crate::services::stock_service::stock_details;
-use crate::models::stock_models::StockDetailsResponse;
 
-#[derive(Deserialize)]
-pub struct StockQuery {
-    detail_level: Option<String>,
+/// Sections the first version of this endpoint always returned, as null when
+/// the company had no such data.
+const V1_SECTIONS: [&str; 4] = ["financials", "key_statistics", "price_data", "profile"];
+
+/// The response body, with missing [`V1_SECTIONS`] as nulls when the request
+/// selected them. Sections added since are left out when missing.
+fn v1_body(details: &StockDetailsResponse, selects_v1_sections: bool) -> serde_json::Value {
+    let mut body = serde_json::to_value(details).unwrap_or_default();
+    if let (true, Some(fields)) = (selects_v1_sections, body.as_object_mut()) {
+        for section in V1_SECTIONS {
+            fields.entry(section).or_insert(serde_json::Value::Null);
+        }
+    }
+    body
 }
 
+/// Company profile, fundamentals, quote and price history for one ticker.
+#[utoipa::path(
+    operation_id = "stock_details",
+    tag = "stocks",
+    params(StockQuery),
+    responses(
+        (status = 200, description = "Requested sections of the stock", body = StockDetailsResponse),
+        (status = 400, description = "Unknown detail_level or field", body = ErrorResponse),
+        (status = 422, description = "Request validation failed", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
 #[post("/stock-details/{ticker}")]
-async fn stock_list_route(
-    data: web::Data<Client>,
+async fn stock_details_route(
+    companies: web::Data<dyn CompanyRepository>,
+    prices: web::Data<dyn PriceRepository>,
+    cache: web::Data<MarketDataCache>,
     path: web::Path<String>, 
-    query: web::Query<StockQuery>
-) -> Result<HttpResponse, Error> {
+    query: ValidQuery<StockQuery>
+) -> Result<HttpResponse, AppError> {
     let ticker = path.into_inner();
+    let query = query.into_inner();
 
     info!("Received request for stock details: {}", ticker);
     capture_message(&format!("Received request for stock details: {}", ticker), sentry::Level::Info);
+    // Without `fields`, every level but `summary` selects every section.
+    let selects_v1_sections = query.fields.is_none()
+        && query.detail_level.as_deref().and_then(DetailLevel::parse) != Some(DetailLevel::Summary);
     let ticker_ref = &ticker;
-    match stock_details(data, ticker_ref.clone()).await {
+    match stock_details(companies.as_ref(), prices.as_ref(), &cache, ticker_ref.clone(), query.detail_level, query.fields, query.adjusted.unwrap_or(false)).await {
         Ok(stock_details) => {
             info!("Successfully retrieved stock details for: {}", ticker_ref);
             capture_message(&format!("Successfully retrieved stock details for: {}", ticker_ref), sentry::Level::Info);
-            Ok(HttpResponse::Ok().json(stock_details))
+            Ok(HttpResponse::Ok().json(v1_body(&stock_details, selects_v1_sections)))
         },
         Err(err) => {
             let error_message = format!("Failed to retrieve stock details for: {}. Error: {:?}", ticker, err);
@@ -38,5 +69,5 @@ async fn stock_list_route(
 }
 
 pub fn configure_routes(cfg: &mut web::ServiceConfig) {
-    cfg.service(stock_list_route);
+    cfg.service(stock_details_route);
 }
@@ -1,26 +1,46 @@
 use actix_web::{post, web, HttpResponse};
-use mongodb::Client;
-use crate::services::stock_service::stockList;
-use crate::models::stock_models::StockListingPayload;
+use crate::error::{AppError, ErrorResponse};
+use crate::validation::ValidJson;
+use crate::repositories::{CompanyRepository, PriceRepository};
+use crate::services::cache_service::MarketDataCache;
+use crate::services::stock_service::list_stocks;
+use crate::models::stock_models::{StockListingPayload, StockListingResponse};
 use sentry::capture_message;
 use log::info;
 
+/// List the companies of a sector with their recent daily closes.
+#[utoipa::path(
+    operation_id = "list_stocks",
+    tag = "stocks",
+    request_body = StockListingPayload,
+    responses(
+        (status = 200, description = "One page of companies", body = StockListingResponse),
+        (status = 400, description = "Malformed request", body = ErrorResponse),
+        (status = 422, description = "Request validation failed", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
 #[post("/stock-list")]
-async fn stock_list_route(data: web::Data<Client>, form: web::Json<StockListingPayload>) -> HttpResponse {
+async fn stock_list_route(
+    companies: web::Data<dyn CompanyRepository>,
+    prices: web::Data<dyn PriceRepository>,
+    cache: web::Data<MarketDataCache>,
+    form: ValidJson<StockListingPayload>,
+) -> Result<HttpResponse, AppError> {
     let payload = form.into_inner();
     info!("Received stock list request with payload: {:?}", payload.industry);
     capture_message(&format!("Received stock list request with payload: {:?}", payload.industry), sentry::Level::Info);
 
-    match stockList(payload, data).await {
+    match list_stocks(payload, companies.as_ref(), prices.as_ref(), &cache).await {
         Ok(stock_list) => {
             info!("Successfully retrieved stock list.");
             capture_message("Successfully retrieved stock list.", sentry::Level::Info);
-            HttpResponse::Ok().json(stock_list)
+            Ok(HttpResponse::Ok().json(stock_list))
         },
         Err(err) => {
             let error_message = format!("Failed to retrieve stock list. Error: {:?}", err);
             capture_message(&error_message, sentry::Level::Error);
-            HttpResponse::BadRequest().body(err.to_string())
+            Err(err)
         },
     }
 }
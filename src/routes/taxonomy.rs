@@ -0,0 +1,66 @@
+use actix_web::{get, web, HttpResponse};
+use sentry::capture_message;
+use log::info;
+
+use crate::error::{AppError, ErrorResponse};
+use crate::models::taxonomy_models::{IndustrySummary, SectorSummary};
+use crate::repositories::{CompanyRepository, PriceRepository};
+use crate::services::cache_service::MarketDataCache;
+use crate::services::taxonomy_service::{list_industries, list_sectors};
+
+/// Every sector with its industries and aggregate statistics.
+#[utoipa::path(
+    operation_id = "list_sectors",
+    tag = "stocks",
+    responses(
+        (status = 200, description = "Sectors by name", body = Vec<SectorSummary>),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+#[get("/sectors")]
+async fn sectors_route(companies: web::Data<dyn CompanyRepository>, prices: web::Data<dyn PriceRepository>, cache: web::Data<MarketDataCache>) -> Result<HttpResponse, AppError> {
+    info!("Received sector listing request");
+
+    match list_sectors(companies.as_ref(), prices.as_ref(), &cache).await {
+        Ok(sectors) => {
+            info!("Successfully listed {} sectors", sectors.len());
+            Ok(HttpResponse::Ok().json(sectors))
+        },
+        Err(err) => {
+            capture_message(&format!("Failed to list sectors. Error: {:?}", err), sentry::Level::Error);
+            Err(err)
+        },
+    }
+}
+
+/// Industries of one sector with aggregate statistics.
+#[utoipa::path(
+    operation_id = "list_industries",
+    tag = "stocks",
+    responses(
+        (status = 200, description = "Industries by name", body = Vec<IndustrySummary>),
+        (status = 404, description = "Unknown sector", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+#[get("/sectors/{sector}/industries")]
+async fn industries_route(companies: web::Data<dyn CompanyRepository>, prices: web::Data<dyn PriceRepository>, cache: web::Data<MarketDataCache>, path: web::Path<String>) -> Result<HttpResponse, AppError> {
+    let sector = path.into_inner();
+    info!("Received industry listing request for sector: {}", sector);
+
+    match list_industries(companies.as_ref(), prices.as_ref(), &cache, sector.clone()).await {
+        Ok(industries) => {
+            info!("Successfully listed {} industries for sector: {}", industries.len(), sector);
+            Ok(HttpResponse::Ok().json(industries))
+        },
+        Err(err) => {
+            capture_message(&format!("Failed to list industries for sector: {}. Error: {:?}", sector, err), sentry::Level::Error);
+            Err(err)
+        },
+    }
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(sectors_route);
+    cfg.service(industries_route);
+}
@@ -1,55 +1,80 @@
-use actix_web::{post, get, web, HttpResponse, Responder};
+use actix_web::{post, get, web, HttpResponse};
 use serde::Deserialize;
-use crate::services::trade_service::{create_trade, update_user_balance_and_trades, get_user_trades};
-use crate::models::trade_models::TradeData;
-use sentry::capture_message;
+use utoipa::IntoParams;
+use validator::Validate;
+use crate::services::trade_service::{get_user_trades, place_order};
+use crate::error::{AppError, ErrorResponse};
+use crate::validation::{object_id, ValidJson, ValidQuery};
+use crate::models::trade_models::{Trade, TradeData, TradeSubmitted};
+use crate::repositories::{TradeRepository, UserRepository};
+use crate::services::activity_service::ActivityFeed;
 use log::{debug, error, info};
-use serde_json::json;
 
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Validate, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct UserIdQuery {
+    #[validate(custom(function = "object_id"))]
     pub user_id: String,
 }
+
+/// Open a trade and debit its amount from the user's balance.
+#[utoipa::path(
+    operation_id = "submit_trade",
+    tag = "trades",
+    request_body = TradeData,
+    responses(
+        (status = 200, description = "Trade opened", body = TradeSubmitted),
+        (status = 400, description = "Malformed request", body = ErrorResponse),
+        (status = 422, description = "Request validation failed", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
 #[post("/trade_submit")]
-pub async fn submit_trade(trade_data: web::Json<TradeData>) -> impl Responder {
+pub async fn submit_trade(
+    users: web::Data<dyn UserRepository>,
+    trades: web::Data<dyn TradeRepository>,
+    trade_data: ValidJson<TradeData>,
+    feed: web::Data<ActivityFeed>,
+) -> Result<HttpResponse, AppError> {
     info!("Received trade submission request: {:?}", trade_data);
 
-    match create_trade(&trade_data).await {
-        Ok(trade_id) => {
-            if let Err(e) = update_user_balance_and_trades(&trade_data.user_id, &trade_id, trade_data.amount).await {
-                error!("Failed to update user balance and trades: {}", e);
-                return HttpResponse::InternalServerError().json(json!({
-                    "error": "Failed to update user balance and trades"
-                }));
-            }
-
-            HttpResponse::Ok().json(json!({
-                "message": "Trade submitted successfully",
-                "trade_id": trade_id.to_hex()
-            }))
-        },
+    match place_order(users.as_ref(), trades.as_ref(), &trade_data, &feed).await {
+        Ok(trade) => Ok(HttpResponse::Ok().json(TradeSubmitted {
+            message: "Trade submitted successfully".to_string(),
+            trade_id: trade.id.map(|id| id.to_hex()).unwrap_or_default(),
+        })),
         Err(e) => {
-            error!("Failed to create trade: {}", e);
-            HttpResponse::InternalServerError().json(json!({
-                "error": "Failed to create trade"
-            }))
+            error!("Failed to submit trade: {}", e);
+            Err(e)
         }
     }
 }
+/// All trades of one user.
+#[utoipa::path(
+    operation_id = "list_user_trades",
+    tag = "trades",
+    params(UserIdQuery),
+    responses(
+        (status = 200, description = "The user's trades", body = Vec<Trade>),
+        (status = 400, description = "Malformed request", body = ErrorResponse),
+        (status = 422, description = "Request validation failed", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
 #[get("/user_trades")]
-pub async fn get_trades(query: web::Query<UserIdQuery>) -> HttpResponse {
+pub async fn get_trades(trades: web::Data<dyn TradeRepository>, query: ValidQuery<UserIdQuery>) -> Result<HttpResponse, AppError> {
     let user_id = &query.user_id;
     debug!("Received request to get trades for user_id: {}", user_id);
 
-    match get_user_trades(user_id).await {
+    match get_user_trades(trades.as_ref(), user_id).await {
         Ok(trades) => {
             info!("Successfully fetched trades for user_id: {}", user_id);
-            HttpResponse::Ok().json(trades)
+            Ok(HttpResponse::Ok().json(trades))
         },
         Err(err) => {
             error!("Failed to fetch trades for user_id: {}: {}", user_id, err);
-            HttpResponse::InternalServerError().body(format!("Failed to fetch trades: {}", err))
+            Err(err)
         }
     }
 }
@@ -0,0 +1,15 @@
+//! Version 2 of the API, served under `/api/v2`.
+//!
+//! Resources are addressed RESTfully (`GET /stocks/{ticker}`, `POST /orders`,
+//! `GET /orders`) and ids are plain hex strings. Handlers call the same
+//! services as their v1 counterparts, which stay unchanged under `/api`.
+
+use actix_web::web;
+
+pub mod orders;
+pub mod stocks;
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    cfg.configure(stocks::configure_routes);
+    cfg.configure(orders::configure_routes);
+}
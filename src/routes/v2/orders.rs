@@ -0,0 +1,69 @@
+use actix_web::{get, post, web, HttpResponse};
+use log::{error, info};
+
+use crate::error::{AppError, ErrorResponse};
+use crate::models::order_models::{Order, OrderListQuery, OrderRequest};
+use crate::models::trade_models::TradeData;
+use crate::repositories::{TradeRepository, UserRepository};
+use crate::services::activity_service::ActivityFeed;
+use crate::services::trade_service::{get_user_trades, place_order};
+use crate::validation::{ValidJson, ValidQuery};
+
+/// Open an order and debit its amount from the user's balance.
+#[utoipa::path(
+    operation_id = "create_order",
+    tag = "v2",
+    request_body = OrderRequest,
+    responses(
+        (status = 201, description = "Order opened", body = Order),
+        (status = 400, description = "Malformed request", body = ErrorResponse),
+        (status = 422, description = "Request validation failed", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+#[post("/orders")]
+async fn create_order(
+    users: web::Data<dyn UserRepository>,
+    trades: web::Data<dyn TradeRepository>,
+    feed: web::Data<ActivityFeed>,
+    order: ValidJson<OrderRequest>,
+) -> Result<HttpResponse, AppError> {
+    let trade_data = TradeData::from(order.into_inner());
+    info!("Received order for {} {} of {}", trade_data.position, trade_data.quantity, trade_data.ticker);
+
+    match place_order(users.as_ref(), trades.as_ref(), &trade_data, &feed).await {
+        Ok(trade) => Ok(HttpResponse::Created().json(Order::from(trade))),
+        Err(e) => {
+            error!("Failed to place order: {}", e);
+            Err(e)
+        }
+    }
+}
+
+/// All orders of one user.
+#[utoipa::path(
+    operation_id = "list_orders",
+    tag = "v2",
+    params(OrderListQuery),
+    responses(
+        (status = 200, description = "The user's orders", body = Vec<Order>),
+        (status = 400, description = "Malformed request", body = ErrorResponse),
+        (status = 422, description = "Request validation failed", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+#[get("/orders")]
+async fn list_orders(trades: web::Data<dyn TradeRepository>, query: ValidQuery<OrderListQuery>) -> Result<HttpResponse, AppError> {
+    match get_user_trades(trades.as_ref(), &query.user_id).await {
+        Ok(trades) => Ok(HttpResponse::Ok().json(trades.into_iter().map(Order::from).collect::<Vec<_>>())),
+        Err(e) => {
+            error!("Failed to fetch orders for user_id: {}: {}", query.user_id, e);
+            Err(e)
+        }
+    }
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(create_order);
+    cfg.service(list_orders);
+}
@@ -0,0 +1,51 @@
+use actix_web::{get, web, HttpResponse};
+use log::info;
+use sentry::capture_message;
+
+use crate::error::{AppError, ErrorResponse};
+use crate::models::stock_models::{StockDetailsResponse, StockQuery};
+use crate::repositories::{CompanyRepository, PriceRepository};
+use crate::services::cache_service::MarketDataCache;
+use crate::services::stock_service::{company_exists, stock_details};
+use crate::validation::ValidQuery;
+
+/// Company profile, fundamentals, quote and price history for one ticker.
+#[utoipa::path(
+    operation_id = "get_stock",
+    tag = "v2",
+    params(StockQuery),
+    responses(
+        (status = 200, description = "Requested sections of the stock", body = StockDetailsResponse),
+        (status = 400, description = "Unknown detail_level or field", body = ErrorResponse),
+        (status = 404, description = "Unknown ticker", body = ErrorResponse),
+        (status = 500, description = "Database error", body = ErrorResponse),
+    )
+)]
+#[get("/stocks/{ticker}")]
+async fn get_stock(
+    companies: web::Data<dyn CompanyRepository>,
+    prices: web::Data<dyn PriceRepository>,
+    cache: web::Data<MarketDataCache>,
+    path: web::Path<String>,
+    query: ValidQuery<StockQuery>,
+) -> Result<HttpResponse, AppError> {
+    let ticker = path.into_inner();
+    let query = query.into_inner();
+    info!("Received v2 request for stock: {}", ticker);
+
+    if !company_exists(companies.as_ref(), &cache, &ticker).await? {
+        return Err(AppError::NotFound(format!("Unknown ticker: {}", ticker)));
+    }
+
+    match stock_details(companies.as_ref(), prices.as_ref(), &cache, ticker.clone(), query.detail_level, query.fields, query.adjusted.unwrap_or(true)).await {
+        Ok(stock) => Ok(HttpResponse::Ok().json(stock)),
+        Err(err) => {
+            capture_message(&format!("Failed to retrieve stock: {}. Error: {:?}", ticker, err), sentry::Level::Error);
+            Err(err)
+        },
+    }
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_stock);
+}
@@ -0,0 +1,74 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use chrono::Utc;
+use log::debug;
+use tokio::sync::broadcast;
+use crate::models::activity_models::{AccountEvent, AccountEventKind};
+
+const HISTORY_CAPACITY: usize = 1024;
+const CHANNEL_CAPACITY: usize = 256;
+
+struct FeedState {
+    next_id: u64,
+    history: VecDeque<AccountEvent>,
+}
+
+/// In-process fan-out of account activity. Every published event gets a
+/// monotonically increasing id and is kept in a bounded history so that a
+/// reconnecting SSE client can resume from its `Last-Event-ID`.
+pub struct ActivityFeed {
+    sender: broadcast::Sender<AccountEvent>,
+    state: Mutex<FeedState>,
+}
+
+impl ActivityFeed {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        ActivityFeed {
+            sender,
+            state: Mutex::new(FeedState { next_id: 1, history: VecDeque::with_capacity(HISTORY_CAPACITY) }),
+        }
+    }
+
+    pub fn publish(&self, user_id: &str, kind: AccountEventKind) {
+        let mut state = self.state.lock().unwrap();
+        let event = AccountEvent {
+            id: state.next_id,
+            user_id: user_id.to_string(),
+            timestamp: Utc::now(),
+            kind,
+        };
+        state.next_id += 1;
+
+        if state.history.len() == HISTORY_CAPACITY {
+            state.history.pop_front();
+        }
+        state.history.push_back(event.clone());
+
+        debug!("Publishing {} event {} for user: {}", event.kind.name(), event.id, user_id);
+        // Sending only fails when nobody is subscribed, which is fine.
+        let _ = self.sender.send(event);
+    }
+
+    /// Returns the buffered events for `user_id` newer than `last_event_id`
+    /// together with a receiver for everything published afterwards. Both are
+    /// taken under the same lock so no event can fall between them.
+    pub fn subscribe(&self, user_id: &str, last_event_id: Option<u64>) -> (Vec<AccountEvent>, broadcast::Receiver<AccountEvent>) {
+        let state = self.state.lock().unwrap();
+        let replay = match last_event_id {
+            Some(last_id) => state.history
+                .iter()
+                .filter(|event| event.id > last_id && event.user_id == user_id)
+                .cloned()
+                .collect(),
+            None => Vec::new(),
+        };
+        (replay, self.sender.subscribe())
+    }
+}
+
+impl Default for ActivityFeed {
+    fn default() -> Self {
+        Self::new()
+    }
+}
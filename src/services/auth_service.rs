@@ -1,56 +1,67 @@
 use log::{info, warn, error};
-use mongodb::{bson::{doc, oid::ObjectId}, Collection, Database, Client};
 use bcrypt::{hash, verify, DEFAULT_COST};
-use crate::models::users::{User, AuthPayload, AuthResponse, UserNotifications, UserProfile, UserSettings};
-use crate::db::mongo;
+use crate::models::users::{User, AuthPayload, AuthResponse, RegisterPayload, UserNotifications, UserProfile, UserSettings};
+use crate::auth::SessionTokens;
+use crate::error::{AppError, AppResult};
+use crate::repositories::UserRepository;
 use sentry::capture_message;
 
-pub async fn get_database() -> Result<Database, String> {
-    let client: Client = mongo::init().await.map_err(|e| e.to_string())?;
-    Ok(client.database("trading_simulator"))
+/// Whether `password` matches the stored bcrypt `hash`. `verify` reports a
+/// wrong password as `Ok(false)`, so the flag itself must be checked; an
+/// `is_ok()` test accepts any password. A hash that cannot be parsed never
+/// matches.
+fn password_matches(password: &str, hash: &str) -> bool {
+    match verify(password, hash) {
+        Ok(matches) => matches,
+        Err(err) => {
+            error!("Stored password hash could not be checked: {}", err);
+            false
+        }
+    }
 }
 
-pub async fn login(payload: AuthPayload) -> Result<AuthResponse, String> {
-    let db = get_database().await?;
-    let users: Collection<User> = db.collection("users");
-
-    match users.find_one(doc! {"email": &payload.email}, None).await {
+pub async fn login(users: &dyn UserRepository, tokens: &SessionTokens, payload: AuthPayload) -> AppResult<AuthResponse> {
+    match users.find_by_email(&payload.email).await {
         Ok(Some(user)) => {
-            if verify(&payload.password, &user.password).is_ok() {
+            if password_matches(&payload.password, &user.password) {
                 info!("Login successful for: {}", payload.email);
                 capture_message(&format!("Login successful for: {}", payload.email), sentry::Level::Info);
+                let user_id = user.id.map(|id| id.to_hex());
                 Ok(AuthResponse {
                     success: true,
                     message: "Login successful".to_string(),
-                    user_id: user.id.map(|id| id.to_hex())
+                    token: user_id.as_deref().map(|user_id| tokens.issue(user_id)),
+                    user_id,
                 })
             } else {
                 warn!("Failed login attempt for: {}", payload.email);
                 capture_message(&format!("Failed login attempt for: {}", payload.email), sentry::Level::Warning);
-                Err("Invalid email or password".into())
+                Err(AppError::InvalidCredentials)
             }
         },
         Ok(None) => {
             warn!("No user found for email: {}", payload.email);
             capture_message(&format!("No user found for email: {}", payload.email), sentry::Level::Warning);
-            Err("User not found".into())
+            Err(AppError::UserNotFound)
         },
         Err(e) => {
             error!("Database error during login: {}", e);
             capture_message(&format!("Database error during login: {}", e), sentry::Level::Error);
-            Err("Internal server error".into())
+            Err(e.into())
         }
     }
 }
 
-pub async fn register(payload: AuthPayload) -> Result<AuthResponse, String> {
-    let db = get_database().await?;
-    let users: Collection<User> = db.collection("users");
-
-    if users.find_one(doc! {"email": &payload.email}, None).await.unwrap().is_some() {
+pub async fn register(users: &dyn UserRepository, tokens: &SessionTokens, payload: RegisterPayload, starting_balance: f64) -> AppResult<AuthResponse> {
+    let existing = users.find_by_email(&payload.email).await.map_err(|e| {
+        error!("Database error during registration: {}", e);
+        capture_message(&format!("Database error during registration: {}", e), sentry::Level::Error);
+        e
+    })?;
+    if existing.is_some() {
         warn!("Registration attempt for already registered email: {}", payload.email);
         capture_message(&format!("Registration attempt for already registered email: {}", payload.email), sentry::Level::Warning);
-        return Err("Email already registered".into());
+        return Err(AppError::EmailTaken);
     }
 
     let hashed_password = match hash(&payload.password, DEFAULT_COST) {
@@ -58,7 +69,7 @@ pub async fn register(payload: AuthPayload) -> Result<AuthResponse, String> {
         Err(_) => {
             error!("Password hashing failed for: {}", payload.email);
             capture_message(&format!("Password hashing failed for: {}", payload.email), sentry::Level::Error);
-            return Err("Password hashing failed".into());
+            return Err(AppError::Internal("Password hashing failed".to_string()));
         }
     };
 
@@ -69,25 +80,25 @@ pub async fn register(payload: AuthPayload) -> Result<AuthResponse, String> {
         name: None,
         profile: UserProfile { bio: None, avatar_url: None },
         settings: UserSettings { theme: "light".to_string(), notifications: UserNotifications { email: true, sms: false } },
-        balance: 10000.0,
+        balance: starting_balance,
         trades: vec![],
     };
 
-    match users.insert_one(new_user, None).await {
-        Ok(insert_result) => {
-            let user_id = insert_result.inserted_id.as_object_id().unwrap();
+    match users.insert(new_user).await {
+        Ok(user_id) => {
             info!("User registration successful for: {}", payload.email);
             capture_message(&format!("User registration successful for: {}", payload.email), sentry::Level::Info);
             Ok(AuthResponse {
                 success: true,
                 message: "Registration successful".to_string(),
-                user_id: Some(user_id.to_hex())
+                user_id: Some(user_id.to_hex()),
+                token: Some(tokens.issue(&user_id.to_hex())),
             })
         },
         Err(e) => {
             error!("Failed to register user: {}", e);
             capture_message(&format!("Failed to register user: {}", e), sentry::Level::Error);
-            Err("Internal server error".into())
+            Err(e.into())
         }
     }
 }
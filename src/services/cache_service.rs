@@ -0,0 +1,363 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use chrono::Utc;
+use futures::TryStreamExt;
+use log::{debug, info};
+use mongodb::{
+    bson::{doc, Document},
+    options::{FindOneOptions, UpdateOptions},
+};
+use crate::models::cache_models::{CacheStats, CacheStatsResponse, CacheUnit};
+use crate::models::stock_models::{StockListingPayload, StockListingResponse};
+use crate::db::mongo::Databases;
+use crate::repositories::{CompanyRepository, PriceRepository, RepositoryResult};
+
+const COMPANY_CACHE_CAPACITY: usize = 4096;
+const COMPANY_CACHE_TTL: Duration = Duration::from_secs(600);
+/// Bytes of BSON held for the full price history of tickers.
+const PRICE_CACHE_CAPACITY: usize = 64 * 1024 * 1024;
+const PRICE_CACHE_TTL: Duration = Duration::from_secs(120);
+/// Bytes of BSON held for single series.
+const SERIES_CACHE_CAPACITY: usize = 16 * 1024 * 1024;
+const LISTING_CACHE_CAPACITY: usize = 256;
+const LISTING_CACHE_TTL: Duration = Duration::from_secs(60);
+/// Re-read this much of the update log on every poll, so an update stamped
+/// by a writer whose clock lags ours is not missed.
+const UPDATE_POLL_OVERLAP_SECONDS: i64 = 30;
+
+struct CacheEntry<V> {
+    value: V,
+    weight: usize,
+    inserted_at: Instant,
+    last_used: Instant,
+}
+
+struct Entries<K, V> {
+    map: HashMap<K, CacheEntry<V>>,
+    /// Sum of the weights in `map`.
+    size: usize,
+}
+
+impl<K: Eq + Hash, V> Entries<K, V> {
+    fn remove(&mut self, key: &K) -> Option<CacheEntry<V>> {
+        let entry = self.map.remove(key)?;
+        self.size -= entry.weight;
+        Some(entry)
+    }
+
+    fn remove_where(&mut self, mut predicate: impl FnMut(&K, &CacheEntry<V>) -> bool) -> usize {
+        let before = self.map.len();
+        let mut removed = 0;
+        self.map.retain(|key, entry| {
+            let remove = predicate(key, entry);
+            if remove {
+                removed += entry.weight;
+            }
+            !remove
+        });
+        self.size -= removed;
+        before - self.map.len()
+    }
+}
+
+/// Map with a per-entry time-to-live and a size bound. When full, the least
+/// recently used entries are evicted.
+pub struct TtlCache<K, V> {
+    entries: Mutex<Entries<K, V>>,
+    capacity: usize,
+    ttl: Duration,
+    /// Size of one value, in `unit`.
+    weigh: fn(&V) -> usize,
+    unit: CacheUnit,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+    invalidations: AtomicU64,
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> TtlCache<K, V> {
+    /// A cache holding up to `capacity` entries.
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self::with_weigher(capacity, ttl, |_| 1, CacheUnit::Entries)
+    }
+
+    /// A cache holding values whose `weigh`ed sizes add up to at most
+    /// `capacity` bytes. A value larger than that is never cached.
+    pub fn bounded_by_bytes(capacity: usize, ttl: Duration, weigh: fn(&V) -> usize) -> Self {
+        Self::with_weigher(capacity, ttl, weigh, CacheUnit::Bytes)
+    }
+
+    fn with_weigher(capacity: usize, ttl: Duration, weigh: fn(&V) -> usize, unit: CacheUnit) -> Self {
+        TtlCache {
+            entries: Mutex::new(Entries { map: HashMap::new(), size: 0 }),
+            capacity,
+            ttl,
+            weigh,
+            unit,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+            invalidations: AtomicU64::new(0),
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        let mut entries = self.entries.lock().unwrap();
+        let now = Instant::now();
+        let value = match entries.map.get_mut(key) {
+            Some(entry) if now.duration_since(entry.inserted_at) < self.ttl => {
+                entry.last_used = now;
+                Some(entry.value.clone())
+            }
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        };
+        let counter = if value.is_some() { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+        value
+    }
+
+    pub fn insert(&self, key: K, value: V) {
+        let weight = (self.weigh)(&value);
+        if weight > self.capacity || self.capacity == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        let now = Instant::now();
+        entries.remove(&key);
+        if entries.size + weight > self.capacity {
+            entries.remove_where(|_, entry| now.duration_since(entry.inserted_at) >= self.ttl);
+            while entries.size + weight > self.capacity {
+                let Some(oldest) = entries.map.iter().min_by_key(|(_, entry)| entry.last_used).map(|(key, _)| key.clone()) else { break };
+                entries.remove(&oldest);
+                self.evictions.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        entries.size += weight;
+        entries.map.insert(key, CacheEntry { value, weight, inserted_at: now, last_used: now });
+    }
+
+    pub fn invalidate(&self, key: &K) {
+        if self.entries.lock().unwrap().remove(key).is_some() {
+            self.invalidations.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Drops every entry whose key matches `predicate`.
+    pub fn invalidate_where(&self, predicate: impl Fn(&K) -> bool) {
+        let removed = self.entries.lock().unwrap().remove_where(|key, _| predicate(key));
+        self.invalidations.fetch_add(removed as u64, Ordering::Relaxed);
+    }
+
+    pub fn clear(&self) {
+        self.invalidate_where(|_| true);
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let hits = self.hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        let (entries, size) = {
+            let entries = self.entries.lock().unwrap();
+            (entries.map.len(), entries.size)
+        };
+        CacheStats {
+            hits,
+            misses,
+            hit_rate: if hits + misses == 0 { 0.0 } else { hits as f64 / (hits + misses) as f64 },
+            evictions: self.evictions.load(Ordering::Relaxed),
+            invalidations: self.invalidations.load(Ordering::Relaxed),
+            entries,
+            size,
+            capacity: self.capacity,
+            unit: self.unit,
+            ttl_seconds: self.ttl.as_secs(),
+        }
+    }
+}
+
+/// Encoded BSON size of `document`.
+fn document_bytes(document: &Document) -> usize {
+    mongodb::bson::to_vec(document).map_or(0, |bytes| bytes.len())
+}
+
+/// Key of one period/interval series of a ticker.
+type SeriesKey = (String, String, String);
+
+/// Read-through cache for the documents behind stock listings and details.
+pub struct MarketDataCache {
+    companies: TtlCache<String, Option<Arc<Document>>>,
+    /// Every stored price document of a ticker, as the price repository returns them.
+    price_series: TtlCache<String, Arc<Vec<Document>>>,
+    /// Single period/interval series, for pages that show one series per
+    /// ticker. A ticker without the series is cached too.
+    series: TtlCache<SeriesKey, Option<Arc<Document>>>,
+    listings: TtlCache<String, StockListingResponse>,
+    /// Epoch seconds of the last poll of `data_updates`.
+    last_update_poll: Mutex<i64>,
+}
+
+impl MarketDataCache {
+    pub fn new() -> Self {
+        MarketDataCache {
+            companies: TtlCache::new(COMPANY_CACHE_CAPACITY, COMPANY_CACHE_TTL),
+            price_series: TtlCache::bounded_by_bytes(PRICE_CACHE_CAPACITY, PRICE_CACHE_TTL, |documents| documents.iter().map(document_bytes).sum()),
+            series: TtlCache::bounded_by_bytes(SERIES_CACHE_CAPACITY, PRICE_CACHE_TTL, |document| document.as_deref().map_or(0, document_bytes)),
+            listings: TtlCache::new(LISTING_CACHE_CAPACITY, LISTING_CACHE_TTL),
+            last_update_poll: Mutex::new(Utc::now().timestamp()),
+        }
+    }
+
+    /// The `companies` document for `ticker`; a missing company is cached too.
+    pub async fn company(&self, companies: &dyn CompanyRepository, ticker: &str) -> RepositoryResult<Option<Arc<Document>>> {
+        if let Some(company) = self.companies.get(&ticker.to_string()) {
+            return Ok(company);
+        }
+        let company = companies.find_by_ticker(ticker).await?.map(Arc::new);
+        self.companies.insert(ticker.to_string(), company.clone());
+        Ok(company)
+    }
+
+    /// Seeds the company cache from documents a listing query already loaded.
+    pub fn store_company(&self, ticker: &str, company: Document) {
+        self.companies.insert(ticker.to_string(), Some(Arc::new(company)));
+    }
+
+    /// Every stored period/interval document for `ticker`, unvalidated.
+    pub async fn price_documents(&self, prices: &dyn PriceRepository, ticker: &str) -> RepositoryResult<Arc<Vec<Document>>> {
+        if let Some(documents) = self.price_series.get(&ticker.to_string()) {
+            return Ok(documents);
+        }
+        let documents = Arc::new(prices.price_documents(ticker).await?);
+        self.price_series.insert(ticker.to_string(), documents.clone());
+        Ok(documents)
+    }
+
+    /// The `period`/`interval` document of each of `tickers` that has one,
+    /// unvalidated. Cache hits are served directly and the misses are
+    /// fetched in one batch, without reading the tickers' other series.
+    pub async fn series_many(&self, prices: &dyn PriceRepository, tickers: &[String], period: &str, interval: &str) -> RepositoryResult<HashMap<String, Arc<Document>>> {
+        let key = |ticker: &str| (ticker.to_string(), period.to_string(), interval.to_string());
+        let mut found = HashMap::with_capacity(tickers.len());
+        let mut missing = Vec::new();
+        for ticker in tickers {
+            match self.series.get(&key(ticker)) {
+                Some(Some(document)) => {
+                    found.insert(ticker.clone(), document);
+                }
+                Some(None) => {}
+                None => missing.push(ticker.clone()),
+            }
+        }
+        missing.sort();
+        missing.dedup();
+        if missing.is_empty() {
+            return Ok(found);
+        }
+
+        let mut loaded = prices.series_many(&missing, period, interval).await?;
+        for ticker in missing {
+            let document = loaded.remove(&ticker).map(Arc::new);
+            self.series.insert(key(&ticker), document.clone());
+            if let Some(document) = document {
+                found.insert(ticker, document);
+            }
+        }
+        Ok(found)
+    }
+
+    fn listing_key(payload: &StockListingPayload) -> String {
+        format!(
+            "{}\u{1f}{}\u{1f}{}\u{1f}{}",
+            payload.sector,
+            payload.industry.as_deref().unwrap_or_default(),
+            payload.page.unwrap_or(0),
+            payload.items_per_page.unwrap_or(10),
+        )
+    }
+
+    pub fn listing(&self, payload: &StockListingPayload) -> Option<StockListingResponse> {
+        self.listings.get(&Self::listing_key(payload))
+    }
+
+    pub fn store_listing(&self, payload: &StockListingPayload, listing: StockListingResponse) {
+        self.listings.insert(Self::listing_key(payload), listing);
+    }
+
+    /// Drops everything cached for `ticker`. Listing pages are cleared
+    /// wholesale since any of them may include the ticker.
+    pub fn invalidate_ticker(&self, ticker: &str) {
+        self.companies.invalidate(&ticker.to_string());
+        self.price_series.invalidate(&ticker.to_string());
+        self.series.invalidate_where(|(cached, _, _)| cached == ticker);
+        self.listings.clear();
+    }
+
+    pub fn stats(&self) -> CacheStatsResponse {
+        CacheStatsResponse {
+            companies: self.companies.stats(),
+            price_series: self.price_series.stats(),
+            series: self.series.stats(),
+            listings: self.listings.stats(),
+        }
+    }
+
+    /// Invalidates every ticker stamped in `data_updates` since the last poll.
+    /// Returns the number of tickers invalidated.
+    pub async fn apply_data_updates(&self, databases: &Databases) -> Result<usize, mongodb::error::Error> {
+        let now = Utc::now().timestamp();
+        let since = *self.last_update_poll.lock().unwrap() - UPDATE_POLL_OVERLAP_SECONDS;
+        let updates: Vec<Document> = data_updates(databases)
+            .find(doc! { "updated_at": { "$gte": since } }, None)
+            .await?
+            .try_collect()
+            .await?;
+        *self.last_update_poll.lock().unwrap() = now;
+
+        for update in &updates {
+            if let Ok(ticker) = update.get_str("ticker") {
+                debug!("Invalidating cached data for {}", ticker);
+                self.invalidate_ticker(ticker);
+            }
+        }
+        if !updates.is_empty() {
+            info!("Invalidated cached data for {} updated tickers", updates.len());
+        }
+        Ok(updates.len())
+    }
+}
+
+impl Default for MarketDataCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn data_updates(databases: &Databases) -> mongodb::Collection<Document> {
+    databases.app.collection("data_updates")
+}
+
+/// Epoch seconds of the most recent [`record_data_update`], if any.
+pub async fn latest_data_update(databases: &Databases) -> Result<Option<i64>, mongodb::error::Error> {
+    let newest_first = FindOneOptions::builder().sort(doc! { "updated_at": -1 }).build();
+    let update = data_updates(databases).find_one(None, newest_first).await?;
+    Ok(update.and_then(|update| update.get_i64("updated_at").ok()))
+}
+
+/// Stamps `tickers` as changed so running servers drop their cached copies.
+/// Writers outside the server process (the importer) call this after writing.
+pub async fn record_data_update(databases: &Databases, tickers: &[String]) -> Result<(), mongodb::error::Error> {
+    let now = Utc::now().timestamp();
+    for ticker in tickers {
+        let options = UpdateOptions::builder().upsert(true).build();
+        data_updates(databases)
+            .update_one(doc! { "ticker": ticker }, doc! { "$set": { "updated_at": now } }, options)
+            .await?;
+    }
+    Ok(())
+}
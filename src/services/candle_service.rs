@@ -0,0 +1,242 @@
+use std::cmp::Reverse;
+use chrono::{DateTime, Datelike, Duration, LocalResult, NaiveDate, NaiveDateTime, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
+use log::{error, info, warn};
+use sentry::capture_message;
+use crate::error::{AppError, AppResult};
+use crate::models::candle_models::{Candle, CandleQuery, CandleResponse};
+use crate::models::stock_models::PriceDataDetails;
+use crate::repositories::PriceRepository;
+use crate::services::corporate_action_service::adjust_for_splits;
+use crate::services::stock_service::all_price_series;
+
+const MINUTES_PER_DAY: u64 = 24 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CandleInterval {
+    Minutes(u32),
+    Hours(u32),
+    Days(u32),
+    Weeks(u32),
+    Months(u32),
+}
+
+impl CandleInterval {
+    /// Parses both our own interval names and the Yahoo style ones stored in
+    /// `stock_data` (`60m`, `1wk`, `3mo`, ...).
+    pub fn parse(value: &str) -> Option<CandleInterval> {
+        let value = value.trim().to_lowercase();
+        let split = value.find(|c: char| !c.is_ascii_digit())?;
+        let (count, unit) = value.split_at(split);
+        let count: u32 = count.parse().ok().filter(|count| *count > 0)?;
+
+        match unit {
+            "m" | "min" => Some(CandleInterval::Minutes(count)),
+            "h" => Some(CandleInterval::Hours(count)),
+            "d" => Some(CandleInterval::Days(count)),
+            "w" | "wk" => Some(CandleInterval::Weeks(count)),
+            "mo" => Some(CandleInterval::Months(count)),
+            _ => None,
+        }
+    }
+
+    /// Approximate length, only used to order intervals by granularity.
+    pub fn approx_seconds(&self) -> i64 {
+        match *self {
+            CandleInterval::Minutes(n) => n as i64 * 60,
+            CandleInterval::Hours(n) => n as i64 * 3_600,
+            CandleInterval::Days(n) => n as i64 * 86_400,
+            CandleInterval::Weeks(n) => n as i64 * 7 * 86_400,
+            CandleInterval::Months(n) => n as i64 * 30 * 86_400,
+        }
+    }
+
+    /// Buckets are aligned within the enclosing day or year, so sub-day
+    /// intervals must divide a day and monthly ones a year; anything else
+    /// would leave a short last bucket or collapse into a longer one.
+    pub fn check_range(&self) -> Result<(), String> {
+        match *self {
+            CandleInterval::Minutes(_) | CandleInterval::Hours(_) if !self.divides(CandleInterval::Days(1)) => {
+                Err("sub-day intervals must divide a day evenly".to_string())
+            }
+            CandleInterval::Months(n) if !12_u32.is_multiple_of(n) => Err("monthly intervals must divide a year evenly".to_string()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Length in minutes of the sub-day intervals.
+    fn minutes(&self) -> Option<u64> {
+        match *self {
+            CandleInterval::Minutes(n) => Some(n as u64),
+            CandleInterval::Hours(n) => Some(n as u64 * 60),
+            _ => None,
+        }
+    }
+
+    /// Whether every `target` bucket is made of whole buckets of this
+    /// interval, so resampling never splits a source bar. Sub-day intervals
+    /// build daily and longer buckets only if they fit a day exactly.
+    pub fn divides(&self, target: CandleInterval) -> bool {
+        match (self.minutes(), target.minutes()) {
+            (Some(source), Some(target)) => target.is_multiple_of(source),
+            (Some(source), None) => MINUTES_PER_DAY.is_multiple_of(source),
+            (None, Some(_)) => false,
+            (None, None) => match (*self, target) {
+                (CandleInterval::Days(a), CandleInterval::Days(b))
+                | (CandleInterval::Weeks(a), CandleInterval::Weeks(b))
+                | (CandleInterval::Months(a), CandleInterval::Months(b)) => b.is_multiple_of(a),
+                (CandleInterval::Days(1), CandleInterval::Weeks(_) | CandleInterval::Months(_)) => true,
+                _ => false,
+            },
+        }
+    }
+
+    /// Start of the bucket containing `timestamp`, aligned on wall-clock
+    /// boundaries in `tz` (midnight, Monday, first of the month, ...).
+    pub fn bucket_start(&self, timestamp: DateTime<Utc>, tz: Tz) -> DateTime<Tz> {
+        let local = timestamp.with_timezone(&tz).naive_local();
+        let date = local.date();
+        let midnight = date.and_hms_opt(0, 0, 0).unwrap_or(local);
+
+        let start: NaiveDateTime = match *self {
+            CandleInterval::Minutes(n) => {
+                let minutes = local.hour() * 60 + local.minute();
+                midnight + Duration::minutes((minutes - minutes % n) as i64)
+            }
+            CandleInterval::Hours(n) => midnight + Duration::hours((local.hour() - local.hour() % n) as i64),
+            CandleInterval::Days(n) => {
+                let days = date.num_days_from_ce() - 1;
+                midnight - Duration::days((days % n as i32) as i64)
+            }
+            CandleInterval::Weeks(n) => {
+                let monday = midnight - Duration::days(date.weekday().num_days_from_monday() as i64);
+                let weeks = (monday.date().num_days_from_ce() - 1) / 7;
+                monday - Duration::weeks((weeks % n as i32) as i64)
+            }
+            CandleInterval::Months(n) => {
+                let month0 = date.month0() - date.month0() % n;
+                NaiveDate::from_ymd_opt(date.year(), month0 + 1, 1)
+                    .and_then(|first| first.and_hms_opt(0, 0, 0))
+                    .unwrap_or(midnight)
+            }
+        };
+
+        // A boundary in a DST gap does not exist locally; the bucket starts
+        // at the first valid local time after it instead. One repeated when
+        // clocks fall back is the occurrence the timestamp follows, so the
+        // two passes through the repeated hour stay separate buckets.
+        (0..=MINUTES_PER_DAY as i64)
+            .find_map(|minute| match tz.from_local_datetime(&(start + Duration::minutes(minute))) {
+                LocalResult::Ambiguous(earliest, latest) => Some(if latest <= timestamp { latest } else { earliest }),
+                other => other.single(),
+            })
+            .unwrap_or_else(|| timestamp.with_timezone(&tz))
+    }
+}
+
+/// Aggregates a series into OHLCV buckets, in time order whatever the order
+/// of the points. Missing opens/highs/lows fall back to the close of the
+/// same point.
+pub fn resample(series: &PriceDataDetails, interval: CandleInterval, tz: Tz) -> Vec<Candle> {
+    let (Some(closes), Some(timestamps)) = (&series.closes, &series.timestamps) else {
+        return Vec::new();
+    };
+    let mut points: Vec<usize> = (0..closes.len().min(timestamps.len())).collect();
+    points.sort_by_key(|&i| timestamps[i]);
+
+    let mut candles: Vec<(DateTime<Tz>, Candle)> = Vec::new();
+    for i in points {
+        let (close, timestamp) = (closes[i], timestamps[i]);
+        let value = |column: &Option<Vec<f64>>| column.as_ref().and_then(|c| c.get(i).copied()).unwrap_or(close);
+        let (open, high, low) = (value(&series.opens), value(&series.highs), value(&series.lows));
+        let volume = series.volumes.as_ref().and_then(|v| v.get(i).copied()).unwrap_or(0);
+        let start = interval.bucket_start(timestamp, tz);
+
+        match candles.last_mut() {
+            Some((current_start, candle)) if *current_start == start => {
+                candle.high = candle.high.max(high);
+                candle.low = candle.low.min(low);
+                candle.close = close;
+                candle.volume += volume;
+            }
+            _ => candles.push((start, Candle {
+                start: start.fixed_offset(),
+                open,
+                high,
+                low,
+                close,
+                volume,
+            })),
+        }
+    }
+
+    candles.into_iter().map(|(_, candle)| candle).collect()
+}
+
+pub async fn candles(prices: &dyn PriceRepository, ticker: String, query: CandleQuery) -> AppResult<CandleResponse> {
+    let interval = CandleInterval::parse(&query.interval)
+        .ok_or_else(|| AppError::BadRequest(format!("Unsupported interval: {}", query.interval)))?;
+    interval.check_range()
+        .map_err(|reason| AppError::BadRequest(format!("Unsupported interval {}: {}", query.interval, reason)))?;
+    let time_zone = query.tz.clone().unwrap_or_else(|| "UTC".to_string());
+    let tz: Tz = time_zone.parse()
+        .map_err(|_| AppError::BadRequest(format!("Unknown time zone: {}", time_zone)))?;
+
+    info!("Resampling {} into {} candles ({})", ticker, query.interval, time_zone);
+    capture_message(&format!("Resampling {} into {} candles ({})", ticker, query.interval, time_zone), sentry::Level::Info);
+
+    let stored: Vec<(CandleInterval, PriceDataDetails)> = all_price_series(prices, &ticker).await?
+        .into_iter()
+        .filter(|series| query.period.as_ref().is_none_or(|period| &series.period == period))
+        .filter_map(|series| Some((CandleInterval::parse(&series.interval)?, series)))
+        .collect();
+    if stored.is_empty() {
+        warn!("No price data to build candles for ticker: {}", ticker);
+        return Err(AppError::NotFound(format!("No price data for {}", ticker)));
+    }
+    let (usable, unusable): (Vec<_>, Vec<_>) = stored.into_iter().partition(|(source, _)| source.divides(interval));
+    if usable.is_empty() {
+        let mut stored: Vec<&str> = unusable.iter().map(|(_, series)| series.interval.as_str()).collect();
+        stored.sort();
+        stored.dedup();
+        return Err(AppError::BadRequest(format!(
+            "Interval {} cannot be built from the stored intervals {}",
+            query.interval,
+            stored.join(", "),
+        )));
+    }
+
+    // Of the series reaching back furthest (within one target bucket), the
+    // finest interval wins, as it carries the most detail into each bucket,
+    // then the most points.
+    let first = |series: &PriceDataDetails| series.timestamps.as_ref().and_then(|timestamps| timestamps.iter().min().copied());
+    let range_start = usable.iter().filter_map(|(_, series)| first(series)).min();
+    let (_, mut series) = usable
+        .into_iter()
+        .filter(|(_, series)| match (first(series), range_start) {
+            (Some(first), Some(range_start)) => (first - range_start).num_seconds() <= interval.approx_seconds(),
+            (None, Some(_)) => false,
+            (_, None) => true,
+        })
+        .max_by_key(|(source_interval, series)| (Reverse(source_interval.approx_seconds()), series.closes.as_ref().map_or(0, Vec::len)))
+        .expect("the series reaching back furthest is kept");
+
+    let adjusted = query.adjusted.unwrap_or(true);
+    if adjusted {
+        let splits = prices.split_history(&ticker).await.map_err(|err| {
+            error!("Error querying splits for ticker {}: {}", ticker, err);
+            AppError::Internal("Database query failed for split history".to_string())
+        })?;
+        adjust_for_splits(&mut series, &splits);
+    }
+
+    Ok(CandleResponse {
+        candles: resample(&series, interval, tz),
+        adjusted,
+        ticker,
+        interval: query.interval,
+        source_period: series.period,
+        source_interval: series.interval,
+        time_zone,
+    })
+}
@@ -0,0 +1,262 @@
+use std::collections::HashMap;
+use chrono::Utc;
+use futures::TryStreamExt;
+use log::{error, info, warn};
+use mongodb::{
+    bson::{doc, oid::ObjectId},
+    Collection,
+};
+use sentry::capture_message;
+use crate::error::{AppError, AppResult};
+use crate::models::activity_models::AccountEventKind;
+use crate::models::corporate_action_models::{CorporateAction, CorporateActionKind, CorporateActionPayload, SplitAdjustment};
+use crate::models::ledger_models::{LedgerEntry, LedgerEntryKind};
+use crate::models::stock_models::PriceDataDetails;
+use crate::models::trade_models::{Trade, TradeStatus};
+use crate::repositories::{CorporateActionRepository, Repositories, TradeRepository};
+use crate::services::activity_service::ActivityFeed;
+use crate::db::mongo::Databases;
+
+fn corporate_actions(databases: &Databases) -> Collection<CorporateAction> {
+    databases.app.collection("corporate_actions")
+}
+
+pub async fn create_corporate_action(actions: &dyn CorporateActionRepository, payload: CorporateActionPayload) -> AppResult<ObjectId> {
+    let action = CorporateAction {
+        id: None,
+        ticker: payload.ticker,
+        ex_date: payload.ex_date,
+        pay_date: payload.pay_date,
+        action: payload.action,
+        processed: false,
+        processed_at: None,
+    };
+    info!("Recording {:?} for {} with ex-date {}", action.action, action.ticker, action.ex_date);
+    capture_message(&format!("Recording {:?} for {} with ex-date {}", action.action, action.ticker, action.ex_date), sentry::Level::Info);
+    Ok(actions.insert(action).await?)
+}
+
+pub async fn corporate_actions_for_ticker(actions: &dyn CorporateActionRepository, ticker: &str) -> AppResult<Vec<CorporateAction>> {
+    Ok(actions.find_by_ticker(ticker).await?)
+}
+
+pub async fn ledger_for_user(actions: &dyn CorporateActionRepository, user_id: &str) -> AppResult<Vec<LedgerEntry>> {
+    let user_id = ObjectId::parse_str(user_id).map_err(|_| AppError::InvalidId("user_id"))?;
+    Ok(actions.ledger_for_user(user_id).await?)
+}
+
+/// Net shares per user at the ex-date: trades opened before it that were
+/// still open then, whether or not they have closed since. Short positions
+/// count negatively.
+async fn holdings_on(trades: &dyn TradeRepository, action: &CorporateAction) -> AppResult<HashMap<ObjectId, f64>> {
+    let mut holdings: HashMap<ObjectId, f64> = HashMap::new();
+    for trade in trades.held_on(&action.ticker, action.ex_date.timestamp()).await? {
+        let quantity = if trade.is_short() { -(trade.quantity as f64) } else { trade.quantity as f64 };
+        *holdings.entry(trade.user_id).or_default() += quantity;
+    }
+    holdings.retain(|_, quantity| *quantity != 0.0);
+    Ok(holdings)
+}
+
+/// Records `entry` unsettled. An entry that already exists means an earlier
+/// run recorded it, which settling then picks up.
+async fn record_entry(actions: &dyn CorporateActionRepository, entry: LedgerEntry) -> AppResult<()> {
+    let (action_id, user_id) = (entry.corporate_action_id, entry.user_id);
+    if !actions.record_entry(entry).await? {
+        warn!("Ledger entry for {:?} and user {} already recorded", action_id, user_id);
+    }
+    Ok(())
+}
+
+/// Applies every unsettled ledger entry of `action_id` to its user's
+/// balance and returns how many were settled.
+///
+/// The balance change and a marker naming the entry are written to the user
+/// in one update that skips users already carrying the marker; the entry is
+/// then flagged settled and the marker removed. A run interrupted at any
+/// point can be repeated without paying anyone twice. Split entries wait
+/// until their trade has been adjusted.
+async fn settle_entries(repositories: &Repositories, feed: &ActivityFeed, action_id: ObjectId) -> AppResult<usize> {
+    let pending = repositories.corporate_actions.unsettled_entries(action_id).await?;
+
+    let mut settled = 0;
+    for entry in pending {
+        let Some(entry_id) = entry.id else { continue };
+        if let Some(trade_id) = entry.trade_id {
+            let trade = repositories.trades.find_by_id(trade_id).await?;
+            if !trade.is_some_and(|trade| trade.applied_splits.contains(&action_id)) {
+                continue;
+            }
+        }
+
+        if let Some(user) = repositories.users.apply_ledger_entry(entry.user_id, entry_id, entry.amount).await? {
+            feed.publish(&entry.user_id.to_hex(), AccountEventKind::BalanceUpdate { balance: user.balance, change: entry.amount });
+        }
+        repositories.corporate_actions.mark_settled(entry_id).await?;
+        repositories.users.release_ledger_entry(entry.user_id, entry_id).await?;
+        settled += 1;
+    }
+
+    Ok(settled)
+}
+
+async fn apply_dividend(repositories: &Repositories, feed: &ActivityFeed, action: &CorporateAction, action_id: ObjectId, amount_per_share: f64) -> AppResult<usize> {
+    for (user_id, quantity) in holdings_on(repositories.trades.as_ref(), action).await? {
+        let entry = LedgerEntry {
+            id: None,
+            user_id,
+            kind: LedgerEntryKind::Dividend,
+            ticker: action.ticker.clone(),
+            amount: quantity * amount_per_share,
+            quantity,
+            corporate_action_id: Some(action_id),
+            trade_id: None,
+            settled: false,
+            created_at: Utc::now(),
+        };
+        record_entry(repositories.corporate_actions.as_ref(), entry).await?;
+    }
+
+    settle_entries(repositories, feed, action_id).await
+}
+
+/// Quantity is multiplied by `ratio` and entry/TP/SL prices divided by it,
+/// so the cost basis is unchanged. Fractional shares are paid out in cash at
+/// the adjusted entry price, so the payout realises no profit or loss.
+pub fn split_adjustment(trade: &Trade, ratio: f64) -> SplitAdjustment {
+    let exact_quantity = trade.quantity as f64 * ratio;
+    // Ratios such as 1/3 are inexact; 3 shares must still become 1, not 0.
+    let quantity = (exact_quantity + 1e-9).floor();
+    let fractional_shares = (exact_quantity - quantity).max(0.0);
+    let price = trade.price / ratio;
+    let cash_in_lieu = fractional_shares * price;
+    SplitAdjustment {
+        quantity: quantity as u32,
+        price,
+        take_profit: trade.take_profit.map(|tp| tp / ratio),
+        stop_loss: trade.stop_loss.map(|sl| sl / ratio),
+        amount: trade.amount - cash_in_lieu,
+        fractional_shares,
+        cash_in_lieu,
+    }
+}
+
+/// Applies [`split_adjustment`] to open trades opened before the ex-date. A
+/// trade the split leaves without a whole share is closed at the adjusted
+/// price.
+///
+/// The cash-in-lieu entry is recorded before its trade is adjusted and
+/// settled after, so a retried run finishes whatever an interrupted one left.
+async fn apply_split(repositories: &Repositories, feed: &ActivityFeed, action: &CorporateAction, action_id: ObjectId) -> AppResult<usize> {
+    let Some(ratio) = action.action.split_ratio() else { return Ok(0) };
+    let open_trades: Vec<Trade> = repositories.trades
+        .held_on(&action.ticker, action.ex_date.timestamp())
+        .await?
+        .into_iter()
+        .filter(|trade| trade.status == TradeStatus::InProgress && !trade.applied_splits.contains(&action_id))
+        .collect();
+
+    let mut adjusted = 0;
+    for trade in open_trades {
+        let Some(trade_id) = trade.id else { continue };
+        let split = split_adjustment(&trade, ratio);
+        let direction = if trade.is_short() { -1.0 } else { 1.0 };
+
+        if split.fractional_shares > 0.0 {
+            let entry = LedgerEntry {
+                id: None,
+                user_id: trade.user_id,
+                kind: LedgerEntryKind::SplitCashInLieu,
+                ticker: action.ticker.clone(),
+                amount: direction * split.cash_in_lieu,
+                quantity: direction * split.fractional_shares,
+                corporate_action_id: Some(action_id),
+                trade_id: Some(trade_id),
+                settled: false,
+                created_at: Utc::now(),
+            };
+            record_entry(repositories.corporate_actions.as_ref(), entry).await?;
+        }
+
+        if repositories.trades.apply_split(trade_id, action_id, &split).await? {
+            adjusted += 1;
+        }
+    }
+
+    settle_entries(repositories, feed, action_id).await?;
+    Ok(adjusted)
+}
+
+/// Splits that have taken effect for each of `tickers`, as (ex-date, ratio)
+/// pairs. Tickers without any are left out.
+pub async fn split_histories(databases: &Databases, tickers: &[String]) -> Result<HashMap<String, Vec<(i64, f64)>>, mongodb::error::Error> {
+    let filter = doc! {
+        "ticker": { "$in": tickers },
+        "action.type": "split",
+        "ex_date": { "$lte": Utc::now().timestamp() },
+    };
+    let actions: Vec<CorporateAction> = corporate_actions(databases).find(filter, None).await?.try_collect().await?;
+    let mut histories: HashMap<String, Vec<(i64, f64)>> = HashMap::new();
+    for action in actions {
+        if let Some(ratio) = action.action.split_ratio() {
+            histories.entry(action.ticker).or_default().push((action.ex_date.timestamp(), ratio));
+        }
+    }
+    Ok(histories)
+}
+
+/// Rewrites a raw series as split-adjusted: every point before a split's
+/// ex-date has its prices divided and its volume multiplied by the ratio.
+pub fn adjust_for_splits(series: &mut PriceDataDetails, splits: &[(i64, f64)]) {
+    let Some(timestamps) = &series.timestamps else { return };
+    let factors: Vec<f64> = timestamps
+        .iter()
+        .map(|timestamp| {
+            splits.iter()
+                .filter(|(ex_date, _)| timestamp.timestamp() < *ex_date)
+                .map(|(_, ratio)| ratio)
+                .product()
+        })
+        .collect();
+
+    for prices in [&mut series.opens, &mut series.highs, &mut series.lows, &mut series.closes].into_iter().flatten() {
+        for (price, factor) in prices.iter_mut().zip(&factors) {
+            *price /= factor;
+        }
+    }
+    if let Some(volumes) = &mut series.volumes {
+        for (volume, factor) in volumes.iter_mut().zip(&factors) {
+            *volume = (*volume as f64 * factor).round() as i64;
+        }
+    }
+}
+
+/// Applies every unprocessed corporate action whose ex-date has passed.
+/// Returns the number of actions processed.
+pub async fn process_due_corporate_actions(repositories: &Repositories, feed: &ActivityFeed) -> AppResult<usize> {
+    let due = repositories.corporate_actions.due(Utc::now().timestamp()).await?;
+
+    let mut processed = 0;
+    for action in due {
+        let Some(action_id) = action.id else { continue };
+        let result = match &action.action {
+            CorporateActionKind::Dividend { amount_per_share } => apply_dividend(repositories, feed, &action, action_id, *amount_per_share).await,
+            CorporateActionKind::Split { .. } => apply_split(repositories, feed, &action, action_id).await,
+        };
+
+        match result {
+            Ok(users) => {
+                repositories.corporate_actions.mark_processed(action_id).await?;
+                info!("Processed {:?} for {} ({} holders)", action.action, action.ticker, users);
+                capture_message(&format!("Processed {:?} for {} ({} holders)", action.action, action.ticker, users), sentry::Level::Info);
+                processed += 1;
+            }
+            Err(err) => {
+                error!("Failed to process corporate action {} for {}: {}", action_id, action.ticker, err);
+                capture_message(&format!("Failed to process corporate action {} for {}: {}", action_id, action.ticker, err), sentry::Level::Error);
+            }
+        }
+    }
+
+    Ok(processed)
+}
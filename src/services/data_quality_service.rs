@@ -0,0 +1,321 @@
+use std::error::Error as StdError;
+use chrono::Utc;
+use futures::TryStreamExt;
+use log::{error, info, warn};
+use mongodb::{
+    bson::{doc, Bson, Document},
+    Collection,
+};
+use sentry::capture_message;
+use crate::error::{AppError, AppResult};
+use crate::models::data_quality_models::{DataHealthReport, DataIssue, IssueKind, IssueSeverity, QuarantineSummary, QuarantinedDocument, SeriesHealth};
+use crate::db::price_store::PriceStore;
+use crate::repositories::{PriceRepository, RepositoryError};
+use crate::services::cache_service::record_data_update;
+use crate::services::candle_service::CandleInterval;
+use crate::services::stock_service::bson_to_f64;
+use crate::db::mongo::Databases;
+
+/// A gap is flagged when consecutive points are further apart than this many
+/// intervals, and never below `MIN_GAP_SECONDS` so weekends and holidays pass.
+const GAP_FACTOR: i64 = 4;
+const MIN_GAP_SECONDS: i64 = 4 * 86_400;
+/// A close-to-close return is an outlier when it is this many median absolute
+/// deviations from the median return.
+const OUTLIER_MADS: f64 = 10.0;
+/// Floor on the deviation so flat series do not flag every small move.
+const MIN_RETURN_DEVIATION: f64 = 0.005;
+/// Point-level issues reported per kind before they are summarised.
+const MAX_ISSUES_PER_KIND: usize = 10;
+
+pub(crate) fn quarantine_collection(databases: &Databases) -> Collection<QuarantinedDocument> {
+    databases.app.collection("price_data_quarantine")
+}
+
+fn issue(kind: IssueKind, severity: IssueSeverity, index: Option<usize>, message: String) -> DataIssue {
+    DataIssue { kind, severity, index, message }
+}
+
+/// Reads a numeric array, or `None` if the field is absent or not an array.
+/// Non-numeric elements are recorded in `issues` and read as NaN.
+fn numbers(doc: &Document, field: &str, issues: &mut Vec<DataIssue>) -> Option<Vec<f64>> {
+    let values = match doc.get(field)? {
+        Bson::Array(values) => values,
+        other => {
+            issues.push(issue(IssueKind::NonNumeric, IssueSeverity::Error, None, format!("{} is a {:?}, not an array", field, other.element_type())));
+            return None;
+        }
+    };
+
+    let mut parsed = Vec::with_capacity(values.len());
+    for (index, value) in values.iter().enumerate() {
+        match bson_to_f64(value).filter(|value| value.is_finite()) {
+            Some(value) => parsed.push(value),
+            None => {
+                issues.push(issue(IssueKind::NonNumeric, IssueSeverity::Error, Some(index), format!("{}[{}] is not a finite number: {}", field, index, value)));
+                parsed.push(f64::NAN);
+            }
+        }
+    }
+    Some(parsed)
+}
+
+/// Issues that make a document unsafe to serve: missing or non-numeric
+/// fields, misaligned arrays, unordered timestamps and impossible OHLC bars.
+pub fn structural_issues(doc: &Document) -> Vec<DataIssue> {
+    let mut issues = Vec::new();
+    for field in ["period", "interval"] {
+        if doc.get_str(field).is_err() {
+            issues.push(issue(IssueKind::MissingField, IssueSeverity::Error, None, format!("{} is missing or not a string", field)));
+        }
+    }
+
+    let timestamps = numbers(doc, "timestamps", &mut issues);
+    let closes = numbers(doc, "closes", &mut issues);
+    let opens = numbers(doc, "opens", &mut issues);
+    let highs = numbers(doc, "highs", &mut issues);
+    let lows = numbers(doc, "lows", &mut issues);
+    let volumes = numbers(doc, "volumes", &mut issues);
+
+    let (Some(timestamps), Some(closes)) = (timestamps, closes) else {
+        issues.push(issue(IssueKind::MissingField, IssueSeverity::Error, None, "timestamps and closes are required".to_string()));
+        return issues;
+    };
+
+    let arrays = [("closes", Some(&closes)), ("opens", opens.as_ref()), ("highs", highs.as_ref()), ("lows", lows.as_ref()), ("volumes", volumes.as_ref())];
+    for (field, values) in arrays {
+        if let Some(values) = values.filter(|values| values.len() != timestamps.len()) {
+            issues.push(issue(IssueKind::LengthMismatch, IssueSeverity::Error, None, format!("{} has {} values for {} timestamps", field, values.len(), timestamps.len())));
+        }
+    }
+    if issues.iter().any(|issue| issue.kind == IssueKind::LengthMismatch) {
+        return issues;
+    }
+
+    for index in 1..timestamps.len() {
+        if timestamps[index] <= timestamps[index - 1] {
+            issues.push(issue(IssueKind::NonMonotonicTimestamps, IssueSeverity::Error, Some(index), format!("timestamp {} does not follow {}", timestamps[index], timestamps[index - 1])));
+        }
+    }
+
+    let at = |values: &Option<Vec<f64>>, index: usize| values.as_ref().map(|values| values[index]).filter(|value| !value.is_nan());
+    for (index, &close) in closes.iter().enumerate() {
+        let (open, high, low) = (at(&opens, index), at(&highs, index), at(&lows, index));
+        let prices = [open, high, low, Some(close)];
+        let inverted = match (high, low) {
+            (Some(high), Some(low)) if high < low => Some(format!("high {} is below low {}", high, low)),
+            _ => None,
+        };
+        let problem = if prices.iter().flatten().any(|price| *price <= 0.0) {
+            Some("prices must be positive".to_string())
+        } else if inverted.is_some() {
+            inverted
+        } else if [open, Some(close)].iter().flatten().any(|price| high.is_some_and(|high| *price > high) || low.is_some_and(|low| *price < low)) {
+            Some("open or close lies outside the high/low range".to_string())
+        } else if at(&volumes, index).is_some_and(|volume| volume < 0.0) {
+            Some("volume is negative".to_string())
+        } else {
+            None
+        };
+        if let Some(problem) = problem {
+            issues.push(issue(IssueKind::OhlcInconsistent, IssueSeverity::Error, Some(index), problem));
+        }
+    }
+
+    issues
+}
+
+fn median(values: &mut [f64]) -> f64 {
+    values.sort_by(f64::total_cmp);
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) { (values[mid - 1] + values[mid]) / 2.0 } else { values[mid] }
+}
+
+/// Suspicious but servable points: gaps longer than the interval allows and
+/// close-to-close jumps far outside the series' usual range.
+fn statistical_issues(doc: &Document) -> Vec<DataIssue> {
+    let mut ignored = Vec::new();
+    let (Some(timestamps), Some(closes)) = (numbers(doc, "timestamps", &mut ignored), numbers(doc, "closes", &mut ignored)) else {
+        return Vec::new();
+    };
+    let mut issues = Vec::new();
+
+    if let Some(interval) = doc.get_str("interval").ok().and_then(CandleInterval::parse) {
+        let max_gap = (interval.approx_seconds() * GAP_FACTOR).max(MIN_GAP_SECONDS);
+        for index in 1..timestamps.len() {
+            let gap = (timestamps[index] - timestamps[index - 1]) as i64;
+            if gap > max_gap {
+                issues.push(issue(IssueKind::Gap, IssueSeverity::Warning, Some(index), format!("{:.1} days without data before this point", gap as f64 / 86_400.0)));
+            }
+        }
+    }
+
+    let returns: Vec<(usize, f64)> = closes
+        .windows(2)
+        .enumerate()
+        .filter(|(_, pair)| pair[0] > 0.0)
+        .map(|(index, pair)| (index + 1, pair[1] / pair[0] - 1.0))
+        .collect();
+    if returns.len() >= 3 {
+        let center = median(&mut returns.iter().map(|(_, r)| *r).collect::<Vec<_>>());
+        let deviation = median(&mut returns.iter().map(|(_, r)| (r - center).abs()).collect::<Vec<_>>()).max(MIN_RETURN_DEVIATION);
+        for (index, value) in returns {
+            if (value - center).abs() > OUTLIER_MADS * deviation {
+                issues.push(issue(IssueKind::Outlier, IssueSeverity::Warning, Some(index), format!("close moved {:+.1}% from the previous point", value * 100.0)));
+            }
+        }
+    }
+
+    issues
+}
+
+/// Keeps the first few issues of each kind and summarises the rest.
+fn cap_issues(issues: Vec<DataIssue>) -> Vec<DataIssue> {
+    let mut capped: Vec<DataIssue> = Vec::new();
+    let mut overflow: Vec<(IssueKind, IssueSeverity, usize)> = Vec::new();
+    for issue in issues {
+        if capped.iter().filter(|kept| kept.kind == issue.kind).count() < MAX_ISSUES_PER_KIND {
+            capped.push(issue);
+        } else if let Some(entry) = overflow.iter_mut().find(|(kind, _, _)| *kind == issue.kind) {
+            entry.2 += 1;
+        } else {
+            overflow.push((issue.kind, issue.severity, 1));
+        }
+    }
+    capped.extend(overflow.into_iter().map(|(kind, severity, count)| {
+        issue(kind, severity, None, format!("{} more {:?} issues not shown", count, kind))
+    }));
+    capped
+}
+
+/// Every issue found in a stored price document.
+pub fn validate_document(doc: &Document) -> Vec<DataIssue> {
+    let mut issues = structural_issues(doc);
+    if issues.is_empty() {
+        issues.extend(statistical_issues(doc));
+    }
+    cap_issues(issues)
+}
+
+pub fn has_errors(issues: &[DataIssue]) -> bool {
+    issues.iter().any(|issue| issue.severity == IssueSeverity::Error)
+}
+
+fn series_health(doc: &Document, issues: Vec<DataIssue>) -> SeriesHealth {
+    let timestamps = doc.get_array("timestamps").ok();
+    SeriesHealth {
+        period: doc.get_str("period").ok().map(str::to_string),
+        interval: doc.get_str("interval").ok().map(str::to_string),
+        points: timestamps.map_or(0, Vec::len),
+        first_timestamp: timestamps.and_then(|ts| ts.first()).and_then(bson_to_f64).map(|ts| ts as i64),
+        last_timestamp: timestamps.and_then(|ts| ts.last()).and_then(bson_to_f64).map(|ts| ts as i64),
+        errors: issues.iter().filter(|issue| issue.severity == IssueSeverity::Error).count(),
+        warnings: issues.iter().filter(|issue| issue.severity == IssueSeverity::Warning).count(),
+        issues,
+    }
+}
+
+/// Validates every stored series of `ticker` without modifying anything.
+pub async fn data_health(prices: &dyn PriceRepository, ticker: &str) -> AppResult<DataHealthReport> {
+    let db_error = |err: RepositoryError| {
+        error!("Error checking data health for ticker {}: {}", ticker, err);
+        capture_message(&format!("Error checking data health for ticker {}: {}", ticker, err), sentry::Level::Error);
+        AppError::Internal("Database query failed for data health".to_string())
+    };
+
+    let documents = prices.price_documents(ticker).await.map_err(db_error)?;
+    let quarantined = prices.quarantined(ticker).await.map_err(db_error)?;
+
+    if documents.is_empty() && quarantined.is_empty() {
+        return Err(AppError::NotFound(format!("No price data for ticker {}", ticker)));
+    }
+
+    let series: Vec<SeriesHealth> = documents.iter().map(|doc| series_health(doc, validate_document(doc))).collect();
+    Ok(DataHealthReport {
+        ticker: ticker.to_string(),
+        checked_at: Utc::now(),
+        healthy: series.iter().all(|series| series.errors == 0),
+        series,
+        quarantined: quarantined
+            .into_iter()
+            .map(|doc| QuarantineSummary { period: doc.period, interval: doc.interval, quarantined_at: doc.quarantined_at, issues: doc.issues })
+            .collect(),
+    })
+}
+
+/// Where a price document is served from.
+#[derive(Debug, Clone, Copy)]
+enum PriceSource {
+    /// A document in the ticker's legacy collection in `stock_data`.
+    Legacy,
+    /// A catalogued series assembled from `price_bars`.
+    Store,
+}
+
+/// Moves a failing document out of reach of readers: a legacy document is
+/// deleted, a store series is removed from the catalog while its bars stay
+/// for the interval's other periods. The copy is written first, so a crash
+/// in between leaves a duplicate rather than losing data.
+async fn quarantine(databases: &Databases, ticker: &str, doc: Document, issues: Vec<DataIssue>, source: PriceSource) -> Result<(), mongodb::error::Error> {
+    let id = doc.get("_id").cloned();
+    let entry = QuarantinedDocument {
+        id: None,
+        ticker: ticker.to_string(),
+        period: doc.get_str("period").ok().map(str::to_string),
+        interval: doc.get_str("interval").ok().map(str::to_string),
+        document: doc,
+        issues,
+        quarantined_at: Utc::now(),
+    };
+    quarantine_collection(databases).insert_one(&entry, None).await?;
+    match (source, id, &entry.period, &entry.interval) {
+        (PriceSource::Legacy, Some(id), _, _) => {
+            databases.stock.collection::<Document>(ticker).delete_one(doc! { "_id": id }, None).await?;
+        }
+        (PriceSource::Store, _, Some(period), Some(interval)) => {
+            PriceStore::new(databases).remove_series(ticker, period, interval).await?;
+        }
+        _ => {}
+    }
+    record_data_update(databases, &[ticker.to_string()]).await?;
+
+    warn!("Quarantined {:?}/{:?} price data for {}", entry.period, entry.interval, ticker);
+    capture_message(&format!("Quarantined {:?}/{:?} price data for {}", entry.period, entry.interval, ticker), sentry::Level::Warning);
+    Ok(())
+}
+
+/// Quarantines the documents of `ticker` with errors; returns how many.
+async fn quarantine_failing(databases: &Databases, ticker: &str, documents: Vec<Document>, source: PriceSource) -> Result<usize, mongodb::error::Error> {
+    let mut quarantined = 0;
+    for doc in documents {
+        let issues = structural_issues(&doc);
+        if has_errors(&issues) {
+            quarantine(databases, ticker, doc, cap_issues(issues), source).await?;
+            quarantined += 1;
+        }
+    }
+    Ok(quarantined)
+}
+
+/// Validates every price document, in the legacy collections and in the
+/// price store, and quarantines those with errors. Returns the number of
+/// documents quarantined.
+pub async fn quarantine_invalid_price_data(databases: &Databases) -> Result<usize, Box<dyn StdError>> {
+    let database = &databases.stock;
+    let store = PriceStore::new(databases);
+    let mut quarantined = 0;
+
+    for ticker in database.list_collection_names(None).await? {
+        let documents: Vec<Document> = database.collection::<Document>(&ticker).find(None, None).await?.try_collect().await?;
+        quarantined += quarantine_failing(databases, &ticker, documents, PriceSource::Legacy).await?;
+    }
+    for ticker in store.catalog_tickers().await? {
+        let documents = store.price_documents(&ticker).await?;
+        quarantined += quarantine_failing(databases, &ticker, documents, PriceSource::Store).await?;
+    }
+
+    if quarantined > 0 {
+        info!("Quarantined {} price documents", quarantined);
+    }
+    Ok(quarantined)
+}
@@ -0,0 +1,73 @@
+use chrono::{Duration, NaiveTime, Utc};
+use log::{error, info, warn};
+use mongodb::bson::{from_document, Bson};
+use sentry::capture_message;
+use crate::error::{AppError, AppResult};
+use crate::models::stock_models::{EarningsCalendarEntry, EarningsCalendarQuery, EarningsHistory, EarningsReport, Metric};
+use crate::repositories::CompanyRepository;
+use crate::services::stock_service::get_path;
+
+const DEFAULT_CALENDAR_DAYS: i64 = 14;
+const MAX_CALENDAR_DAYS: i64 = 92;
+
+/// Companies reporting between `from` and `to`, ordered by report date.
+pub async fn earnings_calendar(companies: &dyn CompanyRepository, query: EarningsCalendarQuery) -> AppResult<Vec<EarningsCalendarEntry>> {
+    let from = query.from.unwrap_or_else(|| Utc::now().date_naive());
+    let to = query.to.unwrap_or(from + Duration::days(DEFAULT_CALENDAR_DAYS));
+    if to < from {
+        return Err(AppError::BadRequest("'to' must not be before 'from'".to_string()));
+    }
+    if (to - from).num_days() > MAX_CALENDAR_DAYS {
+        return Err(AppError::BadRequest(format!("Calendar range is limited to {} days", MAX_CALENDAR_DAYS)));
+    }
+
+    let start = from.and_time(NaiveTime::MIN).and_utc().timestamp();
+    let end = (to + Duration::days(1)).and_time(NaiveTime::MIN).and_utc().timestamp() - 1;
+
+    info!("Fetching earnings calendar from {} to {}", from, to);
+    capture_message(&format!("Fetching earnings calendar from {} to {}", from, to), sentry::Level::Info);
+
+    let docs = companies.reporting_between(start, end, query.sector.as_deref()).await.map_err(|err| {
+        error!("Error querying earnings calendar: {}", err);
+        capture_message(&format!("Error querying earnings calendar: {}", err), sentry::Level::Error);
+        AppError::Internal("Database query failed for earnings calendar".to_string())
+    })?;
+
+    let mut entries = Vec::with_capacity(docs.len());
+    for doc in &docs {
+        let ticker = doc.get_str("ticker").unwrap_or_default();
+        let earnings = match doc.get_document("earnings").map(|earnings| from_document::<EarningsHistory>(earnings.clone())) {
+            Ok(Ok(earnings)) => earnings,
+            Ok(Err(err)) => {
+                warn!("Skipping {} in the earnings calendar: unreadable earnings: {}", ticker, err);
+                continue;
+            }
+            Err(_) => continue,
+        };
+        let Some(report_date) = earnings.next_report_date else { continue };
+
+        let mut last_report: Option<EarningsReport> = earnings.history
+            .into_iter()
+            .filter(|report| report.report_date.is_some())
+            .max_by_key(|report| report.report_date);
+        if let Some(report) = last_report.as_mut() {
+            report.fill_surprise();
+        }
+        let raw = |metric: Option<&Metric>| metric.and_then(|m| m.raw);
+
+        entries.push(EarningsCalendarEntry {
+            ticker: ticker.to_string(),
+            name: doc.get_str("name").unwrap_or_default().to_string(),
+            sector: get_path(doc, "profile.sector").and_then(Bson::as_str).map(str::to_string),
+            report_date,
+            eps_estimate: raw(earnings.next_eps_estimate.as_ref()),
+            revenue_estimate: raw(earnings.next_revenue_estimate.as_ref()),
+            last_eps_actual: raw(last_report.as_ref().and_then(|r| r.eps_actual.as_ref())),
+            last_surprise_percent: raw(last_report.as_ref().and_then(|r| r.surprise_percent.as_ref())),
+        });
+    }
+
+    // Numbers and dates sort apart in MongoDB, so order across both here.
+    entries.sort_by(|a, b| a.report_date.cmp(&b.report_date).then_with(|| a.ticker.cmp(&b.ticker)));
+    Ok(entries)
+}
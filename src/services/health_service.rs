@@ -0,0 +1,222 @@
+//! Liveness and readiness reports for orchestrators.
+//!
+//! Background jobs register with the shared [`HealthMonitor`] and record the
+//! outcome of every run, so readiness can tell a failing job from one that
+//! has stopped running altogether.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use actix_web::rt::time::timeout;
+use chrono::{DateTime, TimeZone, Utc};
+use futures::future::{BoxFuture, FutureExt, Shared};
+use log::warn;
+use crate::models::health_models::{
+    DataFreshnessCheck, DatabaseCheck, HealthStatus, LivenessReport, ReadinessChecks, ReadinessReport, WorkerState, WorkerStatus, WorkersCheck,
+};
+use crate::repositories::{DatabaseProbe, PriceRepository};
+
+/// A job is stalled once no run has finished for this many intervals.
+const STALLED_AFTER_INTERVALS: u32 = 3;
+const PING_TIMEOUT: Duration = Duration::from_secs(2);
+const FRESHNESS_TIMEOUT: Duration = Duration::from_secs(5);
+/// Probes reuse a recent newest-price lookup rather than each running one.
+const FRESHNESS_CACHE_TTL: Duration = Duration::from_secs(60);
+/// A failed lookup is retried sooner than a successful one is refreshed.
+const FRESHNESS_RETRY_AFTER: Duration = Duration::from_secs(10);
+
+struct JobRecord {
+    interval: Duration,
+    registered_at: DateTime<Utc>,
+    last_success: Option<DateTime<Utc>>,
+    last_failure: Option<DateTime<Utc>>,
+    consecutive_failures: u32,
+}
+
+impl JobRecord {
+    fn status(&self, name: &str, now: DateTime<Utc>) -> WorkerStatus {
+        let last_run = self.last_success.max(self.last_failure).unwrap_or(self.registered_at);
+        let stalled = (now - last_run).to_std().is_ok_and(|idle| idle > self.interval * STALLED_AFTER_INTERVALS);
+        let state = if stalled {
+            WorkerState::Stalled
+        } else if self.consecutive_failures > 0 {
+            WorkerState::Failing
+        } else if self.last_success.is_none() {
+            WorkerState::Starting
+        } else {
+            WorkerState::Ok
+        };
+        let status = match state {
+            WorkerState::Stalled => HealthStatus::Fail,
+            WorkerState::Failing => HealthStatus::Warn,
+            WorkerState::Starting | WorkerState::Ok => HealthStatus::Pass,
+        };
+        WorkerStatus {
+            name: name.to_string(),
+            status,
+            state,
+            interval_seconds: self.interval.as_secs(),
+            last_success: self.last_success,
+            last_failure: self.last_failure,
+            consecutive_failures: self.consecutive_failures,
+        }
+    }
+}
+
+/// Newest price epoch seconds, or why the lookup failed.
+type NewestPriceResult = Result<Option<i64>, String>;
+
+#[derive(Default)]
+struct NewestPrice {
+    /// When the last lookup finished, and its result.
+    checked: Option<(Instant, NewestPriceResult)>,
+    in_flight: Option<Shared<BoxFuture<'static, NewestPriceResult>>>,
+}
+
+/// Process start time, background job runs and the last newest-price lookup.
+pub struct HealthMonitor {
+    started_at: DateTime<Utc>,
+    jobs: Mutex<BTreeMap<&'static str, JobRecord>>,
+    newest_price: Arc<Mutex<NewestPrice>>,
+}
+
+impl HealthMonitor {
+    pub fn new() -> Self {
+        HealthMonitor { started_at: Utc::now(), jobs: Mutex::new(BTreeMap::new()), newest_price: Arc::default() }
+    }
+
+    /// Starts tracking `job`, which is expected to finish a run every `interval`.
+    pub fn register(&self, job: &'static str, interval: Duration) {
+        let record = JobRecord { interval, registered_at: Utc::now(), last_success: None, last_failure: None, consecutive_failures: 0 };
+        self.jobs.lock().unwrap().insert(job, record);
+    }
+
+    /// Records a finished run of a registered job.
+    pub fn record<T, E>(&self, job: &'static str, result: &Result<T, E>) {
+        let mut jobs = self.jobs.lock().unwrap();
+        let Some(record) = jobs.get_mut(job) else { return };
+        if result.is_ok() {
+            record.last_success = Some(Utc::now());
+            record.consecutive_failures = 0;
+        } else {
+            record.last_failure = Some(Utc::now());
+            record.consecutive_failures += 1;
+        }
+    }
+
+    pub fn liveness(&self) -> LivenessReport {
+        LivenessReport {
+            status: HealthStatus::Pass,
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            started_at: self.started_at,
+            uptime_seconds: (Utc::now() - self.started_at).num_seconds(),
+        }
+    }
+
+    fn workers(&self) -> WorkersCheck {
+        let now = Utc::now();
+        let jobs: Vec<WorkerStatus> = self.jobs.lock().unwrap().iter().map(|(name, record)| record.status(name, now)).collect();
+        let status = jobs.iter().map(|job| job.status).max().unwrap_or(HealthStatus::Pass);
+        WorkersCheck { status, jobs }
+    }
+
+    /// The newest price, from a recent lookup or a new one. Lookups run on
+    /// their own task and record their result whether or not a probe is
+    /// still waiting, so a probe that times out does not cancel one, and
+    /// concurrent probes share it.
+    async fn newest_price(&self, prices: &Arc<dyn PriceRepository>) -> NewestPriceResult {
+        let lookup = {
+            let mut state = self.newest_price.lock().unwrap();
+            if let Some((checked, result)) = &state.checked {
+                let reuse_for = if result.is_ok() { FRESHNESS_CACHE_TTL } else { FRESHNESS_RETRY_AFTER };
+                if checked.elapsed() < reuse_for {
+                    return result.clone();
+                }
+            }
+            state.in_flight.get_or_insert_with(|| spawn_newest_price_lookup(self.newest_price.clone(), prices.clone())).clone()
+        };
+        lookup.await
+    }
+}
+
+impl Default for HealthMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn spawn_newest_price_lookup(state: Arc<Mutex<NewestPrice>>, prices: Arc<dyn PriceRepository>) -> Shared<BoxFuture<'static, NewestPriceResult>> {
+    let task = tokio::spawn(async move {
+        let result = prices.newest_timestamp().await.map_err(|err| err.to_string());
+        if let Err(err) = &result {
+            warn!("Newest price lookup failed: {}", err);
+        }
+        let mut state = state.lock().unwrap();
+        state.checked = Some((Instant::now(), result.clone()));
+        state.in_flight = None;
+        result
+    });
+    task.map(|joined| joined.unwrap_or_else(|err| Err(format!("lookup task failed: {}", err)))).boxed().shared()
+}
+
+async fn check_database(database: &dyn DatabaseProbe) -> DatabaseCheck {
+    let started = Instant::now();
+    let message = match timeout(PING_TIMEOUT, database.ping()).await {
+        Ok(Ok(())) => {
+            return DatabaseCheck { status: HealthStatus::Pass, latency_ms: Some(started.elapsed().as_millis() as u64), message: None };
+        }
+        Ok(Err(err)) => {
+            warn!("Readiness ping failed: {}", err);
+            "Database unreachable".to_string()
+        }
+        Err(_) => format!("Ping timed out after {}s", PING_TIMEOUT.as_secs()),
+    };
+    DatabaseCheck { status: HealthStatus::Fail, latency_ms: None, message: Some(message) }
+}
+
+/// A data check that could not determine the age of the data.
+fn freshness_unknown(max_age_seconds: u64, message: String) -> DataFreshnessCheck {
+    DataFreshnessCheck { status: HealthStatus::Warn, newest_price_at: None, age_seconds: None, max_age_seconds, message: Some(message) }
+}
+
+async fn check_freshness(monitor: &HealthMonitor, prices: &Arc<dyn PriceRepository>, max_age: Duration) -> DataFreshnessCheck {
+    let max_age_seconds = max_age.as_secs();
+    let unknown = |message: String| freshness_unknown(max_age_seconds, message);
+
+    let newest = match timeout(FRESHNESS_TIMEOUT, monitor.newest_price(prices)).await {
+        Ok(Ok(newest)) => newest,
+        Ok(Err(_)) => return unknown("Newest price lookup failed".to_string()),
+        Err(_) => return unknown(format!("Newest price lookup timed out after {}s", FRESHNESS_TIMEOUT.as_secs())),
+    };
+    let Some(newest) = newest.and_then(|timestamp| Utc.timestamp_opt(timestamp, 0).single()) else {
+        return unknown("No price data stored".to_string());
+    };
+
+    let age_seconds = (Utc::now() - newest).num_seconds();
+    let stale = age_seconds > max_age_seconds as i64;
+    DataFreshnessCheck {
+        status: if stale { HealthStatus::Warn } else { HealthStatus::Pass },
+        newest_price_at: Some(newest),
+        age_seconds: Some(age_seconds),
+        max_age_seconds,
+        message: stale.then(|| format!("Newest price is older than {} hours", max_age_seconds / 3600)),
+    }
+}
+
+/// Runs every readiness check. The data check is skipped while the
+/// database is unreachable, which the database check already reports.
+pub async fn readiness(monitor: &HealthMonitor, database: &dyn DatabaseProbe, prices: &Arc<dyn PriceRepository>, max_data_age: Duration) -> ReadinessReport {
+    let mongo = check_database(database).await;
+    let workers = monitor.workers();
+    let data = if mongo.status == HealthStatus::Pass {
+        check_freshness(monitor, prices, max_data_age).await
+    } else {
+        freshness_unknown(max_data_age.as_secs(), "Not checked while the database is unreachable".to_string())
+    };
+
+    ReadinessReport {
+        status: mongo.status.max(workers.status).max(data.status),
+        checked_at: Utc::now(),
+        checks: ReadinessChecks { mongo, workers, data },
+    }
+}
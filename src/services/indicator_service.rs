@@ -0,0 +1,266 @@
+use log::{info, warn};
+use sentry::capture_message;
+use crate::error::{AppError, AppResult};
+use crate::models::indicator_models::{BollingerSeries, IndicatorQuery, IndicatorResponse, MacdSeries, StochasticSeries};
+use crate::repositories::PriceRepository;
+use crate::services::stock_service::price_series;
+
+const ALL_INDICATORS: [&str; 8] = ["sma", "ema", "rsi", "macd", "bollinger", "atr", "vwap", "stochastic"];
+
+pub fn sma(values: &[f64], window: usize) -> Vec<Option<f64>> {
+    let mut result = vec![None; values.len()];
+    if window == 0 || values.len() < window {
+        return result;
+    }
+    let mut sum: f64 = values[..window].iter().sum();
+    result[window - 1] = Some(sum / window as f64);
+    for i in window..values.len() {
+        sum += values[i] - values[i - window];
+        result[i] = Some(sum / window as f64);
+    }
+    result
+}
+
+/// Exponential moving average seeded with the simple average of the first window.
+pub fn ema(values: &[f64], window: usize) -> Vec<Option<f64>> {
+    let mut result = vec![None; values.len()];
+    if window == 0 || values.len() < window {
+        return result;
+    }
+    let alpha = 2.0 / (window as f64 + 1.0);
+    let mut current = values[..window].iter().sum::<f64>() / window as f64;
+    result[window - 1] = Some(current);
+    for i in window..values.len() {
+        current = alpha * values[i] + (1.0 - alpha) * current;
+        result[i] = Some(current);
+    }
+    result
+}
+
+/// EMA over a series that starts with undefined points, e.g. the MACD line.
+fn ema_of_partial(values: &[Option<f64>], window: usize) -> Vec<Option<f64>> {
+    let Some(start) = values.iter().position(Option::is_some) else {
+        return vec![None; values.len()];
+    };
+    let defined: Vec<f64> = values[start..].iter().map(|v| v.unwrap_or_default()).collect();
+    let mut result = vec![None; start];
+    result.extend(ema(&defined, window));
+    result
+}
+
+/// Relative strength index using Wilder's smoothing.
+pub fn rsi(closes: &[f64], window: usize) -> Vec<Option<f64>> {
+    let mut result = vec![None; closes.len()];
+    if window == 0 || closes.len() <= window {
+        return result;
+    }
+
+    let changes: Vec<f64> = closes.windows(2).map(|pair| pair[1] - pair[0]).collect();
+    let mut avg_gain = changes[..window].iter().map(|c| c.max(0.0)).sum::<f64>() / window as f64;
+    let mut avg_loss = changes[..window].iter().map(|c| (-c).max(0.0)).sum::<f64>() / window as f64;
+
+    let to_rsi = |gain: f64, loss: f64| {
+        if loss == 0.0 { 100.0 } else { 100.0 - 100.0 / (1.0 + gain / loss) }
+    };
+    result[window] = Some(to_rsi(avg_gain, avg_loss));
+
+    for (i, change) in changes.iter().enumerate().skip(window) {
+        avg_gain = (avg_gain * (window as f64 - 1.0) + change.max(0.0)) / window as f64;
+        avg_loss = (avg_loss * (window as f64 - 1.0) + (-change).max(0.0)) / window as f64;
+        result[i + 1] = Some(to_rsi(avg_gain, avg_loss));
+    }
+    result
+}
+
+pub fn macd(closes: &[f64], fast: usize, slow: usize, signal: usize) -> MacdSeries {
+    let fast_ema = ema(closes, fast);
+    let slow_ema = ema(closes, slow);
+    let macd_line: Vec<Option<f64>> = fast_ema.iter().zip(&slow_ema)
+        .map(|(f, s)| Some((*f)? - (*s)?))
+        .collect();
+    let signal_line = ema_of_partial(&macd_line, signal);
+    let histogram = macd_line.iter().zip(&signal_line)
+        .map(|(m, s)| Some((*m)? - (*s)?))
+        .collect();
+
+    MacdSeries { macd: macd_line, signal: signal_line, histogram }
+}
+
+pub fn bollinger(closes: &[f64], window: usize, std_dev: f64) -> BollingerSeries {
+    let middle = sma(closes, window);
+    let mut upper = vec![None; closes.len()];
+    let mut lower = vec![None; closes.len()];
+
+    for (i, mean) in middle.iter().enumerate() {
+        if let Some(mean) = mean {
+            let slice = &closes[i + 1 - window..=i];
+            let variance = slice.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / window as f64;
+            let band = std_dev * variance.sqrt();
+            upper[i] = Some(mean + band);
+            lower[i] = Some(mean - band);
+        }
+    }
+
+    BollingerSeries { middle, upper, lower }
+}
+
+/// Average true range using Wilder's smoothing.
+pub fn atr(highs: &[f64], lows: &[f64], closes: &[f64], window: usize) -> Vec<Option<f64>> {
+    let len = closes.len().min(highs.len()).min(lows.len());
+    let mut result = vec![None; closes.len()];
+    if window == 0 || len < window {
+        return result;
+    }
+
+    let true_ranges: Vec<f64> = (0..len)
+        .map(|i| {
+            let range = highs[i] - lows[i];
+            if i == 0 {
+                range
+            } else {
+                range
+                    .max((highs[i] - closes[i - 1]).abs())
+                    .max((lows[i] - closes[i - 1]).abs())
+            }
+        })
+        .collect();
+
+    let mut current = true_ranges[..window].iter().sum::<f64>() / window as f64;
+    result[window - 1] = Some(current);
+    for i in window..len {
+        current = (current * (window as f64 - 1.0) + true_ranges[i]) / window as f64;
+        result[i] = Some(current);
+    }
+    result
+}
+
+/// Volume weighted average price anchored at the start of the series.
+pub fn vwap(highs: &[f64], lows: &[f64], closes: &[f64], volumes: &[i64]) -> Vec<Option<f64>> {
+    let mut cumulative_value = 0.0;
+    let mut cumulative_volume = 0.0;
+
+    (0..closes.len())
+        .map(|i| {
+            let (high, low, volume) = (highs.get(i)?, lows.get(i)?, *volumes.get(i)? as f64);
+            cumulative_value += (high + low + closes[i]) / 3.0 * volume;
+            cumulative_volume += volume;
+            (cumulative_volume > 0.0).then(|| cumulative_value / cumulative_volume)
+        })
+        .collect()
+}
+
+pub fn stochastic(highs: &[f64], lows: &[f64], closes: &[f64], k_window: usize, d_window: usize) -> StochasticSeries {
+    let len = closes.len().min(highs.len()).min(lows.len());
+    let mut k = vec![None; closes.len()];
+
+    if k_window > 0 {
+        for i in k_window.saturating_sub(1)..len {
+            let range = i + 1 - k_window..=i;
+            let highest = highs[range.clone()].iter().cloned().fold(f64::MIN, f64::max);
+            let lowest = lows[range].iter().cloned().fold(f64::MAX, f64::min);
+            k[i] = Some(if highest > lowest {
+                100.0 * (closes[i] - lowest) / (highest - lowest)
+            } else {
+                50.0
+            });
+        }
+    }
+
+    let d = match k.iter().position(Option::is_some) {
+        Some(start) => {
+            let defined: Vec<f64> = k[start..].iter().map(|v| v.unwrap_or_default()).collect();
+            let mut d = vec![None; start];
+            d.extend(sma(&defined, d_window));
+            d
+        }
+        None => vec![None; k.len()],
+    };
+
+    StochasticSeries { k, d }
+}
+
+pub async fn indicators(prices: &dyn PriceRepository, ticker: String, query: IndicatorQuery) -> AppResult<IndicatorResponse> {
+    let period = query.period.clone().unwrap_or_else(|| "1mo".to_string());
+    let interval = query.interval.clone().unwrap_or_else(|| "1d".to_string());
+
+    let requested: Vec<String> = match &query.indicators {
+        Some(list) => list.split(',').map(|name| name.trim().to_lowercase()).filter(|name| !name.is_empty()).collect(),
+        None => ALL_INDICATORS.iter().map(|name| name.to_string()).collect(),
+    };
+    if let Some(unknown) = requested.iter().find(|name| !ALL_INDICATORS.contains(&name.as_str())) {
+        warn!("Unknown indicator requested: {}", unknown);
+        return Err(AppError::BadRequest(format!("Unknown indicator: {}", unknown)));
+    }
+
+    let (macd_fast, macd_slow) = (query.macd_fast.unwrap_or(12), query.macd_slow.unwrap_or(26));
+    if requested.iter().any(|name| name == "macd") && macd_fast >= macd_slow {
+        warn!("Rejected MACD windows {}/{}", macd_fast, macd_slow);
+        return Err(AppError::BadRequest(format!("macd_fast ({}) must be less than macd_slow ({})", macd_fast, macd_slow)));
+    }
+
+    info!("Computing indicators {:?} for {} ({}/{})", requested, ticker, period, interval);
+    capture_message(&format!("Computing indicators {:?} for {} ({}/{})", requested, ticker, period, interval), sentry::Level::Info);
+
+    let series = price_series(prices, &ticker, &period, &interval).await?
+        .ok_or_else(|| AppError::NotFound(format!("No {}/{} price data for {}", period, interval, ticker)))?;
+
+    let closes = series.closes.unwrap_or_default();
+    let highs = series.highs.unwrap_or_default();
+    let lows = series.lows.unwrap_or_default();
+    let volumes = series.volumes.unwrap_or_default();
+    let wants = |name: &str| requested.iter().any(|r| r == name);
+
+    // Range indicators need a high and low (and VWAP a volume) for every close;
+    // leaving them out of the response would look like a successful answer.
+    // Range indicators need a value for every close; answering without them
+    // would read as a successful response.
+    for (name, fields) in [("atr", &["highs", "lows"][..]), ("vwap", &["highs", "lows", "volumes"]), ("stochastic", &["highs", "lows"])] {
+        if !wants(name) {
+            continue;
+        }
+        for field in fields {
+            let len = match *field {
+                "highs" => highs.len(),
+                "lows" => lows.len(),
+                _ => volumes.len(),
+            };
+            if len != closes.len() {
+                warn!("Cannot compute {} for {} ({}/{}): {} has {} values for {} closes", name, ticker, period, interval, field, len, closes.len());
+                return Err(AppError::BadRequest(format!(
+                    "{} needs {} for every close, but the {}/{} series has {} for {} closes",
+                    name, field, period, interval, len, closes.len()
+                )));
+            }
+        }
+    }
+
+    Ok(IndicatorResponse {
+        ticker,
+        period,
+        interval,
+        timestamps: series.timestamps.unwrap_or_default(),
+        sma: wants("sma").then(|| sma(&closes, query.sma_window.unwrap_or(20))),
+        ema: wants("ema").then(|| ema(&closes, query.ema_window.unwrap_or(20))),
+        rsi: wants("rsi").then(|| rsi(&closes, query.rsi_window.unwrap_or(14))),
+        macd: wants("macd").then(|| macd(
+            &closes,
+            macd_fast,
+            macd_slow,
+            query.macd_signal.unwrap_or(9),
+        )),
+        bollinger: wants("bollinger").then(|| bollinger(
+            &closes,
+            query.bollinger_window.unwrap_or(20),
+            query.bollinger_std_dev.unwrap_or(2.0),
+        )),
+        atr: wants("atr").then(|| atr(&highs, &lows, &closes, query.atr_window.unwrap_or(14))),
+        vwap: wants("vwap").then(|| vwap(&highs, &lows, &closes, &volumes)),
+        stochastic: wants("stochastic").then(|| stochastic(
+            &highs,
+            &lows,
+            &closes,
+            query.stochastic_k.unwrap_or(14),
+            query.stochastic_d.unwrap_or(3),
+        )),
+    })
+}
@@ -0,0 +1,150 @@
+use std::sync::RwLock;
+use chrono::{DateTime, Utc};
+use log::info;
+use mongodb::bson::Bson;
+use sentry::capture_message;
+use crate::error::{AppError, AppResult};
+use crate::models::market_models::{MarketOverviewResponse, MoverEntry, MoverList, MoverRank, MoverWindow, MoversQuery, MoversResponse};
+use crate::repositories::{CompanyQuery, Repositories};
+use crate::services::corporate_action_service::adjust_for_splits;
+use crate::services::stock_service::{get_path, parse_series};
+
+const DEFAULT_LIMIT: usize = 10;
+const MAX_LIMIT: usize = 100;
+/// Trading days in a week of daily bars.
+const WEEKLY_LOOKBACK: usize = 5;
+
+pub struct MarketSnapshot {
+    pub computed_at: DateTime<Utc>,
+    pub entries: Vec<MoverEntry>,
+}
+
+/// Latest market snapshot, recomputed by a background job so the home screen
+/// never waits on a scan of every `stock_data` collection.
+#[derive(Default)]
+pub struct MarketOverview {
+    snapshot: RwLock<Option<MarketSnapshot>>,
+}
+
+impl MarketOverview {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn replace(&self, snapshot: MarketSnapshot) {
+        *self.snapshot.write().unwrap() = Some(snapshot);
+    }
+
+    fn ranked(&self, list: MoverList, window: MoverWindow, rank: MoverRank, sector: Option<&str>, limit: usize) -> AppResult<MoversResponse> {
+        let guard = self.snapshot.read().unwrap();
+        let snapshot = guard.as_ref()
+            .ok_or_else(|| AppError::Unavailable("Market overview is still being computed".to_string()))?;
+
+        let metric = |entry: &MoverEntry| match (list, window, rank) {
+            (MoverList::Active, _, _) => entry.volume.map(|volume| volume as f64),
+            (_, MoverWindow::Daily, MoverRank::Percent) => entry.daily_change_percent,
+            (_, MoverWindow::Daily, MoverRank::Absolute) => entry.daily_change,
+            (_, MoverWindow::Weekly, MoverRank::Percent) => entry.weekly_change_percent,
+            (_, MoverWindow::Weekly, MoverRank::Absolute) => entry.weekly_change,
+        };
+
+        let mut ranked: Vec<(f64, &MoverEntry)> = snapshot.entries
+            .iter()
+            .filter(|entry| sector.is_none_or(|sector| entry.sector.as_deref() == Some(sector)))
+            .filter_map(|entry| Some((metric(entry)?, entry)))
+            .filter(|(value, _)| match list {
+                MoverList::Gainers => *value > 0.0,
+                MoverList::Losers => *value < 0.0,
+                MoverList::Active => true,
+            })
+            .collect();
+
+        if list == MoverList::Losers {
+            ranked.sort_by(|a, b| a.0.total_cmp(&b.0));
+        } else {
+            ranked.sort_by(|a, b| b.0.total_cmp(&a.0));
+        }
+
+        Ok(MoversResponse {
+            computed_at: snapshot.computed_at,
+            results: ranked.into_iter().take(limit).map(|(_, entry)| entry.clone()).collect(),
+        })
+    }
+
+    pub fn movers(&self, query: MoversQuery) -> AppResult<MoversResponse> {
+        self.ranked(
+            query.list,
+            query.window.unwrap_or(MoverWindow::Daily),
+            query.rank.unwrap_or(MoverRank::Percent),
+            query.sector.as_deref(),
+            query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT),
+        )
+    }
+
+    pub fn overview(&self, sector: Option<&str>, limit: Option<usize>) -> AppResult<MarketOverviewResponse> {
+        let limit = limit.unwrap_or(5).clamp(1, MAX_LIMIT);
+        let list = |list| self.ranked(list, MoverWindow::Daily, MoverRank::Percent, sector, limit);
+        let gainers = list(MoverList::Gainers)?;
+
+        Ok(MarketOverviewResponse {
+            computed_at: gainers.computed_at,
+            gainers: gainers.results,
+            losers: list(MoverList::Losers)?.results,
+            most_active: list(MoverList::Active)?.results,
+        })
+    }
+}
+
+fn change_since(closes: &[f64], lookback: usize) -> Option<(f64, Option<f64>)> {
+    let last = *closes.last()?;
+    let base = *closes.get(closes.len().checked_sub(lookback + 1)?)?;
+    let change = last - base;
+    Some((change, (base != 0.0).then(|| change / base * 100.0)))
+}
+
+/// Computes daily and weekly changes for every company from its
+/// split-adjusted 1mo/1d series.
+pub async fn compute_snapshot(repositories: &Repositories) -> AppResult<MarketSnapshot> {
+    let query = CompanyQuery { fields: vec!["name", "profile.sector"], ..CompanyQuery::default() };
+    let companies = repositories.companies.find_companies(&query).await.map_err(|err| {
+        capture_message(&format!("Error querying companies for market overview: {}", err), sentry::Level::Error);
+        AppError::from(err)
+    })?;
+
+    let tickers: Vec<String> = companies.iter().map(|company| company.get_str("ticker").unwrap_or_default().to_string()).collect();
+    let series = repositories.prices.series_many(&tickers, "1mo", "1d").await.map_err(|err| {
+        capture_message(&format!("Error querying prices for market overview: {}", err), sentry::Level::Error);
+        AppError::from(err)
+    })?;
+    let splits = repositories.prices.split_histories(&tickers).await.map_err(|err| {
+        capture_message(&format!("Error querying splits for market overview: {}", err), sentry::Level::Error);
+        AppError::from(err)
+    })?;
+
+    let mut entries = Vec::with_capacity(companies.len());
+    for (company, ticker) in companies.iter().zip(&tickers) {
+        let Some(mut series) = series.get(ticker).and_then(|doc| parse_series(doc, ticker)) else { continue };
+        if let Some(splits) = splits.get(ticker) {
+            adjust_for_splits(&mut series, splits);
+        }
+        let closes = series.closes.unwrap_or_default();
+        let Some(&price) = closes.last() else { continue };
+        let daily = change_since(&closes, 1);
+        let weekly = change_since(&closes, WEEKLY_LOOKBACK);
+
+        entries.push(MoverEntry {
+            ticker: ticker.clone(),
+            name: company.get_str("name").unwrap_or_default().to_string(),
+            sector: get_path(company, "profile.sector").and_then(Bson::as_str).map(str::to_string),
+            price,
+            daily_change: daily.map(|(change, _)| change),
+            daily_change_percent: daily.and_then(|(_, percent)| percent),
+            weekly_change: weekly.map(|(change, _)| change),
+            weekly_change_percent: weekly.and_then(|(_, percent)| percent),
+            volume: series.volumes.and_then(|volumes| volumes.last().copied()),
+        });
+    }
+
+    info!("Computed market overview for {} tickers", entries.len());
+    Ok(MarketSnapshot { computed_at: Utc::now(), entries })
+}
@@ -1,3 +1,15 @@
 pub mod auth_service;
 pub mod stock_service;
 pub mod trade_service;
+pub mod activity_service;
+pub mod indicator_service;
+pub mod candle_service;
+pub mod screener_service;
+pub mod search_service;
+pub mod taxonomy_service;
+pub mod market_service;
+pub mod earnings_service;
+pub mod corporate_action_service;
+pub mod data_quality_service;
+pub mod cache_service;
+pub mod health_service;
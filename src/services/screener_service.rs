@@ -0,0 +1,232 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use log::{error, info};
+use mongodb::bson::{Bson, Document};
+use sentry::capture_message;
+use crate::error::{AppError, AppResult};
+use crate::models::screener_models::{ScreenerPayload, ScreenerResponse, ScreenerRow, SortOrder};
+use crate::repositories::{CompanyQuery, CompanyRepository, CompanySort, FieldRange, PriceRepository, RepositoryError};
+use crate::services::cache_service::MarketDataCache;
+use crate::services::stock_service::{bson_to_f64, get_path, one_month_return_of};
+
+const MAX_ITEMS_PER_PAGE: u32 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScreenerField {
+    MarketCap,
+    TrailingPe,
+    ForwardPe,
+    DividendYield,
+    DividendRate,
+    Beta,
+    PegRatio,
+    ProfitMargins,
+    TrailingEps,
+    ForwardEps,
+    OneMonthReturn,
+    Name,
+    Ticker,
+}
+
+impl ScreenerField {
+    pub fn parse(value: &str) -> Option<ScreenerField> {
+        match value {
+            "market_cap" => Some(ScreenerField::MarketCap),
+            "trailing_pe" => Some(ScreenerField::TrailingPe),
+            "forward_pe" => Some(ScreenerField::ForwardPe),
+            "dividend_yield" => Some(ScreenerField::DividendYield),
+            "dividend_rate" => Some(ScreenerField::DividendRate),
+            "beta" => Some(ScreenerField::Beta),
+            "peg_ratio" => Some(ScreenerField::PegRatio),
+            "profit_margins" => Some(ScreenerField::ProfitMargins),
+            "trailing_eps" => Some(ScreenerField::TrailingEps),
+            "forward_eps" => Some(ScreenerField::ForwardEps),
+            "one_month_return" => Some(ScreenerField::OneMonthReturn),
+            "name" => Some(ScreenerField::Name),
+            "ticker" => Some(ScreenerField::Ticker),
+            _ => None,
+        }
+    }
+
+    /// Location of the field in a `companies` document; `None` for fields
+    /// computed from price data.
+    pub fn path(&self) -> Option<&'static str> {
+        match self {
+            ScreenerField::MarketCap => Some("financials.marketCap.raw"),
+            ScreenerField::TrailingPe => Some("financials.trailingPE.raw"),
+            ScreenerField::ForwardPe => Some("financials.forwardPE.raw"),
+            ScreenerField::DividendYield => Some("financials.dividendYield.raw"),
+            ScreenerField::DividendRate => Some("financials.dividendRate.raw"),
+            ScreenerField::Beta => Some("financials.beta.raw"),
+            ScreenerField::PegRatio => Some("keyStatistics.pegRatio.raw"),
+            ScreenerField::ProfitMargins => Some("keyStatistics.profitMargins.raw"),
+            ScreenerField::TrailingEps => Some("keyStatistics.trailingEPS.raw"),
+            ScreenerField::ForwardEps => Some("keyStatistics.forwardEPS.raw"),
+            ScreenerField::Name => Some("name"),
+            ScreenerField::Ticker => Some("ticker"),
+            ScreenerField::OneMonthReturn => None,
+        }
+    }
+
+    fn is_numeric(&self) -> bool {
+        !matches!(self, ScreenerField::Name | ScreenerField::Ticker)
+    }
+}
+
+fn path_f64(doc: &Document, path: &str) -> Option<f64> {
+    get_path(doc, path).and_then(bson_to_f64)
+}
+
+fn path_str(doc: &Document, path: &str) -> Option<String> {
+    get_path(doc, path).and_then(Bson::as_str).map(str::to_string)
+}
+
+fn to_row(doc: &Document, one_month_return: Option<f64>) -> ScreenerRow {
+    let value = |field: ScreenerField| field.path().and_then(|path| path_f64(doc, path));
+    ScreenerRow {
+        ticker: doc.get_str("ticker").unwrap_or_default().to_string(),
+        name: doc.get_str("name").unwrap_or_default().to_string(),
+        sector: path_str(doc, "profile.sector"),
+        industry: path_str(doc, "profile.industry"),
+        market_cap: value(ScreenerField::MarketCap),
+        trailing_pe: value(ScreenerField::TrailingPe),
+        forward_pe: value(ScreenerField::ForwardPe),
+        dividend_yield: value(ScreenerField::DividendYield),
+        dividend_rate: value(ScreenerField::DividendRate),
+        beta: value(ScreenerField::Beta),
+        peg_ratio: value(ScreenerField::PegRatio),
+        profit_margins: value(ScreenerField::ProfitMargins),
+        trailing_eps: value(ScreenerField::TrailingEps),
+        forward_eps: value(ScreenerField::ForwardEps),
+        one_month_return,
+    }
+}
+
+#[derive(PartialEq, PartialOrd)]
+enum SortValue<'a> {
+    Number(f64),
+    Text(&'a str),
+}
+
+/// The value `field` sorts `row` by; `None` when the row has none.
+fn sort_value(row: &ScreenerRow, field: ScreenerField) -> Option<SortValue<'_>> {
+    let number = match field {
+        ScreenerField::MarketCap => row.market_cap,
+        ScreenerField::TrailingPe => row.trailing_pe,
+        ScreenerField::ForwardPe => row.forward_pe,
+        ScreenerField::DividendYield => row.dividend_yield,
+        ScreenerField::DividendRate => row.dividend_rate,
+        ScreenerField::Beta => row.beta,
+        ScreenerField::PegRatio => row.peg_ratio,
+        ScreenerField::ProfitMargins => row.profit_margins,
+        ScreenerField::TrailingEps => row.trailing_eps,
+        ScreenerField::ForwardEps => row.forward_eps,
+        ScreenerField::OneMonthReturn => row.one_month_return,
+        ScreenerField::Name => return Some(SortValue::Text(&row.name)).filter(|_| !row.name.is_empty()),
+        ScreenerField::Ticker => return Some(SortValue::Text(&row.ticker)).filter(|_| !row.ticker.is_empty()),
+    };
+    number.filter(|value| !value.is_nan()).map(SortValue::Number)
+}
+
+/// Orders rows by `field` in `order`. Rows without a value go last in
+/// either direction, as they do when the database sorts.
+fn compare_rows(a: &ScreenerRow, b: &ScreenerRow, field: ScreenerField, order: SortOrder) -> Ordering {
+    match (sort_value(a, field), sort_value(b, field)) {
+        (Some(x), Some(y)) => {
+            let ordering = x.partial_cmp(&y).unwrap_or(Ordering::Equal);
+            if order == SortOrder::Desc { ordering.reverse() } else { ordering }
+        }
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// One month returns of the companies in `docs`, from one batch of their
+/// 1mo/1d series. Companies without a return are left out.
+async fn monthly_returns(prices: &dyn PriceRepository, cache: &MarketDataCache, docs: &[Document]) -> AppResult<HashMap<String, f64>> {
+    let tickers: Vec<String> = docs.iter().map(|doc| doc.get_str("ticker").unwrap_or_default().to_string()).collect();
+    let series = cache.series_many(prices, &tickers, "1mo", "1d").await.map_err(|err| {
+        error!("Error fetching price data for screener: {}", err);
+        capture_message(&format!("Error fetching price data for screener: {}", err), sentry::Level::Error);
+        AppError::Internal("Database query failed for screener price data".to_string())
+    })?;
+    let splits = prices.split_histories(&tickers).await.map_err(|err| {
+        error!("Error fetching splits for screener: {}", err);
+        AppError::Internal("Database query failed for split history".to_string())
+    })?;
+    Ok(series
+        .iter()
+        .filter_map(|(ticker, doc)| {
+            let splits = splits.get(ticker).map(Vec::as_slice).unwrap_or_default();
+            Some((ticker.clone(), one_month_return_of(doc, ticker, splits)?))
+        })
+        .collect())
+}
+
+pub async fn screen_stocks(payload: ScreenerPayload, companies: &dyn CompanyRepository, prices: &dyn PriceRepository, cache: &MarketDataCache) -> AppResult<ScreenerResponse> {
+    let page = payload.page.unwrap_or(0);
+    let items_per_page = payload.items_per_page.unwrap_or(10).clamp(1, MAX_ITEMS_PER_PAGE);
+    let sort_order = payload.sort_order.unwrap_or(SortOrder::Desc);
+    let sort_field = match &payload.sort_by {
+        Some(value) => ScreenerField::parse(value)
+            .ok_or_else(|| AppError::BadRequest(format!("Unknown sort field: {}", value)))?,
+        None => ScreenerField::MarketCap,
+    };
+
+    let mut query = CompanyQuery { sector: payload.sector.clone(), industry: payload.industry.clone(), ..CompanyQuery::default() };
+    let mut return_range: Option<(Option<f64>, Option<f64>)> = None;
+    for range in &payload.filters {
+        let field = ScreenerField::parse(&range.field)
+            .filter(ScreenerField::is_numeric)
+            .ok_or_else(|| AppError::BadRequest(format!("Unknown filter field: {}", range.field)))?;
+        match field.path() {
+            Some(path) => query.ranges.push(FieldRange { path, min: range.min, max: range.max }),
+            None => return_range = Some((range.min, range.max)),
+        }
+    }
+
+    info!("Screening stocks with query: {:?}, sort: {:?} {:?}", query, sort_field, sort_order);
+    capture_message(&format!("Screening stocks with query: {:?}, sort: {:?} {:?}", query, sort_field, sort_order), sentry::Level::Info);
+
+    let db_error = |err: RepositoryError| {
+        error!("Error querying companies for screener: {}", err);
+        capture_message(&format!("Error querying companies for screener: {}", err), sentry::Level::Error);
+        AppError::Internal("Database query failed for screener".to_string())
+    };
+
+    let skip = page as u64 * items_per_page as u64;
+
+    // Database-side sort and pagination when nothing depends on price data.
+    if let (Some(sort_path), None) = (sort_field.path(), return_range) {
+        let total = companies.count_companies(&query).await.map_err(db_error)?;
+        query.sort = Some(CompanySort { path: sort_path, descending: sort_order == SortOrder::Desc, numeric: sort_field.is_numeric() });
+        query.skip = skip;
+        query.limit = Some(items_per_page as i64);
+        let docs = companies.find_companies(&query).await.map_err(db_error)?;
+        let returns = monthly_returns(prices, cache, &docs).await?;
+        let results = docs.iter().map(|doc| to_row(doc, returns.get(doc.get_str("ticker").unwrap_or_default()).copied())).collect();
+        return Ok(ScreenerResponse { total, page, items_per_page, results });
+    }
+
+    // Computed fields need every candidate's price data before paginating.
+    let docs = companies.find_companies(&query).await.map_err(db_error)?;
+    let returns = monthly_returns(prices, cache, &docs).await?;
+    let mut rows = Vec::with_capacity(docs.len());
+    for doc in &docs {
+        let monthly_return = returns.get(doc.get_str("ticker").unwrap_or_default()).copied();
+        let in_range = return_range.is_none_or(|(min, max)| match monthly_return {
+            Some(value) => min.is_none_or(|min| value >= min) && max.is_none_or(|max| value <= max),
+            None => false,
+        });
+        if in_range {
+            rows.push(to_row(doc, monthly_return));
+        }
+    }
+
+    rows.sort_by(|a, b| compare_rows(a, b, sort_field, sort_order).then_with(|| a.ticker.cmp(&b.ticker)));
+
+    let total = rows.len() as u64;
+    let results = rows.into_iter().skip(skip as usize).take(items_per_page as usize).collect();
+    Ok(ScreenerResponse { total, page, items_per_page, results })
+}
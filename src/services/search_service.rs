@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+use log::{error, info};
+use mongodb::bson::{Bson, Document};
+use sentry::capture_message;
+use crate::error::{AppError, AppResult};
+use crate::models::search_models::{SearchMatch, SearchResponse, SearchResult};
+use crate::repositories::{CompanyRepository, RepositoryError};
+use crate::services::stock_service::get_path;
+
+const DEFAULT_LIMIT: usize = 10;
+const MAX_LIMIT: usize = 50;
+const CANDIDATE_LIMIT: i64 = 200;
+const FUZZY_CANDIDATE_LIMIT: i64 = 1_000;
+
+/// Levenshtein distance, counted in characters.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+fn fuzzy_tolerance(query: &str) -> usize {
+    match query.chars().count() {
+        0..=2 => 0,
+        3..=5 => 1,
+        _ => 2,
+    }
+}
+
+/// Scores how well `ticker`/`name` match the lower-cased query. Exact ticker
+/// hits rank first, then prefixes, then substrings, then typos.
+pub fn score_match(query: &str, ticker: &str, name: &str) -> Option<(f64, SearchMatch)> {
+    let ticker = ticker.to_lowercase();
+    let name = name.to_lowercase();
+    let length_penalty = |candidate: &str| (candidate.len().saturating_sub(query.len())) as f64 * 0.1;
+
+    if ticker == query {
+        return Some((100.0, SearchMatch::Ticker));
+    }
+    if ticker.starts_with(query) {
+        return Some((80.0 - length_penalty(&ticker), SearchMatch::Ticker));
+    }
+    if name.starts_with(query) {
+        return Some((60.0 - length_penalty(&name).min(10.0), SearchMatch::Name));
+    }
+    if name.split_whitespace().any(|word| word.starts_with(query)) {
+        return Some((50.0, SearchMatch::Name));
+    }
+    if name.contains(query) {
+        return Some((40.0, SearchMatch::Name));
+    }
+
+    let tolerance = fuzzy_tolerance(query);
+    let distance = std::iter::once(ticker.as_str())
+        .chain(name.split_whitespace())
+        .map(|word| {
+            // Compare against the word's prefix so "appl" still finds "apple".
+            let prefix: String = word.chars().take(query.chars().count()).collect();
+            edit_distance(query, word).min(edit_distance(query, &prefix))
+        })
+        .min()?;
+    (tolerance > 0 && distance <= tolerance).then_some((30.0 - 5.0 * distance as f64, SearchMatch::Fuzzy))
+}
+
+/// Keeps the highest scoring match per ticker.
+fn keep_best(results: &mut HashMap<String, SearchResult>, result: SearchResult) {
+    if results.get(&result.ticker).is_none_or(|existing| existing.score < result.score) {
+        results.insert(result.ticker.clone(), result);
+    }
+}
+
+fn to_result(doc: &Document, score: f64, matched: SearchMatch) -> SearchResult {
+    SearchResult {
+        ticker: doc.get_str("ticker").unwrap_or_default().to_string(),
+        name: doc.get_str("name").unwrap_or_default().to_string(),
+        sector: get_path(doc, "profile.sector").and_then(Bson::as_str).map(str::to_string),
+        industry: get_path(doc, "profile.industry").and_then(Bson::as_str).map(str::to_string),
+        score,
+        matched,
+    }
+}
+
+pub async fn search_stocks(companies: &dyn CompanyRepository, query: String, limit: Option<usize>) -> AppResult<SearchResponse> {
+    let query = query.trim().to_string();
+    if query.is_empty() {
+        return Err(AppError::BadRequest("Missing search query".to_string()));
+    }
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let needle = query.to_lowercase();
+
+    info!("Searching companies for: '{}'", query);
+    capture_message(&format!("Searching companies for: '{}'", query), sentry::Level::Info);
+
+    let db_error = |err: RepositoryError| {
+        error!("Error searching companies: {}", err);
+        capture_message(&format!("Error searching companies: {}", err), sentry::Level::Error);
+        AppError::Internal("Database query failed for search".to_string())
+    };
+
+    let mut results: HashMap<String, SearchResult> = HashMap::new();
+
+    // Ticker prefixes and name substrings.
+    for doc in companies.search_names(&query, CANDIDATE_LIMIT).await.map_err(db_error)? {
+        let (ticker, name) = (doc.get_str("ticker").unwrap_or_default(), doc.get_str("name").unwrap_or_default());
+        if let Some((score, matched)) = score_match(&needle, ticker, name) {
+            keep_best(&mut results, to_result(&doc, score, matched));
+        }
+    }
+
+    // Business summary words through the text index.
+    for (doc, text_score) in companies.search_summaries(&query, CANDIDATE_LIMIT).await.map_err(db_error)? {
+        keep_best(&mut results, to_result(&doc, 10.0 + text_score.min(19.0), SearchMatch::Summary));
+    }
+
+    // Typo tolerance only when the direct matches don't fill the box. The
+    // candidates share the query's first letter, which keeps the lookup on
+    // the ticker and name indexes; a typo in the first letter is not found.
+    let initial = needle.chars().next().filter(|_| results.len() < limit && fuzzy_tolerance(&needle) > 0);
+    if let Some(initial) = initial {
+        for doc in companies.starting_with(initial, FUZZY_CANDIDATE_LIMIT).await.map_err(db_error)? {
+            let (ticker, name) = (doc.get_str("ticker").unwrap_or_default(), doc.get_str("name").unwrap_or_default());
+            if let Some((score, matched)) = score_match(&needle, ticker, name) {
+                keep_best(&mut results, to_result(&doc, score, matched));
+            }
+        }
+    }
+
+    let mut results: Vec<SearchResult> = results.into_values().collect();
+    results.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.ticker.cmp(&b.ticker)));
+    results.truncate(limit);
+
+    Ok(SearchResponse { query, results })
+}
@@ -1,89 +1,84 @@
-use actix_web::{error::{self, ErrorInternalServerError}, web, Error, HttpResponse};
 use chrono::{DateTime, Utc, TimeZone};
-use futures::stream::StreamExt;
-use mongodb::{bson::{self, doc, from_document, Bson, Document}, options::FindOptions, Client};
-use crate::models::stock_models::{Financials, KeyStatistics, PriceData, PriceDataDetails, Profile, StockData, StockDetailsResponse, StockListingPayload, StockListingResponse};
+use mongodb::bson::{from_document, Bson, Document};
+use crate::error::{AppError, AppResult};
+use crate::models::stock_models::{DetailLevel, EarningsHistory, EarningsReport, Financials, KeyMetrics, KeyStatistics, Metric, PriceData, PriceDataDetails, Profile, Quote, StockData, StockDetailsResponse, StockListingPayload, StockListingResponse, STOCK_DETAIL_FIELDS};
+use crate::services::corporate_action_service::adjust_for_splits;
+use crate::repositories::{CompanyRepository, PriceRepository, RepositoryResult};
+use crate::services::cache_service::MarketDataCache;
+use crate::services::data_quality_service::structural_issues;
 use log::{debug, error, info, warn};
 use sentry::capture_message;
 
-pub async fn stockList(
+pub async fn list_stocks(
     payload: StockListingPayload, 
-    client: web::Data<Client>
-) -> Result<StockListingResponse, Error> {
+    companies: &dyn CompanyRepository,
+    prices: &dyn PriceRepository,
+    cache: &MarketDataCache,
+) -> AppResult<StockListingResponse> {
     if payload.user_id.is_none() {
         warn!("Missing user ID in payload");
         capture_message("Missing user ID in payload", sentry::Level::Warning);
-        return Err(error::ErrorBadRequest("Missing user ID"));
+        return Err(AppError::BadRequest("Missing user ID".to_string()));
     }
 
     info!("Fetching stock data for industry: '{}', sector: '{}'", payload.industry.clone().unwrap_or_default(), payload.sector);
     capture_message(&format!("Fetching stock data for industry: '{}', sector: '{}'", payload.industry.clone().unwrap_or_default(), payload.sector), sentry::Level::Info);
 
-    let companies_collection = client.database("trading_simulator").collection::<Document>("companies");
-    let stock_data_collection = client.database("stock_data");
-
-    let mut filter = doc! {
-        "profile.sector": &payload.sector
-    };
-    if let Some(industry) = payload.industry {
-        filter.insert("profile.industry", industry);
+    if let Some(listing) = cache.listing(&payload) {
+        debug!("Serving stock list for sector '{}' from cache", payload.sector);
+        return Ok(listing);
     }
-    let find_options = FindOptions::builder()
-        .skip(Some(payload.page.unwrap_or(0) as u64 * payload.items_per_page.unwrap_or(10) as u64))
-        .limit(Some(payload.items_per_page.unwrap_or(10) as i64))
-        .build();
 
-    info!("Query Filter: {:?}", filter);
-    capture_message(&format!("Query Filter: {:?}", filter), sentry::Level::Info);
+    let skip = payload.page.unwrap_or(0) as u64 * payload.items_per_page.unwrap_or(10) as u64;
+    let limit = payload.items_per_page.unwrap_or(10) as i64;
 
-    info!("Find Options: {:?}", find_options);
-    capture_message(&format!("Find Options: {:?}", find_options), sentry::Level::Info);
+    info!("Query: sector {:?}, industry {:?}, skip {}, limit {}", payload.sector, payload.industry, skip, limit);
+    capture_message(&format!("Query: sector {:?}, industry {:?}, skip {}, limit {}", payload.sector, payload.industry, skip, limit), sentry::Level::Info);
 
-    let mut cursor = companies_collection.find(filter, find_options).await.map_err(|err| {
+    let companies = companies.find_page(&payload.sector, payload.industry.as_deref(), skip, limit).await.map_err(|err| {
         error!("Error querying companies collection: {}", err);
         capture_message(&format!("Error querying companies collection: {}", err), sentry::Level::Error);
-        error::ErrorInternalServerError(err)
+        AppError::from(err)
     })?;
 
-    let mut documents = Vec::new();
-    while let Some(result) = cursor.next().await {
-        let doc = result.map_err(|err| {
-            error!("Error fetching document from cursor: {}", err);
-            capture_message(&format!("Error fetching document from cursor: {}", err), sentry::Level::Error);
-            error::ErrorInternalServerError(err)
-        })?;
+    // One round of concurrent lookups for the whole page instead of one
+    // query per company in turn.
+    let tickers: Vec<String> = companies.iter().map(|doc| doc.get_str("ticker").unwrap_or_default().to_string()).collect();
+    let monthly_series = cache.series_many(prices, &tickers, "1mo", "1d").await.map_err(|err| {
+        error!("Error fetching price data for sector '{}': {}", payload.sector, err);
+        capture_message(&format!("Error fetching price data for sector '{}': {}", payload.sector, err), sentry::Level::Error);
+        AppError::from(err)
+    })?;
 
-        let ticker = doc.get_str("ticker").unwrap_or_default();
+    let mut documents = Vec::new();
+    for (doc, ticker) in companies.into_iter().zip(&tickers) {
+        let ticker = ticker.as_str();
         info!("Processing ticker: {}", ticker);
         capture_message(&format!("Processing ticker: {}", ticker), sentry::Level::Info);
 
-        let price_data_filter = doc! {"symbol": ticker, "period": "1mo", "interval": "1d"};
-        let price_doc_option = stock_data_collection.collection::<Document>(ticker).find_one(price_data_filter, None).await.map_err(|err| {
-            error!("Error fetching price data for ticker '{}': {}", ticker, err);
-            capture_message(&format!("Error fetching price data for ticker '{}': {}", ticker, err), sentry::Level::Error);
-            error::ErrorInternalServerError(err)
-        })?;
+        let price_doc_option = monthly_series.get(ticker).map(|price_doc| price_doc.as_ref());
 
-        if let Some(price_doc) = price_doc_option {
+        if let Some(price_doc) = price_doc_option.filter(|price_doc| is_servable(price_doc, ticker)) {
             let closes: Vec<f32> = price_doc.get_array("closes")
                 .unwrap_or(&Vec::new())
                 .iter()
-                .map(|b| b.as_f64().unwrap() as f32)
+                .filter_map(bson_to_f64)
+                .map(|close| close as f32)
                 .collect();
 
             let timestamps: Vec<DateTime<Utc>> = price_doc.get_array("timestamps")
                 .unwrap_or(&Vec::new())
                 .iter()
-                .map(|b| {
-                    let timestamp = b.as_i64().unwrap(); 
-                    match Utc.timestamp_opt(timestamp, 0) {
+                .filter_map(bson_to_f64)
+                .map(|timestamp| {
+                    match Utc.timestamp_opt(timestamp as i64, 0) {
                         chrono::LocalResult::Single(dt) => dt,
-                        _ => Utc.timestamp(0, 0) 
+                        _ => DateTime::<Utc>::UNIX_EPOCH
                     }
                 })
                 .collect();
 
-            let price_data: Vec<PriceData> = closes.into_iter().zip(timestamps.into_iter())
+            let price_data: Vec<PriceData> = closes.into_iter().zip(timestamps)
                 .map(|(price, timestamp)| PriceData { 
                     date: timestamp.to_rfc3339(),
                     price 
@@ -96,57 +91,73 @@ pub async fn stockList(
                 price_data,
             });
         }
+        cache.store_company(ticker, doc);
     }
 
-    Ok(StockListingResponse { documents })
+    let listing = StockListingResponse { documents };
+    cache.store_listing(&payload, listing.clone());
+    Ok(listing)
 }
 
-pub async fn stock_details(client: web::Data<Client>, ticker: String) -> Result<StockDetailsResponse, Error> {
-    let companies_collection = client.database("trading_simulator").collection::<Document>("companies");
-    let stock_data_collection = client.database("stock_data").collection::<Document>(&ticker);
-
-    let company_filter = doc! { "ticker": &ticker };
-    let company_doc = companies_collection
-        .find_one(Some(company_filter), None)
-        .await
-        .map_err(|err| {
-            error!("Error querying company data for ticker {}: {}", &ticker, err);
-            capture_message(&format!("Error querying company data for ticker {}: {}", &ticker, err), sentry::Level::Error);
-            actix_web::error::ErrorInternalServerError("Database query failed for company data")
-        })?;
+pub async fn stock_details(
+    companies: &dyn CompanyRepository,
+    prices: &dyn PriceRepository,
+    cache: &MarketDataCache,
+    ticker: String,
+    detail_level: Option<String>,
+    fields: Option<String>,
+    adjusted: bool,
+) -> AppResult<StockDetailsResponse> {
+    let level = match detail_level.as_deref() {
+        Some(value) => DetailLevel::parse(value)
+            .ok_or_else(|| AppError::BadRequest(format!("Unknown detail_level: {}", value)))?,
+        None => DetailLevel::Full,
+    };
+    let selected: Vec<String> = match fields {
+        Some(fields) => fields.split(',').map(|field| field.trim().to_string()).filter(|field| !field.is_empty()).collect(),
+        None => level.default_fields().iter().map(|field| field.to_string()).collect(),
+    };
+    if let Some(unknown) = selected.iter().find(|field| !STOCK_DETAIL_FIELDS.contains(&field.as_str())) {
+        return Err(AppError::BadRequest(format!("Unknown field: {}", unknown)));
+    }
+    let wants = |field: &str| selected.iter().any(|selected| selected == field);
+    debug!("Loading {:?} stock details for {} with fields {:?}", level, ticker, selected);
 
-    let mut cursor = stock_data_collection
-        .find(None, None)
-        .await
-        .map_err(|err| {
+    let needs_company = ["financials", "key_statistics", "profile", "key_metrics", "quote", "earnings"].iter().any(|field| wants(field));
+    let company_doc = if needs_company {
+        cache
+            .company(companies, &ticker)
+            .await
+            .map_err(|err| {
+                error!("Error querying company data for ticker {}: {}", &ticker, err);
+                capture_message(&format!("Error querying company data for ticker {}: {}", &ticker, err), sentry::Level::Error);
+                AppError::Internal("Database query failed for company data".to_string())
+            })?
+    } else {
+        None
+    };
+
+    let mut price_data: Option<Vec<PriceDataDetails>> = if wants("price_data") {
+        let price_docs = cache.price_documents(prices, &ticker).await.map_err(|err| {
             error!("Error querying stock price data for ticker {}: {}", &ticker, err);
             capture_message(&format!("Error querying stock price data for ticker {}: {}", &ticker, err), sentry::Level::Error);
-            actix_web::error::ErrorInternalServerError("Database query failed for stock price data")
+            AppError::Internal("Database query failed for stock price data".to_string())
         })?;
-
-    let mut price_data = Vec::new();
-    while let Some(result) = cursor.next().await {
-        match result {
-            Ok(doc) => {
-                debug!("Price data document retrieved: {:?}", doc);
-                match from_document::<PriceDataDetails>(doc.clone()) {
-                    Ok(price_detail) => {
-                        debug!("Parsed price data: {:?}", price_detail);
-                        price_data.push(price_detail);
-                    }
-                    Err(err) => {
-                        error!("Failed to parse price data for ticker {}: {:?}", &ticker, err);
-                        capture_message(&format!("Failed to parse price data for ticker {}: {:?}", &ticker, err), sentry::Level::Error);
-                        error!("Document content: {:?}", doc);
-                        return Err(actix_web::error::ErrorInternalServerError("Data parsing failed for price data"));
-                    }
-                }
-            }
-            Err(err) => {
-                error!("Cursor iteration failed for stock price data: {:?}", err);
-                capture_message(&format!("Cursor iteration failed for stock price data: {:?}", err), sentry::Level::Error);
-                return Err(actix_web::error::ErrorInternalServerError("Cursor failed during stock price data retrieval"));
-            }
+        Some(if level == DetailLevel::Full {
+            price_docs.iter().filter_map(|doc| parse_series(doc, &ticker)).collect()
+        } else {
+            find_series(&price_docs, "1mo", "1d").and_then(|doc| parse_series(doc, &ticker)).into_iter().collect()
+        })
+    } else {
+        None
+    };
+    if let (Some(series), true) = (price_data.as_mut(), adjusted) {
+        let splits = prices.split_history(&ticker).await.map_err(|err| {
+            error!("Error querying splits for ticker {}: {}", &ticker, err);
+            AppError::Internal("Database query failed for split history".to_string())
+        })?;
+        if !splits.is_empty() {
+            series.iter_mut().for_each(|series| adjust_for_splits(series, &splits));
         }
     }
 
@@ -168,13 +179,161 @@ pub async fn stock_details(client: web::Data<Client>, ticker: String) -> Result<
         .cloned()
         .and_then(|doc| from_document(doc).ok());
 
+    let earnings: Option<EarningsHistory> = company_doc
+        .as_ref()
+        .filter(|_| wants("earnings"))
+        .and_then(|doc| doc.get("earnings").and_then(Bson::as_document))
+        .cloned()
+        .and_then(|doc| from_document(doc).ok())
+        .map(|mut earnings: EarningsHistory| {
+            earnings.history.iter_mut().for_each(EarningsReport::fill_surprise);
+            earnings
+        });
+
+    let raw = |metric: &Option<Metric>| metric.as_ref().and_then(|m| m.raw);
+    let key_metrics = wants("key_metrics").then(|| KeyMetrics {
+        market_cap: financials.as_ref().and_then(|f| f.market_cap.as_ref()).and_then(|m| m.raw),
+        trailing_pe: financials.as_ref().and_then(|f| raw(&f.trailing_pe)),
+        forward_pe: financials.as_ref().and_then(|f| raw(&f.forward_pe)),
+        dividend_yield: financials.as_ref().and_then(|f| raw(&f.dividend_yield)),
+        beta: financials.as_ref().and_then(|f| raw(&f.beta)),
+        trailing_eps: key_statistics.as_ref().and_then(|k| raw(&k.trailing_eps)),
+    });
+
+    let quote = if wants("quote") {
+        let previous_close = financials.as_ref().and_then(|f| raw(&f.previous_close));
+        latest_quote(prices, &ticker, previous_close).await.map_err(|err| {
+            error!("Error querying latest quote for ticker {}: {}", &ticker, err);
+            capture_message(&format!("Error querying latest quote for ticker {}: {}", &ticker, err), sentry::Level::Error);
+            AppError::Internal("Database query failed for latest quote".to_string())
+        })?
+    } else {
+        None
+    };
+
     let response = StockDetailsResponse {
         ticker: ticker.clone(),
-        financials,
-        key_statistics,
-        price_data: Some(price_data),
-        profile,
+        financials: financials.filter(|_| wants("financials")),
+        key_statistics: key_statistics.filter(|_| wants("key_statistics")),
+        price_data,
+        profile: profile.filter(|_| wants("profile")),
+        key_metrics,
+        quote,
+        earnings,
     };
 
     Ok(response)
 }
+
+pub fn bson_to_f64(value: &Bson) -> Option<f64> {
+    match value {
+        Bson::Double(v) => Some(*v),
+        Bson::Int32(v) => Some(*v as f64),
+        Bson::Int64(v) => Some(*v as f64),
+        _ => None,
+    }
+}
+
+/// Resolves a dotted path such as `financials.marketCap.raw` in `doc`.
+pub fn get_path<'a>(doc: &'a Document, path: &str) -> Option<&'a Bson> {
+    let mut parts = path.split('.');
+    let mut value = doc.get(parts.next()?)?;
+    for part in parts {
+        value = value.as_document()?.get(part)?;
+    }
+    Some(value)
+}
+
+/// Whether a stored price document passes the structural checks. Failing
+/// documents are logged and skipped so one bad series does not fail the
+/// request; the data quality job quarantines them.
+fn is_servable(doc: &Document, ticker: &str) -> bool {
+    let issues = structural_issues(doc);
+    if let Some(issue) = issues.first() {
+        warn!("Skipping malformed {:?}/{:?} price data for ticker {}: {} ({} issues)", doc.get_str("period").ok(), doc.get_str("interval").ok(), ticker, issue.message, issues.len());
+        capture_message(&format!("Skipping malformed price data for ticker {}: {}", ticker, issue.message), sentry::Level::Warning);
+        return false;
+    }
+    true
+}
+
+/// Validates and parses a stored price document, logging why it was skipped.
+pub fn parse_series(doc: &Document, ticker: &str) -> Option<PriceDataDetails> {
+    if !is_servable(doc, ticker) {
+        return None;
+    }
+    from_document::<PriceDataDetails>(doc.clone()).map_err(|err| {
+        error!("Failed to parse price data for ticker {}: {:?}", ticker, err);
+        capture_message(&format!("Failed to parse price data for ticker {}: {:?}", ticker, err), sentry::Level::Error);
+    }).ok()
+}
+
+/// The document for one `period`/`interval` combination among `docs`.
+fn find_series<'a>(docs: &'a [Document], period: &str, interval: &str) -> Option<&'a Document> {
+    docs.iter().find(|doc| doc.get_str("period") == Ok(period) && doc.get_str("interval") == Ok(interval))
+}
+
+/// Whether a company document exists for `ticker`. Goes through the cache,
+/// so a following `stock_details` call does not read it again.
+pub async fn company_exists(companies: &dyn CompanyRepository, cache: &MarketDataCache, ticker: &str) -> AppResult<bool> {
+    Ok(cache.company(companies, ticker).await?.is_some())
+}
+
+/// Latest quote for `ticker`. The change is measured against the company's
+/// reported previous close when known, otherwise against the prior point.
+pub async fn latest_quote(prices: &dyn PriceRepository, ticker: &str, previous_close: Option<f64>) -> RepositoryResult<Option<Quote>> {
+    let Some(point) = prices.latest_price(ticker).await? else {
+        return Ok(None);
+    };
+    let previous_close = previous_close.or(point.previous);
+    let change = previous_close.map(|previous| point.close - previous);
+
+    Ok(Some(Quote {
+        price: point.close,
+        previous_close,
+        change,
+        change_percent: previous_close
+            .filter(|previous| *previous != 0.0)
+            .zip(change)
+            .map(|(previous, change)| change / previous * 100.0),
+        volume: point.volume,
+        timestamp: Utc.timestamp_opt(point.timestamp, 0).single().unwrap_or_default(),
+        interval: point.interval,
+    }))
+}
+
+/// Loads the stored series for one `period`/`interval` combination of `ticker`.
+pub async fn price_series(prices: &dyn PriceRepository, ticker: &str, period: &str, interval: &str) -> AppResult<Option<PriceDataDetails>> {
+    let price_docs = prices.price_documents(ticker).await.map_err(|err| {
+        error!("Error querying {}/{} price data for ticker {}: {}", period, interval, ticker, err);
+        capture_message(&format!("Error querying {}/{} price data for ticker {}: {}", period, interval, ticker, err), sentry::Level::Error);
+        AppError::Internal("Database query failed for stock price data".to_string())
+    })?;
+
+    Ok(find_series(&price_docs, period, interval).and_then(|doc| parse_series(doc, ticker)))
+}
+
+/// Loads every stored period/interval series for `ticker`.
+pub async fn all_price_series(prices: &dyn PriceRepository, ticker: &str) -> AppResult<Vec<PriceDataDetails>> {
+    let price_docs = prices.price_documents(ticker).await.map_err(|err| {
+        error!("Error querying stock price data for ticker {}: {}", ticker, err);
+        capture_message(&format!("Error querying stock price data for ticker {}: {}", ticker, err), sentry::Level::Error);
+        AppError::Internal("Database query failed for stock price data".to_string())
+    })?;
+
+    Ok(price_docs.iter().filter_map(|doc| parse_series(doc, ticker)).collect())
+}
+
+/// Percent change from the first to the last close of a series.
+pub fn period_return(closes: &[f64]) -> Option<f64> {
+    let (first, last) = (closes.first()?, closes.last()?);
+    (*first != 0.0).then(|| (last / first - 1.0) * 100.0)
+}
+
+/// Return over the 1mo/1d document of `ticker`, in percent, after
+/// adjusting for `splits`. A malformed series has no return.
+pub fn one_month_return_of(price_doc: &Document, ticker: &str, splits: &[(i64, f64)]) -> Option<f64> {
+    let mut series = parse_series(price_doc, ticker)?;
+    adjust_for_splits(&mut series, splits);
+    period_return(series.closes.as_deref()?)
+}
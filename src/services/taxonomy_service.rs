@@ -0,0 +1,155 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use log::{error, info, warn};
+use mongodb::bson::{Bson, Document};
+use sentry::capture_message;
+use crate::error::{AppError, AppResult};
+use crate::models::taxonomy_models::{GroupStats, IndustrySummary, SectorSummary};
+use crate::repositories::{CompanyQuery, CompanyRepository, PriceRepository};
+use crate::services::cache_service::MarketDataCache;
+use crate::services::stock_service::{bson_to_f64, get_path, one_month_return_of};
+
+const UNCLASSIFIED: &str = "Unclassified";
+
+#[derive(Default)]
+struct StatsAccumulator {
+    company_count: usize,
+    total_market_cap: f64,
+    trailing_pes: Vec<f64>,
+    returns: Vec<f64>,
+}
+
+impl StatsAccumulator {
+    fn add(&mut self, market_cap: Option<f64>, trailing_pe: Option<f64>, monthly_return: Option<f64>) {
+        self.company_count += 1;
+        self.total_market_cap += market_cap.unwrap_or_default();
+        self.trailing_pes.extend(trailing_pe.filter(|pe| pe.is_finite()));
+        self.returns.extend(monthly_return.filter(|value| value.is_finite()));
+    }
+
+    fn finish(mut self) -> GroupStats {
+        GroupStats {
+            company_count: self.company_count,
+            total_market_cap: self.total_market_cap,
+            median_trailing_pe: median(&mut self.trailing_pes),
+            average_one_month_return: (!self.returns.is_empty())
+                .then(|| self.returns.iter().sum::<f64>() / self.returns.len() as f64),
+        }
+    }
+}
+
+pub fn median(values: &mut [f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(f64::total_cmp);
+    let mid = values.len() / 2;
+    Some(if values.len().is_multiple_of(2) { (values[mid - 1] + values[mid]) / 2.0 } else { values[mid] })
+}
+
+#[derive(Default)]
+struct SectorAccumulator {
+    stats: StatsAccumulator,
+    industries: BTreeMap<String, StatsAccumulator>,
+}
+
+/// The 1mo/1d series of `tickers` through the cache. If the batch fails,
+/// tickers are fetched one by one and those that still fail are left out,
+/// so one unreadable series does not fail the whole taxonomy.
+async fn monthly_series(prices: &dyn PriceRepository, cache: &MarketDataCache, tickers: &[String]) -> HashMap<String, Arc<Document>> {
+    let err = match cache.series_many(prices, tickers, "1mo", "1d").await {
+        Ok(documents) => return documents,
+        Err(err) => err,
+    };
+    warn!("Error fetching price data for taxonomy, retrying per ticker: {}", err);
+    let mut documents = HashMap::with_capacity(tickers.len());
+    for ticker in tickers {
+        match cache.series_many(prices, std::slice::from_ref(ticker), "1mo", "1d").await {
+            Ok(found) => documents.extend(found),
+            Err(err) => {
+                warn!("Leaving {} out of taxonomy returns: {}", ticker, err);
+                capture_message(&format!("Leaving {} out of taxonomy returns: {}", ticker, err), sentry::Level::Warning);
+            }
+        }
+    }
+    documents
+}
+
+async fn load_sectors(
+    companies: &dyn CompanyRepository,
+    prices: &dyn PriceRepository,
+    cache: &MarketDataCache,
+    sector: Option<&str>,
+) -> AppResult<BTreeMap<String, SectorAccumulator>> {
+    let query = CompanyQuery {
+        sector: sector.map(str::to_string),
+        // Companies without a sector are listed under `UNCLASSIFIED`.
+        include_unclassified: sector == Some(UNCLASSIFIED),
+        fields: vec!["profile.sector", "profile.industry", "financials.marketCap.raw", "financials.trailingPE.raw"],
+        ..CompanyQuery::default()
+    };
+    let docs = companies.find_companies(&query).await.map_err(|err| {
+        error!("Error querying companies for taxonomy: {}", err);
+        capture_message(&format!("Error querying companies for taxonomy: {}", err), sentry::Level::Error);
+        AppError::Internal("Database query failed for sector taxonomy".to_string())
+    })?;
+    let tickers: Vec<String> = docs.iter().map(|doc| doc.get_str("ticker").unwrap_or_default().to_string()).collect();
+    let monthly_series = monthly_series(prices, cache, &tickers).await;
+    // Unadjusted returns would be wrong across a split, so without the
+    // split history no returns are reported.
+    let splits = prices.split_histories(&tickers).await
+        .inspect_err(|err| warn!("Leaving returns out of taxonomy without split history: {}", err))
+        .ok();
+
+    let mut sectors: BTreeMap<String, SectorAccumulator> = BTreeMap::new();
+    for (doc, ticker) in docs.iter().zip(&tickers) {
+        let label = |path: &str| get_path(doc, path)
+            .and_then(Bson::as_str)
+            .filter(|value| !value.is_empty())
+            .unwrap_or(UNCLASSIFIED)
+            .to_string();
+        let market_cap = get_path(doc, "financials.marketCap.raw").and_then(bson_to_f64);
+        let trailing_pe = get_path(doc, "financials.trailingPE.raw").and_then(bson_to_f64);
+        let monthly_return = splits.as_ref().and_then(|splits| {
+            let splits = splits.get(ticker).map(Vec::as_slice).unwrap_or_default();
+            one_month_return_of(monthly_series.get(ticker)?, ticker, splits)
+        });
+
+        let sector = sectors.entry(label("profile.sector")).or_default();
+        sector.stats.add(market_cap, trailing_pe, monthly_return);
+        sector.industries.entry(label("profile.industry")).or_default().add(market_cap, trailing_pe, monthly_return);
+    }
+
+    Ok(sectors)
+}
+
+fn industry_summaries(industries: BTreeMap<String, StatsAccumulator>) -> Vec<IndustrySummary> {
+    industries
+        .into_iter()
+        .map(|(industry, stats)| IndustrySummary { industry, stats: stats.finish() })
+        .collect()
+}
+
+pub async fn list_sectors(companies: &dyn CompanyRepository, prices: &dyn PriceRepository, cache: &MarketDataCache) -> AppResult<Vec<SectorSummary>> {
+    info!("Building sector taxonomy");
+    let sectors = load_sectors(companies, prices, cache, None).await?;
+
+    Ok(sectors
+        .into_iter()
+        .map(|(sector, accumulator)| SectorSummary {
+            sector,
+            stats: accumulator.stats.finish(),
+            industries: industry_summaries(accumulator.industries),
+        })
+        .collect())
+}
+
+pub async fn list_industries(companies: &dyn CompanyRepository, prices: &dyn PriceRepository, cache: &MarketDataCache, sector: String) -> AppResult<Vec<IndustrySummary>> {
+    info!("Building industry taxonomy for sector: {}", sector);
+    let mut sectors = load_sectors(companies, prices, cache, Some(&sector)).await?;
+
+    match sectors.remove(&sector) {
+        Some(accumulator) => Ok(industry_summaries(accumulator.industries)),
+        None => Err(AppError::NotFound(format!("Unknown sector: {}", sector))),
+    }
+}
@@ -1,24 +1,18 @@
-use futures::TryStreamExt;
-use mongodb::{bson::{doc, oid::ObjectId}, Client, Collection, Database};
+use std::collections::HashMap;
+use mongodb::bson::oid::ObjectId;
+use crate::error::{AppError, AppResult};
+use crate::models::activity_models::AccountEventKind;
 use crate::models::trade_models::{TradeData, Trade, TradeStatus};
 use crate::models::users::User;
-use std::env;
-use std::error::Error;
-use log::{info, warn, error};
+use crate::repositories::{Repositories, TradeRepository, UserRepository};
+use crate::services::activity_service::ActivityFeed;
+use log::{error, info, warn};
 use sentry::capture_message;
-use crate::db::mongo;
 
 
-pub async fn get_database() -> Result<Database, String> {
-    let client: Client = mongo::init().await.map_err(|e| e.to_string())?;
-    Ok(client.database("trading_simulator"))
-}
-
-pub async fn create_trade(trade_data: &TradeData) -> Result<ObjectId, Box<dyn Error>> {
-    let db = get_database().await?;
-    let collection: Collection<Trade> = db.collection("trades");
-
-    let user_id = ObjectId::parse_str(&trade_data.user_id)?;
+/// Stores a new in-progress trade and returns it with its id.
+pub async fn create_trade(trades: &dyn TradeRepository, trade_data: &TradeData) -> AppResult<Trade> {
+    let user_id = ObjectId::parse_str(&trade_data.user_id).map_err(|_| AppError::InvalidId("user_id"))?;
     
     let new_trade = Trade {
         id: None,
@@ -32,57 +26,146 @@ pub async fn create_trade(trade_data: &TradeData) -> Result<ObjectId, Box<dyn Er
         user_id,
         amount: trade_data.amount,
         trade_type: trade_data.trade_type.clone(),
-
+        exit_price: None,
+        closed_at: None,
+        applied_splits: vec![],
     };
 
-    let insert_result = collection.insert_one(new_trade, None).await?;
-    let trade_id = insert_result.inserted_id.as_object_id().unwrap();
+    let trade_id = trades.insert(new_trade.clone()).await?;
     
     info!("Trade created successfully: {:?}", trade_id);
     capture_message(&format!("Trade created successfully: {:?}", trade_id), sentry::Level::Info);
 
-    Ok(trade_id)
+    Ok(Trade { id: Some(trade_id), ..new_trade })
 }
 
-pub async fn update_user_balance_and_trades(user_id: &str, trade_id: &ObjectId, amount: f64) -> Result<(), Box<dyn Error>> {
-    let db = get_database().await?;
-    let users_collection: Collection<User> = db.collection("users");
+/// Opens a trade and debits its amount from the user's balance. The order's
+/// events are published only once the debit has gone through.
+pub async fn place_order(users: &dyn UserRepository, trades: &dyn TradeRepository, trade_data: &TradeData, feed: &ActivityFeed) -> AppResult<Trade> {
+    let trade = create_trade(trades, trade_data).await?;
+    let trade_id = trade.id.expect("stored trades have an id");
+    let user = match update_user_balance_and_trades(users, &trade_data.user_id, &trade_id, trade_data.amount).await {
+        Ok(user) => user,
+        Err(e) => {
+            error!("Failed to update user balance and trades: {}", e);
+            return Err(e);
+        }
+    };
 
-    let user_id = ObjectId::parse_str(user_id)?;
+    // Orders are filled immediately at the submitted price.
+    feed.publish(&trade_data.user_id, AccountEventKind::OrderStatus {
+        trade_id: trade_id.to_hex(),
+        ticker: trade.ticker.clone(),
+        status: TradeStatus::InProgress,
+    });
+    feed.publish(&trade_data.user_id, AccountEventKind::Fill {
+        trade_id: trade_id.to_hex(),
+        ticker: trade.ticker.clone(),
+        quantity: trade.quantity,
+        price: trade.price,
+    });
+    if let Some(user) = user {
+        feed.publish(&trade_data.user_id, AccountEventKind::BalanceUpdate { balance: user.balance, change: -trade_data.amount });
+    }
+    Ok(trade)
+}
 
-    let filter = doc! { "_id": user_id };
-    let update = doc! {
-        "$addToSet": { "trades": trade_id },
-        "$inc": { "balance": -amount },
-    };
+/// Debits `amount` and records the trade on the user. Returns the updated
+/// user, or `None` if there is no such user.
+pub async fn update_user_balance_and_trades(users: &dyn UserRepository, user_id: &str, trade_id: &ObjectId, amount: f64) -> AppResult<Option<User>> {
+    let user_id = ObjectId::parse_str(user_id).map_err(|_| AppError::InvalidId("user_id"))?;
 
-    match users_collection.update_one(filter, update, None).await {
-        Ok(_) => {
+    match users.debit_for_trade(user_id, *trade_id, amount).await {
+        Ok(user) => {
             info!("User balance and trades updated successfully for user: {:?}", user_id);
             capture_message(&format!("User balance and trades updated successfully for user: {:?}", user_id), sentry::Level::Info);
-            Ok(())
+            Ok(user)
         },
         Err(e) => {
             error!("Failed to update user balance and trades for user: {}. Error: {}", user_id, e);
             capture_message(&format!("Failed to update user balance and trades for user: {}. Error: {}", user_id, e), sentry::Level::Error);
-            Err(Box::new(e))
+            Err(e.into())
         }
     }
 }
 
-pub async fn get_user_trades(user_id: &str) -> Result<Vec<Trade>, Box<dyn std::error::Error>> {
-    let db = get_database().await?;
-    let collection: Collection<Trade> = db.collection("trades");
+/// An exit level a trade's price has reached.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExitTrigger {
+    TakeProfit(f64),
+    StopLoss(f64),
+}
 
-    let user_id = ObjectId::parse_str(user_id)?;
-    let filter = doc! { "user_id": user_id };
+/// The exit level `price` reaches for `trade`, if any. Longs take profit at
+/// or above the take-profit level and stop out at or below the stop-loss,
+/// shorts the other way round. A price past both levels stops out.
+pub fn exit_trigger(trade: &Trade, price: f64) -> Option<ExitTrigger> {
+    let reached = |level: f64, above: bool| if above { price >= level } else { price <= level };
+    let short = trade.is_short();
+    if let Some(stop_loss) = trade.stop_loss.filter(|level| reached(*level, short)) {
+        return Some(ExitTrigger::StopLoss(stop_loss));
+    }
+    trade.take_profit.filter(|level| reached(*level, !short)).map(ExitTrigger::TakeProfit)
+}
+
+/// Cash returned when `trade` closes at `exit_price`: the amount debited
+/// when it opened plus the profit, or less the loss.
+pub fn closing_proceeds(trade: &Trade, exit_price: f64) -> f64 {
+    let direction = if trade.is_short() { -1.0 } else { 1.0 };
+    trade.amount + direction * (exit_price - trade.price) * trade.quantity as f64
+}
 
-    let mut cursor = collection.find(filter, None).await?;
-    let mut trades = Vec::new();
+/// Closes every in-progress trade whose ticker's latest close has reached
+/// its take-profit or stop-loss level, at that close, and credits the
+/// proceeds. Returns the number of trades closed.
+///
+/// A trade is only credited by the run that closed it, so overlapping runs
+/// never pay twice.
+pub async fn execute_exit_levels(repositories: &Repositories, feed: &ActivityFeed) -> AppResult<usize> {
+    let trades = repositories.trades.with_exit_levels().await?;
+
+    let mut closes: HashMap<String, Option<f64>> = HashMap::new();
+    let mut closed = 0;
+    for trade in trades {
+        let Some(trade_id) = trade.id else { continue };
+        if !closes.contains_key(&trade.ticker) {
+            let close = match repositories.prices.latest_price(&trade.ticker).await {
+                Ok(latest) => latest.map(|latest| latest.close),
+                Err(err) => {
+                    warn!("Skipping exit levels for {}: {}", trade.ticker, err);
+                    None
+                }
+            };
+            closes.insert(trade.ticker.clone(), close);
+        }
+        let Some(price) = closes[&trade.ticker] else { continue };
+        let Some(trigger) = exit_trigger(&trade, price) else { continue };
+        if !repositories.trades.close(trade_id, price).await? {
+            continue;
+        }
 
-    while let Some(trade) = cursor.try_next().await? {
-        trades.push(trade);
+        let proceeds = closing_proceeds(&trade, price);
+        let user = repositories.users.credit(trade.user_id, proceeds).await?;
+        info!("Closed trade {} for {} at {} ({:?})", trade_id, trade.ticker, price, trigger);
+        capture_message(&format!("Closed trade {} for {} at {} ({:?})", trade_id, trade.ticker, price, trigger), sentry::Level::Info);
+
+        let user_id = trade.user_id.to_hex();
+        let (trade_id, ticker) = (trade_id.to_hex(), trade.ticker.clone());
+        feed.publish(&user_id, AccountEventKind::OrderStatus { trade_id: trade_id.clone(), ticker: ticker.clone(), status: TradeStatus::Closed });
+        feed.publish(&user_id, match trigger {
+            ExitTrigger::TakeProfit(level) => AccountEventKind::TakeProfitTriggered { trade_id, ticker, trigger_price: level, exit_price: price },
+            ExitTrigger::StopLoss(level) => AccountEventKind::StopLossTriggered { trade_id, ticker, trigger_price: level, exit_price: price },
+        });
+        if let Some(user) = user {
+            feed.publish(&user_id, AccountEventKind::BalanceUpdate { balance: user.balance, change: proceeds });
+        }
+        closed += 1;
     }
 
-    Ok(trades)
+    Ok(closed)
+}
+
+pub async fn get_user_trades(trades: &dyn TradeRepository, user_id: &str) -> AppResult<Vec<Trade>> {
+    let user_id = ObjectId::parse_str(user_id).map_err(|_| AppError::InvalidId("user_id"))?;
+    Ok(trades.find_by_user(user_id).await?)
 }
@@ -0,0 +1,130 @@
+//! Request payload validation.
+//!
+//! Request models derive [`Validate`] and declare their rules as field
+//! attributes. Handlers take [`ValidJson`] or [`ValidQuery`] instead of
+//! `web::Json` / `web::Query`, so a request that breaks a rule is answered
+//! with a `validation_failed` error listing every rejected field before the
+//! handler or any service code runs.
+
+use std::borrow::Cow;
+use std::ops::Deref;
+use actix_web::dev::Payload;
+use actix_web::{web, FromRequest, HttpRequest};
+use futures::future::LocalBoxFuture;
+use mongodb::bson::oid::ObjectId;
+use serde::de::DeserializeOwned;
+use validator::{Validate, ValidationError, ValidationErrors, ValidationErrorsKind};
+use crate::error::{AppError, FieldError};
+
+/// A JSON body that deserialized and passed validation.
+#[derive(Debug)]
+pub struct ValidJson<T>(pub T);
+
+/// A query string that deserialized and passed validation.
+#[derive(Debug)]
+pub struct ValidQuery<T>(pub T);
+
+impl<T> ValidJson<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> ValidQuery<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for ValidJson<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> Deref for ValidQuery<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: DeserializeOwned + Validate + 'static> FromRequest for ValidJson<T> {
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let json = web::Json::<T>::from_request(req, payload);
+        Box::pin(async move {
+            let value = json.await?.into_inner();
+            value.validate().map_err(AppError::from)?;
+            Ok(ValidJson(value))
+        })
+    }
+}
+
+impl<T: DeserializeOwned + Validate + 'static> FromRequest for ValidQuery<T> {
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let query = web::Query::<T>::from_request(req, payload);
+        Box::pin(async move {
+            let value = query.await?.into_inner();
+            value.validate().map_err(AppError::from)?;
+            Ok(ValidQuery(value))
+        })
+    }
+}
+
+impl From<ValidationErrors> for AppError {
+    fn from(errors: ValidationErrors) -> Self {
+        let mut fields = Vec::new();
+        collect_field_errors(&errors, "", &mut fields);
+        fields.sort_by(|a, b| a.field.cmp(&b.field));
+        AppError::Validation(fields)
+    }
+}
+
+/// Flattens nested struct and list errors into one entry per failed rule,
+/// keyed by JSON path. Struct-level (`__all__`) errors belong to the
+/// object itself.
+fn collect_field_errors(errors: &ValidationErrors, prefix: &str, fields: &mut Vec<FieldError>) {
+    for (name, kind) in errors.errors() {
+        let path = match (prefix, name.as_ref()) {
+            (prefix, "__all__") => prefix.to_string(),
+            ("", name) => name.to_string(),
+            (prefix, name) => format!("{}.{}", prefix, name),
+        };
+        match kind {
+            ValidationErrorsKind::Field(errors) => {
+                fields.extend(errors.iter().map(|error| FieldError {
+                    field: path.clone(),
+                    code: error.code.to_string(),
+                    message: error.message.as_deref().unwrap_or("is invalid").to_string(),
+                }));
+            }
+            ValidationErrorsKind::Struct(errors) => collect_field_errors(errors, &path, fields),
+            ValidationErrorsKind::List(items) => {
+                for (index, errors) in items {
+                    collect_field_errors(errors, &format!("{}[{}]", path, index), fields);
+                }
+            }
+        }
+    }
+}
+
+/// Builds a rule failure with a message, for custom validators.
+pub fn rule_error(code: &'static str, message: &'static str) -> ValidationError {
+    ValidationError::new(code).with_message(Cow::Borrowed(message))
+}
+
+/// Custom validator for ids sent as hex strings.
+pub fn object_id(value: &str) -> Result<(), ValidationError> {
+    ObjectId::parse_str(value)
+        .map(|_| ())
+        .map_err(|_| rule_error("object_id", "must be a 24 character hex ObjectId"))
+}
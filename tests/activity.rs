@@ -0,0 +1,215 @@
+//! The account activity stream, the session tokens that guard it and the
+//! order events published to it.
+
+mod common;
+
+use std::future::poll_fn;
+use actix_web::body::MessageBody;
+use actix_web::cookie::Cookie;
+use actix_web::test::{call_service, read_body_json, TestRequest};
+use trading_sim_server::auth::{SessionTokens, SESSION_COOKIE};
+use trading_sim_server::config::AuthConfig;
+use async_trait::async_trait;
+use mongodb::bson::oid::ObjectId;
+use trading_sim_server::models::activity_models::AccountEventKind;
+use trading_sim_server::models::trade_models::TradeData;
+use trading_sim_server::models::users::User;
+use trading_sim_server::repositories::memory::InMemoryRepositories;
+use trading_sim_server::repositories::{RepositoryError, RepositoryResult, UserRepository};
+use trading_sim_server::services::activity_service::ActivityFeed;
+use trading_sim_server::services::trade_service::place_order;
+use common::{call, error_message, register, TestApp};
+
+fn stream_request(token: Option<&str>) -> TestRequest {
+    let request = TestRequest::get().uri("/api/activity/stream").insert_header(("Last-Event-ID", "0"));
+    match token {
+        Some(token) => request.insert_header(("Authorization", format!("Bearer {}", token))),
+        None => request,
+    }
+}
+
+fn balance_update(balance: f64) -> AccountEventKind {
+    AccountEventKind::BalanceUpdate { balance, change: 1.0 }
+}
+
+/// The next chunk of a streaming body as text.
+async fn next_chunk<B: MessageBody>(body: &mut std::pin::Pin<Box<B>>) -> String {
+    let chunk = poll_fn(|cx| body.as_mut().poll_next(cx)).await.expect("stream ended");
+    let chunk = chunk.map_err(Into::into).expect("stream chunk");
+    String::from_utf8(chunk.to_vec()).unwrap()
+}
+
+#[actix_web::test]
+async fn login_and_registration_return_a_session_token() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+    let registered = register(&service, "trader@example.com").await;
+
+    let tokens = SessionTokens::from_config(&app.config.auth);
+    let user_id = registered.user_id.unwrap();
+    assert_eq!(tokens.verify(&registered.token.unwrap()), Some(user_id.clone()));
+
+    let login = TestRequest::post()
+        .uri("/api/login")
+        .set_json(serde_json::json!({ "email": "trader@example.com", "password": common::PASSWORD }))
+        .to_request();
+    let response = call_service(&service, login).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let cookie = response.response().cookies().find(|cookie| cookie.name() == SESSION_COOKIE).expect("session cookie");
+    assert_eq!((cookie.http_only(), cookie.secure(), cookie.path()), (Some(true), Some(true), Some("/api")));
+    let cookie_token = cookie.value().to_string();
+    let body: serde_json::Value = read_body_json(response).await;
+    assert_eq!(body["token"].as_str(), Some(cookie_token.as_str()));
+    assert_eq!(tokens.verify(&cookie_token), Some(user_id));
+}
+
+#[actix_web::test]
+async fn stream_requires_a_session_token() {
+    let service = TestApp::with_fixtures().service().await;
+
+    let (status, body) = call(&service, stream_request(None).to_request()).await;
+    assert_eq!(status, 401);
+    assert_eq!(error_message(&body, "unauthorized"), "Missing session token");
+
+    // A user id alone no longer opens anyone's stream.
+    let registered = register(&service, "trader@example.com").await;
+    let request = TestRequest::get().uri(&format!("/api/activity/stream?user_id={}", registered.user_id.unwrap())).to_request();
+    let (status, _) = call(&service, request).await;
+    assert_eq!(status, 401);
+
+    // Nor does a token in the query string, which access logs would record.
+    let request = TestRequest::get().uri(&format!("/api/activity/stream?token={}", registered.token.unwrap())).to_request();
+    let (status, body) = call(&service, request).await;
+    assert_eq!(status, 401);
+    assert_eq!(error_message(&body, "unauthorized"), "Missing session token");
+}
+
+#[actix_web::test]
+async fn stream_rejects_forged_tampered_and_expired_tokens() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+    let victim = register(&service, "victim@example.com").await;
+    let attacker = register(&service, "attacker@example.com").await;
+
+    let other_key = SessionTokens::from_config(&AuthConfig { token_secret: "another secret".to_string(), ..Default::default() });
+    let forged = other_key.issue(victim.user_id.as_deref().unwrap());
+    let attacker_token = attacker.token.unwrap();
+    let (_, rest) = attacker_token.split_once('.').unwrap();
+    let tampered = format!("{}.{}", victim.user_id.unwrap(), rest);
+    let expired = SessionTokens::from_config(&AuthConfig { token_ttl_hours: 0, ..app.config.auth.clone() }).issue("65f1c0a1b2c3d4e5f6a7b8c9");
+
+    for token in [forged, tampered, expired, "garbage".to_string()] {
+        let (status, body) = call(&service, stream_request(Some(&token)).to_request()).await;
+        assert_eq!(status, 401, "{}", token);
+        assert_eq!(error_message(&body, "unauthorized"), "Invalid or expired session token");
+    }
+}
+
+#[actix_web::test]
+async fn stream_carries_only_the_token_holders_events() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+    let trader = register(&service, "trader@example.com").await;
+    let trader_id = trader.user_id.unwrap();
+    let other_id = register(&service, "other@example.com").await.user_id.unwrap();
+
+    app.feed.publish(&other_id, balance_update(1.0));
+    app.feed.publish(&trader_id, balance_update(2.0));
+
+    // EventSource clients send the token in the session cookie.
+    let request = TestRequest::get()
+        .uri("/api/activity/stream")
+        .cookie(Cookie::new(SESSION_COOKIE, trader.token.unwrap()))
+        .insert_header(("Last-Event-ID", "0"))
+        .to_request();
+    let response = call_service(&service, request).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers().get("content-type").unwrap(), "text/event-stream");
+    let mut body = Box::pin(response.into_body());
+
+    assert_eq!(next_chunk(&mut body).await, "retry: 3000\n\n");
+    let replayed = next_chunk(&mut body).await;
+    assert!(replayed.starts_with("id: 2\nevent: balance_update\n"), "{}", replayed);
+    assert!(replayed.contains(&trader_id));
+
+    app.feed.publish(&other_id, balance_update(3.0));
+    app.feed.publish(&trader_id, balance_update(4.0));
+    let live = next_chunk(&mut body).await;
+    assert!(live.starts_with("id: 4\n"), "{}", live);
+}
+
+fn order(user_id: &str) -> TradeData {
+    TradeData {
+        ticker: "AAPL".to_string(),
+        position: "long".to_string(),
+        quantity: 5,
+        price: 186.0,
+        take_profit: None,
+        stop_loss: None,
+        user_id: user_id.to_string(),
+        amount: 930.0,
+        trade_type: "market".to_string(),
+        user_balance: 0.0,
+    }
+}
+
+fn published(feed: &ActivityFeed, user_id: &str) -> Vec<&'static str> {
+    feed.subscribe(user_id, Some(0)).0.iter().map(|event| event.kind.name()).collect()
+}
+
+#[actix_web::test]
+async fn orders_are_announced_after_the_debit() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+    let user_id = register(&service, "trader@example.com").await.user_id.unwrap();
+
+    let request = TestRequest::post().uri("/api/trade_submit").set_json(order(&user_id)).to_request();
+    let (status, body) = call(&service, request).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(published(&app.feed, &user_id), ["order_status", "fill", "balance_update"]);
+}
+
+/// Users whose balance cannot be debited.
+struct UnwritableUsers;
+
+fn unavailable() -> RepositoryError {
+    mongodb::error::Error::from(std::io::Error::new(std::io::ErrorKind::ConnectionRefused, "database unreachable")).into()
+}
+
+#[async_trait]
+impl UserRepository for UnwritableUsers {
+    async fn find_by_email(&self, _email: &str) -> RepositoryResult<Option<User>> {
+        Ok(None)
+    }
+
+    async fn insert(&self, _user: User) -> RepositoryResult<ObjectId> {
+        Err(unavailable())
+    }
+
+    async fn debit_for_trade(&self, _user_id: ObjectId, _trade_id: ObjectId, _amount: f64) -> RepositoryResult<Option<User>> {
+        Err(unavailable())
+    }
+
+    async fn credit(&self, _user_id: ObjectId, _amount: f64) -> RepositoryResult<Option<User>> {
+        Err(unavailable())
+    }
+
+    async fn apply_ledger_entry(&self, _user_id: ObjectId, _entry_id: ObjectId, _amount: f64) -> RepositoryResult<Option<User>> {
+        Err(unavailable())
+    }
+
+    async fn release_ledger_entry(&self, _user_id: ObjectId, _entry_id: ObjectId) -> RepositoryResult<()> {
+        Err(unavailable())
+    }
+}
+
+#[actix_web::test]
+async fn a_failed_debit_announces_no_fill() {
+    let repositories = InMemoryRepositories::new();
+    let feed = ActivityFeed::new();
+    let user_id = ObjectId::new().to_hex();
+
+    let result = place_order(&UnwritableUsers, repositories.trades.as_ref(), &order(&user_id), &feed).await;
+    assert!(result.is_err());
+    assert!(published(&feed, &user_id).is_empty());
+}
@@ -0,0 +1,520 @@
+//! End-to-end tests of the `/api` routes against the in-memory backend.
+
+mod common;
+
+use actix_web::test::TestRequest;
+use mongodb::bson::oid::ObjectId;
+use serde_json::{json, Value};
+use trading_sim_server::models::trade_models::{Trade, TradeStatus};
+use trading_sim_server::models::users::AuthResponse;
+use trading_sim_server::repositories::UserRepository;
+use common::{call, error_message, field_errors, from_value, registered_user, TestApp, PASSWORD, AAPL_CLOSES, AAPL_PREVIOUS_CLOSE, DAY, FIRST_TIMESTAMP, HARDWARE, SECTOR, SOFTWARE, SOFTWARE_TICKERS};
+
+const EMAIL: &str = "trader@example.com";
+
+fn auth_request(path: &str, email: &str, password: &str) -> actix_http::Request {
+    TestRequest::post().uri(path).set_json(json!({ "email": email, "password": password })).to_request()
+}
+
+fn listing_request(body: Value) -> actix_http::Request {
+    TestRequest::post().uri("/api/stock-list").set_json(body).to_request()
+}
+
+fn listed_tickers(body: &Value) -> Vec<String> {
+    body["documents"].as_array().expect("documents array")
+        .iter()
+        .map(|stock| stock["ticker"].as_str().expect("ticker").to_string())
+        .collect()
+}
+
+fn trade_request(user_id: &str, amount: f64) -> actix_http::Request {
+    TestRequest::post()
+        .uri("/api/trade_submit")
+        .set_json(json!({
+            "ticker": "AAPL",
+            "position": "long",
+            "quantity": 5,
+            "price": 186.0,
+            "take_profit": 200.0,
+            "stop_loss": null,
+            "user_id": user_id,
+            "amount": amount,
+            "trade_type": "market",
+            "user_balance": 10000.0,
+        }))
+        .to_request()
+}
+
+// Register and login
+
+#[actix_web::test]
+async fn register_then_login() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+
+    let (status, body) = call(&service, auth_request("/api/register", EMAIL, PASSWORD)).await;
+    assert_eq!(status, 200, "{}", body);
+    let registered: AuthResponse = from_value(body);
+    assert!(registered.success);
+    let user_id = registered.user_id.expect("registration returns the user id");
+
+    let user = app.repositories.users.find_by_id(ObjectId::parse_str(&user_id).unwrap()).expect("user stored");
+    assert_eq!(user.email, EMAIL);
+    assert_ne!(user.password, PASSWORD, "password must be stored hashed");
+    assert_eq!(user.balance, app.config.trading.starting_balance);
+
+    let (status, body) = call(&service, auth_request("/api/login", EMAIL, PASSWORD)).await;
+    assert_eq!(status, 200, "{}", body);
+    let logged_in: AuthResponse = from_value(body);
+    assert!(logged_in.success);
+    assert_eq!(logged_in.user_id, Some(user_id));
+}
+
+#[actix_web::test]
+async fn registration_uses_configured_starting_balance() {
+    let mut app = TestApp::with_fixtures();
+    app.config.trading.starting_balance = 2500.0;
+    let service = app.service().await;
+
+    let (status, body) = call(&service, auth_request("/api/register", EMAIL, PASSWORD)).await;
+    assert_eq!(status, 200, "{}", body);
+    let user_id = from_value::<AuthResponse>(body).user_id.unwrap();
+    let user = app.repositories.users.find_by_id(ObjectId::parse_str(&user_id).unwrap()).unwrap();
+    assert_eq!(user.balance, 2500.0);
+}
+
+#[actix_web::test]
+async fn duplicate_registration_is_rejected() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+
+    let (status, _) = call(&service, auth_request("/api/register", EMAIL, PASSWORD)).await;
+    assert_eq!(status, 200);
+    let (status, body) = call(&service, auth_request("/api/register", EMAIL, "another password")).await;
+    assert_eq!(status, 409);
+    assert_eq!(error_message(&body, "email_taken"), "Email already registered");
+}
+
+#[actix_web::test]
+async fn login_with_wrong_password_is_rejected() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+
+    call(&service, auth_request("/api/register", EMAIL, PASSWORD)).await;
+    let (status, body) = call(&service, auth_request("/api/login", EMAIL, "wrong password")).await;
+    assert_eq!(status, 401);
+    assert_eq!(error_message(&body, "invalid_credentials"), "Invalid email or password");
+}
+
+#[actix_web::test]
+async fn login_with_unreadable_password_hash_is_rejected() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+    let user_id = registered_user(&service, EMAIL).await;
+    let mut user = app.repositories.users.find_by_id(ObjectId::parse_str(&user_id).unwrap()).unwrap();
+    user.id = None;
+    user.email = "legacy@example.com".to_string();
+    user.password = "not a bcrypt hash".to_string();
+    app.repositories.users.insert(user).await.unwrap();
+
+    let (status, body) = call(&service, auth_request("/api/login", "legacy@example.com", "not a bcrypt hash")).await;
+    assert_eq!(status, 401);
+    assert_eq!(error_message(&body, "invalid_credentials"), "Invalid email or password");
+}
+
+#[actix_web::test]
+async fn login_for_unknown_user_is_rejected() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+
+    let (status, body) = call(&service, auth_request("/api/login", "nobody@example.com", PASSWORD)).await;
+    assert_eq!(status, 404);
+    assert_eq!(error_message(&body, "user_not_found"), "User not found");
+}
+
+#[actix_web::test]
+async fn malformed_auth_payload_is_rejected() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+
+    let request = TestRequest::post().uri("/api/login").set_json(json!({ "email": EMAIL })).to_request();
+    let (status, body) = call(&service, request).await;
+    assert_eq!(status, 400);
+    assert!(error_message(&body, "malformed_request").contains("password"), "{}", body);
+}
+
+// Stock listing
+
+#[actix_web::test]
+async fn stock_list_pages_through_a_sector() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+
+    let mut seen = Vec::new();
+    for (page, expected) in [(0, 3), (1, 3), (2, 1), (3, 0)] {
+        let body = json!({ "user_id": "u1", "sector": SECTOR, "industry": SOFTWARE, "page": page, "items_per_page": 3 });
+        let (status, body) = call(&service, listing_request(body)).await;
+        assert_eq!(status, 200, "{}", body);
+        let tickers = listed_tickers(&body);
+        assert_eq!(tickers.len(), expected, "page {}: {:?}", page, tickers);
+        seen.extend(tickers);
+    }
+    assert_eq!(seen, SOFTWARE_TICKERS);
+}
+
+#[actix_web::test]
+async fn stock_list_returns_names_and_one_month_prices() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+
+    let body = json!({ "user_id": "u1", "sector": SECTOR, "industry": SOFTWARE, "items_per_page": 1 });
+    let (status, body) = call(&service, listing_request(body)).await;
+    assert_eq!(status, 200, "{}", body);
+    let stock = &body["documents"][0];
+    assert_eq!(stock["ticker"], "MSFT");
+    assert_eq!(stock["name"], "MSFT Inc.");
+    let prices: Vec<f64> = stock["price_data"].as_array().unwrap().iter().map(|point| point["price"].as_f64().unwrap()).collect();
+    assert_eq!(prices, [100.0, 101.0, 102.0]);
+    assert_eq!(stock["price_data"][1]["date"], "2023-11-15T22:13:20+00:00");
+}
+
+#[actix_web::test]
+async fn stock_list_skips_companies_without_servable_prices() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+
+    let body = json!({ "user_id": "u1", "sector": SECTOR, "industry": HARDWARE, "items_per_page": 10 });
+    let (status, body) = call(&service, listing_request(body)).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(listed_tickers(&body), ["AAPL"]);
+}
+
+#[actix_web::test]
+async fn stock_list_without_industry_covers_the_whole_sector() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+
+    let body = json!({ "user_id": "u1", "sector": SECTOR, "items_per_page": 100 });
+    let (status, body) = call(&service, listing_request(body)).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(listed_tickers(&body).len(), SOFTWARE_TICKERS.len() + 1);
+}
+
+#[actix_web::test]
+async fn stock_list_requires_a_user_id() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+
+    let (status, body) = call(&service, listing_request(json!({ "sector": SECTOR }))).await;
+    assert_eq!(status, 422);
+    assert_eq!(field_errors(&body), [("user_id", "required")]);
+}
+
+#[actix_web::test]
+async fn stock_list_for_unknown_sector_is_empty() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+
+    let (status, body) = call(&service, listing_request(json!({ "user_id": "u1", "sector": "Utilities" }))).await;
+    assert_eq!(status, 200, "{}", body);
+    assert!(listed_tickers(&body).is_empty());
+}
+
+// Stock details
+
+#[actix_web::test]
+async fn stock_details_returns_every_series_by_default() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+
+    let request = TestRequest::post().uri("/api/stock-details/AAPL").to_request();
+    let (status, body) = call(&service, request).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["ticker"], "AAPL");
+    assert_eq!(body["profile"]["industry"], HARDWARE);
+    assert_eq!(body["key_metrics"]["market_cap"], 2.9e12);
+    assert_eq!(body["key_metrics"]["trailing_pe"], 30.5);
+    let periods: Vec<&str> = body["price_data"].as_array().unwrap().iter().map(|series| series["period"].as_str().unwrap()).collect();
+    assert_eq!(periods, ["1mo", "5d"]);
+}
+
+#[actix_web::test]
+async fn stock_details_summary_has_profile_metrics_and_quote() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+
+    let request = TestRequest::post().uri("/api/stock-details/AAPL?detail_level=summary").to_request();
+    let (status, body) = call(&service, request).await;
+    assert_eq!(status, 200, "{}", body);
+    let fields: Vec<&String> = body.as_object().unwrap().keys().collect();
+    assert_eq!(fields, ["ticker", "profile", "key_metrics", "quote"]);
+
+    let last_close = *AAPL_CLOSES.last().unwrap();
+    let quote = &body["quote"];
+    assert_eq!(quote["price"], last_close);
+    assert_eq!(quote["previous_close"], AAPL_PREVIOUS_CLOSE);
+    assert_eq!(quote["change"], last_close - AAPL_PREVIOUS_CLOSE);
+    assert_eq!(quote["volume"], 1_000_000);
+    assert_eq!(quote["interval"], "1d");
+}
+
+#[actix_web::test]
+async fn stock_details_adjusts_prices_for_splits() {
+    let app = TestApp::with_fixtures();
+    app.repositories.prices.insert_split("AAPL", FIRST_TIMESTAMP + 2 * DAY, 2.0);
+    let service = app.service().await;
+
+    let closes = |body: &Value| -> Vec<f64> {
+        body["price_data"][0]["closes"].as_array().unwrap().iter().map(|close| close.as_f64().unwrap()).collect()
+    };
+
+    // The original endpoint keeps serving raw prices unless asked.
+    let request = TestRequest::post().uri("/api/stock-details/AAPL?detail_level=standard&fields=price_data").to_request();
+    let (status, body) = call(&service, request).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(closes(&body), AAPL_CLOSES);
+
+    let request = TestRequest::post().uri("/api/stock-details/AAPL?detail_level=standard&fields=price_data&adjusted=true").to_request();
+    let (status, body) = call(&service, request).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(closes(&body), [90.0, 91.0, 181.0, 184.0, 186.0]);
+
+    // v2 adjusts by default.
+    let request = TestRequest::get().uri("/api/v2/stocks/AAPL?detail_level=standard&fields=price_data").to_request();
+    let (status, body) = call(&service, request).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(closes(&body), [90.0, 91.0, 181.0, 184.0, 186.0]);
+
+    let request = TestRequest::get().uri("/api/v2/stocks/AAPL?detail_level=standard&fields=price_data&adjusted=false").to_request();
+    let (status, body) = call(&service, request).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(closes(&body), AAPL_CLOSES);
+}
+
+#[actix_web::test]
+async fn stock_details_for_unknown_ticker_is_empty() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+
+    let request = TestRequest::post().uri("/api/stock-details/ZZZZ?detail_level=summary").to_request();
+    let (status, body) = call(&service, request).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["ticker"], "ZZZZ");
+    assert!(body.get("profile").is_none());
+    assert!(body.get("quote").is_none());
+}
+
+#[actix_web::test]
+async fn stock_details_keeps_the_original_sections_as_null_by_default() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+
+    let request = TestRequest::post().uri("/api/stock-details/ZZZZ").to_request();
+    let (status, body) = call(&service, request).await;
+    assert_eq!(status, 200, "{}", body);
+    for section in ["financials", "key_statistics", "profile"] {
+        assert_eq!(body.get(section), Some(&Value::Null), "{}: {}", section, body);
+    }
+    assert_eq!(body["price_data"], json!([]));
+    // Sections added later are omitted when missing.
+    assert!(body.get("quote").is_none());
+
+    // Selecting fields explicitly returns only those.
+    let request = TestRequest::post().uri("/api/stock-details/ZZZZ?fields=profile").to_request();
+    let (status, body) = call(&service, request).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body.as_object().unwrap().keys().collect::<Vec<_>>(), ["ticker"]);
+}
+
+#[actix_web::test]
+async fn stock_details_rejects_unknown_detail_level_and_fields() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+
+    let request = TestRequest::post().uri("/api/stock-details/AAPL?detail_level=everything").to_request();
+    let (status, body) = call(&service, request).await;
+    assert_eq!(status, 400);
+    assert_eq!(error_message(&body, "bad_request"), "Unknown detail_level: everything");
+
+    let request = TestRequest::post().uri("/api/stock-details/AAPL?fields=profile,insider_trades").to_request();
+    let (status, body) = call(&service, request).await;
+    assert_eq!(status, 400);
+    assert_eq!(error_message(&body, "bad_request"), "Unknown field: insider_trades");
+}
+
+// Trades
+
+#[actix_web::test]
+async fn submitted_trade_is_stored_and_debited() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+    let user_id = registered_user(&service, EMAIL).await;
+
+    let (status, body) = call(&service, trade_request(&user_id, 930.0)).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["message"], "Trade submitted successfully");
+    let trade_id = ObjectId::parse_str(body["trade_id"].as_str().unwrap()).unwrap();
+
+    let user = app.repositories.users.find_by_id(ObjectId::parse_str(&user_id).unwrap()).unwrap();
+    assert_eq!(user.balance, app.config.trading.starting_balance - 930.0);
+    assert_eq!(user.trades, [trade_id]);
+
+    let stored = app.repositories.trades.all();
+    assert_eq!(stored.len(), 1);
+    assert_eq!(stored[0].id, Some(trade_id));
+    assert_eq!(stored[0].status, TradeStatus::InProgress);
+    assert_eq!(stored[0].take_profit, Some(200.0));
+}
+
+#[actix_web::test]
+async fn user_trades_returns_only_that_users_trades() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+    let user_id = registered_user(&service, EMAIL).await;
+    let other_id = registered_user(&service, "other@example.com").await;
+
+    for amount in [100.0, 200.0] {
+        let (status, body) = call(&service, trade_request(&user_id, amount)).await;
+        assert_eq!(status, 200, "{}", body);
+    }
+    let (status, body) = call(&service, trade_request(&other_id, 300.0)).await;
+    assert_eq!(status, 200, "{}", body);
+
+    let request = TestRequest::get().uri(&format!("/api/user_trades?user_id={}", user_id)).to_request();
+    let (status, body) = call(&service, request).await;
+    assert_eq!(status, 200, "{}", body);
+    let trades: Vec<Trade> = from_value(body);
+    let amounts: Vec<f64> = trades.iter().map(|trade| trade.amount).collect();
+    assert_eq!(amounts, [100.0, 200.0]);
+    assert!(trades.iter().all(|trade| trade.user_id.to_hex() == user_id));
+}
+
+#[actix_web::test]
+async fn user_trades_is_empty_for_a_new_user() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+    let user_id = registered_user(&service, EMAIL).await;
+
+    let request = TestRequest::get().uri(&format!("/api/user_trades?user_id={}", user_id)).to_request();
+    let (status, body) = call(&service, request).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body, json!([]));
+}
+
+#[actix_web::test]
+async fn trade_with_invalid_user_id_is_rejected() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+
+    let (status, body) = call(&service, trade_request("not-an-object-id", 100.0)).await;
+    assert_eq!(status, 422);
+    assert_eq!(field_errors(&body), [("user_id", "object_id")]);
+    assert!(app.repositories.trades.all().is_empty());
+}
+
+#[actix_web::test]
+async fn malformed_trade_payload_is_rejected() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+
+    let request = TestRequest::post()
+        .uri("/api/trade_submit")
+        .set_json(json!({ "ticker": "AAPL", "quantity": -1 }))
+        .to_request();
+    let (status, body) = call(&service, request).await;
+    assert_eq!(status, 400);
+    error_message(&body, "malformed_request");
+    assert!(app.repositories.trades.all().is_empty());
+}
+
+#[actix_web::test]
+async fn user_trades_rejects_invalid_or_missing_user_id() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+
+    let request = TestRequest::get().uri("/api/user_trades?user_id=not-an-object-id").to_request();
+    let (status, body) = call(&service, request).await;
+    assert_eq!(status, 422);
+    assert_eq!(field_errors(&body), [("user_id", "object_id")]);
+
+    let request = TestRequest::get().uri("/api/user_trades").to_request();
+    let (status, body) = call(&service, request).await;
+    assert_eq!(status, 400);
+    error_message(&body, "malformed_request");
+}
+
+// Payload validation
+
+#[actix_web::test]
+async fn registration_rejects_invalid_email_and_short_password() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+
+    let (status, body) = call(&service, auth_request("/api/register", "not-an-email", "short")).await;
+    assert_eq!(status, 422);
+    assert_eq!(field_errors(&body), [("email", "email"), ("password", "length")]);
+    assert_eq!(body["error"]["fields"][1]["message"], "must be between 8 and 72 characters");
+    assert!(app.repositories.users.find_by_email("not-an-email").await.unwrap().is_none());
+}
+
+#[actix_web::test]
+async fn login_rejects_empty_password() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+
+    let (status, body) = call(&service, auth_request("/api/login", EMAIL, "")).await;
+    assert_eq!(status, 422);
+    assert_eq!(field_errors(&body), [("password", "length")]);
+}
+
+#[actix_web::test]
+async fn trade_rejects_every_invalid_field_before_storing_anything() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+    let user_id = registered_user(&service, EMAIL).await;
+
+    let request = TestRequest::post()
+        .uri("/api/trade_submit")
+        .set_json(json!({
+            "ticker": "",
+            "position": "sideways",
+            "quantity": 0,
+            "price": -186.0,
+            "take_profit": 0.0,
+            "stop_loss": null,
+            "user_id": user_id,
+            "amount": 930.0,
+            "trade_type": "market",
+            "user_balance": 10000.0,
+        }))
+        .to_request();
+    let (status, body) = call(&service, request).await;
+    assert_eq!(status, 422);
+    assert_eq!(field_errors(&body), [
+        ("position", "position"),
+        ("price", "range"),
+        ("quantity", "range"),
+        ("take_profit", "range"),
+        ("ticker", "length"),
+    ]);
+    assert!(app.repositories.trades.all().is_empty());
+    let user = app.repositories.users.find_by_id(ObjectId::parse_str(&user_id).unwrap()).unwrap();
+    assert_eq!(user.balance, app.config.trading.starting_balance);
+}
+
+#[actix_web::test]
+async fn stock_list_bounds_items_per_page() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+
+    for items_per_page in [0, 101] {
+        let body = json!({ "user_id": "u1", "sector": SECTOR, "items_per_page": items_per_page });
+        let (status, body) = call(&service, listing_request(body)).await;
+        assert_eq!(status, 422, "items_per_page {}", items_per_page);
+        assert_eq!(field_errors(&body), [("items_per_page", "range")]);
+    }
+
+    let body = json!({ "user_id": "u1", "sector": SECTOR, "items_per_page": 100 });
+    let (status, _) = call(&service, listing_request(body)).await;
+    assert_eq!(status, 200);
+}
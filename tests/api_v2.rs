@@ -0,0 +1,139 @@
+//! End-to-end tests of the `/api/v2` routes against the in-memory backend.
+
+mod common;
+
+use actix_web::test::TestRequest;
+use mongodb::bson::oid::ObjectId;
+use serde_json::{json, Value};
+use trading_sim_server::models::order_models::Order;
+use trading_sim_server::models::trade_models::TradeStatus;
+use common::{call, error_message, field_errors, from_value, registered_user, TestApp};
+
+fn order_request(user_id: &str, amount: f64) -> actix_http::Request {
+    TestRequest::post()
+        .uri("/api/v2/orders")
+        .set_json(json!({
+            "ticker": "AAPL",
+            "position": "long",
+            "quantity": 5,
+            "price": 186.0,
+            "take_profit": 200.0,
+            "user_id": user_id,
+            "amount": amount,
+            "trade_type": "market",
+        }))
+        .to_request()
+}
+
+fn orders_request(user_id: &str) -> actix_http::Request {
+    TestRequest::get().uri(&format!("/api/v2/orders?user_id={}", user_id)).to_request()
+}
+
+// Stocks
+
+#[actix_web::test]
+async fn get_stock_matches_the_v1_details() {
+    let service = TestApp::with_fixtures().service().await;
+
+    let (status, v2) = call(&service, TestRequest::get().uri("/api/v2/stocks/AAPL?detail_level=summary").to_request()).await;
+    assert_eq!(status, 200, "{}", v2);
+    let (status, v1) = call(&service, TestRequest::post().uri("/api/stock-details/AAPL?detail_level=summary").to_request()).await;
+    assert_eq!(status, 200, "{}", v1);
+    assert_eq!(v2, v1);
+    assert_eq!(v2["ticker"], "AAPL");
+    assert!(v2["quote"].is_object());
+}
+
+#[actix_web::test]
+async fn get_stock_for_unknown_ticker_is_not_found() {
+    let service = TestApp::with_fixtures().service().await;
+
+    let (status, body) = call(&service, TestRequest::get().uri("/api/v2/stocks/ZZZZ").to_request()).await;
+    assert_eq!(status, 404);
+    assert_eq!(error_message(&body, "not_found"), "Unknown ticker: ZZZZ");
+}
+
+#[actix_web::test]
+async fn get_stock_rejects_unknown_fields() {
+    let service = TestApp::with_fixtures().service().await;
+
+    let (status, body) = call(&service, TestRequest::get().uri("/api/v2/stocks/AAPL?fields=insider_trades").to_request()).await;
+    assert_eq!(status, 400);
+    assert_eq!(error_message(&body, "bad_request"), "Unknown field: insider_trades");
+}
+
+// Orders
+
+#[actix_web::test]
+async fn created_order_is_returned_stored_and_debited() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+    let user_id = registered_user(&service, "trader@example.com").await;
+
+    let (status, body) = call(&service, order_request(&user_id, 930.0)).await;
+    assert_eq!(status, 201, "{}", body);
+    let order: Order = from_value(body);
+    assert_eq!(order.user_id, user_id);
+    assert_eq!(order.status, TradeStatus::InProgress);
+    assert_eq!(order.take_profit, Some(200.0));
+
+    let stored = app.repositories.trades.all();
+    assert_eq!(stored.len(), 1);
+    assert_eq!(stored[0].id.map(|id| id.to_hex()), Some(order.id));
+    let user = app.repositories.users.find_by_id(ObjectId::parse_str(&user_id).unwrap()).unwrap();
+    assert_eq!(user.balance, app.config.trading.starting_balance - 930.0);
+}
+
+#[actix_web::test]
+async fn invalid_order_is_rejected_before_storing_anything() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+    let user_id = registered_user(&service, "trader@example.com").await;
+
+    let request = TestRequest::post()
+        .uri("/api/v2/orders")
+        .set_json(json!({
+            "ticker": "AAPL",
+            "position": "sideways",
+            "quantity": 0,
+            "price": 186.0,
+            "user_id": user_id,
+            "amount": 930.0,
+            "trade_type": "market",
+        }))
+        .to_request();
+    let (status, body) = call(&service, request).await;
+    assert_eq!(status, 422);
+    assert_eq!(field_errors(&body), [("position", "position"), ("quantity", "range")]);
+    assert!(app.repositories.trades.all().is_empty());
+}
+
+#[actix_web::test]
+async fn list_orders_returns_only_that_users_orders_with_hex_ids() {
+    let service = TestApp::with_fixtures().service().await;
+    let user_id = registered_user(&service, "trader@example.com").await;
+    let other_id = registered_user(&service, "other@example.com").await;
+
+    for (user, amount) in [(&user_id, 100.0), (&user_id, 200.0), (&other_id, 300.0)] {
+        let (status, body) = call(&service, order_request(user, amount)).await;
+        assert_eq!(status, 201, "{}", body);
+    }
+
+    let (status, body) = call(&service, orders_request(&user_id)).await;
+    assert_eq!(status, 200, "{}", body);
+    let orders = body.as_array().expect("orders array");
+    assert_eq!(orders.len(), 2);
+    for order in orders {
+        assert!(order["id"].is_string(), "{}", order);
+        assert_eq!(order["user_id"], Value::String(user_id.clone()));
+    }
+}
+
+#[actix_web::test]
+async fn list_orders_rejects_invalid_user_id() {
+    let service = TestApp::with_fixtures().service().await;
+
+    let (status, body) = call(&service, orders_request("not-an-id")).await;
+    assert_eq!(status, 422);
+    assert_eq!(field_errors(&body), [("user_id", "object_id")]);
+}
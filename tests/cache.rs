@@ -0,0 +1,99 @@
+//! Size bounds of the market data caches, and what listings put in them.
+
+mod common;
+
+use std::time::Duration;
+use actix_web::test::TestRequest;
+use serde_json::json;
+use trading_sim_server::models::cache_models::CacheUnit;
+use trading_sim_server::services::cache_service::TtlCache;
+use common::{call, TestApp, HARDWARE, SECTOR};
+
+fn bytes_cache(capacity: usize) -> TtlCache<&'static str, String> {
+    TtlCache::bounded_by_bytes(capacity, Duration::from_secs(60), |value: &String| value.len())
+}
+
+#[test]
+fn byte_bounded_caches_evict_the_least_recently_used_entries() {
+    let cache = bytes_cache(10);
+    cache.insert("a", "aaaa".to_string());
+    cache.insert("b", "bbbb".to_string());
+    assert!(cache.get(&"a").is_some());
+
+    cache.insert("c", "cccc".to_string());
+    assert!(cache.get(&"b").is_none());
+    assert!(cache.get(&"a").is_some());
+    assert!(cache.get(&"c").is_some());
+
+    let stats = cache.stats();
+    assert_eq!((stats.entries, stats.size, stats.capacity, stats.unit), (2, 8, 10, CacheUnit::Bytes));
+    assert_eq!(stats.evictions, 1);
+}
+
+#[test]
+fn values_larger_than_a_byte_bounded_cache_are_not_stored() {
+    let cache = bytes_cache(10);
+    cache.insert("a", "aaaa".to_string());
+    cache.insert("big", "x".repeat(11));
+    assert!(cache.get(&"big").is_none());
+    assert!(cache.get(&"a").is_some());
+    assert_eq!(cache.stats().evictions, 0);
+}
+
+#[test]
+fn replacing_an_entry_replaces_its_size() {
+    let cache = bytes_cache(10);
+    cache.insert("a", "aaaa".to_string());
+    cache.insert("a", "aaaaaaaa".to_string());
+    cache.insert("b", "bb".to_string());
+    let stats = cache.stats();
+    assert_eq!((stats.entries, stats.size, stats.evictions), (2, 10, 0));
+
+    cache.invalidate(&"a");
+    assert_eq!(cache.stats().size, 2);
+}
+
+#[test]
+fn entry_bounded_caches_count_entries() {
+    let cache: TtlCache<&str, String> = TtlCache::new(2, Duration::from_secs(60));
+    for key in ["a", "b", "c"] {
+        cache.insert(key, "a long value".to_string());
+    }
+    let stats = cache.stats();
+    assert_eq!((stats.entries, stats.size, stats.capacity, stats.unit), (2, 2, 2, CacheUnit::Entries));
+}
+
+#[actix_web::test]
+async fn listings_cache_only_the_series_they_show() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+
+    // AAPL also stores a 5d/1h series, which a listing has no use for.
+    let body = json!({ "user_id": "u1", "sector": SECTOR, "industry": HARDWARE, "items_per_page": 10 });
+    let request = TestRequest::post().uri("/api/stock-list").set_json(body).to_request();
+    let (status, body) = call(&service, request).await;
+    assert_eq!(status, 200, "{}", body);
+
+    let (status, stats) = call(&service, TestRequest::get().uri("/api/cache/stats").to_request()).await;
+    assert_eq!(status, 200, "{}", stats);
+    assert_eq!(stats["price_series"]["entries"], 0);
+    // AAPL, HPQ and DELL; DELL has no series and is cached as missing.
+    assert_eq!(stats["series"]["entries"], 3);
+    assert_eq!(stats["series"]["unit"], "bytes");
+    assert!(stats["series"]["size"].as_u64().unwrap() > 0, "{}", stats);
+}
+
+#[actix_web::test]
+async fn screens_on_returns_fetch_only_the_monthly_series() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+
+    let body = json!({ "industry": HARDWARE, "sort_by": "one_month_return" });
+    let request = TestRequest::post().uri("/api/screener").set_json(body).to_request();
+    let (status, body) = call(&service, request).await;
+    assert_eq!(status, 200, "{}", body);
+
+    let (_, stats) = call(&service, TestRequest::get().uri("/api/cache/stats").to_request()).await;
+    assert_eq!(stats["price_series"]["entries"], 0);
+    assert_eq!(stats["series"]["entries"], 3);
+}
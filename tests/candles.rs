@@ -0,0 +1,233 @@
+//! Interval parsing, bucket alignment and resampling behind the candles
+//! endpoint, and the choice of the stored series it resamples.
+
+mod common;
+
+use actix_web::test::TestRequest;
+use chrono::{DateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use trading_sim_server::models::stock_models::PriceDataDetails;
+use trading_sim_server::services::candle_service::{resample, CandleInterval};
+use common::{call, error_message, series, TestApp, DAY, FIRST_TIMESTAMP};
+
+fn utc(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(year, month, day, hour, minute, 0).unwrap()
+}
+
+#[test]
+fn intervals_parse_our_names_and_yahoo_names() {
+    assert_eq!(CandleInterval::parse("5m"), Some(CandleInterval::Minutes(5)));
+    assert_eq!(CandleInterval::parse("15min"), Some(CandleInterval::Minutes(15)));
+    assert_eq!(CandleInterval::parse("60m"), Some(CandleInterval::Minutes(60)));
+    assert_eq!(CandleInterval::parse("4h"), Some(CandleInterval::Hours(4)));
+    assert_eq!(CandleInterval::parse("1d"), Some(CandleInterval::Days(1)));
+    assert_eq!(CandleInterval::parse("1w"), Some(CandleInterval::Weeks(1)));
+    assert_eq!(CandleInterval::parse(" 1WK "), Some(CandleInterval::Weeks(1)));
+    assert_eq!(CandleInterval::parse("3mo"), Some(CandleInterval::Months(3)));
+}
+
+#[test]
+fn malformed_intervals_are_rejected() {
+    for value in ["", "m", "0d", "1y", "1.5h", "d1", "-1d", "5"] {
+        assert_eq!(CandleInterval::parse(value), None, "{:?}", value);
+    }
+}
+
+#[test]
+fn intervals_divide_whole_buckets_only() {
+    use CandleInterval::*;
+    assert!(Minutes(5).divides(Minutes(15)));
+    assert!(Minutes(30).divides(Hours(1)));
+    assert!(Hours(1).divides(Days(1)));
+    assert!(Days(1).divides(Weeks(1)));
+    assert!(Days(1).divides(Months(3)));
+    assert!(Months(1).divides(Months(3)));
+
+    assert!(!Minutes(15).divides(Minutes(20)));
+    assert!(!Minutes(7).divides(Days(1)));
+    assert!(!Days(2).divides(Weeks(1)));
+    assert!(!Weeks(1).divides(Months(1)));
+    assert!(!Days(1).divides(Hours(4)));
+}
+
+#[test]
+fn buckets_align_on_wall_clock_boundaries() {
+    let start = |interval: CandleInterval, at: DateTime<Utc>| interval.bucket_start(at, Tz::UTC).with_timezone(&Utc);
+    assert_eq!(start(CandleInterval::Minutes(15), utc(2024, 3, 7, 10, 37)), utc(2024, 3, 7, 10, 30));
+    assert_eq!(start(CandleInterval::Hours(4), utc(2024, 3, 7, 10, 37)), utc(2024, 3, 7, 8, 0));
+    assert_eq!(start(CandleInterval::Days(1), utc(2024, 3, 7, 10, 37)), utc(2024, 3, 7, 0, 0));
+    // 2024-03-07 is a Thursday.
+    assert_eq!(start(CandleInterval::Weeks(1), utc(2024, 3, 7, 10, 37)), utc(2024, 3, 4, 0, 0));
+    assert_eq!(start(CandleInterval::Months(1), utc(2024, 3, 7, 10, 37)), utc(2024, 3, 1, 0, 0));
+    assert_eq!(start(CandleInterval::Months(3), utc(2024, 5, 15, 10, 37)), utc(2024, 4, 1, 0, 0));
+}
+
+#[test]
+fn multiples_must_divide_a_day_or_a_year() {
+    use CandleInterval::*;
+    for interval in [Minutes(45), Minutes(90), Hours(8), Hours(24), Days(3), Weeks(2), Months(4), Months(12)] {
+        assert_eq!(interval.check_range(), Ok(()), "{:?}", interval);
+    }
+    for interval in [Minutes(7), Minutes(2_880), Hours(5), Hours(48)] {
+        assert_eq!(interval.check_range(), Err("sub-day intervals must divide a day evenly".to_string()), "{:?}", interval);
+    }
+    for interval in [Months(5), Months(24)] {
+        assert_eq!(interval.check_range(), Err("monthly intervals must divide a year evenly".to_string()), "{:?}", interval);
+    }
+}
+
+#[test]
+fn buckets_align_in_the_requested_time_zone() {
+    let new_york: Tz = "America/New_York".parse().unwrap();
+    // 03:00 UTC is still the previous evening in New York.
+    let start = CandleInterval::Days(1).bucket_start(utc(2024, 1, 10, 3, 0), new_york);
+    assert_eq!(start.with_timezone(&Utc), utc(2024, 1, 9, 5, 0));
+}
+
+#[test]
+fn buckets_starting_in_a_dst_gap_start_when_the_gap_ends() {
+    // New York skips 02:00-03:00 on 2024-03-10. The two-hour bucket holding
+    // 03:30 EDT would start at 02:00, so it starts at 03:00 EDT instead.
+    let new_york: Tz = "America/New_York".parse().unwrap();
+    let start = CandleInterval::Hours(2).bucket_start(utc(2024, 3, 10, 7, 30), new_york);
+    assert_eq!(start.with_timezone(&Utc), utc(2024, 3, 10, 7, 0));
+
+    // São Paulo skipped midnight on 2018-11-04, so that day began at 01:00.
+    let sao_paulo: Tz = "America/Sao_Paulo".parse().unwrap();
+    let start = CandleInterval::Days(1).bucket_start(utc(2018, 11, 4, 15, 0), sao_paulo);
+    assert_eq!(start.with_timezone(&Utc), utc(2018, 11, 4, 3, 0));
+}
+
+#[test]
+fn the_repeated_hour_of_a_dst_fall_back_is_two_buckets() {
+    // New York repeats 01:00-02:00 on 2024-11-03: first as EDT (05:00 UTC),
+    // then as EST (06:00 UTC).
+    let new_york: Tz = "America/New_York".parse().unwrap();
+    let start = |at: DateTime<Utc>| CandleInterval::Hours(1).bucket_start(at, new_york).with_timezone(&Utc);
+    assert_eq!(start(utc(2024, 11, 3, 5, 30)), utc(2024, 11, 3, 5, 0));
+    assert_eq!(start(utc(2024, 11, 3, 6, 30)), utc(2024, 11, 3, 6, 0));
+
+    let series = hourly(&[
+        (utc(2024, 11, 3, 4, 30), 10.0),
+        (utc(2024, 11, 3, 5, 30), 11.0),
+        (utc(2024, 11, 3, 6, 30), 12.0),
+        (utc(2024, 11, 3, 7, 30), 13.0),
+    ]);
+    let candles = resample(&series, CandleInterval::Hours(1), new_york);
+    let starts: Vec<String> = candles.iter().map(|candle| candle.start.to_rfc3339()).collect();
+    assert_eq!(starts, ["2024-11-03T00:00:00-04:00", "2024-11-03T01:00:00-04:00", "2024-11-03T01:00:00-05:00", "2024-11-03T02:00:00-05:00"]);
+    assert_eq!(candles.iter().map(|candle| candle.close).collect::<Vec<_>>(), [10.0, 11.0, 12.0, 13.0]);
+
+    // The day itself is one 25-hour bucket.
+    let candles = resample(&series, CandleInterval::Days(1), new_york);
+    assert_eq!(candles.len(), 1);
+    assert_eq!(candles[0].start.to_rfc3339(), "2024-11-03T00:00:00-04:00");
+}
+
+fn hourly(points: &[(DateTime<Utc>, f64)]) -> PriceDataDetails {
+    PriceDataDetails {
+        period: "5d".to_string(),
+        interval: "1h".to_string(),
+        closes: Some(points.iter().map(|(_, close)| *close).collect()),
+        highs: Some(points.iter().map(|(_, close)| close + 1.0).collect()),
+        lows: None,
+        opens: None,
+        timestamps: Some(points.iter().map(|(timestamp, _)| *timestamp).collect()),
+        volumes: Some(vec![10; points.len()]),
+    }
+}
+
+#[test]
+fn resampling_aggregates_each_bucket() {
+    let series = hourly(&[
+        (utc(2024, 3, 7, 14, 0), 10.0),
+        (utc(2024, 3, 7, 15, 0), 12.0),
+        (utc(2024, 3, 7, 16, 0), 9.0),
+        (utc(2024, 3, 8, 14, 0), 11.0),
+    ]);
+    let candles = resample(&series, CandleInterval::Days(1), Tz::UTC);
+
+    assert_eq!(candles.len(), 2);
+    assert_eq!(candles[0].start, utc(2024, 3, 7, 0, 0).fixed_offset());
+    // Opens and lows fall back to the close of their point.
+    assert_eq!((candles[0].open, candles[0].high, candles[0].low, candles[0].close, candles[0].volume), (10.0, 13.0, 9.0, 9.0, 30));
+    assert_eq!((candles[1].open, candles[1].close, candles[1].volume), (11.0, 11.0, 10));
+}
+
+#[test]
+fn resampling_orders_points_by_time() {
+    let series = hourly(&[
+        (utc(2024, 3, 8, 14, 0), 11.0),
+        (utc(2024, 3, 7, 16, 0), 9.0),
+        (utc(2024, 3, 7, 14, 0), 10.0),
+        (utc(2024, 3, 7, 15, 0), 12.0),
+    ]);
+    let candles = resample(&series, CandleInterval::Days(1), Tz::UTC);
+
+    assert_eq!(candles.len(), 2);
+    assert_eq!((candles[0].open, candles[0].close), (10.0, 9.0));
+    assert_eq!(candles[1].start, utc(2024, 3, 8, 0, 0).fixed_offset());
+}
+
+#[test]
+fn resampling_without_timestamps_is_empty() {
+    let mut series = hourly(&[(utc(2024, 3, 7, 14, 0), 10.0)]);
+    series.timestamps = None;
+    assert!(resample(&series, CandleInterval::Days(1), Tz::UTC).is_empty());
+}
+
+fn candles(ticker: &str, interval: &str) -> actix_http::Request {
+    TestRequest::get().uri(&format!("/api/candles/{}?interval={}", ticker, interval)).to_request()
+}
+
+#[actix_web::test]
+async fn the_finest_dividing_series_is_resampled() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+
+    // AAPL stores 1mo/1d and 5d/1h from the same first day; the hourly bars
+    // carry the most detail into daily and weekly candles.
+    for interval in ["1d", "1w", "2h"] {
+        let (status, body) = call(&service, candles("AAPL", interval)).await;
+        assert_eq!(status, 200, "{}", body);
+        assert_eq!(body["source_interval"], "1h", "{}", interval);
+    }
+
+    // Only daily bars build monthly candles over the whole month.
+    let (status, body) = call(&service, candles("MSFT", "1mo")).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["source_interval"], "1d");
+}
+
+#[actix_web::test]
+async fn a_finer_series_reaching_further_back_wins() {
+    let app = TestApp::with_fixtures();
+    let mut hourly = series("3mo", "1h", &[100.0, 101.0, 102.0, 103.0]);
+    let earlier: Vec<i64> = (0..4).map(|hour| FIRST_TIMESTAMP - 30 * DAY + hour * 3_600).collect();
+    hourly.insert("timestamps", earlier);
+    app.repositories.prices.insert_series("MSFT", hourly);
+    let service = app.service().await;
+
+    let (status, body) = call(&service, candles("MSFT", "1d")).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["source_interval"], "1h");
+}
+
+#[actix_web::test]
+async fn intervals_without_a_dividing_series_are_rejected() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+
+    let (status, body) = call(&service, candles("AAPL", "30m")).await;
+    assert_eq!(status, 400, "{}", body);
+    assert_eq!(error_message(&body, "bad_request"), "Interval 30m cannot be built from the stored intervals 1d, 1h");
+
+    let (status, body) = call(&service, candles("AAPL", "7m")).await;
+    assert_eq!(status, 400, "{}", body);
+    assert_eq!(error_message(&body, "bad_request"), "Unsupported interval 7m: sub-day intervals must divide a day evenly");
+
+    // Without the range check, 24mo would quietly serve yearly candles.
+    let (status, body) = call(&service, candles("AAPL", "24mo")).await;
+    assert_eq!(status, 400, "{}", body);
+    assert_eq!(error_message(&body, "bad_request"), "Unsupported interval 24mo: monthly intervals must divide a year evenly");
+}
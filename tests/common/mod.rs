@@ -0,0 +1,206 @@
+//! Shared harness for the HTTP integration tests: the real `/api` routes
+//! over in-memory repositories seeded with fixture companies and prices.
+
+#![allow(dead_code)]
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::test::TestRequest;
+use actix_web::{test, web, App, Error};
+use actix_http::Request;
+use mongodb::bson::{doc, Document};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use trading_sim_server::auth::SessionTokens;
+use trading_sim_server::config::AppConfig;
+use mongodb::bson::oid::ObjectId;
+use trading_sim_server::models::users::{AuthResponse, User, UserNotifications, UserProfile, UserSettings};
+use trading_sim_server::repositories::memory::InMemoryRepositories;
+use trading_sim_server::routes::configure_routes;
+use trading_sim_server::services::activity_service::ActivityFeed;
+use trading_sim_server::services::cache_service::MarketDataCache;
+use trading_sim_server::services::health_service::HealthMonitor;
+
+pub const SECTOR: &str = "Technology";
+pub const SOFTWARE: &str = "Software";
+pub const HARDWARE: &str = "Hardware";
+
+/// Software companies in listing order; every one has a 1mo/1d series.
+pub const SOFTWARE_TICKERS: &[&str] = &["MSFT", "ORCL", "ADBE", "CRM", "INTU", "NOW", "SNPS"];
+/// Hardware companies in listing order. `DELL` has no price data and `HPQ`
+/// has a malformed series, so neither appears in listings.
+pub const HARDWARE_TICKERS: &[&str] = &["AAPL", "HPQ", "DELL"];
+
+/// First timestamp of every fixture series, one day apart.
+pub const FIRST_TIMESTAMP: i64 = 1_700_000_000;
+pub const DAY: i64 = 86_400;
+
+/// Daily closes of the AAPL 1mo/1d series.
+pub const AAPL_CLOSES: &[f64] = &[180.0, 182.0, 181.0, 184.0, 186.0];
+/// Previous close reported in the AAPL company document.
+pub const AAPL_PREVIOUS_CLOSE: f64 = 184.0;
+
+/// Password of every account created with [`registered_user`].
+pub const PASSWORD: &str = "correct horse battery staple";
+
+pub struct TestApp {
+    pub repositories: InMemoryRepositories,
+    pub config: AppConfig,
+    // Shared by every service built from this app, like the repositories.
+    pub health: web::Data<HealthMonitor>,
+    pub feed: web::Data<ActivityFeed>,
+}
+
+impl TestApp {
+    /// An app with the fixture companies and price data loaded.
+    pub fn with_fixtures() -> Self {
+        let repositories = InMemoryRepositories::new();
+        load_fixtures(&repositories);
+        let mut config = AppConfig::default();
+        config.auth.token_secret = "test secret".to_string();
+        TestApp {
+            repositories,
+            config,
+            health: web::Data::new(HealthMonitor::new()),
+            feed: web::Data::new(ActivityFeed::new()),
+        }
+    }
+
+    /// Builds a fresh service, with empty caches, over this app's data.
+    pub async fn service(&self) -> impl Service<Request, Response = ServiceResponse<impl MessageBody>, Error = Error> {
+        let repositories = self.repositories.repositories();
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new(self.config.clone()))
+                .configure(|cfg| repositories.configure(cfg))
+                .app_data(web::Data::new(SessionTokens::from_config(&self.config.auth)))
+                .app_data(self.feed.clone())
+                .app_data(web::Data::new(MarketDataCache::new()))
+                .app_data(self.health.clone())
+                .configure(configure_routes),
+        )
+        .await
+    }
+}
+
+/// Sends `request` and returns the status with the body parsed as JSON, or
+/// as a JSON string when the body is plain text.
+pub async fn call<S, B>(service: &S, request: Request) -> (u16, Value)
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody,
+{
+    let response = test::call_service(service, request).await;
+    let status = response.status().as_u16();
+    let body = test::read_body(response).await;
+    let value = serde_json::from_slice(&body).unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&body).into_owned()));
+    (status, value)
+}
+
+/// Asserts that `body` is the error envelope with `code`, returning the message.
+pub fn error_message<'a>(body: &'a Value, code: &str) -> &'a str {
+    assert_eq!(body["error"]["code"], code, "{}", body);
+    body["error"]["message"].as_str().expect("error message")
+}
+
+/// The `(field, code)` pairs of a `validation_failed` response, in order.
+pub fn field_errors(body: &Value) -> Vec<(&str, &str)> {
+    error_message(body, "validation_failed");
+    body["error"]["fields"].as_array().expect("fields array")
+        .iter()
+        .map(|field| (field["field"].as_str().unwrap(), field["code"].as_str().unwrap()))
+        .collect()
+}
+
+/// Registers `email` with [`PASSWORD`] and returns the new user's id.
+pub async fn registered_user<S, B>(service: &S, email: &str) -> String
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody,
+{
+    register(service, email).await.user_id.unwrap()
+}
+
+/// Registers `email` with [`PASSWORD`] and returns the response, which
+/// carries the user id and a session token.
+pub async fn register<S, B>(service: &S, email: &str) -> AuthResponse
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody,
+{
+    let request = TestRequest::post().uri("/api/register").set_json(json!({ "email": email, "password": PASSWORD })).to_request();
+    let (status, body) = call(service, request).await;
+    assert_eq!(status, 200, "{}", body);
+    from_value(body)
+}
+
+/// A stored-shape user with `balance` and no trades, for services driven
+/// without going through registration.
+pub fn user(balance: f64) -> User {
+    User {
+        id: Some(ObjectId::new()),
+        email: format!("{}@example.com", ObjectId::new().to_hex()),
+        password: String::new(),
+        name: None,
+        profile: UserProfile { bio: None, avatar_url: None },
+        settings: UserSettings { theme: "light".to_string(), notifications: UserNotifications { email: false, sms: false } },
+        balance,
+        trades: vec![],
+    }
+}
+
+pub fn from_value<T: DeserializeOwned>(value: Value) -> T {
+    serde_json::from_value(value).expect("response matches the expected shape")
+}
+
+fn load_fixtures(repositories: &InMemoryRepositories) {
+    for (index, ticker) in SOFTWARE_TICKERS.iter().enumerate() {
+        repositories.companies.insert(company(ticker, SOFTWARE));
+        let base = 100.0 + 10.0 * index as f64;
+        repositories.prices.insert_series(ticker, series("1mo", "1d", &[base, base + 1.0, base + 2.0]));
+    }
+
+    let mut aapl = company("AAPL", HARDWARE);
+    aapl.insert("financials", doc! {
+        "previousClose": { "raw": AAPL_PREVIOUS_CLOSE, "fmt": "184.00" },
+        "marketCap": { "raw": 2.9e12, "fmt": "2.9T" },
+        "trailingPE": { "raw": 30.5, "fmt": "30.50" },
+    });
+    repositories.companies.insert(aapl);
+    repositories.prices.insert_series("AAPL", series("1mo", "1d", AAPL_CLOSES));
+    repositories.prices.insert_series("AAPL", series("5d", "1h", &[185.0, 185.5, 186.0]));
+
+    repositories.companies.insert(company("HPQ", HARDWARE));
+    let mut malformed = series("1mo", "1d", &[30.0, 31.0, 32.0]);
+    malformed.insert("closes", vec![30.0, 31.0]);
+    repositories.prices.insert_series("HPQ", malformed);
+
+    repositories.companies.insert(company("DELL", HARDWARE));
+}
+
+fn company(ticker: &str, industry: &str) -> Document {
+    doc! {
+        "ticker": ticker,
+        "name": format!("{} Inc.", ticker),
+        "profile": {
+            "sector": SECTOR,
+            "industry": industry,
+            "country": "United States",
+        },
+    }
+}
+
+/// A legacy-shaped series with one daily point per close.
+pub fn series(period: &str, interval: &str, closes: &[f64]) -> Document {
+    let timestamps: Vec<i64> = (0..closes.len() as i64).map(|day| FIRST_TIMESTAMP + day * DAY).collect();
+    doc! {
+        "period": period,
+        "interval": interval,
+        "timestamps": timestamps,
+        "opens": closes,
+        "highs": closes.iter().map(|close| close + 1.0).collect::<Vec<_>>(),
+        "lows": closes.iter().map(|close| close - 1.0).collect::<Vec<_>>(),
+        "closes": closes,
+        "volumes": vec![1_000_000_i64; closes.len()],
+    }
+}
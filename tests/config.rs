@@ -0,0 +1,185 @@
+//! Configuration layering (defaults, file, environment, flags) and the
+//! checks `AppConfig::validate` runs at startup.
+
+use std::path::PathBuf;
+use std::sync::Mutex;
+use trading_sim_server::config::{AppConfig, ConfigArgs, ConfigError};
+
+/// Every variable `AppConfig::load` reads. Tests in this binary share the
+/// process environment, so they hold `ENV_LOCK` while they change it.
+const ENV_VARS: &[&str] = &[
+    "TRADING_SIM_CONFIG",
+    "TRADING_SIM_BIND_ADDRESS",
+    "MONGO_URI",
+    "MONGO_DB_NAME",
+    "MONGO_STOCK_DB_NAME",
+    "MONGO_MIN_POOL_SIZE",
+    "MONGO_MAX_POOL_SIZE",
+    "MONGO_CONNECT_TIMEOUT_MS",
+    "MONGO_SERVER_SELECTION_TIMEOUT_MS",
+    "MONGO_MAX_IDLE_TIME_MS",
+    "SENTRY_DSN",
+    "SENTRY_ENVIRONMENT",
+    "TRADING_SIM_STARTING_BALANCE",
+    "TRADING_SIM_TOKEN_SECRET",
+    "TRADING_SIM_TOKEN_TTL_HOURS",
+    "TRADING_SIM_OPERATOR_TOKEN",
+    "HEALTH_MAX_DATA_AGE_HOURS",
+];
+
+static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+/// Runs `f` with exactly `vars` set among [`ENV_VARS`].
+fn with_env<T>(vars: &[(&str, &str)], f: impl FnOnce() -> T) -> T {
+    let _guard = ENV_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    for name in ENV_VARS {
+        std::env::remove_var(name);
+    }
+    for (name, value) in vars {
+        std::env::set_var(name, value);
+    }
+    let result = f();
+    for (name, _) in vars {
+        std::env::remove_var(name);
+    }
+    result
+}
+
+/// Writes `contents` to a configuration file unique to `name`.
+fn config_file(name: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("trading-sim-config-{}-{}.toml", std::process::id(), name));
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+fn problems(config: &AppConfig) -> Vec<String> {
+    match config.validate() {
+        Err(ConfigError::Invalid(problems)) => problems,
+        other => panic!("expected validation problems, got {:?}", other),
+    }
+}
+
+const FILE: &str = r#"
+[server]
+bind_address = "0.0.0.0:9000"
+
+[mongo]
+app_database = "from_file"
+stock_database = "stock_from_file"
+
+[trading]
+starting_balance = 500.0
+"#;
+
+#[test]
+fn defaults_are_valid_and_report_nothing() {
+    let config = AppConfig::default();
+    assert!(config.validate().is_ok());
+    assert_eq!(config.sentry.dsn, "");
+    assert_eq!(config.mongo.app_database, "trading_simulator");
+    assert_eq!(config.mongo.stock_database, "stock_data");
+}
+
+#[test]
+fn the_example_file_lists_the_defaults() {
+    let example = AppConfig::from_file(&PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("config.example.toml")).unwrap();
+    assert_eq!(toml::to_string(&example).unwrap(), toml::to_string(&AppConfig::default()).unwrap());
+}
+
+#[test]
+fn the_file_overrides_defaults_and_keeps_the_rest() {
+    let path = config_file("file", FILE);
+    let config = with_env(&[], || AppConfig::load(&ConfigArgs { config: Some(path), ..Default::default() })).unwrap();
+    assert_eq!(config.server.bind_address, "0.0.0.0:9000");
+    assert_eq!(config.mongo.app_database, "from_file");
+    assert_eq!(config.trading.starting_balance, 500.0);
+    assert_eq!(config.mongo.uri, "mongodb://localhost:27017");
+    assert_eq!(config.auth.token_ttl_hours, 24);
+}
+
+#[test]
+fn environment_overrides_the_file_and_flags_override_both() {
+    let path = config_file("layers", FILE);
+    let env = [("MONGO_DB_NAME", "from_env"), ("TRADING_SIM_STARTING_BALANCE", "750"), ("SENTRY_ENVIRONMENT", "staging")];
+    let args = ConfigArgs { config: Some(path), starting_balance: Some(1000.0), ..Default::default() };
+    let config = with_env(&env, || AppConfig::load(&args)).unwrap();
+
+    assert_eq!(config.server.bind_address, "0.0.0.0:9000");
+    assert_eq!(config.mongo.stock_database, "stock_from_file");
+    assert_eq!(config.mongo.app_database, "from_env");
+    assert_eq!(config.sentry.environment.as_deref(), Some("staging"));
+    assert_eq!(config.trading.starting_balance, 1000.0);
+}
+
+#[test]
+fn the_config_flag_wins_over_the_environment_variable() {
+    let flagged = config_file("flagged", "[mongo]\napp_database = \"flagged\"\n");
+    let from_env = config_file("from-env", "[mongo]\napp_database = \"named_by_env\"\n");
+    let env = [("TRADING_SIM_CONFIG", from_env.to_str().unwrap())];
+
+    let config = with_env(&env, || AppConfig::load(&ConfigArgs::default())).unwrap();
+    assert_eq!(config.mongo.app_database, "named_by_env");
+
+    let config = with_env(&env, || AppConfig::load(&ConfigArgs { config: Some(flagged), ..Default::default() })).unwrap();
+    assert_eq!(config.mongo.app_database, "flagged");
+}
+
+#[test]
+fn unparsable_environment_values_are_rejected() {
+    let err = with_env(&[("MONGO_MAX_POOL_SIZE", "lots")], || AppConfig::load(&ConfigArgs::default())).unwrap_err();
+    assert!(matches!(err, ConfigError::Env { name: "MONGO_MAX_POOL_SIZE", .. }), "{:?}", err);
+}
+
+#[test]
+fn unknown_keys_in_the_file_are_rejected() {
+    let path = config_file("unknown", "[mongo]\ndatabase = \"typo\"\n");
+    let err = with_env(&[], || AppConfig::load(&ConfigArgs { config: Some(path), ..Default::default() })).unwrap_err();
+    assert!(matches!(err, ConfigError::Parse { .. }), "{:?}", err);
+}
+
+#[test]
+fn loading_validates_the_merged_result() {
+    let err = with_env(&[("MONGO_URI", "localhost:27017")], || AppConfig::load(&ConfigArgs::default())).unwrap_err();
+    assert!(matches!(err, ConfigError::Invalid(ref problems) if problems.len() == 1), "{:?}", err);
+}
+
+#[test]
+fn validation_reports_every_problem_at_once() {
+    let mut config = AppConfig::default();
+    config.server.bind_address = "nowhere".to_string();
+    config.mongo.uri = "http://localhost".to_string();
+    config.mongo.app_database = "bad.name".to_string();
+    config.mongo.min_pool_size = Some(10);
+    config.mongo.max_pool_size = Some(5);
+    config.mongo.connect_timeout_ms = Some(0);
+    config.sentry.dsn = "not a dsn".to_string();
+    config.trading.starting_balance = -1.0;
+    config.auth.token_ttl_hours = 0;
+    config.health.max_data_age_hours = 0;
+
+    assert_eq!(problems(&config), [
+        "server.bind_address 'nowhere' is not a host:port address",
+        "mongo.uri 'http://localhost' must start with mongodb:// or mongodb+srv://",
+        "mongo.app_database 'bad.name' is not a valid MongoDB database name",
+        "mongo.min_pool_size 10 exceeds mongo.max_pool_size 5",
+        "mongo.connect_timeout_ms must be positive, got 0",
+        "sentry.dsn is not a valid Sentry DSN",
+        "trading.starting_balance must be a non-negative amount, got -1",
+        "auth.token_ttl_hours must be positive, got 0",
+        "health.max_data_age_hours must be positive, got 0",
+    ]);
+}
+
+#[test]
+fn the_two_databases_must_differ() {
+    let mut config = AppConfig::default();
+    config.mongo.stock_database = config.mongo.app_database.clone();
+    assert_eq!(problems(&config), ["mongo.app_database and mongo.stock_database must differ"]);
+}
+
+#[test]
+fn a_valid_sentry_dsn_is_accepted() {
+    let mut config = AppConfig::default();
+    config.sentry.dsn = "https://public@example.invalid/1".to_string();
+    assert!(config.validate().is_ok());
+}
@@ -0,0 +1,82 @@
+//! Load test for the shared connection pool: hundreds of concurrent auth and
+//! trade lookups through one client must never hold more connections than the
+//! configured pool size.
+//!
+//! Needs a reachable MongoDB (`TEST_MONGO_URI`, default
+//! `mongodb://localhost:27017`) and only reads, so it is ignored by default:
+//!
+//! ```text
+//! cargo test --test connection_pool -- --ignored --nocapture
+//! ```
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use futures::future::join_all;
+use mongodb::bson::oid::ObjectId;
+use mongodb::event::cmap::{CmapEventHandler, ConnectionClosedEvent, ConnectionCreatedEvent};
+use mongodb::Client;
+use trading_sim_server::auth::SessionTokens;
+use trading_sim_server::config::{AuthConfig, MongoConfig};
+use trading_sim_server::db::mongo::{client_options, Databases, PoolSettings, DEFAULT_MONGO_URI};
+use trading_sim_server::models::users::AuthPayload;
+use trading_sim_server::repositories::Repositories;
+use trading_sim_server::services::{auth_service, trade_service};
+
+const MAX_POOL_SIZE: u32 = 8;
+const REQUESTS: usize = 500;
+
+#[derive(Default)]
+struct ConnectionCounter {
+    created: AtomicUsize,
+    open: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+impl CmapEventHandler for ConnectionCounter {
+    fn handle_connection_created_event(&self, _event: ConnectionCreatedEvent) {
+        self.created.fetch_add(1, Ordering::SeqCst);
+        let open = self.open.fetch_add(1, Ordering::SeqCst) + 1;
+        self.peak.fetch_max(open, Ordering::SeqCst);
+    }
+
+    fn handle_connection_closed_event(&self, _event: ConnectionClosedEvent) {
+        self.open.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+#[ignore = "needs a running MongoDB"]
+async fn concurrent_requests_stay_within_the_pool() {
+    let uri = std::env::var("TEST_MONGO_URI").unwrap_or_else(|_| DEFAULT_MONGO_URI.to_string());
+    let counter = Arc::new(ConnectionCounter::default());
+    let pool = PoolSettings { max_pool_size: Some(MAX_POOL_SIZE), ..PoolSettings::default() };
+    let mut options = client_options(&uri, &pool).await.expect("valid connection string");
+    options.cmap_event_handler = Some(counter.clone());
+    let client = Client::with_options(options).expect("client");
+    let repositories = Repositories::mongo(&Databases::new(&client, &MongoConfig::default()));
+    let tokens = Arc::new(SessionTokens::from_config(&AuthConfig::default()));
+
+    let requests = (0..REQUESTS).map(|i| {
+        let repositories = repositories.clone();
+        let tokens = tokens.clone();
+        tokio::spawn(async move {
+            if i % 2 == 0 {
+                let payload = AuthPayload { email: format!("pool-test-{}@example.invalid", i), password: "unused".to_string() };
+                // No such user: the lookup still runs, without hashing or writing.
+                assert!(auth_service::login(repositories.users.as_ref(), &tokens, payload).await.is_err());
+            } else {
+                let trades = trade_service::get_user_trades(repositories.trades.as_ref(), &ObjectId::new().to_hex()).await.expect("trade lookup");
+                assert!(trades.is_empty());
+            }
+        })
+    });
+    for result in join_all(requests).await {
+        result.expect("request task panicked");
+    }
+
+    let created = counter.created.load(Ordering::SeqCst);
+    let peak = counter.peak.load(Ordering::SeqCst);
+    println!("{} requests: {} connections created, at most {} open", REQUESTS, created, peak);
+    assert!(created > 0, "no connections were opened; is MongoDB reachable?");
+    assert!(peak <= MAX_POOL_SIZE as usize, "{} connections open at once, pool allows {}", peak, MAX_POOL_SIZE);
+}
@@ -0,0 +1,395 @@
+//! Recording and listing corporate actions, which only operators may
+//! record, the ledger they produce, the arithmetic of applying splits to
+//! trades and price series, and the processor that settles them against
+//! the in-memory backend.
+
+mod common;
+
+use actix_web::test::TestRequest;
+use chrono::{TimeZone, Utc};
+use mongodb::bson::oid::ObjectId;
+use serde_json::json;
+use trading_sim_server::models::corporate_action_models::{CorporateAction, CorporateActionKind};
+use trading_sim_server::models::ledger_models::{LedgerEntry, LedgerEntryKind};
+use trading_sim_server::models::stock_models::PriceDataDetails;
+use trading_sim_server::models::trade_models::{Trade, TradeStatus};
+use trading_sim_server::repositories::memory::InMemoryRepositories;
+use trading_sim_server::repositories::{CorporateActionRepository, TradeRepository, UserRepository};
+use trading_sim_server::services::activity_service::ActivityFeed;
+use trading_sim_server::services::corporate_action_service::{adjust_for_splits, process_due_corporate_actions, split_adjustment};
+use common::{call, error_message, user, TestApp, DAY, FIRST_TIMESTAMP};
+
+const OPERATOR_TOKEN: &str = "operator secret";
+
+fn record_dividend(token: Option<&str>) -> actix_http::Request {
+    let mut request = TestRequest::post().uri("/api/corporate-actions").set_json(json!({
+        "ticker": "AAPL",
+        "ex_date": 1_700_000_000,
+        "action": { "type": "dividend", "amount_per_share": 0.24 },
+    }));
+    if let Some(token) = token {
+        request = request.insert_header(("X-Operator-Token", token));
+    }
+    request.to_request()
+}
+
+#[actix_web::test]
+async fn recording_is_disabled_without_an_operator_token() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+
+    let (status, body) = call(&service, record_dividend(Some(""))).await;
+    assert_eq!(status, 401, "{}", body);
+    assert_eq!(error_message(&body, "unauthorized"), "Operator endpoints are disabled");
+}
+
+#[actix_web::test]
+async fn recording_requires_the_operator_token() {
+    let mut app = TestApp::with_fixtures();
+    app.config.auth.operator_token = OPERATOR_TOKEN.to_string();
+    let service = app.service().await;
+
+    let (status, body) = call(&service, record_dividend(None)).await;
+    assert_eq!(status, 401, "{}", body);
+    assert_eq!(error_message(&body, "unauthorized"), "Missing operator token");
+
+    let (status, body) = call(&service, record_dividend(Some("operator secreT"))).await;
+    assert_eq!(status, 401, "{}", body);
+    assert_eq!(error_message(&body, "unauthorized"), "Invalid operator token");
+    assert!(app.repositories.corporate_actions.all().is_empty());
+}
+
+#[actix_web::test]
+async fn recorded_actions_are_listed_by_ex_date() {
+    let mut app = TestApp::with_fixtures();
+    app.config.auth.operator_token = OPERATOR_TOKEN.to_string();
+    let service = app.service().await;
+
+    let (status, body) = call(&service, record_dividend(Some(OPERATOR_TOKEN))).await;
+    assert_eq!(status, 200, "{}", body);
+    let request = TestRequest::post().uri("/api/corporate-actions")
+        .insert_header(("X-Operator-Token", OPERATOR_TOKEN))
+        .set_json(json!({
+            "ticker": "AAPL",
+            "ex_date": 1_700_500_000,
+            "action": { "type": "split", "numerator": 4, "denominator": 1 },
+        }))
+        .to_request();
+    let (status, split) = call(&service, request).await;
+    assert_eq!(status, 200, "{}", split);
+
+    let (status, body) = call(&service, TestRequest::get().uri("/api/corporate-actions/AAPL").to_request()).await;
+    assert_eq!(status, 200, "{}", body);
+    let actions = body.as_array().unwrap();
+    assert_eq!(actions.len(), 2);
+    assert_eq!(actions[0]["_id"]["$oid"], split["corporate_action_id"]);
+    assert_eq!(actions[0]["action"], json!({ "type": "split", "numerator": 4, "denominator": 1 }));
+    assert_eq!(actions[1]["processed"], false);
+
+    let (status, body) = call(&service, TestRequest::get().uri("/api/corporate-actions/MSFT").to_request()).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body, json!([]));
+}
+
+fn ledger_entry(user_id: ObjectId, amount: f64, settled: bool, created_at: i64) -> LedgerEntry {
+    LedgerEntry {
+        id: Some(ObjectId::new()),
+        user_id,
+        kind: LedgerEntryKind::Dividend,
+        ticker: "AAPL".to_string(),
+        amount,
+        quantity: amount / 0.24,
+        corporate_action_id: Some(ObjectId::new()),
+        trade_id: None,
+        settled,
+        created_at: Utc.timestamp_opt(created_at, 0).unwrap(),
+    }
+}
+
+#[actix_web::test]
+async fn ledger_lists_settled_entries_newest_first() {
+    let app = TestApp::with_fixtures();
+    let user_id = ObjectId::new();
+    app.repositories.corporate_actions.insert_ledger_entry(ledger_entry(user_id, 2.4, true, FIRST_TIMESTAMP));
+    app.repositories.corporate_actions.insert_ledger_entry(ledger_entry(user_id, 4.8, true, FIRST_TIMESTAMP + DAY));
+    app.repositories.corporate_actions.insert_ledger_entry(ledger_entry(user_id, 9.6, false, FIRST_TIMESTAMP + 2 * DAY));
+    app.repositories.corporate_actions.insert_ledger_entry(ledger_entry(ObjectId::new(), 1.2, true, FIRST_TIMESTAMP));
+    let service = app.service().await;
+
+    let request = TestRequest::get().uri(&format!("/api/ledger?user_id={}", user_id.to_hex())).to_request();
+    let (status, body) = call(&service, request).await;
+    assert_eq!(status, 200, "{}", body);
+    let amounts: Vec<f64> = body.as_array().unwrap().iter().map(|entry| entry["amount"].as_f64().unwrap()).collect();
+    assert_eq!(amounts, [4.8, 2.4]);
+}
+
+fn long_trade(quantity: u32, price: f64) -> Trade {
+    Trade {
+        id: Some(ObjectId::new()),
+        ticker: "AAPL".to_string(),
+        position: "long".to_string(),
+        quantity,
+        price,
+        take_profit: Some(price * 1.2),
+        stop_loss: Some(price * 0.9),
+        status: TradeStatus::InProgress,
+        user_id: ObjectId::new(),
+        amount: quantity as f64 * price,
+        trade_type: "market".to_string(),
+        exit_price: None,
+        closed_at: None,
+        applied_splits: vec![],
+    }
+}
+
+fn assert_close(actual: f64, expected: f64) {
+    assert!((actual - expected).abs() < 1e-9, "expected {}, got {}", expected, actual);
+}
+
+#[test]
+fn forward_split_keeps_the_cost_basis() {
+    let split = split_adjustment(&long_trade(10, 100.0), 4.0);
+    assert_eq!(split.quantity, 40);
+    assert_close(split.price, 25.0);
+    assert_close(split.take_profit.unwrap(), 30.0);
+    assert_close(split.stop_loss.unwrap(), 22.5);
+    assert_close(split.amount, 1000.0);
+    assert_eq!(split.fractional_shares, 0.0);
+    assert!(!split.closes_trade());
+}
+
+#[test]
+fn reverse_split_pays_fractional_shares_in_cash() {
+    // 25 shares 1-for-10: 2 shares at 500, half a share paid out as 250.
+    let split = split_adjustment(&long_trade(25, 50.0), 0.1);
+    assert_eq!(split.quantity, 2);
+    assert_close(split.price, 500.0);
+    assert_close(split.fractional_shares, 0.5);
+    assert_close(split.cash_in_lieu, 250.0);
+    assert_close(split.amount, 1000.0);
+    assert!(!split.closes_trade());
+}
+
+#[test]
+fn reverse_split_below_one_share_closes_the_trade() {
+    let split = split_adjustment(&long_trade(5, 10.0), 0.1);
+    assert_eq!(split.quantity, 0);
+    assert!(split.closes_trade());
+    assert_close(split.cash_in_lieu, 50.0);
+    assert_close(split.amount, 0.0);
+}
+
+#[test]
+fn inexact_ratios_do_not_lose_a_share() {
+    let split = split_adjustment(&long_trade(3, 10.0), 1.0 / 3.0);
+    assert_eq!(split.quantity, 1);
+    assert_eq!(split.fractional_shares, 0.0);
+    assert!(!split.closes_trade());
+}
+
+fn daily_series(closes: &[f64], volumes: &[i64]) -> PriceDataDetails {
+    PriceDataDetails {
+        period: "1mo".to_string(),
+        interval: "1d".to_string(),
+        closes: Some(closes.to_vec()),
+        highs: Some(closes.iter().map(|close| close + 2.0).collect()),
+        lows: None,
+        opens: Some(closes.to_vec()),
+        timestamps: Some((0..closes.len() as i64).map(|day| Utc.timestamp_opt(FIRST_TIMESTAMP + day * DAY, 0).unwrap()).collect()),
+        volumes: Some(volumes.to_vec()),
+    }
+}
+
+#[test]
+fn points_before_the_ex_date_are_split_adjusted() {
+    let mut series = daily_series(&[400.0, 404.0, 101.0], &[100, 200, 800]);
+    adjust_for_splits(&mut series, &[(FIRST_TIMESTAMP + 2 * DAY, 4.0)]);
+
+    assert_eq!(series.closes.unwrap(), vec![100.0, 101.0, 101.0]);
+    assert_eq!(series.opens.unwrap(), vec![100.0, 101.0, 101.0]);
+    assert_eq!(series.highs.unwrap(), vec![100.5, 101.5, 103.0]);
+    assert_eq!(series.lows, None);
+    assert_eq!(series.volumes.unwrap(), vec![400, 800, 800]);
+}
+
+#[test]
+fn successive_splits_compound() {
+    let mut series = daily_series(&[600.0, 300.0, 100.0], &[10, 20, 60]);
+    adjust_for_splits(&mut series, &[(FIRST_TIMESTAMP + DAY, 2.0), (FIRST_TIMESTAMP + 2 * DAY, 3.0)]);
+
+    assert_eq!(series.closes.unwrap(), vec![100.0, 100.0, 100.0]);
+    assert_eq!(series.volumes.unwrap(), vec![60, 60, 60]);
+}
+
+#[test]
+fn series_without_timestamps_are_left_alone() {
+    let mut series = daily_series(&[400.0], &[100]);
+    series.timestamps = None;
+    adjust_for_splits(&mut series, &[(FIRST_TIMESTAMP + DAY, 4.0)]);
+    assert_eq!(series.closes.unwrap(), vec![400.0]);
+}
+
+/// An ObjectId generated at `timestamp`, so the trade reads as opened then.
+fn id_at(timestamp: i64) -> ObjectId {
+    let mut bytes = ObjectId::new().bytes();
+    bytes[..4].copy_from_slice(&(timestamp as u32).to_be_bytes());
+    ObjectId::from_bytes(bytes)
+}
+
+struct Processor {
+    repositories: InMemoryRepositories,
+    feed: ActivityFeed,
+    /// The ex-date of every action, a day ago; trades open ten days ago.
+    ex_date: i64,
+}
+
+impl Processor {
+    fn new() -> Self {
+        Processor { repositories: InMemoryRepositories::new(), feed: ActivityFeed::new(), ex_date: Utc::now().timestamp() - DAY }
+    }
+
+    async fn user(&self, balance: f64) -> ObjectId {
+        self.repositories.users.insert(user(balance)).await.unwrap()
+    }
+
+    async fn trade(&self, user_id: ObjectId, position: &str, quantity: u32, opened: i64) -> ObjectId {
+        let trade = Trade { id: Some(id_at(opened)), user_id, position: position.to_string(), ..long_trade(quantity, 50.0) };
+        self.repositories.trades.insert(trade).await.unwrap()
+    }
+
+    async fn action(&self, action: CorporateActionKind) -> ObjectId {
+        let action = CorporateAction {
+            id: None,
+            ticker: "AAPL".to_string(),
+            ex_date: Utc.timestamp_opt(self.ex_date, 0).unwrap(),
+            pay_date: None,
+            action,
+            processed: false,
+            processed_at: None,
+        };
+        self.repositories.corporate_actions.insert(action).await.unwrap()
+    }
+
+    async fn run(&self) -> usize {
+        process_due_corporate_actions(&self.repositories.repositories(), &self.feed).await.unwrap()
+    }
+
+    fn balance(&self, user_id: ObjectId) -> f64 {
+        self.repositories.users.find_by_id(user_id).unwrap().balance
+    }
+
+    fn trade_state(&self, trade_id: ObjectId) -> Trade {
+        self.repositories.trades.all().into_iter().find(|trade| trade.id == Some(trade_id)).unwrap()
+    }
+
+    fn ledger(&self) -> Vec<(ObjectId, LedgerEntryKind, f64, bool)> {
+        let mut entries: Vec<_> = self.repositories.corporate_actions.ledger()
+            .into_iter()
+            .map(|entry| (entry.user_id, entry.kind, entry.amount, entry.settled))
+            .collect();
+        entries.sort_by(|a, b| a.2.total_cmp(&b.2));
+        entries
+    }
+}
+
+#[actix_web::test]
+async fn dividends_credit_longs_and_debit_shorts_once() {
+    let processor = Processor::new();
+    let opened = processor.ex_date - 10 * DAY;
+    let long = processor.user(1000.0).await;
+    let short = processor.user(1000.0).await;
+    let late = processor.user(1000.0).await;
+    processor.trade(long, "long", 4, opened).await;
+    // Closed after the ex-date, so still held on it.
+    let sold = processor.trade(long, "buy", 6, opened).await;
+    processor.repositories.trades.close(sold, 55.0).await.unwrap();
+    processor.trade(short, "short", 4, opened).await;
+    // Opened after the ex-date.
+    processor.trade(late, "long", 100, processor.ex_date + 60).await;
+    let action_id = processor.action(CorporateActionKind::Dividend { amount_per_share: 0.5 }).await;
+
+    assert_eq!(processor.run().await, 1);
+    assert_eq!(processor.run().await, 0);
+    // A run stopped before marking the action processed is picked up again.
+    processor.repositories.corporate_actions.reopen(action_id);
+    assert_eq!(processor.run().await, 1);
+
+    assert_close(processor.balance(long), 1005.0);
+    assert_close(processor.balance(short), 998.0);
+    assert_close(processor.balance(late), 1000.0);
+    assert_eq!(processor.ledger(), [
+        (short, LedgerEntryKind::Dividend, -2.0, true),
+        (long, LedgerEntryKind::Dividend, 5.0, true),
+    ]);
+    assert!(processor.repositories.corporate_actions.all()[0].processed);
+}
+
+#[actix_web::test]
+async fn splits_adjust_open_positions_and_settle_cash_in_lieu_once() {
+    let processor = Processor::new();
+    let opened = processor.ex_date - 10 * DAY;
+    let long = processor.user(1000.0).await;
+    let short = processor.user(1000.0).await;
+    let long_trade_id = processor.trade(long, "long", 25, opened).await;
+    let short_trade_id = processor.trade(short, "short", 25, opened).await;
+    let closed = processor.trade(long, "long", 30, opened).await;
+    processor.repositories.trades.close(closed, 50.0).await.unwrap();
+    // 1-for-10: 25 shares at 50 become 2 at 500 and half a share paid out.
+    let action_id = processor.action(CorporateActionKind::Split { numerator: 1, denominator: 10 }).await;
+
+    assert_eq!(processor.run().await, 1);
+    processor.repositories.corporate_actions.reopen(action_id);
+    assert_eq!(processor.run().await, 1);
+
+    for trade_id in [long_trade_id, short_trade_id] {
+        let trade = processor.trade_state(trade_id);
+        assert_eq!((trade.quantity, trade.status, trade.applied_splits), (2, TradeStatus::InProgress, vec![action_id]));
+        assert_close(trade.price, 500.0);
+        assert_close(trade.amount, 1000.0);
+    }
+    // Closed trades are not split.
+    assert_eq!(processor.trade_state(closed).quantity, 30);
+    assert_close(processor.balance(long), 1250.0);
+    assert_close(processor.balance(short), 750.0);
+    assert_eq!(processor.ledger(), [
+        (short, LedgerEntryKind::SplitCashInLieu, -250.0, true),
+        (long, LedgerEntryKind::SplitCashInLieu, 250.0, true),
+    ]);
+}
+
+#[actix_web::test]
+async fn forward_splits_multiply_shares_without_a_payout() {
+    let processor = Processor::new();
+    let long = processor.user(1000.0).await;
+    let trade_id = processor.trade(long, "long", 10, processor.ex_date - 10 * DAY).await;
+    processor.action(CorporateActionKind::Split { numerator: 4, denominator: 1 }).await;
+
+    assert_eq!(processor.run().await, 1);
+
+    let trade = processor.trade_state(trade_id);
+    assert_eq!(trade.quantity, 40);
+    assert_close(trade.price, 12.5);
+    assert_close(processor.balance(long), 1000.0);
+    assert!(processor.ledger().is_empty());
+}
+
+#[actix_web::test]
+async fn a_run_stopped_after_paying_does_not_pay_again() {
+    let processor = Processor::new();
+    let long = processor.user(1000.0).await;
+    processor.trade(long, "long", 10, processor.ex_date - 10 * DAY).await;
+    let action_id = processor.action(CorporateActionKind::Dividend { amount_per_share: 0.5 }).await;
+
+    // The earlier run recorded the entry and paid it, but stopped before
+    // flagging the entry settled.
+    let mut entry = ledger_entry(long, 5.0, false, processor.ex_date);
+    entry.corporate_action_id = Some(action_id);
+    let entry_id = entry.id.unwrap();
+    processor.repositories.corporate_actions.insert_ledger_entry(entry);
+    processor.repositories.users.apply_ledger_entry(long, entry_id, 5.0).await.unwrap();
+
+    assert_eq!(processor.run().await, 1);
+
+    assert_close(processor.balance(long), 1005.0);
+    assert_eq!(processor.ledger(), [(long, LedgerEntryKind::Dividend, 5.0, true)]);
+}
@@ -0,0 +1,139 @@
+//! The checks behind quarantining and the data health report, run on
+//! single price documents.
+
+mod common;
+
+use mongodb::bson::{Bson, Document};
+use trading_sim_server::models::data_quality_models::{DataIssue, IssueKind, IssueSeverity};
+use trading_sim_server::services::data_quality_service::{has_errors, structural_issues, validate_document};
+use common::{series, DAY, FIRST_TIMESTAMP};
+
+fn daily(closes: &[f64]) -> Document {
+    series("1mo", "1d", closes)
+}
+
+fn found(issues: &[DataIssue]) -> Vec<(IssueKind, Option<usize>, &str)> {
+    issues.iter().map(|issue| (issue.kind, issue.index, issue.message.as_str())).collect()
+}
+
+#[test]
+fn a_clean_series_has_no_issues() {
+    let doc = daily(&[100.0, 101.0, 100.5, 102.0]);
+    assert!(structural_issues(&doc).is_empty());
+    assert!(validate_document(&doc).is_empty());
+}
+
+#[test]
+fn nan_and_infinite_values_are_not_numbers() {
+    let mut doc = daily(&[100.0, 101.0, 102.0]);
+    doc.insert("closes", vec![100.0, f64::NAN, 102.0]);
+    doc.insert("opens", vec![100.0, 101.0, f64::INFINITY]);
+    let issues = structural_issues(&doc);
+
+    assert_eq!(issues.iter().map(|issue| (issue.kind, issue.index)).collect::<Vec<_>>(), [
+        (IssueKind::NonNumeric, Some(1)),
+        (IssueKind::NonNumeric, Some(2)),
+    ]);
+    assert!(issues[0].message.starts_with("closes[1] is not a finite number"), "{:?}", issues);
+    assert!(issues[1].message.starts_with("opens[2] is not a finite number"), "{:?}", issues);
+    assert!(has_errors(&issues));
+}
+
+#[test]
+fn null_and_text_values_are_not_numbers() {
+    let mut doc = daily(&[100.0, 101.0, 102.0]);
+    doc.insert("highs", vec![Bson::Double(101.0), Bson::Null, Bson::Double(103.0)]);
+    doc.insert("volumes", vec![Bson::Int64(1), Bson::Int64(2), Bson::String("3".to_string())]);
+    assert_eq!(found(&structural_issues(&doc)), [
+        (IssueKind::NonNumeric, Some(1), "highs[1] is not a finite number: null"),
+        (IssueKind::NonNumeric, Some(2), "volumes[2] is not a finite number: \"3\""),
+    ]);
+}
+
+#[test]
+fn non_positive_prices_and_negative_volumes_are_inconsistent() {
+    let mut doc = daily(&[100.0, 0.0, 102.0, 103.0]);
+    doc.insert("lows", vec![99.0, -1.0, -5.0, 102.0]);
+    doc.insert("volumes", vec![1_i64, 1, 1, -1]);
+    assert_eq!(found(&structural_issues(&doc)), [
+        (IssueKind::OhlcInconsistent, Some(1), "prices must be positive"),
+        (IssueKind::OhlcInconsistent, Some(2), "prices must be positive"),
+        (IssueKind::OhlcInconsistent, Some(3), "volume is negative"),
+    ]);
+}
+
+#[test]
+fn prices_must_lie_within_the_high_low_range() {
+    let mut doc = daily(&[100.0, 101.0, 102.0]);
+    doc.insert("highs", vec![101.0, 100.0, 103.0]);
+    doc.insert("lows", vec![99.0, 102.0, 102.5]);
+    assert_eq!(found(&structural_issues(&doc)), [
+        (IssueKind::OhlcInconsistent, Some(1), "high 100 is below low 102"),
+        (IssueKind::OhlcInconsistent, Some(2), "open or close lies outside the high/low range"),
+    ]);
+}
+
+#[test]
+fn arrays_must_match_the_timestamps() {
+    let mut doc = daily(&[100.0, 101.0, 102.0]);
+    doc.insert("closes", vec![100.0, 0.0]);
+    doc.insert("volumes", vec![1_i64, 2, 3, 4]);
+    // Point checks are skipped once the arrays disagree, so the zero close
+    // is not reported on its own.
+    assert_eq!(found(&structural_issues(&doc)), [
+        (IssueKind::LengthMismatch, None, "closes has 2 values for 3 timestamps"),
+        (IssueKind::LengthMismatch, None, "volumes has 4 values for 3 timestamps"),
+    ]);
+}
+
+#[test]
+fn timestamps_and_closes_are_required() {
+    let mut doc = daily(&[100.0, 101.0]);
+    doc.remove("closes");
+    doc.insert("period", 5);
+    doc.insert("opens", "100");
+    assert_eq!(found(&structural_issues(&doc)), [
+        (IssueKind::MissingField, None, "period is missing or not a string"),
+        (IssueKind::NonNumeric, None, "opens is a String, not an array"),
+        (IssueKind::MissingField, None, "timestamps and closes are required"),
+    ]);
+}
+
+#[test]
+fn timestamps_must_increase() {
+    let mut doc = daily(&[100.0, 101.0, 102.0]);
+    doc.insert("timestamps", vec![FIRST_TIMESTAMP, FIRST_TIMESTAMP + DAY, FIRST_TIMESTAMP + DAY]);
+    let issues = structural_issues(&doc);
+    assert_eq!(issues.iter().map(|issue| (issue.kind, issue.index)).collect::<Vec<_>>(), [(IssueKind::NonMonotonicTimestamps, Some(2))]);
+}
+
+#[test]
+fn gaps_and_outliers_are_warnings() {
+    let mut doc = daily(&[100.0, 101.0, 100.0, 101.0, 200.0, 201.0]);
+    let mut timestamps: Vec<i64> = (0..6).map(|day| FIRST_TIMESTAMP + day * DAY).collect();
+    timestamps[5] += 9 * DAY;
+    doc.insert("timestamps", timestamps);
+    let issues = validate_document(&doc);
+
+    assert_eq!(found(&issues), [
+        (IssueKind::Gap, Some(5), "10.0 days without data before this point"),
+        (IssueKind::Outlier, Some(4), "close moved +98.0% from the previous point"),
+    ]);
+    assert!(issues.iter().all(|issue| issue.severity == IssueSeverity::Warning));
+    assert!(!has_errors(&issues));
+}
+
+#[test]
+fn statistical_checks_wait_for_a_sound_structure() {
+    let mut doc = daily(&[100.0, 101.0, 100.0, 101.0, 200.0, 201.0]);
+    doc.insert("volumes", vec![-1_i64, 1, 1, 1, 1, 1]);
+    assert_eq!(found(&validate_document(&doc)), [(IssueKind::OhlcInconsistent, Some(0), "volume is negative")]);
+}
+
+#[test]
+fn issues_of_one_kind_are_capped_and_summarised() {
+    let issues = validate_document(&daily(&[-1.0; 12]));
+    assert_eq!(issues.len(), 11);
+    assert!(issues[..10].iter().all(|issue| issue.kind == IssueKind::OhlcInconsistent));
+    assert_eq!(found(&issues[10..]), [(IssueKind::OhlcInconsistent, None, "2 more OhlcInconsistent issues not shown")]);
+}
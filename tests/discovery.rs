@@ -0,0 +1,449 @@
+//! Finding companies: the screener, search, the sector taxonomy, the
+//! earnings calendar and the data health report, over the in-memory backend.
+
+mod common;
+
+use actix_web::test::TestRequest;
+use chrono::{TimeZone, Utc};
+use mongodb::bson::{doc, Bson, DateTime as BsonDateTime};
+use serde_json::{json, Value};
+use trading_sim_server::models::data_quality_models::{DataIssue, IssueKind, IssueSeverity, QuarantinedDocument};
+use common::{call, error_message, series, TestApp, AAPL_CLOSES, DAY, FIRST_TIMESTAMP, HARDWARE_TICKERS, SECTOR, SOFTWARE_TICKERS};
+
+const ENERGY: &str = "Energy";
+
+/// Adds energy companies with fundamentals; the fixture companies have none
+/// apart from AAPL. COP reports no trailing P/E, dividend or EPS.
+fn with_energy_companies(app: &TestApp) {
+    let profile = |summary: &str| doc! { "sector": ENERGY, "industry": "Oil & Gas", "longBusinessSummary": summary };
+    app.repositories.companies.insert(doc! {
+        "ticker": "XOM",
+        "name": "Exxon Mobil Corporation",
+        "profile": profile("Explores for and refines crude oil."),
+        "financials": { "marketCap": { "raw": 4.5e11 }, "trailingPE": { "raw": 12.0 }, "dividendRate": { "raw": 3.8 } },
+        "keyStatistics": { "trailingEPS": { "raw": 8.9 }, "forwardEPS": { "raw": 9.5 } },
+    });
+    app.repositories.companies.insert(doc! {
+        "ticker": "COP",
+        "name": "ConocoPhillips",
+        "profile": profile("Explores for crude oil and natural gas."),
+        "financials": { "marketCap": { "raw": 1.3e11 }, "trailingPE": { "raw": "N/A" } },
+    });
+    app.repositories.companies.insert(doc! {
+        "ticker": "CVX",
+        "name": "Chevron Corporation",
+        "profile": profile("Integrated energy company."),
+        "financials": { "marketCap": { "raw": 3.0e11 }, "trailingPE": { "raw": 14.0 }, "dividendRate": { "raw": 6.5 } },
+        "keyStatistics": { "trailingEPS": { "raw": 10.1 } },
+    });
+}
+
+/// Gives each energy company a 1mo/1d series, so screens can filter on the
+/// one month return and sort in memory.
+fn with_energy_prices(app: &TestApp) {
+    for (ticker, closes) in [("XOM", [100.0, 110.0]), ("COP", [100.0, 105.0]), ("CVX", [100.0, 95.0])] {
+        app.repositories.prices.insert_series(ticker, series("1mo", "1d", &closes));
+    }
+}
+
+fn screen(body: Value) -> actix_http::Request {
+    TestRequest::post().uri("/api/screener").set_json(body).to_request()
+}
+
+fn tickers(rows: &Value) -> Vec<&str> {
+    rows.as_array().expect("array").iter().map(|row| row["ticker"].as_str().unwrap()).collect()
+}
+
+#[actix_web::test]
+async fn screener_filters_and_sorts_by_fundamentals() {
+    let app = TestApp::with_fixtures();
+    with_energy_companies(&app);
+    let service = app.service().await;
+
+    let (status, body) = call(&service, screen(json!({ "sector": ENERGY }))).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["total"], 3);
+    assert_eq!(tickers(&body["results"]), ["XOM", "CVX", "COP"]);
+
+    let (status, body) = call(&service, screen(json!({
+        "sector": ENERGY,
+        "filters": [{ "field": "trailing_pe", "min": 13.0 }],
+    }))).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["total"], 1);
+    assert_eq!(tickers(&body["results"]), ["CVX"]);
+}
+
+#[actix_web::test]
+async fn screener_pages_after_sorting() {
+    let app = TestApp::with_fixtures();
+    with_energy_companies(&app);
+    let service = app.service().await;
+
+    let (status, body) = call(&service, screen(json!({
+        "sector": ENERGY,
+        "sort_by": "ticker",
+        "sort_order": "asc",
+        "page": 1,
+        "items_per_page": 2,
+    }))).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["total"], 3);
+    assert_eq!(tickers(&body["results"]), ["XOM"]);
+}
+
+#[actix_web::test]
+async fn screener_sorts_by_one_month_return() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+
+    // Every software series rises by two, so the lowest base gains the most.
+    let (status, body) = call(&service, screen(json!({ "industry": "Software", "sort_by": "one_month_return" }))).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["total"], SOFTWARE_TICKERS.len());
+    assert_eq!(tickers(&body["results"])[..3], ["MSFT", "ORCL", "ADBE"]);
+}
+
+#[actix_web::test]
+async fn one_month_returns_are_split_adjusted() {
+    let app = TestApp::with_fixtures();
+    // A 1-for-2 reverse split after ORCL's first close of 110: adjusted, that
+    // close is 220 and the month lost half its value instead of gaining.
+    app.repositories.prices.insert_split("ORCL", FIRST_TIMESTAMP + DAY, 0.5);
+    app.repositories.prices.insert_split("AAPL", FIRST_TIMESTAMP + 2 * DAY, 2.0);
+    let service = app.service().await;
+
+    let body = json!({ "industry": "Software", "sort_by": "one_month_return", "sort_order": "asc" });
+    let (status, body) = call(&service, screen(body)).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(tickers(&body["results"])[..2], ["ORCL", "SNPS"]);
+
+    let (status, body) = call(&service, TestRequest::get().uri("/api/sectors/Technology/industries").to_request()).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body[0]["industry"], "Hardware");
+    let expected = (AAPL_CLOSES[AAPL_CLOSES.len() - 1] / (AAPL_CLOSES[0] / 2.0) - 1.0) * 100.0;
+    assert!((body[0]["average_one_month_return"].as_f64().unwrap() - expected).abs() < 1e-9, "{}", body);
+}
+
+#[actix_web::test]
+async fn screener_rows_carry_dividend_rate_and_eps() {
+    let app = TestApp::with_fixtures();
+    with_energy_companies(&app);
+    let service = app.service().await;
+
+    let (status, body) = call(&service, screen(json!({ "sector": ENERGY, "sort_by": "ticker", "sort_order": "asc" }))).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(tickers(&body["results"]), ["COP", "CVX", "XOM"]);
+    let xom = &body["results"][2];
+    assert_eq!((xom["dividend_rate"].as_f64(), xom["trailing_eps"].as_f64(), xom["forward_eps"].as_f64()), (Some(3.8), Some(8.9), Some(9.5)));
+    assert_eq!(body["results"][0]["trailing_pe"], Value::Null);
+}
+
+/// Sorts energy companies by `field`, through the database sort or, with
+/// `in_memory`, through a one month return filter that forces an in-memory
+/// sort.
+async fn sorted_energy_tickers(field: &str, order: &str, in_memory: bool) -> Vec<String> {
+    let app = TestApp::with_fixtures();
+    with_energy_companies(&app);
+    with_energy_prices(&app);
+    let service = app.service().await;
+
+    let filters = if in_memory { json!([{ "field": "one_month_return" }]) } else { json!([]) };
+    let (status, body) = call(&service, screen(json!({ "sector": ENERGY, "sort_by": field, "sort_order": order, "filters": filters }))).await;
+    assert_eq!(status, 200, "{}", body);
+    tickers(&body["results"]).into_iter().map(str::to_string).collect()
+}
+
+#[actix_web::test]
+async fn screener_puts_missing_values_last_in_both_directions() {
+    for in_memory in [false, true] {
+        assert_eq!(sorted_energy_tickers("trailing_pe", "asc", in_memory).await, ["XOM", "CVX", "COP"], "in memory: {}", in_memory);
+        assert_eq!(sorted_energy_tickers("trailing_pe", "desc", in_memory).await, ["CVX", "XOM", "COP"], "in memory: {}", in_memory);
+        assert_eq!(sorted_energy_tickers("dividend_rate", "asc", in_memory).await, ["XOM", "CVX", "COP"], "in memory: {}", in_memory);
+        assert_eq!(sorted_energy_tickers("trailing_eps", "desc", in_memory).await, ["CVX", "XOM", "COP"], "in memory: {}", in_memory);
+        assert_eq!(sorted_energy_tickers("forward_eps", "asc", in_memory).await, ["XOM", "COP", "CVX"], "in memory: {}", in_memory);
+    }
+}
+
+#[actix_web::test]
+async fn screener_sorts_by_one_month_return_with_a_filter() {
+    assert_eq!(sorted_energy_tickers("one_month_return", "desc", true).await, ["XOM", "COP", "CVX"]);
+    assert_eq!(sorted_energy_tickers("one_month_return", "asc", true).await, ["CVX", "COP", "XOM"]);
+}
+
+#[actix_web::test]
+async fn screener_rejects_unknown_fields() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+
+    let (status, body) = call(&service, screen(json!({ "sort_by": "shoe_size" }))).await;
+    assert_eq!(status, 400, "{}", body);
+    assert_eq!(error_message(&body, "bad_request"), "Unknown sort field: shoe_size");
+
+    let (status, body) = call(&service, screen(json!({ "filters": [{ "field": "name", "min": 1.0 }] }))).await;
+    assert_eq!(status, 400, "{}", body);
+    assert_eq!(error_message(&body, "bad_request"), "Unknown filter field: name");
+}
+
+async fn search<S, B>(service: &S, query: &str) -> Value
+where
+    S: actix_web::dev::Service<actix_http::Request, Response = actix_web::dev::ServiceResponse<B>, Error = actix_web::Error>,
+    B: actix_web::body::MessageBody,
+{
+    let request = TestRequest::get().uri(&format!("/api/search?q={}", query)).to_request();
+    let (status, body) = call(service, request).await;
+    assert_eq!(status, 200, "{}", body);
+    body
+}
+
+#[actix_web::test]
+async fn search_ranks_ticker_matches_first() {
+    let app = TestApp::with_fixtures();
+    with_energy_companies(&app);
+    let service = app.service().await;
+
+    let body = search(&service, "aapl").await;
+    assert_eq!(body["results"][0]["ticker"], "AAPL");
+    assert_eq!(body["results"][0]["matched"], "ticker");
+
+    let body = search(&service, "chevron").await;
+    assert_eq!(tickers(&body["results"]), ["CVX"]);
+    assert_eq!(body["results"][0]["matched"], "name");
+}
+
+#[actix_web::test]
+async fn search_finds_business_summaries_and_typos() {
+    let app = TestApp::with_fixtures();
+    with_energy_companies(&app);
+    let service = app.service().await;
+
+    let body = search(&service, "crude").await;
+    assert_eq!(tickers(&body["results"]), ["COP", "XOM"]);
+    assert_eq!(body["results"][0]["matched"], "summary");
+
+    let body = search(&service, "exxom").await;
+    assert_eq!(body["results"][0]["ticker"], "XOM");
+    assert_eq!(body["results"][0]["matched"], "fuzzy");
+}
+
+#[actix_web::test]
+async fn typo_tolerance_needs_the_right_first_letter() {
+    let app = TestApp::with_fixtures();
+    with_energy_companies(&app);
+    let service = app.service().await;
+
+    let body = search(&service, "chevrin").await;
+    assert_eq!(tickers(&body["results"]), ["CVX"]);
+    assert_eq!(body["results"][0]["matched"], "fuzzy");
+
+    // Candidates are looked up by their first letter.
+    let body = search(&service, "shevron").await;
+    assert_eq!(body["results"], json!([]));
+}
+
+#[actix_web::test]
+async fn search_requires_a_query() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+
+    let (status, body) = call(&service, TestRequest::get().uri("/api/search?q=%20").to_request()).await;
+    assert_eq!(status, 400, "{}", body);
+    assert_eq!(error_message(&body, "bad_request"), "Missing search query");
+}
+
+#[actix_web::test]
+async fn sectors_group_companies_by_industry() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+
+    let (status, body) = call(&service, TestRequest::get().uri("/api/sectors").to_request()).await;
+    assert_eq!(status, 200, "{}", body);
+    let sectors = body.as_array().unwrap();
+    assert_eq!(sectors.len(), 1);
+    assert_eq!(sectors[0]["sector"], SECTOR);
+    assert_eq!(sectors[0]["company_count"], SOFTWARE_TICKERS.len() + HARDWARE_TICKERS.len());
+    let industries: Vec<&str> = sectors[0]["industries"].as_array().unwrap()
+        .iter()
+        .map(|industry| industry["industry"].as_str().unwrap())
+        .collect();
+    assert_eq!(industries, ["Hardware", "Software"]);
+}
+
+#[actix_web::test]
+async fn industries_of_an_unknown_sector_are_not_found() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+
+    let (status, body) = call(&service, TestRequest::get().uri("/api/sectors/Technology/industries").to_request()).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body.as_array().unwrap().len(), 2);
+
+    let (status, body) = call(&service, TestRequest::get().uri("/api/sectors/Utilities/industries").to_request()).await;
+    assert_eq!(status, 404, "{}", body);
+    assert_eq!(error_message(&body, "not_found"), "Unknown sector: Utilities");
+}
+
+#[actix_web::test]
+async fn industry_returns_skip_missing_and_malformed_series() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+
+    // Of the hardware companies only AAPL has a usable 1mo series: HPQ's is
+    // malformed and DELL has none.
+    let (status, body) = call(&service, TestRequest::get().uri("/api/sectors/Technology/industries").to_request()).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body[0]["industry"], "Hardware");
+    assert_eq!(body[0]["company_count"], 3);
+    let expected = (AAPL_CLOSES[AAPL_CLOSES.len() - 1] / AAPL_CLOSES[0] - 1.0) * 100.0;
+    assert!((body[0]["average_one_month_return"].as_f64().unwrap() - expected).abs() < 1e-9, "{}", body);
+}
+
+#[actix_web::test]
+async fn an_unreadable_series_leaves_only_its_company_out_of_returns() {
+    let app = TestApp::with_fixtures();
+    app.repositories.prices.set_unreadable("MSFT");
+    let service = app.service().await;
+
+    let (status, body) = call(&service, TestRequest::get().uri("/api/sectors").to_request()).await;
+    assert_eq!(status, 200, "{}", body);
+    let software = &body[0]["industries"][1];
+    assert_eq!(software["industry"], "Software");
+    assert_eq!(software["company_count"], SOFTWARE_TICKERS.len());
+    // Each software series rises by two from 100 + 10 * index; MSFT is index 0.
+    let returns: Vec<f64> = (1..SOFTWARE_TICKERS.len()).map(|index| 2.0 / (100.0 + 10.0 * index as f64) * 100.0).collect();
+    let expected = returns.iter().sum::<f64>() / returns.len() as f64;
+    assert!((software["average_one_month_return"].as_f64().unwrap() - expected).abs() < 1e-9, "{}", body);
+}
+
+#[actix_web::test]
+async fn companies_without_a_sector_are_unclassified() {
+    let app = TestApp::with_fixtures();
+    app.repositories.companies.insert(doc! { "ticker": "NOSEC", "name": "No Sector Inc.", "profile": { "industry": "Shell Companies" } });
+    app.repositories.companies.insert(doc! { "ticker": "BLANK", "name": "Blank Inc.", "profile": { "sector": "", "industry": "" } });
+    app.repositories.companies.insert(doc! { "ticker": "NULL", "name": "Null Inc.", "profile": { "sector": null } });
+    let service = app.service().await;
+
+    let (status, body) = call(&service, TestRequest::get().uri("/api/sectors").to_request()).await;
+    assert_eq!(status, 200, "{}", body);
+    let sectors: Vec<&str> = body.as_array().unwrap().iter().map(|sector| sector["sector"].as_str().unwrap()).collect();
+    assert_eq!(sectors, [SECTOR, "Unclassified"]);
+    assert_eq!(body[1]["company_count"], 3);
+
+    let (status, body) = call(&service, TestRequest::get().uri("/api/sectors/Unclassified/industries").to_request()).await;
+    assert_eq!(status, 200, "{}", body);
+    let industries: Vec<(&str, u64)> = body.as_array().unwrap()
+        .iter()
+        .map(|industry| (industry["industry"].as_str().unwrap(), industry["company_count"].as_u64().unwrap()))
+        .collect();
+    assert_eq!(industries, [("Shell Companies", 1), ("Unclassified", 2)]);
+}
+
+#[actix_web::test]
+async fn earnings_calendar_lists_reports_in_range_by_date() {
+    let app = TestApp::with_fixtures();
+    let reporting = |ticker: &str, day: u32| {
+        let date = Utc.with_ymd_and_hms(2030, 1, day, 21, 0, 0).unwrap().timestamp();
+        doc! {
+            "ticker": ticker,
+            "name": format!("{} Inc.", ticker),
+            "profile": { "sector": ENERGY },
+            "earnings": { "nextReportDate": date, "nextEpsEstimate": { "raw": 1.5 } },
+        }
+    };
+    app.repositories.companies.insert(reporting("LATE", 20));
+    app.repositories.companies.insert(reporting("SOON", 10));
+    app.repositories.companies.insert(reporting("AFTER", 31));
+    let service = app.service().await;
+
+    let request = TestRequest::get().uri("/api/earnings/calendar?from=2030-01-01&to=2030-01-20").to_request();
+    let (status, body) = call(&service, request).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(tickers(&body), ["SOON", "LATE"]);
+    assert_eq!(body[0]["eps_estimate"], 1.5);
+
+    let request = TestRequest::get().uri("/api/earnings/calendar?from=2030-01-01&to=2030-01-20&sector=Technology").to_request();
+    let (status, body) = call(&service, request).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body, json!([]));
+
+    let request = TestRequest::get().uri("/api/earnings/calendar?from=2030-01-20&to=2030-01-01").to_request();
+    let (status, body) = call(&service, request).await;
+    assert_eq!(status, 400, "{}", body);
+    assert_eq!(error_message(&body, "bad_request"), "'to' must not be before 'from'");
+}
+
+#[actix_web::test]
+async fn earnings_calendar_reads_report_dates_stored_as_dates() {
+    let app = TestApp::with_fixtures();
+    let reporting = |ticker: &str, date: Bson| doc! {
+        "ticker": ticker,
+        "name": format!("{} Inc.", ticker),
+        "earnings": {
+            "nextReportDate": date,
+            "history": [{ "reportDate": BsonDateTime::from_millis(1_893_456_000_000), "epsActual": { "raw": 2.0 } }],
+        },
+    };
+    let day = |day: u32| Utc.with_ymd_and_hms(2030, 1, day, 21, 0, 0).unwrap();
+    app.repositories.companies.insert(reporting("DATE", Bson::DateTime(BsonDateTime::from_millis(day(5).timestamp_millis()))));
+    app.repositories.companies.insert(reporting("SECS", Bson::Int64(day(3).timestamp())));
+    app.repositories.companies.insert(reporting("TEXT", Bson::String("2030-01-04".to_string())));
+    let service = app.service().await;
+
+    let request = TestRequest::get().uri("/api/earnings/calendar?from=2030-01-01&to=2030-01-20").to_request();
+    let (status, body) = call(&service, request).await;
+    assert_eq!(status, 200, "{}", body);
+    // A report date in neither form is skipped rather than guessed.
+    assert_eq!(tickers(&body), ["SECS", "DATE"]);
+    assert_eq!(body[1]["report_date"], "2030-01-05T21:00:00Z");
+    assert_eq!(body[1]["last_eps_actual"], 2.0);
+}
+
+#[actix_web::test]
+async fn data_health_reports_every_series() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+
+    let (status, body) = call(&service, TestRequest::get().uri("/api/data-health/AAPL").to_request()).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["healthy"], true);
+    assert_eq!(body["series"].as_array().unwrap().len(), 2);
+
+    // HPQ has fewer closes than timestamps.
+    let (status, body) = call(&service, TestRequest::get().uri("/api/data-health/HPQ").to_request()).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["healthy"], false);
+    assert!(body["series"][0]["errors"].as_u64().unwrap() > 0, "{}", body);
+}
+
+#[actix_web::test]
+async fn data_health_includes_quarantined_series() {
+    let app = TestApp::with_fixtures();
+    let issue = DataIssue {
+        kind: IssueKind::LengthMismatch,
+        severity: IssueSeverity::Error,
+        index: None,
+        message: "closes has 2 points, timestamps has 3".to_string(),
+    };
+    let quarantined = |period: &str, seconds: i64| QuarantinedDocument {
+        id: None,
+        ticker: "GONE".to_string(),
+        period: Some(period.to_string()),
+        interval: Some("1d".to_string()),
+        document: series(period, "1d", &[1.0, 2.0]),
+        issues: vec![issue.clone()],
+        quarantined_at: Utc.timestamp_opt(seconds, 0).unwrap(),
+    };
+    app.repositories.prices.insert_quarantined(quarantined("1mo", 1_700_000_000));
+    app.repositories.prices.insert_quarantined(quarantined("5d", 1_700_100_000));
+    let service = app.service().await;
+
+    let (status, body) = call(&service, TestRequest::get().uri("/api/data-health/GONE").to_request()).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["series"], json!([]));
+    let periods: Vec<&str> = body["quarantined"].as_array().unwrap().iter().map(|doc| doc["period"].as_str().unwrap()).collect();
+    assert_eq!(periods, ["5d", "1mo"]);
+
+    let (status, body) = call(&service, TestRequest::get().uri("/api/data-health/NONE").to_request()).await;
+    assert_eq!(status, 404, "{}", body);
+    assert_eq!(error_message(&body, "not_found"), "No price data for ticker NONE");
+}
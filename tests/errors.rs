@@ -0,0 +1,53 @@
+//! Status codes and JSON envelopes produced by `AppError`.
+
+use actix_web::body::to_bytes;
+use actix_web::ResponseError;
+use trading_sim_server::error::{AppError, ErrorResponse, FieldError};
+
+async fn render(err: AppError) -> (u16, ErrorResponse) {
+    let response = err.error_response();
+    let status = response.status().as_u16();
+    let body = to_bytes(response.into_body()).await.expect("error body");
+    (status, serde_json::from_slice(&body).expect("error envelope"))
+}
+
+#[actix_web::test]
+async fn variants_map_to_status_and_code() {
+    let cases = [
+        (AppError::MalformedRequest("expected value".to_string()), 400, "malformed_request"),
+        (AppError::BadRequest("Unknown field: x".to_string()), 400, "bad_request"),
+        (AppError::InvalidId("user_id"), 400, "invalid_id"),
+        (AppError::Validation(Vec::new()), 422, "validation_failed"),
+        (AppError::InvalidCredentials, 401, "invalid_credentials"),
+        (AppError::Unauthorized("Missing session token".to_string()), 401, "unauthorized"),
+        (AppError::UserNotFound, 404, "user_not_found"),
+        (AppError::NotFound("Unknown sector: x".to_string()), 404, "not_found"),
+        (AppError::EmailTaken, 409, "email_taken"),
+        (AppError::Unavailable("warming up".to_string()), 503, "unavailable"),
+        (AppError::Internal("Password hashing failed".to_string()), 500, "internal_error"),
+    ];
+    for (err, expected_status, expected_code) in cases {
+        let message = err.to_string();
+        let (status, body) = render(err).await;
+        assert_eq!(status, expected_status, "{}", expected_code);
+        assert_eq!(body.error.code, expected_code);
+        assert_eq!(body.error.message, message);
+    }
+}
+
+#[actix_web::test]
+async fn database_errors_hide_their_details() {
+    let err = AppError::from(mongodb::error::Error::from(std::io::Error::other("connection reset by 10.0.0.7:27017")));
+    let (status, body) = render(err).await;
+    assert_eq!(status, 500);
+    assert_eq!(body.error.code, "database_error");
+    assert_eq!(body.error.message, "Internal server error");
+}
+
+#[actix_web::test]
+async fn validation_errors_list_their_fields() {
+    let field = FieldError { field: "filters[0]".to_string(), code: "empty_range".to_string(), message: "min must not be greater than max".to_string() };
+    let (status, body) = render(AppError::Validation(vec![field.clone()])).await;
+    assert_eq!(status, 422);
+    assert_eq!(body.error.fields, [field]);
+}
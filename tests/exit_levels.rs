@@ -0,0 +1,88 @@
+//! Closing trades at their take-profit and stop-loss levels, and the
+//! events and balance changes that follow.
+
+mod common;
+
+use mongodb::bson::oid::ObjectId;
+use trading_sim_server::models::trade_models::{Trade, TradeStatus};
+use trading_sim_server::services::trade_service::{closing_proceeds, execute_exit_levels, exit_trigger, ExitTrigger};
+use common::{user, TestApp};
+
+fn trade(ticker: &str, position: &str, take_profit: Option<f64>, stop_loss: Option<f64>) -> Trade {
+    Trade {
+        id: None,
+        ticker: ticker.to_string(),
+        position: position.to_string(),
+        quantity: 5,
+        price: 180.0,
+        take_profit,
+        stop_loss,
+        status: TradeStatus::InProgress,
+        user_id: ObjectId::new(),
+        amount: 900.0,
+        trade_type: "market".to_string(),
+        exit_price: None,
+        closed_at: None,
+        applied_splits: vec![],
+    }
+}
+
+#[test]
+fn longs_exit_above_take_profit_and_below_stop_loss() {
+    let long = trade("AAPL", "long", Some(200.0), Some(170.0));
+    assert_eq!(exit_trigger(&long, 200.0), Some(ExitTrigger::TakeProfit(200.0)));
+    assert_eq!(exit_trigger(&long, 169.5), Some(ExitTrigger::StopLoss(170.0)));
+    assert_eq!(exit_trigger(&long, 185.0), None);
+    assert_eq!(exit_trigger(&trade("AAPL", "long", None, None), 1000.0), None);
+}
+
+#[test]
+fn shorts_exit_the_other_way_round() {
+    let short = trade("AAPL", "sell", Some(160.0), Some(190.0));
+    assert_eq!(exit_trigger(&short, 159.0), Some(ExitTrigger::TakeProfit(160.0)));
+    assert_eq!(exit_trigger(&short, 190.0), Some(ExitTrigger::StopLoss(190.0)));
+    assert_eq!(exit_trigger(&short, 175.0), None);
+    // Levels on the wrong side are both reached; the stop-loss wins.
+    assert_eq!(exit_trigger(&trade("AAPL", "short", Some(200.0), Some(150.0)), 175.0), Some(ExitTrigger::StopLoss(150.0)));
+}
+
+#[test]
+fn proceeds_return_the_amount_with_the_profit_or_loss() {
+    assert_eq!(closing_proceeds(&trade("AAPL", "long", None, None), 186.0), 930.0);
+    assert_eq!(closing_proceeds(&trade("AAPL", "short", None, None), 186.0), 870.0);
+}
+
+#[actix_web::test]
+async fn reached_levels_close_the_trade_and_credit_the_user_once() {
+    let app = TestApp::with_fixtures();
+    let repositories = app.repositories.repositories();
+    let user_id = repositories.users.insert(user(1000.0)).await.unwrap();
+    let insert = |trade: Trade| repositories.trades.insert(Trade { user_id, ..trade });
+
+    // AAPL last closed at 186.
+    let taken = insert(trade("AAPL", "long", Some(185.0), Some(170.0))).await.unwrap();
+    let stopped = insert(trade("AAPL", "short", None, Some(185.0))).await.unwrap();
+    let open = insert(trade("MSFT", "long", Some(200.0), None)).await.unwrap();
+    // DELL has no prices, so its levels cannot be checked.
+    let unpriced = insert(trade("DELL", "long", None, Some(1000.0))).await.unwrap();
+
+    assert_eq!(execute_exit_levels(&repositories, &app.feed).await.unwrap(), 2);
+    assert_eq!(execute_exit_levels(&repositories, &app.feed).await.unwrap(), 0);
+
+    let trades = app.repositories.trades.all();
+    let state = |id: ObjectId| trades.iter().find(|trade| trade.id == Some(id)).map(|trade| (trade.status, trade.exit_price)).unwrap();
+    assert_eq!(state(taken), (TradeStatus::Closed, Some(186.0)));
+    assert_eq!(state(stopped), (TradeStatus::Closed, Some(186.0)));
+    assert_eq!(state(open), (TradeStatus::InProgress, None));
+    assert_eq!(state(unpriced), (TradeStatus::InProgress, None));
+    assert_eq!(app.repositories.users.find_by_id(user_id).unwrap().balance, 1000.0 + 930.0 + 870.0);
+
+    let events = app.feed.subscribe(&user_id.to_hex(), Some(0)).0;
+    let names: Vec<&str> = events.iter().map(|event| event.kind.name()).collect();
+    assert_eq!(names, [
+        "order_status", "take_profit_triggered", "balance_update",
+        "order_status", "stop_loss_triggered", "balance_update",
+    ]);
+    let triggered = serde_json::to_value(&events[1]).unwrap();
+    assert_eq!((triggered["trigger_price"].as_f64(), triggered["exit_price"].as_f64()), (Some(185.0), Some(186.0)));
+}
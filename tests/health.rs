@@ -0,0 +1,211 @@
+//! Liveness and readiness probes against the in-memory backend.
+
+mod common;
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use actix_web::test::TestRequest;
+use async_trait::async_trait;
+use chrono::Utc;
+use mongodb::bson::{doc, Document};
+use serde_json::Value;
+use trading_sim_server::models::data_quality_models::QuarantinedDocument;
+use trading_sim_server::models::health_models::HealthStatus;
+use trading_sim_server::models::price_bar_models::PriceBar;
+use trading_sim_server::repositories::memory::InMemoryRepositories;
+use trading_sim_server::repositories::{LatestPrice, PriceRepository, RepositoryResult};
+use trading_sim_server::services::health_service::{readiness, HealthMonitor};
+use common::{call, TestApp};
+
+fn live() -> actix_http::Request {
+    TestRequest::get().uri("/health/live").to_request()
+}
+
+fn ready() -> actix_http::Request {
+    TestRequest::get().uri("/health/ready").to_request()
+}
+
+/// Fixture prices date from 2023; this adds a bar from now.
+fn with_fresh_prices(app: &TestApp) {
+    let now = Utc::now().timestamp();
+    app.repositories.prices.insert_series("AAPL", doc! {
+        "period": "1d",
+        "interval": "1m",
+        "timestamps": [now - 60, now],
+        "closes": [186.0, 186.5],
+    });
+}
+
+fn job<'a>(body: &'a Value, name: &str) -> &'a Value {
+    body["checks"]["workers"]["jobs"].as_array().expect("jobs array")
+        .iter()
+        .find(|job| job["name"] == name)
+        .unwrap_or_else(|| panic!("no job {} in {}", name, body))
+}
+
+#[actix_web::test]
+async fn liveness_passes_without_dependencies() {
+    let app = TestApp::with_fixtures();
+    app.repositories.database.set_reachable(false);
+    let service = app.service().await;
+
+    let (status, body) = call(&service, live()).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["status"], "pass");
+    assert_eq!(body["version"], env!("CARGO_PKG_VERSION"));
+    assert!(body["uptime_seconds"].as_i64().unwrap() >= 0);
+}
+
+#[actix_web::test]
+async fn ready_when_every_check_passes() {
+    let app = TestApp::with_fixtures();
+    with_fresh_prices(&app);
+    app.health.register("corporate_action_processor", Duration::from_secs(900));
+    app.health.record("corporate_action_processor", &Ok::<_, String>(()));
+    let service = app.service().await;
+
+    let (status, body) = call(&service, ready()).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["status"], "pass");
+    assert_eq!(body["checks"]["mongo"]["status"], "pass");
+    assert!(body["checks"]["mongo"]["latency_ms"].is_u64());
+    assert_eq!(body["checks"]["data"]["status"], "pass");
+    assert!(body["checks"]["data"]["age_seconds"].as_i64().unwrap() < 3600);
+    let processor = job(&body, "corporate_action_processor");
+    assert_eq!(processor["state"], "ok");
+    assert_eq!(processor["interval_seconds"], 900);
+}
+
+#[actix_web::test]
+async fn stale_prices_warn_but_stay_ready() {
+    let mut app = TestApp::with_fixtures();
+    let service = app.service().await;
+
+    let (status, body) = call(&service, ready()).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["status"], "warn");
+    let data = &body["checks"]["data"];
+    assert_eq!(data["status"], "warn");
+    assert_eq!(data["max_age_seconds"], 96 * 3600);
+    assert!(data["age_seconds"].as_i64().unwrap() > 96 * 3600);
+
+    // The threshold comes from `[health] max_data_age_hours`.
+    app.config.health.max_data_age_hours = 100 * 365 * 24;
+    let service = app.service().await;
+    let (status, body) = call(&service, ready()).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["checks"]["data"]["status"], "pass");
+}
+
+#[actix_web::test]
+async fn unreachable_database_is_not_ready() {
+    let app = TestApp::with_fixtures();
+    with_fresh_prices(&app);
+    app.repositories.database.set_reachable(false);
+    let service = app.service().await;
+
+    let (status, body) = call(&service, ready()).await;
+    assert_eq!(status, 503, "{}", body);
+    assert_eq!(body["status"], "fail");
+    assert_eq!(body["checks"]["mongo"]["status"], "fail");
+    assert_eq!(body["checks"]["mongo"]["message"], "Database unreachable");
+    assert_eq!(body["checks"]["data"]["message"], "Not checked while the database is unreachable");
+}
+
+#[actix_web::test]
+async fn failing_jobs_degrade_and_stalled_jobs_fail() {
+    let app = TestApp::with_fixtures();
+    with_fresh_prices(&app);
+    app.health.register("market_overview_refresh", Duration::from_secs(300));
+    app.health.record("market_overview_refresh", &Err::<(), _>("timed out"));
+    app.health.record("market_overview_refresh", &Err::<(), _>("timed out"));
+    let service = app.service().await;
+
+    let (status, body) = call(&service, ready()).await;
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["checks"]["workers"]["status"], "warn");
+    let refresh = job(&body, "market_overview_refresh");
+    assert_eq!(refresh["state"], "failing");
+    assert_eq!(refresh["consecutive_failures"], 2);
+    assert!(refresh["last_failure"].is_string());
+
+    app.health.register("cache_invalidation", Duration::from_millis(1));
+    actix_web::rt::time::sleep(Duration::from_millis(20)).await;
+    let (status, body) = call(&service, ready()).await;
+    assert_eq!(status, 503, "{}", body);
+    assert_eq!(body["checks"]["workers"]["status"], "fail");
+    assert_eq!(job(&body, "cache_invalidation")["state"], "stalled");
+}
+
+/// Prices whose newest-timestamp lookup takes `delay`.
+struct SlowPrices {
+    delay: Duration,
+    newest: i64,
+    lookups: AtomicUsize,
+}
+
+#[async_trait]
+impl PriceRepository for SlowPrices {
+    async fn price_documents(&self, _ticker: &str) -> RepositoryResult<Vec<Document>> {
+        Ok(Vec::new())
+    }
+
+    async fn series_many(&self, _tickers: &[String], _period: &str, _interval: &str) -> RepositoryResult<HashMap<String, Document>> {
+        Ok(HashMap::new())
+    }
+
+    async fn latest_price(&self, _ticker: &str) -> RepositoryResult<Option<LatestPrice>> {
+        Ok(None)
+    }
+
+    async fn split_history(&self, _ticker: &str) -> RepositoryResult<Vec<(i64, f64)>> {
+        Ok(Vec::new())
+    }
+
+    async fn split_histories(&self, _tickers: &[String]) -> RepositoryResult<HashMap<String, Vec<(i64, f64)>>> {
+        Ok(HashMap::new())
+    }
+
+    async fn newest_timestamp(&self) -> RepositoryResult<Option<i64>> {
+        self.lookups.fetch_add(1, Ordering::SeqCst);
+        tokio::time::sleep(self.delay).await;
+        Ok(Some(self.newest))
+    }
+
+    async fn quarantined(&self, _ticker: &str) -> RepositoryResult<Vec<QuarantinedDocument>> {
+        Ok(Vec::new())
+    }
+
+    async fn merge_bars(&self, _ticker: &str, _interval: &str, _bars: Vec<PriceBar>) -> RepositoryResult<usize> {
+        Ok(0)
+    }
+
+    async fn record_series(&self, _ticker: &str, _period: &str, _interval: &str, _bars: usize) -> RepositoryResult<bool> {
+        Ok(false)
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn a_slow_price_lookup_keeps_running_after_the_probe_gives_up() {
+    let slow = Arc::new(SlowPrices { delay: Duration::from_secs(8), newest: Utc::now().timestamp(), lookups: AtomicUsize::new(0) });
+    let prices: Arc<dyn PriceRepository> = slow.clone();
+    let database = InMemoryRepositories::new().database;
+    let monitor = HealthMonitor::new();
+    let max_age = Duration::from_secs(3600);
+
+    // The probe stops waiting after five seconds; the lookup carries on.
+    let report = readiness(&monitor, database.as_ref(), &prices, max_age).await;
+    assert_eq!(report.checks.data.status, HealthStatus::Warn);
+    assert_eq!(report.checks.data.message.as_deref(), Some("Newest price lookup timed out after 5s"));
+
+    // The next probe waits on the same lookup, which now finishes in time.
+    let report = readiness(&monitor, database.as_ref(), &prices, max_age).await;
+    assert_eq!(report.checks.data.status, HealthStatus::Pass, "{:?}", report.checks.data);
+
+    // And later probes reuse its result.
+    let report = readiness(&monitor, database.as_ref(), &prices, max_age).await;
+    assert_eq!(report.checks.data.status, HealthStatus::Pass);
+    assert_eq!(slow.lookups.load(Ordering::SeqCst), 1);
+}
@@ -0,0 +1,232 @@
+//! Column names, number parsing, row validation and price writes in the
+//! import tool.
+
+use mongodb::bson::doc;
+use trading_sim_server::import::fundamentals::build_updates;
+use trading_sim_server::import::prices::{build_series, write_series, SeriesDefaults};
+use trading_sim_server::import::readers::{read_csv, read_json};
+use trading_sim_server::import::{normalize_column, ImportReport, RawValue, Record};
+use trading_sim_server::repositories::memory::InMemoryRepositories;
+use trading_sim_server::repositories::PriceRepository;
+
+fn csv(contents: &str) -> Vec<Record> {
+    read_csv(contents.as_bytes()).unwrap()
+}
+
+fn number(text: &str) -> Result<Option<f64>, String> {
+    let mut record = Record::new(2);
+    record.insert("close", RawValue::Text(text.to_string()));
+    record.number("close")
+}
+
+fn daily() -> SeriesDefaults {
+    SeriesDefaults { period: Some("1mo".to_string()), interval: Some("1d".to_string()) }
+}
+
+fn errors(report: &ImportReport) -> Vec<(usize, &str)> {
+    report.errors.iter().map(|err| (err.row, err.message.as_str())).collect()
+}
+
+#[test]
+fn columns_are_snake_cased_and_aliased() {
+    assert_eq!(normalize_column("forwardPE"), "forward_pe");
+    assert_eq!(normalize_column("marketCap"), "market_cap");
+    assert_eq!(normalize_column("Trailing EPS"), "trailing_eps");
+    assert_eq!(normalize_column(" dividend-yield "), "dividend_yield");
+    assert_eq!(normalize_column("Adj Close"), "adjusted_close");
+    assert_eq!(normalize_column("Symbol"), "ticker");
+    assert_eq!(normalize_column("Date"), "timestamp");
+    assert_eq!(normalize_column("company.name"), "name");
+}
+
+#[test]
+fn numbers_accept_unambiguous_thousands_separators() {
+    assert_eq!(number("1.5"), Ok(Some(1.5)));
+    assert_eq!(number(" -42 "), Ok(Some(-42.0)));
+    assert_eq!(number("1,500.25"), Ok(Some(1500.25)));
+    assert_eq!(number("1,500,000"), Ok(Some(1_500_000.0)));
+    assert_eq!(number("-12,345.5"), Ok(Some(-12_345.5)));
+    assert_eq!(number(""), Ok(None));
+}
+
+#[test]
+fn numbers_with_ambiguous_or_malformed_commas_are_rejected() {
+    assert!(number("1,5").unwrap_err().contains("ambiguous"));
+    assert!(number("1,500").unwrap_err().contains("ambiguous"));
+    for text in ["1,5.0", "12,34,567", "1,500,00", ",500", "1,,500.0", "1,500.2,5", "abc"] {
+        assert_eq!(number(text), Err(format!("close is not a number: '{}'", text)), "{:?}", text);
+    }
+    assert_eq!(number("inf"), Err("close is not finite".to_string()));
+}
+
+#[test]
+fn rows_are_grouped_into_sorted_series() {
+    let records = csv("\
+Symbol,Date,Open,High,Low,Close,Volume
+msft,2024-01-03,11,12,10,11.5,2000
+AAPL,2024-01-02,10,12,9,11,1000
+MSFT,2024-01-02,10,11,9,10.5,
+");
+    let mut report = ImportReport::default();
+    let series = build_series(&records, &daily(), &mut report);
+
+    assert!(report.errors.is_empty(), "{:?}", report.errors);
+    assert_eq!((report.rows_read, report.rows_rejected, report.documents), (3, 0, 2));
+    assert_eq!(report.tickers, ["AAPL", "MSFT"]);
+
+    let msft = &series[1];
+    assert_eq!((msft.ticker.as_str(), msft.period.as_str(), msft.interval.as_str()), ("MSFT", "1mo", "1d"));
+    let bars = msft.to_bars();
+    assert_eq!(bars.iter().map(|bar| bar.close).collect::<Vec<_>>(), [10.5, 11.5]);
+    // A missing volume is stored as zero.
+    assert_eq!(bars[0].volume, 0);
+    assert_eq!(bars[0].timestamp.timestamp_millis(), 1_704_153_600_000);
+    assert_eq!(bars[0].meta.interval, "1d");
+}
+
+#[test]
+fn invalid_bars_are_reported_by_row_and_left_out() {
+    let records = csv("\
+ticker,timestamp,open,high,low,close,volume
+AAPL,2024-01-02,10,12,9,11,1000
+AAPL,2024-01-03,10,12,9,11,
+AAPL,2024-01-04,0,12,9,11,1
+AAPL,2024-01-05,10,9,12,11,1
+AAPL,2024-01-06,13,14,9,15,1
+AAPL,2024-01-07,10,12,9,11,1.5
+AAPL,2024-01-08,10,12,9,,1
+AAPL,yesterday,10,12,9,11,1
+AAPL,2024-01-09,\"1,5\",12,9,11,1
+AAPL,2024-01-02,10,12,9,11,1
+,2024-01-10,10,12,9,11,1
+");
+    let mut report = ImportReport::default();
+    let series = build_series(&records, &daily(), &mut report);
+
+    assert_eq!(errors(&report), [
+        (4, "prices must be positive"),
+        (5, "high 9 is below low 12"),
+        (6, "open and close must lie between low and high"),
+        (7, "volume must be a non-negative whole number, got 1.5"),
+        (8, "close is missing"),
+        (9, "timestamp is not a timestamp: 'yesterday'"),
+        (10, "open uses an ambiguous ',' separator; write it without thousands separators and with '.' as the decimal point: '1,5'"),
+        (11, "duplicate timestamp 1704153600 for AAPL 1mo/1d (first seen on row 2)"),
+        (12, "ticker is missing"),
+    ]);
+    assert_eq!((report.rows_read, report.rows_rejected, report.documents), (11, 9, 1));
+    assert_eq!(series[0].to_bars().len(), 2);
+}
+
+#[test]
+fn series_need_a_period_and_interval() {
+    let records = csv("ticker,timestamp,open,high,low,close\nAAPL,2024-01-02,10,12,9,11\n");
+    let mut report = ImportReport::default();
+    assert!(build_series(&records, &SeriesDefaults::default(), &mut report).is_empty());
+    assert_eq!(errors(&report), [(2, "period is missing; add a column or pass --period")]);
+
+    let records = csv("ticker,timestamp,open,high,low,close,period,interval\nAAPL,1704153600,10,12,9,11,5d,1h\n");
+    let mut report = ImportReport::default();
+    let series = build_series(&records, &SeriesDefaults::default(), &mut report);
+    assert_eq!((series[0].period.as_str(), series[0].interval.as_str()), ("5d", "1h"));
+}
+
+/// Imports `rows` of daily MSFT bars as the 1mo/1d series.
+async fn import_daily(prices: &dyn PriceRepository, rows: &str) -> ImportReport {
+    let records = csv(&format!("ticker,timestamp,open,high,low,close\n{}", rows));
+    let mut report = ImportReport::default();
+    let series = build_series(&records, &daily(), &mut report);
+    write_series(prices, &series, &mut report).await.unwrap();
+    report
+}
+
+async fn served_closes(prices: &dyn PriceRepository) -> Vec<f64> {
+    let documents = prices.price_documents("MSFT").await.unwrap();
+    assert_eq!(documents.len(), 1);
+    documents[0].get_array("closes").unwrap().iter().map(|close| close.as_f64().unwrap()).collect()
+}
+
+#[actix_web::test]
+async fn appending_a_bar_keeps_the_whole_window_served() {
+    let repositories = InMemoryRepositories::new();
+    let prices = repositories.prices.as_ref();
+
+    let month: String = (1..=5).map(|day| format!("MSFT,2024-01-0{},10,20,9,1{}\n", day, day)).collect();
+    let report = import_daily(prices, &month).await;
+    assert_eq!((report.inserted, report.updated), (1, 0));
+    assert_eq!(served_closes(prices).await, [11.0, 12.0, 13.0, 14.0, 15.0]);
+
+    let report = import_daily(prices, "MSFT,2024-01-08,10,12,9,11.5\n").await;
+    assert_eq!((report.inserted, report.updated), (0, 1));
+    // Still five bars: the window moves forward instead of shrinking to the import.
+    assert_eq!(served_closes(prices).await, [12.0, 13.0, 14.0, 15.0, 11.5]);
+}
+
+#[actix_web::test]
+async fn reimported_bars_replace_the_stored_ones() {
+    let repositories = InMemoryRepositories::new();
+    let prices = repositories.prices.as_ref();
+    import_daily(prices, "MSFT,2024-01-01,10,12,9,11\nMSFT,2024-01-02,10,12,9,11\n").await;
+
+    let bars = |close: f64| vec![mongodb::bson::from_document(doc! {
+        "timestamp": mongodb::bson::DateTime::from_millis(1_704_153_600_000),
+        "meta": { "ticker": "MSFT", "interval": "1d" },
+        "open": 10.0, "high": 12.0, "low": 9.0, "close": close, "volume": 0_i64,
+    }).unwrap()];
+    // Only bars that differ from the stored ones count as written.
+    assert_eq!(prices.merge_bars("MSFT", "1d", bars(11.0)).await.unwrap(), 0);
+    assert_eq!(prices.merge_bars("MSFT", "1d", bars(11.75)).await.unwrap(), 1);
+    assert_eq!(served_closes(prices).await, [11.0, 11.75]);
+}
+
+#[test]
+fn company_rows_set_only_the_columns_they_carry() {
+    let records = read_json(r#"[
+        {"symbol": "aapl", "companyName": "Apple Inc.", "sector": "Technology", "forwardPE": 28.456,
+         "dividendYield": 0.0052, "marketCap": 2950000000000, "sharesOutstanding": "15,441,900,000"},
+        {"ticker": "MSFT", "beta": "0.9"}
+    ]"#).unwrap();
+    let mut report = ImportReport::default();
+    let updates = build_updates(&records, &mut report);
+
+    assert!(report.errors.is_empty(), "{:?}", report.errors);
+    assert_eq!(report.tickers, ["AAPL", "MSFT"]);
+    assert_eq!(updates[0].update(), doc! { "$set": {
+        "ticker": "AAPL",
+        "name": "Apple Inc.",
+        "profile.sector": "Technology",
+        "financials.dividendYield": { "raw": 0.0052, "fmt": "0.52%" },
+        "financials.forwardPE": { "raw": 28.456, "fmt": "28.46" },
+        "financials.marketCap": { "raw": 2.95e12, "fmt": "2.95T" },
+        "keyStatistics.sharesOutstanding": { "raw": 15_441_900_000_i64, "fmt": "15.44B" },
+    } });
+    assert_eq!(updates[1].update(), doc! { "$set": {
+        "ticker": "MSFT",
+        "financials.beta": { "raw": 0.9, "fmt": "0.90" },
+    } });
+}
+
+#[test]
+fn invalid_company_rows_are_reported_by_row() {
+    let records = csv("\
+ticker,beta,shares_outstanding,industry
+AAPL,1.2,,
+,1.0,,
+MSFT,,,
+GOOG,\"1,2\",,
+AMZN,,12.5,
+aapl,,,Hardware
+");
+    let mut report = ImportReport::default();
+    let updates = build_updates(&records, &mut report);
+
+    assert_eq!(errors(&report), [
+        (3, "ticker is missing"),
+        (4, "row has no recognised company fields"),
+        (5, "beta uses an ambiguous ',' separator; write it without thousands separators and with '.' as the decimal point: '1,2'"),
+        (6, "shares_outstanding must be a non-negative whole number"),
+        (7, "duplicate row for AAPL"),
+    ]);
+    assert_eq!((report.rows_read, report.rows_rejected, report.documents), (6, 5, 1));
+    assert_eq!(updates[0].ticker, "AAPL");
+}
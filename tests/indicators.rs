@@ -0,0 +1,134 @@
+//! Indicator formulas against hand-computed values, and the checks the
+//! indicators endpoint runs on its windows.
+
+mod common;
+
+use actix_web::test::TestRequest;
+use trading_sim_server::services::indicator_service::{atr, bollinger, ema, macd, rsi, sma, stochastic, vwap};
+use common::{call, error_message, series, TestApp};
+
+fn assert_series(actual: &[Option<f64>], expected: &[Option<f64>]) {
+    assert_eq!(actual.len(), expected.len(), "{:?}", actual);
+    for (a, e) in actual.iter().zip(expected) {
+        match (a, e) {
+            (Some(a), Some(e)) => assert!((a - e).abs() < 1e-9, "{:?} != {:?}", actual, expected),
+            _ => assert_eq!(a, e, "{:?} != {:?}", actual, expected),
+        }
+    }
+}
+
+#[test]
+fn sma_averages_each_full_window() {
+    assert_series(&sma(&[1.0, 2.0, 3.0, 4.0, 5.0], 3), &[None, None, Some(2.0), Some(3.0), Some(4.0)]);
+    assert_series(&sma(&[1.0, 2.0], 3), &[None, None]);
+    assert_series(&sma(&[1.0, 2.0], 0), &[None, None]);
+}
+
+#[test]
+fn ema_is_seeded_with_the_first_average() {
+    // alpha = 2 / (3 + 1) = 0.5 after a seed of (2 + 4 + 6) / 3.
+    assert_series(&ema(&[2.0, 4.0, 6.0, 8.0, 12.0], 3), &[None, None, Some(4.0), Some(6.0), Some(9.0)]);
+}
+
+#[test]
+fn rsi_uses_wilder_smoothing() {
+    // Changes +1, -1, +2, +1: averages 0.5/0.5, then 1.25/0.25 and 1.125/0.125.
+    let values = rsi(&[10.0, 11.0, 10.0, 12.0, 13.0], 2);
+    assert_series(&values, &[None, None, Some(50.0), Some(100.0 - 100.0 / 6.0), Some(90.0)]);
+    // No losses at all reads as 100.
+    assert_series(&rsi(&[1.0, 2.0, 3.0], 2), &[None, None, Some(100.0)]);
+}
+
+#[test]
+fn macd_subtracts_the_slow_ema_and_smooths_the_signal() {
+    let series = macd(&[2.0, 4.0, 6.0, 8.0, 12.0], 2, 3, 2);
+    // EMA(2) is 3, 5, 7, 31/3 and EMA(3) is 4, 6, 9.
+    assert_series(&series.macd, &[None, None, Some(1.0), Some(1.0), Some(4.0 / 3.0)]);
+    assert_series(&series.signal, &[None, None, None, Some(1.0), Some(11.0 / 9.0)]);
+    assert_series(&series.histogram, &[None, None, None, Some(0.0), Some(1.0 / 9.0)]);
+}
+
+#[test]
+fn bollinger_bands_use_the_population_deviation() {
+    let bands = bollinger(&[1.0, 3.0, 3.0, 7.0], 2, 2.0);
+    assert_series(&bands.middle, &[None, Some(2.0), Some(3.0), Some(5.0)]);
+    assert_series(&bands.upper, &[None, Some(4.0), Some(3.0), Some(9.0)]);
+    assert_series(&bands.lower, &[None, Some(0.0), Some(3.0), Some(1.0)]);
+}
+
+#[test]
+fn atr_counts_gaps_from_the_previous_close() {
+    // True ranges 2, 3 and 5; the last bar gaps up from a close of 11.
+    let values = atr(&[10.0, 12.0, 16.0], &[8.0, 9.0, 14.0], &[9.0, 11.0, 15.0], 2);
+    assert_series(&values, &[None, Some(2.5), Some(3.75)]);
+}
+
+#[test]
+fn vwap_accumulates_from_the_first_traded_bar() {
+    let values = vwap(&[11.0, 11.0, 13.0], &[9.0, 9.0, 11.0], &[10.0, 10.0, 12.0], &[0, 100, 300]);
+    assert_series(&values, &[None, Some(10.0), Some(11.5)]);
+}
+
+#[test]
+fn stochastic_places_the_close_in_the_window_range() {
+    let series = stochastic(&[10.0, 12.0, 11.0, 14.0], &[8.0, 9.0, 9.0, 10.0], &[9.0, 11.0, 10.0, 13.0], 2, 2);
+    assert_series(&series.k, &[None, Some(75.0), Some(100.0 / 3.0), Some(80.0)]);
+    assert_series(&series.d, &[None, None, Some(325.0 / 6.0), Some(170.0 / 3.0)]);
+
+    // A window without any range reads as the midpoint.
+    let flat = stochastic(&[5.0, 5.0], &[5.0, 5.0], &[5.0, 5.0], 2, 1);
+    assert_series(&flat.k, &[None, Some(50.0)]);
+}
+
+fn indicators(query: &str) -> actix_http::Request {
+    TestRequest::get().uri(&format!("/api/indicators/AAPL?{}", query)).to_request()
+}
+
+#[actix_web::test]
+async fn macd_windows_must_have_a_faster_fast_line() {
+    let app = TestApp::with_fixtures();
+    let service = app.service().await;
+
+    let (status, body) = call(&service, indicators("macd_fast=26&macd_slow=12")).await;
+    assert_eq!(status, 400, "{}", body);
+    assert_eq!(error_message(&body, "bad_request"), "macd_fast (26) must be less than macd_slow (12)");
+
+    // The defaults count: a slow window of 12 is not above the default fast window.
+    let (status, body) = call(&service, indicators("indicators=macd&macd_slow=12")).await;
+    assert_eq!(status, 400, "{}", body);
+    assert_eq!(error_message(&body, "bad_request"), "macd_fast (12) must be less than macd_slow (12)");
+
+    // MACD windows do not matter when MACD is not requested.
+    let (status, body) = call(&service, indicators("indicators=sma&sma_window=2&macd_fast=30")).await;
+    assert_eq!(status, 200, "{}", body);
+}
+
+#[actix_web::test]
+async fn range_indicators_name_the_series_they_cannot_use() {
+    let app = TestApp::with_fixtures();
+    let mut closes_only = series("3mo", "1d", &[10.0, 11.0, 12.0]);
+    closes_only.remove("highs");
+    app.repositories.prices.insert_series("AAPL", closes_only);
+    let mut no_volumes = series("6mo", "1d", &[10.0, 11.0, 12.0]);
+    no_volumes.remove("volumes");
+    app.repositories.prices.insert_series("AAPL", no_volumes);
+    let service = app.service().await;
+
+    for (query, message) in [
+        ("period=3mo&indicators=stochastic", "stochastic needs highs for every close, but the 3mo/1d series has 0 for 3 closes"),
+        // Requesting every indicator includes ATR.
+        ("period=3mo", "atr needs highs for every close, but the 3mo/1d series has 0 for 3 closes"),
+        ("period=6mo&indicators=vwap", "vwap needs volumes for every close, but the 6mo/1d series has 0 for 3 closes"),
+    ] {
+        let (status, body) = call(&service, indicators(query)).await;
+        assert_eq!(status, 400, "{}", body);
+        assert_eq!(error_message(&body, "bad_request"), message);
+    }
+
+    let (status, body) = call(&service, indicators("period=3mo&indicators=sma,rsi")).await;
+    assert_eq!(status, 200, "{}", body);
+    // Volumes only matter to VWAP.
+    let (status, body) = call(&service, indicators("period=6mo&indicators=atr,stochastic&atr_window=2&stochastic_k=2")).await;
+    assert_eq!(status, 200, "{}", body);
+    assert!(body["atr"][2].is_number() && body["stochastic"]["k"][2].is_number(), "{}", body);
+}
@@ -0,0 +1,65 @@
+//! The movers snapshot computed over the in-memory backend and the lists
+//! ranked from it.
+
+mod common;
+
+use mongodb::bson::doc;
+use trading_sim_server::models::market_models::{MoverList, MoverRank, MoverWindow, MoversQuery};
+use trading_sim_server::services::market_service::{compute_snapshot, MarketOverview};
+use common::{series, TestApp, DAY, FIRST_TIMESTAMP, SECTOR, SOFTWARE_TICKERS};
+
+fn movers(list: MoverList, rank: Option<MoverRank>, sector: Option<&str>) -> MoversQuery {
+    MoversQuery { list, window: None, rank, sector: sector.map(str::to_string), limit: Some(3) }
+}
+
+fn tickers(overview: &MarketOverview, query: MoversQuery) -> Vec<String> {
+    overview.movers(query).unwrap().results.into_iter().map(|entry| entry.ticker).collect()
+}
+
+#[actix_web::test]
+async fn movers_rank_every_company_with_a_readable_series() {
+    let app = TestApp::with_fixtures();
+    app.repositories.companies.insert(doc! { "ticker": "GME", "name": "GameStop", "profile": { "sector": "Consumer Cyclical" } });
+    app.repositories.prices.insert_series("GME", series("1mo", "1d", &[30.0, 24.0, 20.0, 21.0, 22.0, 18.0]));
+
+    let overview = MarketOverview::new();
+    overview.replace(compute_snapshot(&app.repositories.repositories()).await.unwrap());
+
+    let everyone = overview.movers(MoversQuery { limit: Some(100), ..movers(MoverList::Active, None, None) }).unwrap().results;
+    // HPQ's series is malformed and DELL has none.
+    assert_eq!(everyone.len(), SOFTWARE_TICKERS.len() + 2);
+    assert!(everyone.iter().all(|entry| entry.ticker != "HPQ" && entry.ticker != "DELL"));
+
+    // AAPL's 184 -> 186 beats the software names' +1 on a base of 101 or more.
+    assert_eq!(tickers(&overview, movers(MoverList::Gainers, None, None)), ["AAPL", "MSFT", "ORCL"]);
+    assert_eq!(tickers(&overview, movers(MoverList::Losers, Some(MoverRank::Absolute), None)), ["GME"]);
+    assert_eq!(tickers(&overview, movers(MoverList::Gainers, None, Some("Consumer Cyclical"))), Vec::<String>::new());
+
+    let gme = everyone.iter().find(|entry| entry.ticker == "GME").unwrap();
+    assert_eq!((gme.name.as_str(), gme.price, gme.daily_change), ("GameStop", 18.0, Some(-4.0)));
+    assert_eq!((gme.weekly_change, gme.weekly_change_percent), (Some(-12.0), Some(-40.0)));
+    // Fewer than six closes leave no weekly change.
+    let weekly = MoversQuery { window: Some(MoverWindow::Weekly), ..movers(MoverList::Losers, None, None) };
+    assert_eq!(tickers(&overview, weekly), ["GME"]);
+}
+
+#[actix_web::test]
+async fn changes_are_measured_on_split_adjusted_closes() {
+    let app = TestApp::with_fixtures();
+    app.repositories.companies.insert(doc! { "ticker": "NVDA", "name": "NVIDIA", "profile": { "sector": SECTOR } });
+    app.repositories.prices.insert_series("NVDA", series("1mo", "1d", &[200.0, 200.0, 200.0, 100.0, 101.0, 102.0]));
+    app.repositories.prices.insert_split("NVDA", FIRST_TIMESTAMP + 3 * DAY, 2.0);
+
+    let snapshot = compute_snapshot(&app.repositories.repositories()).await.unwrap();
+    let nvda = snapshot.entries.iter().find(|entry| entry.ticker == "NVDA").unwrap();
+    // Raw closes would show a 49% weekly loss.
+    assert_eq!((nvda.weekly_change, nvda.weekly_change_percent), (Some(2.0), Some(2.0)));
+    assert_eq!(nvda.daily_change, Some(1.0));
+}
+
+#[actix_web::test]
+async fn an_unreadable_price_read_fails_the_snapshot() {
+    let app = TestApp::with_fixtures();
+    app.repositories.prices.set_unreadable("AAPL");
+    assert!(compute_snapshot(&app.repositories.repositories()).await.is_err());
+}
@@ -0,0 +1,79 @@
+//! Keeps the OpenAPI document in step with the routes and models.
+//!
+//! `openapi.json` at the repository root is the reviewed copy of the spec.
+//! After an intended API change, regenerate it with
+//!
+//!     UPDATE_OPENAPI=1 cargo test --test openapi
+//!
+//! and commit the diff alongside the change.
+
+mod common;
+
+use std::path::Path;
+use actix_web::test::{call_service, TestRequest};
+use serde_json::Value;
+use trading_sim_server::openapi::{ApiDoc, SPEC_PATH};
+use utoipa::OpenApi;
+use common::{call, TestApp};
+
+const SNAPSHOT: &str = "openapi.json";
+
+fn generated_spec() -> String {
+    ApiDoc::openapi().to_pretty_json().expect("spec serializes") + "\n"
+}
+
+#[test]
+fn spec_matches_committed_document() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(SNAPSHOT);
+    let generated = generated_spec();
+    if std::env::var_os("UPDATE_OPENAPI").is_some() {
+        std::fs::write(&path, &generated).expect("write openapi.json");
+        return;
+    }
+    let committed = std::fs::read_to_string(&path).unwrap_or_default();
+    assert!(
+        committed == generated,
+        "{} is out of date with the routes and models; review the change and \
+         regenerate it with `UPDATE_OPENAPI=1 cargo test --test openapi`",
+        SNAPSHOT,
+    );
+}
+
+#[actix_web::test]
+async fn spec_is_served() {
+    let service = TestApp::with_fixtures().service().await;
+    let (status, body) = call(&service, TestRequest::get().uri(SPEC_PATH).to_request()).await;
+    assert_eq!(status, 200);
+    assert_eq!(body, serde_json::from_str::<Value>(&generated_spec()).unwrap());
+
+    let response = call_service(&service, TestRequest::get().uri("/api/docs/").to_request()).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+/// Every documented operation reaches a handler. Requests carry no body or
+/// parameters and the harness has no Mongo client, so most handlers fail,
+/// but a 404 or 405 without the error envelope can only come from the router.
+#[actix_web::test]
+async fn documented_operations_are_routed() {
+    let service = TestApp::with_fixtures().service().await;
+    let spec: Value = serde_json::from_str(&generated_spec()).unwrap();
+    let paths = spec["paths"].as_object().expect("paths");
+    assert!(!paths.is_empty());
+
+    for (path, operations) in paths {
+        let uri = path.replace("{ticker}", "AAPL").replace("{sector}", common::SECTOR);
+        for method in operations.as_object().expect("operations").keys() {
+            let request = TestRequest::default()
+                .method(method.to_uppercase().parse().expect("HTTP method"))
+                .uri(&uri)
+                .to_request();
+            let (status, body) = call(&service, request).await;
+            let unrouted = matches!(status, 404 | 405) && !body["error"]["code"].is_string();
+            assert!(
+                !unrouted,
+                "{} {} is documented but not routed ({}: {})",
+                method, path, status, body,
+            );
+        }
+    }
+}
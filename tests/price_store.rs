@@ -0,0 +1,93 @@
+//! The pure parts of the price store: the conversions between legacy series
+//! documents and bars.
+
+use mongodb::bson::{doc, from_document, DateTime};
+use trading_sim_server::db::price_store::{bars_from_series, legacy_document};
+use trading_sim_server::models::price_bar_models::{BarMeta, PriceBar};
+use trading_sim_server::models::stock_models::PriceDataDetails;
+
+fn series(document: mongodb::bson::Document) -> PriceDataDetails {
+    from_document(document).expect("valid series")
+}
+
+#[test]
+fn series_become_one_bar_per_timestamp() {
+    let series = series(doc! {
+        "period": "5d",
+        "interval": "1d",
+        "opens": [10.0, 11.0],
+        "highs": [12.0, 13.0],
+        "lows": [9.0, 10.5],
+        "closes": [11.0, 12.5],
+        "timestamps": [1_700_000_000_i64, 1_700_086_400_i64],
+        "volumes": [100_i64, 200_i64],
+    });
+    let bars = bars_from_series("AAPL", &series);
+
+    assert_eq!(bars.len(), 2);
+    assert_eq!(bars[1].timestamp, DateTime::from_millis(1_700_086_400_000));
+    assert_eq!(bars[1].meta, BarMeta::new("AAPL", "1d"));
+    assert_eq!((bars[1].open, bars[1].high, bars[1].low, bars[1].close, bars[1].volume), (11.0, 13.0, 10.5, 12.5, 200));
+}
+
+#[test]
+fn missing_fields_fall_back_to_the_close() {
+    let series = series(doc! {
+        "period": "5d",
+        "interval": "1d",
+        "closes": [11.0, 12.5, 13.0],
+        "timestamps": [1_700_000_000_i64, 1_700_086_400_i64],
+        "opens": [10.0],
+    });
+    let bars = bars_from_series("AAPL", &series);
+
+    // Points stop at the shorter of timestamps and closes.
+    assert_eq!(bars.len(), 2);
+    assert_eq!((bars[0].open, bars[0].high, bars[0].low, bars[0].volume), (10.0, 11.0, 11.0, 0));
+    assert_eq!((bars[1].open, bars[1].high, bars[1].low, bars[1].volume), (12.5, 12.5, 12.5, 0));
+}
+
+#[test]
+fn series_without_timestamps_have_no_bars() {
+    let series = series(doc! { "period": "5d", "interval": "1d", "closes": [11.0], "timestamps": null });
+    assert!(bars_from_series("AAPL", &series).is_empty());
+}
+
+#[test]
+fn bars_become_a_legacy_document() {
+    let bar = |seconds: i64, close: f64| PriceBar {
+        id: None,
+        timestamp: DateTime::from_millis(seconds * 1000),
+        meta: BarMeta::new("AAPL", "1d"),
+        open: close - 1.0,
+        high: close + 1.0,
+        low: close - 2.0,
+        close,
+        volume: 1_000,
+    };
+    let bars = [bar(1_700_000_000, 11.0), bar(1_700_086_400, 12.0)];
+    let document = legacy_document("AAPL", "5d", "1d", &bars.iter().collect::<Vec<_>>());
+
+    assert_eq!(document, doc! {
+        "symbol": "AAPL",
+        "period": "5d",
+        "interval": "1d",
+        "opens": [10.0, 11.0],
+        "highs": [12.0, 13.0],
+        "lows": [9.0, 10.0],
+        "closes": [11.0, 12.0],
+        "timestamps": [1_700_000_000_i64, 1_700_086_400_i64],
+        "volumes": [1_000_i64, 1_000_i64],
+    });
+
+    // The document reads back as the same bars.
+    let round_trip = bars_from_series("AAPL", &series(document));
+    assert_eq!(round_trip.iter().map(|bar| (bar.timestamp, bar.close)).collect::<Vec<_>>(), bars.iter().map(|bar| (bar.timestamp, bar.close)).collect::<Vec<_>>());
+}
+
+#[test]
+fn an_empty_window_is_an_empty_document() {
+    let document = legacy_document("AAPL", "1d", "1m", &[]);
+    assert_eq!(document.get_array("closes").unwrap().len(), 0);
+    assert_eq!(document.get_array("timestamps").unwrap().len(), 0);
+}